@@ -0,0 +1,207 @@
+use gl::types::{GLint, GLsizeiptr, GLuint};
+use nalgebra::{Vector2, Vector4};
+
+use crate::{render, shader::Shader};
+
+const COMPUTE_SOURCE: &str = "#version 430 core
+layout(local_size_x = 16, local_size_y = 16) in;
+
+layout(std430, binding = 0) buffer Result {
+    uint u_Min[4];
+    uint u_Max[4];
+    uint u_Histogram[];
+};
+
+uniform sampler2D u_Texture;
+uniform vec2 u_Range;
+uniform bool u_Log2;
+uniform int u_Bins;
+
+// Maps float bits to uint, so atomic min/max of uints keeps float order (negative values too).
+uint to_sortable(float value) {
+    uint bits = floatBitsToUint(value);
+    return (bits & 0x80000000u) != 0u ? ~bits : bits | 0x80000000u;
+}
+
+void main() {
+    ivec2 coord = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(coord, textureSize(u_Texture, 0)))) {
+        return;
+    }
+
+    vec4 color = texelFetch(u_Texture, coord, 0);
+    for (int i = 0; i < 4; i++) {
+        uint value = to_sortable(color[i]);
+        atomicMin(u_Min[i], value);
+        atomicMax(u_Max[i], value);
+    }
+
+    float luminance = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+    float value = u_Log2 ? log2(max(luminance, 1e-8)) : luminance;
+    float t = clamp((value - u_Range.x) / (u_Range.y - u_Range.x), 0.0, 1.0);
+    atomicAdd(u_Histogram[min(int(t * float(u_Bins)), u_Bins - 1)], 1u);
+}
+";
+
+/// Result of [TextureAnalyzer::analyze].
+#[derive(Clone, PartialEq, Debug)]
+pub struct TextureStats {
+    /// Smallest value of every channel (RGBA).
+    pub min: Vector4<f32>,
+    /// Biggest value of every channel (RGBA).
+    pub max: Vector4<f32>,
+    /// Number of pixels in every luminance bin. Values outside of the range go to the first or the last bin.
+    pub histogram: Vec<u32>,
+    /// Luminance range the histogram covers (in log2 units if [TextureAnalyzer::with_log2] is used).
+    pub range: Vector2<f32>,
+}
+
+impl TextureStats {
+    /// Gets the luminance value below which ```fraction``` (0.0 - 1.0) of all pixels are.
+    /// Auto-exposure usually uses something like 0.5 (median) or 0.9 to ignore small very bright spots.
+    pub fn percentile(&self, fraction: f32) -> f32 {
+        let total: u64 = self.histogram.iter().map(|&count| count as u64).sum();
+        let target = (total as f64 * fraction.clamp(0.0, 1.0) as f64).ceil() as u64;
+
+        let mut sum = 0;
+        for (bin, &count) in self.histogram.iter().enumerate() {
+            sum += count as u64;
+            if sum >= target.max(1) {
+                return self.bin_center(bin);
+            }
+        }
+        self.range.y
+    }
+    /// Gets average luminance, approximated by histogram bin centers.
+    pub fn mean(&self) -> f32 {
+        let total: u64 = self.histogram.iter().map(|&count| count as u64).sum();
+        if total == 0 {
+            return self.range.x;
+        }
+
+        let sum: f64 = self.histogram.iter().enumerate().map(|(bin, &count)| self.bin_center(bin) as f64 * count as f64).sum();
+        (sum / total as f64) as f32
+    }
+
+    fn bin_center(&self, bin: usize) -> f32 {
+        let size = (self.range.y - self.range.x) / self.histogram.len() as f32;
+        self.range.x + (bin as f32 + 0.5) * size
+    }
+}
+
+/// Computes per-channel min/max and a luminance histogram of a texture with a compute shader, then reads them back to the CPU.
+/// Useful for auto-exposure, debugging HDR ranges and tooling. Requires OpenGL 4.3.
+///
+/// Readback waits for the GPU to finish, so don't analyze big textures every frame if you don't have to
+/// (analyze a downscaled copy or do it every few frames instead).
+/// # Example
+/// ```rust
+/// use tinystorm::analysis::TextureAnalyzer;
+///
+/// // Log2 luminance from 1/256 to 16, good for HDR scenes.
+/// let analyzer = TextureAnalyzer::new(64).with_range(-8.0, 4.0).with_log2(true);
+///
+/// while window.is_running() {
+///     ...
+///     let stats = analyzer.analyze(hdr_scene.get_color_texture_id());
+///     let target_exposure = 0.18 / 2.0_f32.powf(stats.percentile(0.5));
+///     exposure += (target_exposure - exposure) * (1.0 - (-2.0 * window.get_delta()).exp());
+/// }
+/// ```
+pub struct TextureAnalyzer {
+    shader: Shader,
+    buffer: GLuint,
+    bins: u32,
+    range: Vector2<f32>,
+    log2: bool,
+    generation: u64,
+}
+
+impl TextureAnalyzer {
+    /// Creates an analyzer with ```bins``` histogram bins covering 0.0 - 1.0 linear luminance.
+    pub fn new(bins: u32) -> Self {
+        let bins = bins.max(1);
+        let shader = Shader::from_compute_source(COMPUTE_SOURCE);
+
+        let mut buffer = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut buffer);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, buffer);
+            gl::BufferData(gl::SHADER_STORAGE_BUFFER, Self::buffer_size(bins), std::ptr::null(), gl::DYNAMIC_READ);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+        }
+
+        Self { shader, buffer, bins, range: Vector2::new(0.0, 1.0), log2: false, generation: render::context_generation() }
+    }
+    /// Sets luminance range the histogram covers.
+    pub fn with_range(mut self, min: f32, max: f32) -> Self {
+        self.range = Vector2::new(min, max);
+        self
+    }
+    /// Makes histogram use ```log2(luminance)``` instead of plain luminance (range is in log2 units too).
+    /// Much better for HDR images, where values differ by orders of magnitude.
+    pub fn with_log2(mut self, log2: bool) -> Self {
+        self.log2 = log2;
+        self
+    }
+
+    /// Analyzes level 0 of a 2D ```texture``` (e.g. [crate::framebuffer::Framebuffer::get_color_texture_id]).
+    /// Texture bound to slot 0 and bound shader are changed.
+    pub fn analyze(&self, texture: GLuint) -> TextureStats {
+        let mut data = vec![0_u32; 8 + self.bins as usize];
+        data[..4].fill(u32::MAX);
+
+        unsafe {
+            let (mut width, mut height): (GLint, GLint) = (0, 0);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::GetTexLevelParameteriv(gl::TEXTURE_2D, 0, gl::TEXTURE_WIDTH, &mut width);
+            gl::GetTexLevelParameteriv(gl::TEXTURE_2D, 0, gl::TEXTURE_HEIGHT, &mut height);
+
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.buffer);
+            gl::BufferSubData(gl::SHADER_STORAGE_BUFFER, 0, Self::buffer_size(self.bins), data.as_ptr() as *const std::ffi::c_void);
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 0, self.buffer);
+            render::count_buffer_upload(Self::buffer_size(self.bins) as usize);
+
+            self.shader.bind();
+            self.shader.set_int("u_Texture", 0);
+            self.shader.set_vec2("u_Range", &self.range);
+            self.shader.set_bool("u_Log2", self.log2);
+            self.shader.set_int("u_Bins", self.bins as i32);
+            self.shader.dispatch((width as u32).div_ceil(16), (height as u32).div_ceil(16), 1);
+
+            gl::MemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT);
+            gl::GetBufferSubData(gl::SHADER_STORAGE_BUFFER, 0, Self::buffer_size(self.bins), data.as_mut_ptr() as *mut std::ffi::c_void);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+        }
+
+        TextureStats {
+            min: Vector4::from_fn(|i, _| Self::from_sortable(data[i])),
+            max: Vector4::from_fn(|i, _| Self::from_sortable(data[4 + i])),
+            histogram: data[8..].to_vec(),
+            range: self.range,
+        }
+    }
+
+    /// Gets number of histogram bins.
+    pub fn get_bins(&self) -> u32 {
+        self.bins
+    }
+
+    fn buffer_size(bins: u32) -> GLsizeiptr {
+        ((8 + bins as usize) * std::mem::size_of::<u32>()) as GLsizeiptr
+    }
+    // Inverse of to_sortable in the compute shader.
+    fn from_sortable(value: u32) -> f32 {
+        f32::from_bits(if value & 0x8000_0000 != 0 { value & 0x7FFF_FFFF } else { !value })
+    }
+}
+impl Drop for TextureAnalyzer {
+    /// You don't need to manually free OpenGL resources, it's done automatically.
+    fn drop(&mut self) {
+        if !render::is_current_context(self.generation) {
+            return;
+        }
+        unsafe { gl::DeleteBuffers(1, &self.buffer); }
+    }
+}
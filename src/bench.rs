@@ -0,0 +1,286 @@
+use std::time::Instant;
+
+use gl::types::GLboolean;
+use nalgebra::{Matrix4, Point3, Vector2, Vector3, Vector4};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    mesh::{Attribute, IndexedMesh, Layout, MeshBuilder},
+    render,
+    shader::Shader,
+    texture::Texture,
+    window::Window,
+};
+
+// Frames drawn before measuring, so shader compilation and driver warm-up don't count.
+const WARMUP_FRAMES: u32 = 10;
+
+const CUBE_VERTEX_SOURCE: &str = "#version 330 core
+layout(location = 0) in vec3 a_Position;
+layout(location = 2) in vec3 a_Normal;
+layout(location = 3) in vec4 a_Instance;
+
+out vec3 v_Position;
+out vec3 v_Normal;
+
+uniform mat4 u_ViewProjection;
+
+void main() {
+    v_Position = a_Position * a_Instance.w + a_Instance.xyz;
+    v_Normal = a_Normal;
+    gl_Position = u_ViewProjection * vec4(v_Position, 1.0);
+}
+";
+const CUBE_FRAGMENT_SOURCE: &str = "#version 330 core
+in vec3 v_Position;
+in vec3 v_Normal;
+out vec4 f_Color;
+
+uniform sampler2D u_Lights;
+uniform int u_LightCount;
+uniform float u_LightRange;
+
+void main() {
+    vec3 normal = normalize(v_Normal);
+    vec3 light = vec3(0.1) + vec3(0.6) * max(dot(normal, normalize(vec3(0.3, 1.0, 0.2))), 0.0);
+    for (int i = 0; i < u_LightCount; i++) {
+        vec3 offset = texelFetch(u_Lights, ivec2(i, 0), 0).xyz - v_Position;
+        float falloff = max(1.0 - length(offset) / u_LightRange, 0.0);
+        light += texelFetch(u_Lights, ivec2(i, 1), 0).rgb * max(dot(normal, normalize(offset)), 0.0) * falloff * falloff;
+    }
+    f_Color = vec4(light, 1.0);
+}
+";
+const SPRITE_VERTEX_SOURCE: &str = "#version 330 core
+layout(location = 0) in vec2 a_Corner;
+layout(location = 1) in vec4 a_Instance;
+
+out vec2 v_Uv;
+
+uniform float u_Aspect;
+uniform float u_Time;
+
+void main() {
+    float angle = a_Instance.w + u_Time;
+    vec2 corner = mat2(cos(angle), sin(angle), -sin(angle), cos(angle)) * a_Corner * a_Instance.z;
+    v_Uv = a_Corner + 0.5;
+    gl_Position = vec4((a_Instance.xy + corner) / vec2(u_Aspect, 1.0), 0.0, 1.0);
+}
+";
+const SPRITE_FRAGMENT_SOURCE: &str = "#version 330 core
+in vec2 v_Uv;
+out vec4 f_Color;
+
+uniform sampler2D u_Texture;
+
+void main() {
+    float alpha = 1.0 - smoothstep(0.4, 0.5, length(v_Uv - 0.5));
+    f_Color = vec4(texture(u_Texture, v_Uv).rgb, alpha);
+}
+";
+
+/// Frame times and rendering statistics of one [crate::bench] scene. Save it (e.g. with ```ron```) and compare with
+/// [BenchReport::get_slowdown] to catch performance regressions between versions.
+///
+/// Frame time covers drawing and waiting for the GPU to finish (```glFinish```), but not swapping buffers,
+/// so disable vsync and max FPS for meaningful numbers.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct BenchReport {
+    /// Name of the scene, e.g. ```"instanced_cubes"```.
+    pub name: String,
+    /// Number of measured frames.
+    pub frames: u32,
+    /// Average frame time in milliseconds.
+    pub average_ms: f32,
+    /// The fastest frame time in milliseconds.
+    pub min_ms: f32,
+    /// The slowest frame time in milliseconds.
+    pub max_ms: f32,
+    /// 99% of frames were faster than this, in milliseconds.
+    pub p99_ms: f32,
+    /// Draw calls per frame, see [render::RenderStatistics].
+    pub draw_calls: u64,
+    /// Triangles per frame, see [render::RenderStatistics].
+    pub triangles: u64,
+}
+
+impl BenchReport {
+    /// Gets average frames per second.
+    pub fn get_average_fps(&self) -> f32 {
+        1000.0 / self.average_ms.max(f32::EPSILON)
+    }
+    /// Gets how many times slower this report is than ```baseline``` by average frame time, e.g. 1.1 is 10% slower.
+    pub fn get_slowdown(&self, baseline: &BenchReport) -> f32 {
+        self.average_ms / baseline.average_ms.max(f32::EPSILON)
+    }
+}
+impl std::fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {:.3} ms avg ({:.0} FPS), {:.3} ms min, {:.3} ms max, {:.3} ms p99 over {} frames, {} draw calls, {} triangles",
+            self.name, self.average_ms, self.get_average_fps(), self.min_ms, self.max_ms, self.p99_ms, self.frames, self.draw_calls, self.triangles,
+        )
+    }
+}
+
+/// Draws ```count``` lit cubes with one instanced draw call for ```frames``` frames, the camera orbits around them.
+/// Measures vertex throughput and instancing. Stops early if the window is closed.
+/// # Example
+/// ```rust
+/// use tinystorm::bench;
+///
+/// let mut window = WindowBuilder::default().with_vsync(false).build();
+/// let report = bench::instanced_cubes(&mut window, 100_000, 600);
+/// println!("{}", report);
+/// ```
+pub fn instanced_cubes(window: &mut Window, count: u32, frames: u32) -> BenchReport {
+    lit_cubes(window, "instanced_cubes", count, 0, frames)
+}
+/// Draws ```count``` rotating, alpha blended, textured sprites with one instanced draw call for ```frames``` frames.
+/// Measures fill rate and blending. Stops early if the window is closed.
+pub fn sprites(window: &mut Window, count: u32, frames: u32) -> BenchReport {
+    let mesh = IndexedMesh::new::<f32>(
+        &[0, 1, 2, 2, 3, 0],
+        &[-0.5, -0.5, 0.5, -0.5, 0.5, 0.5, -0.5, 0.5],
+        &Layout::basic_2d().next_instanced_attribute(Attribute::Vec4, 1),
+        gl::TRIANGLES,
+    );
+    let shader = Shader::from_source(SPRITE_VERTEX_SOURCE, SPRITE_FRAGMENT_SOURCE);
+
+    // 8x8 checkerboard.
+    let pixels: Vec<f32> = (0..64).flat_map(|i| {
+        let value = if (i % 8 + i / 8) % 2 == 0 { 1.0 } else { 0.3 };
+        [value, value * 0.8, 0.4]
+    }).collect();
+    let texture = Texture::from_rgb32f(8, 8, &pixels);
+
+    // XY - position, Z - size, W - rotation.
+    let grid = (count as f32).sqrt().ceil().max(1.0);
+    let size = 2.0 / grid * 1.5;
+    let instances: Vec<Vector4<f32>> = (0..count).map(|i| {
+        let cell = Vector2::new((i as f32 % grid + 0.5) / grid, ((i as f32 / grid).floor() + 0.5) / grid);
+        Vector4::new(cell.x * 2.0 - 1.0, cell.y * 2.0 - 1.0, size, i as f32 * 0.37)
+    }).collect();
+    mesh.set_instances(&instances);
+
+    let (depth_test, blend): (GLboolean, GLboolean) = unsafe { (gl::IsEnabled(gl::DEPTH_TEST), gl::IsEnabled(gl::BLEND)) };
+    unsafe {
+        gl::Disable(gl::DEPTH_TEST);
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+    }
+
+    let report = run(window, "sprites", frames, |window, frame| {
+        shader.bind();
+        shader.set_float("u_Aspect", window.get_aspect());
+        shader.set_float("u_Time", frame as f32 * 0.01);
+        shader.set_int("u_Texture", 0);
+        texture.bind(0);
+        mesh.draw_instanced(count as usize);
+    });
+
+    unsafe {
+        if depth_test == gl::TRUE { gl::Enable(gl::DEPTH_TEST); }
+        if blend != gl::TRUE { gl::Disable(gl::BLEND); }
+    }
+    report
+}
+/// Draws 1024 instanced cubes lit by ```count``` point lights, looped over in the fragment shader, for ```frames``` frames.
+/// Measures fragment shading cost. Stops early if the window is closed.
+pub fn lights(window: &mut Window, count: u32, frames: u32) -> BenchReport {
+    lit_cubes(window, "lights", 1024, count, frames)
+}
+
+fn lit_cubes(window: &mut Window, name: &str, count: u32, lights: u32, frames: u32) -> BenchReport {
+    let mut builder = MeshBuilder::new();
+    for axis in 0..3 {
+        for sign in [-1.0, 1.0] {
+            let normal = Vector3::ith(axis, sign);
+            let (u, v) = (Vector3::ith((axis + 1) % 3, sign), Vector3::ith((axis + 2) % 3, 1.0));
+            builder.push_quad(
+                [normal - u - v, normal + u - v, normal + u + v, normal - u + v].map(|corner| corner * 0.5),
+                [Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.0), Vector2::new(1.0, 1.0), Vector2::new(0.0, 1.0)],
+            );
+        }
+    }
+    let data = builder.into_data();
+    let mesh = IndexedMesh::new::<f32>(&data.indices, &data.vertices, &Layout::default_3d().next_instanced_attribute(Attribute::Vec4, 1), gl::TRIANGLES);
+    let shader = Shader::from_source(CUBE_VERTEX_SOURCE, CUBE_FRAGMENT_SOURCE);
+
+    // Cubes fill a box of side ```extent```, XYZ - position, W - size.
+    let side = (count as f32).cbrt().ceil().max(1.0) as u32;
+    let extent = side as f32 * 2.0;
+    let instances: Vec<Vector4<f32>> = (0..count).map(|i| {
+        let cell = Vector3::new(i % side, i / side % side, i / side / side).cast::<f32>();
+        Vector4::new(cell.x * 2.0 - extent * 0.5, cell.y * 2.0 - extent * 0.5, cell.z * 2.0 - extent * 0.5, 1.0)
+    }).collect();
+    mesh.set_instances(&instances);
+
+    // Row 0 - positions, row 1 - colors. At least one texel, so the texture is valid without lights.
+    let columns = lights.max(1);
+    let mut light_pixels = vec![0.0; (columns * 2 * 3) as usize];
+    for i in 0..lights as usize {
+        let angle = i as f32 * 2.399963;
+        let radius = extent * 0.5 * ((i as f32 + 0.5) / lights as f32).sqrt();
+        light_pixels[i * 3..i * 3 + 3].copy_from_slice(&[angle.cos() * radius, (i as f32 * 0.618).fract() * extent - extent * 0.5, angle.sin() * radius]);
+        let color = Vector3::new(angle.sin(), (angle + 2.1).sin(), (angle + 4.2).sin()).map(|c| c * 0.25 + 0.35);
+        let row = (columns * 3) as usize;
+        light_pixels[row + i * 3..row + i * 3 + 3].copy_from_slice(color.as_slice());
+    }
+    let light_texture = Texture::from_rgb32f(columns, 2, &light_pixels);
+
+    run(window, name, frames, |window, frame| {
+        let angle = frame as f32 * 0.01;
+        let eye = Point3::new(angle.sin(), 0.5, angle.cos()) * extent * 1.2;
+        let projection = Matrix4::new_perspective(window.get_aspect(), 60f32.to_radians(), 0.1, extent * 4.0);
+        let view = Matrix4::look_at_rh(&eye, &Point3::origin(), &Vector3::y());
+
+        shader.bind();
+        shader.set_mat4("u_ViewProjection", &(projection * view));
+        shader.set_int("u_Lights", 0);
+        shader.set_int("u_LightCount", lights as i32);
+        shader.set_float("u_LightRange", extent * 0.5);
+        light_texture.bind(0);
+        mesh.draw_instanced(count as usize);
+    })
+}
+
+// Draws warm-up frames, then measures ```frames``` frames of ```draw```.
+fn run(window: &mut Window, name: &str, frames: u32, mut draw: impl FnMut(&Window, u32)) -> BenchReport {
+    let mut times = Vec::with_capacity(frames as usize);
+    let mut statistics = render::RenderStatistics::default();
+
+    for frame in 0..WARMUP_FRAMES + frames {
+        if !window.is_running() {
+            break;
+        }
+        window.poll_events();
+        render::reset_statistics();
+
+        let start = Instant::now();
+        unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }
+        draw(window, frame);
+        unsafe { gl::Finish(); }
+
+        if frame >= WARMUP_FRAMES {
+            times.push(start.elapsed().as_secs_f32() * 1000.0);
+            statistics = render::get_statistics();
+        }
+        window.swap_buffers();
+    }
+
+    let measured = times.len().max(1) as f32;
+    let mut sorted = times.clone();
+    sorted.sort_by(f32::total_cmp);
+    BenchReport {
+        name: String::from(name),
+        frames: times.len() as u32,
+        average_ms: times.iter().sum::<f32>() / measured,
+        min_ms: sorted.first().copied().unwrap_or(0.0),
+        max_ms: sorted.last().copied().unwrap_or(0.0),
+        p99_ms: sorted.get(((sorted.len() as f32 * 0.99) as usize).min(sorted.len().saturating_sub(1))).copied().unwrap_or(0.0),
+        draw_calls: statistics.draw_calls,
+        triangles: statistics.triangles,
+    }
+}
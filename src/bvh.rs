@@ -0,0 +1,513 @@
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+
+use crate::camera::Ray;
+
+const MAX_LEAF_ITEMS: usize = 4;
+
+/// An axis-aligned bounding box.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    /// Creates a box from its corners.
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Self {
+        Self { min, max }
+    }
+    /// Creates the smallest box containing all ```points```. Empty ```points``` give an empty box, see [Aabb::is_empty].
+    pub fn from_points(points: impl IntoIterator<Item = Vector3<f32>>) -> Self {
+        points.into_iter().fold(Self::empty(), |bounds, point| bounds.union(&Self::new(point, point)))
+    }
+    /// Creates an empty box: it contains nothing, and a union with it gives the other box.
+    pub fn empty() -> Self {
+        Self { min: Vector3::repeat(f32::INFINITY), max: Vector3::repeat(f32::NEG_INFINITY) }
+    }
+
+    /// Gets the smallest box containing both boxes.
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self { min: self.min.inf(&other.min), max: self.max.sup(&other.max) }
+    }
+    /// Gets the box around this one transformed by ```transform```, e.g. world bounds of a mesh from its local bounds.
+    pub fn transformed(&self, transform: &Matrix4<f32>) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+        Self::from_points((0..8).map(|corner| {
+            let point = Vector3::new(
+                if corner & 1 == 0 { self.min.x } else { self.max.x },
+                if corner & 2 == 0 { self.min.y } else { self.max.y },
+                if corner & 4 == 0 { self.min.z } else { self.max.z },
+            );
+            transform.transform_point(&Point3::from(point)).coords
+        }))
+    }
+
+    /// Checks if the box contains nothing.
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+    /// Checks if boxes overlap (touching counts).
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|axis| self.min[axis] <= other.max[axis] && other.min[axis] <= self.max[axis])
+    }
+    /// Gets distance along ```ray``` to the box, 0.0 if the ray starts inside, or ```None``` if it misses.
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<f32> {
+        let (mut near, mut far) = (0.0_f32, f32::INFINITY);
+        for axis in 0..3 {
+            // Division by zero gives infinities, which the comparisons handle.
+            let inverse = 1.0 / ray.direction[axis];
+            let a = (self.min[axis] - ray.origin[axis]) * inverse;
+            let b = (self.max[axis] - ray.origin[axis]) * inverse;
+            near = near.max(a.min(b));
+            far = far.min(a.max(b));
+        }
+        (near <= far).then_some(near)
+    }
+
+    /// Gets the center of the box.
+    pub fn get_center(&self) -> Vector3<f32> {
+        (self.min + self.max) * 0.5
+    }
+    /// Gets the size of the box.
+    pub fn get_size(&self) -> Vector3<f32> {
+        self.max - self.min
+    }
+
+    fn surface_area(&self) -> f32 {
+        let size = self.get_size();
+        size.x * size.y + size.y * size.z + size.z * size.x
+    }
+}
+/// # Default values
+/// ```
+/// Aabb::empty()
+/// ```
+impl Default for Aabb {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+/// View volume of a camera as 6 planes, for culling objects outside of the screen.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Frustum {
+    // Normal in XYZ, distance in W. Points with dot(plane.xyz, point) + plane.w >= 0.0 are inside.
+    planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts planes from ```projection * view``` matrix.
+    /// Works with reverse-Z and infinite projections too, missing planes just never cull.
+    /// # Example
+    /// ```rust
+    /// use tinystorm::bvh::Frustum;
+    ///
+    /// let frustum = Frustum::from_matrix(&(projection * view));
+    /// ```
+    pub fn from_matrix(view_projection: &Matrix4<f32>) -> Self {
+        let row = |index: usize| view_projection.row(index).transpose();
+        let planes = [
+            row(3) + row(0),
+            row(3) - row(0),
+            row(3) + row(1),
+            row(3) - row(1),
+            row(3) + row(2),
+            row(3) - row(2),
+        ];
+
+        Self {
+            planes: planes.map(|plane| {
+                let length = plane.xyz().norm();
+                // Infinite projections have no far plane, it becomes a plane that contains everything.
+                if length < f32::EPSILON { Vector4::new(0.0, 0.0, 0.0, 1.0) } else { plane / length }
+            }),
+        }
+    }
+
+    /// Checks if a box is at least partially inside. It's conservative: big boxes near frustum corners may pass while being outside.
+    pub fn intersects_aabb(&self, bounds: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner furthest along the plane normal.
+            let corner = Vector3::new(
+                if plane.x >= 0.0 { bounds.max.x } else { bounds.min.x },
+                if plane.y >= 0.0 { bounds.max.y } else { bounds.min.y },
+                if plane.z >= 0.0 { bounds.max.z } else { bounds.min.z },
+            );
+            plane.xyz().dot(&corner) + plane.w >= 0.0
+        })
+    }
+    /// Checks if a sphere is at least partially inside.
+    pub fn intersects_sphere(&self, center: &Vector3<f32>, radius: f32) -> bool {
+        self.planes.iter().all(|plane| plane.xyz().dot(center) + plane.w >= -radius)
+    }
+    /// Gets planes as normal in XYZ and distance in W (left, right, bottom, top, near, far), normals point inside.
+    /// Useful to upload them to shaders.
+    pub fn get_planes(&self) -> &[Vector4<f32>; 6] {
+        &self.planes
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Node {
+    bounds: Aabb,
+    // Index of the left child (the right one follows it) for inner nodes, or of the first item in order for leaves.
+    first: usize,
+    // 0 for inner nodes. An empty tree has a single root with 0 items that's never visited.
+    count: usize,
+}
+
+/// A bounding volume hierarchy: objects grouped into a tree of boxes, so ray casts and frustum queries
+/// skip whole groups instead of testing every object.
+///
+/// Items are addressed by their index in the ```items``` given to [Bvh::new]. For moving objects update their bounds
+/// with [Bvh::set_bounds] and call [Bvh::refit] once per frame. Refitting is cheap but the tree gets worse
+/// when objects move far from where they were, so call [Bvh::rebuild] from time to time (e.g. after loading a level).
+/// # Example
+/// ```rust
+/// use tinystorm::bvh::{Bvh, Frustum};
+///
+/// let mut bvh = Bvh::new(props.iter().map(|prop| (prop.local_bounds.transformed(&prop.transform), prop.id)).collect());
+///
+/// // Mouse picking: boxes narrow it down, your exact test (e.g. against triangles) decides.
+/// if let Some((index, distance)) = bvh.cast_ray(&ray, 1000.0, |index, _| props[index].intersect(&ray)) {
+///     select(index, ray.at(distance));
+/// }
+///
+/// // Culling.
+/// for index in bvh.query_frustum(&Frustum::from_matrix(&(projection * view))) {
+///     props[index].draw();
+/// }
+///
+/// // Moving objects.
+/// bvh.set_bounds(door, door_bounds.transformed(&door_transform));
+/// bvh.refit();
+/// ```
+pub struct Bvh<T> {
+    items: Vec<(Aabb, T)>,
+    // Item indices grouped by leaves.
+    order: Vec<usize>,
+    // Parents are always before children, the root is the first one.
+    nodes: Vec<Node>,
+    dirty: bool,
+}
+
+impl<T> Bvh<T> {
+    /// Builds a tree over ```items``` with their bounds.
+    pub fn new(items: Vec<(Aabb, T)>) -> Self {
+        let mut bvh = Self { items, order: Vec::new(), nodes: Vec::new(), dirty: false };
+        bvh.rebuild();
+        bvh
+    }
+
+    /// Builds the tree again from current bounds. Slower than [Bvh::refit], but gives the best tree for where objects are now.
+    pub fn rebuild(&mut self) {
+        self.order = (0..self.items.len()).collect();
+        self.nodes.clear();
+        self.nodes.push(Node { bounds: Aabb::empty(), first: 0, count: self.items.len() });
+
+        // Splits nodes until leaves are small enough. Children are pushed after their parent.
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let Node { first, count, .. } = self.nodes[index];
+            let range = first..first + count;
+            self.nodes[index].bounds = self.order[range.clone()].iter().fold(Aabb::empty(), |bounds, &item| bounds.union(&self.items[item].0));
+            if count <= MAX_LEAF_ITEMS {
+                continue;
+            }
+
+            // Median split of centers along the longest axis of center bounds.
+            let centers = Aabb::from_points(self.order[range.clone()].iter().map(|&item| self.items[item].0.get_center()));
+            let axis = centers.get_size().imax();
+            let middle = count / 2;
+            let items = &self.items;
+            self.order[range].select_nth_unstable_by(middle, |&a, &b| {
+                items[a].0.get_center()[axis].total_cmp(&items[b].0.get_center()[axis])
+            });
+
+            let left = self.nodes.len();
+            self.nodes.push(Node { bounds: Aabb::empty(), first, count: middle });
+            self.nodes.push(Node { bounds: Aabb::empty(), first: first + middle, count: count - middle });
+            self.nodes[index] = Node { bounds: self.nodes[index].bounds, first: left, count: 0 };
+            stack.push(left);
+            stack.push(left + 1);
+        }
+        self.dirty = false;
+    }
+    /// Updates node bounds after [Bvh::set_bounds] calls without changing the tree. Does nothing if no bounds changed.
+    pub fn refit(&mut self) {
+        if !self.dirty || self.items.is_empty() {
+            return;
+        }
+        // Children are after parents, so going backwards visits children first.
+        for index in (0..self.nodes.len()).rev() {
+            let Node { first, count, .. } = self.nodes[index];
+            self.nodes[index].bounds = if count > 0 {
+                self.order[first..first + count].iter().fold(Aabb::empty(), |bounds, &item| bounds.union(&self.items[item].0))
+            } else {
+                self.nodes[first].bounds.union(&self.nodes[first + 1].bounds)
+            };
+        }
+        self.dirty = false;
+    }
+    /// Sets bounds of the item at ```index```. Queries use old node bounds until [Bvh::refit] or [Bvh::rebuild] is called.
+    /// Panics if there's no such item.
+    pub fn set_bounds(&mut self, index: usize, bounds: Aabb) {
+        match self.items.get_mut(index) {
+            Some(item) => item.0 = bounds,
+            None => panic!("Failed to set BVH item bounds. Item {} doesn't exist, there are only {} items.", index, self.items.len()),
+        }
+        self.dirty = true;
+    }
+
+    /// Finds the closest item hit by ```ray``` within ```max_distance```. Returns its index and distance.
+    /// ```test``` is called with items whose boxes are hit and returns exact hit distance, or ```None``` if the item is missed.
+    /// Use ```|index, _| bvh.get_bounds(index).intersect_ray(&ray)``` to just pick boxes.
+    pub fn cast_ray(&self, ray: &Ray, max_distance: f32, mut test: impl FnMut(usize, &T) -> Option<f32>) -> Option<(usize, f32)> {
+        let mut closest: Option<(usize, f32)> = None;
+        let mut stack = Vec::with_capacity(32);
+        if self.items.is_empty() {
+            return None;
+        }
+        if let Some(distance) = self.entry_distance(0, ray) {
+            stack.push((0, distance));
+        }
+
+        while let Some((index, distance)) = stack.pop() {
+            let limit = closest.map_or(max_distance, |(_, closest)| closest);
+            if distance > limit {
+                continue;
+            }
+
+            let node = self.nodes[index];
+            if node.count > 0 {
+                for &item in &self.order[node.first..node.first + node.count] {
+                    if self.items[item].0.intersect_ray(ray).is_none_or(|entry| entry > limit) {
+                        continue;
+                    }
+                    if let Some(hit) = test(item, &self.items[item].1)
+                        && hit <= closest.map_or(max_distance, |(_, closest)| closest)
+                    {
+                        closest = Some((item, hit));
+                    }
+                }
+                continue;
+            }
+
+            // The nearer child is pushed last, so it's visited first and can make the other one skip.
+            let mut children: Vec<(usize, f32)> = [node.first, node.first + 1]
+                .into_iter()
+                .filter_map(|child| self.entry_distance(child, ray).map(|distance| (child, distance)))
+                .collect();
+            children.sort_by(|a, b| b.1.total_cmp(&a.1));
+            stack.extend(children);
+        }
+
+        closest
+    }
+    /// Gets indices of items whose boxes are at least partially inside ```frustum```.
+    pub fn query_frustum(&self, frustum: &Frustum) -> Vec<usize> {
+        self.query(|bounds| frustum.intersects_aabb(bounds))
+    }
+    /// Gets indices of items whose boxes overlap ```bounds```.
+    pub fn query_aabb(&self, bounds: &Aabb) -> Vec<usize> {
+        self.query(|other| other.intersects(bounds))
+    }
+
+    /// Gets the item at ```index```.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.items.get(index).map(|item| &item.1)
+    }
+    /// Gets the item at ```index``` for changing. Use [Bvh::set_bounds] to change its bounds.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.items.get_mut(index).map(|item| &mut item.1)
+    }
+    /// Gets bounds of the item at ```index```. Panics if there's no such item.
+    pub fn get_bounds(&self, index: usize) -> Aabb {
+        self.items[index].0
+    }
+    /// Gets bounds of everything in the tree (as of the last refit or rebuild).
+    pub fn get_root_bounds(&self) -> Aabb {
+        self.nodes[0].bounds
+    }
+    /// Gets number of items.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+    /// Checks if there are no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+    /// Gets surface area heuristic cost of the tree (lower is better), to decide when refitted tree got bad enough to rebuild.
+    pub fn get_cost(&self) -> f32 {
+        let root = self.nodes[0].bounds.surface_area().max(f32::EPSILON);
+        self.nodes.iter().map(|node| node.bounds.surface_area() / root * if node.count > 0 { node.count as f32 } else { 1.0 }).sum()
+    }
+
+    fn entry_distance(&self, index: usize, ray: &Ray) -> Option<f32> {
+        self.nodes[index].bounds.intersect_ray(ray)
+    }
+    fn query(&self, overlaps: impl Fn(&Aabb) -> bool) -> Vec<usize> {
+        let mut result = Vec::new();
+        if self.items.is_empty() {
+            return result;
+        }
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = self.nodes[index];
+            if !overlaps(&node.bounds) {
+                continue;
+            }
+            if node.count > 0 {
+                result.extend(self.order[node.first..node.first + node.count].iter().filter(|&&item| overlaps(&self.items[item].0)));
+            } else {
+                stack.extend([node.first, node.first + 1]);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{camera, random::Random};
+    use nalgebra::Perspective3;
+
+    fn random_boxes(random: &mut Random, count: usize) -> Vec<(Aabb, usize)> {
+        (0..count).map(|index| {
+            let center = Vector3::new(random.range_f32(-50.0, 50.0), random.range_f32(-50.0, 50.0), random.range_f32(-50.0, 50.0));
+            let half_size = Vector3::new(random.range_f32(0.1, 3.0), random.range_f32(0.1, 3.0), random.range_f32(0.1, 3.0));
+            (Aabb::new(center - half_size, center + half_size), index)
+        }).collect()
+    }
+    fn brute_force_ray(items: &[(Aabb, usize)], ray: &Ray, max_distance: f32) -> Option<(usize, f32)> {
+        items.iter()
+            .filter_map(|(bounds, index)| bounds.intersect_ray(ray).map(|distance| (*index, distance)))
+            .filter(|(_, distance)| *distance <= max_distance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+    fn sorted(mut indices: Vec<usize>) -> Vec<usize> {
+        indices.sort_unstable();
+        indices
+    }
+    fn view_projection(eye: Vector3<f32>, target: Vector3<f32>, projection: Matrix4<f32>) -> Matrix4<f32> {
+        projection * Matrix4::look_at_rh(&Point3::from(eye), &Point3::from(target), &Vector3::y())
+    }
+
+    #[test]
+    fn aabb_ray_intersection() {
+        let bounds = Aabb::new(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0));
+
+        assert_eq!(bounds.intersect_ray(&Ray::new(Vector3::new(-5.0, 0.0, 0.0), Vector3::x())), Some(4.0));
+        assert_eq!(bounds.intersect_ray(&Ray::new(Vector3::zeros(), Vector3::new(1.0, 2.0, 3.0))), Some(0.0));
+        assert_eq!(bounds.intersect_ray(&Ray::new(Vector3::new(-5.0, 2.0, 0.0), Vector3::x())), None);
+        // Pointing away.
+        assert_eq!(bounds.intersect_ray(&Ray::new(Vector3::new(5.0, 0.0, 0.0), Vector3::x())), None);
+    }
+
+    #[test]
+    fn cast_ray_matches_brute_force() {
+        let mut random = Random::new(3);
+        let items = random_boxes(&mut random, 500);
+        let bvh = Bvh::new(items.clone());
+
+        let mut hits = 0;
+        for _ in 0..200 {
+            let origin = Vector3::new(random.range_f32(-60.0, 60.0), random.range_f32(-60.0, 60.0), random.range_f32(-60.0, 60.0));
+            let ray = Ray::new(origin, random.on_unit_sphere());
+            let max_distance = random.range_f32(10.0, 200.0);
+
+            let hit = bvh.cast_ray(&ray, max_distance, |index, _| items[index].0.intersect_ray(&ray));
+            let expected = brute_force_ray(&items, &ray, max_distance);
+            assert_eq!(hit.map(|(_, distance)| distance), expected.map(|(_, distance)| distance));
+            hits += hit.is_some() as usize;
+        }
+        assert!(hits >= 20, "Only {} of the rays hit anything", hits);
+    }
+
+    #[test]
+    fn cast_ray_uses_exact_test() {
+        let items: Vec<(Aabb, usize)> = (0..10).map(|i| {
+            let x = i as f32 * 4.0;
+            (Aabb::new(Vector3::new(x, -1.0, -1.0), Vector3::new(x + 2.0, 1.0, 1.0)), i)
+        }).collect();
+        let bvh = Bvh::new(items);
+        let ray = Ray::new(Vector3::new(-10.0, 0.0, 0.0), Vector3::x());
+
+        assert_eq!(bvh.cast_ray(&ray, 1000.0, |index, _| bvh.get_bounds(index).intersect_ray(&ray)), Some((0, 10.0)));
+        // Even items are "missed" by the exact test, so the closest hit is item 1.
+        assert_eq!(bvh.cast_ray(&ray, 1000.0, |index, _| (index % 2 == 1).then(|| bvh.get_bounds(index).intersect_ray(&ray).unwrap())), Some((1, 14.0)));
+        assert_eq!(bvh.cast_ray(&ray, 5.0, |index, _| bvh.get_bounds(index).intersect_ray(&ray)), None);
+    }
+
+    #[test]
+    fn query_frustum_matches_brute_force() {
+        let mut random = Random::new(5);
+        let items = random_boxes(&mut random, 500);
+        let bvh = Bvh::new(items.clone());
+        let projection = Perspective3::new(16.0 / 9.0, 70.0_f32.to_radians(), 0.1, 60.0).to_homogeneous();
+
+        let mut visible = 0;
+        for _ in 0..50 {
+            let eye = Vector3::new(random.range_f32(-40.0, 40.0), random.range_f32(-40.0, 40.0), random.range_f32(-40.0, 40.0));
+            let frustum = Frustum::from_matrix(&view_projection(eye, eye + random.on_unit_sphere(), projection));
+
+            let expected: Vec<usize> = items.iter().filter(|(bounds, _)| frustum.intersects_aabb(bounds)).map(|(_, index)| *index).collect();
+            assert_eq!(sorted(bvh.query_frustum(&frustum)), expected);
+            visible += expected.len();
+        }
+        assert!(visible > 0 && visible < 50 * items.len(), "Frustums see nothing or everything");
+    }
+
+    #[test]
+    fn frustum_culls_outside_objects() {
+        let frustum = Frustum::from_matrix(&view_projection(Vector3::zeros(), -Vector3::z(), Perspective3::new(1.0, 90.0_f32.to_radians(), 0.1, 100.0).to_homogeneous()));
+        let unit = |center: Vector3<f32>| Aabb::new(center - Vector3::repeat(0.5), center + Vector3::repeat(0.5));
+
+        assert!(frustum.intersects_aabb(&unit(Vector3::new(0.0, 0.0, -10.0))));
+        assert!(!frustum.intersects_aabb(&unit(Vector3::new(0.0, 0.0, 10.0))));
+        assert!(!frustum.intersects_aabb(&unit(Vector3::new(30.0, 0.0, -10.0))));
+        assert!(!frustum.intersects_aabb(&unit(Vector3::new(0.0, 0.0, -200.0))));
+        assert!(frustum.intersects_sphere(&Vector3::new(10.5, 0.0, -10.0), 1.0));
+        assert!(!frustum.intersects_sphere(&Vector3::new(0.0, 0.0, 5.0), 1.0));
+    }
+
+    #[test]
+    fn frustum_with_infinite_reverse_z_projection() {
+        let projection = camera::reverse_z_infinite_perspective(90.0_f32.to_radians(), 1.0, 0.1);
+        let frustum = Frustum::from_matrix(&view_projection(Vector3::zeros(), -Vector3::z(), projection));
+
+        assert!(frustum.intersects_sphere(&Vector3::new(0.0, 0.0, -100000.0), 1.0));
+        assert!(!frustum.intersects_sphere(&Vector3::new(0.0, 0.0, 10.0), 1.0));
+    }
+
+    #[test]
+    fn refit_updates_queries() {
+        let mut random = Random::new(9);
+        let mut items = random_boxes(&mut random, 100);
+        let mut bvh = Bvh::new(items.clone());
+
+        // Far away from everything else.
+        let moved = Aabb::new(Vector3::repeat(500.0), Vector3::repeat(501.0));
+        bvh.set_bounds(42, moved);
+        items[42].0 = moved;
+        assert!(bvh.query_aabb(&moved).is_empty(), "Queries must use old bounds until refit");
+
+        bvh.refit();
+        assert_eq!(bvh.query_aabb(&moved), vec![42]);
+        let ray = Ray::new(Vector3::repeat(600.0), -Vector3::repeat(1.0));
+        assert_eq!(bvh.cast_ray(&ray, 1000.0, |index, _| items[index].0.intersect_ray(&ray)).map(|(index, _)| index), Some(42));
+    }
+
+    #[test]
+    fn empty_tree() {
+        let bvh: Bvh<()> = Bvh::new(Vec::new());
+        let ray = Ray::new(Vector3::zeros(), Vector3::x());
+
+        assert!(bvh.is_empty());
+        assert_eq!(bvh.cast_ray(&ray, 100.0, |_, _| Some(0.0)), None);
+        assert!(bvh.query_aabb(&Aabb::new(Vector3::repeat(-1.0), Vector3::repeat(1.0))).is_empty());
+    }
+}
@@ -0,0 +1,300 @@
+use nalgebra::{Matrix4, Vector2, Vector3};
+
+/// A simple 2D platformer-style camera that follows a target.
+/// Supports dead-zone box, look-ahead, smoothing, world-bounds clamping and pixel-perfect snapping.
+/// # Example
+/// ```rust
+/// use tinystorm::{camera::Camera2D, nalgebra::Vector2};
+///
+/// let mut camera = Camera2D::new(Vector2::zeros())
+///     .with_view_size(Vector2::new(16.0, 9.0))
+///     .with_dead_zone(Vector2::new(2.0, 1.0))
+///     .with_look_ahead(0.5)
+///     .with_smoothing(8.0)
+///     .with_bounds(Vector2::new(-50.0, -10.0), Vector2::new(50.0, 10.0));
+///
+/// while window.is_running() {
+///     window.poll_events();
+///     camera.update(&player_position, &player_velocity, window.get_delta());
+///
+///     shader.bind();
+///     shader.set_mat4("u_View", &camera.get_view_matrix());
+///     ...
+/// }
+/// ```
+pub struct Camera2D {
+    position: Vector2<f32>,
+    focus: Vector2<f32>,
+    look_ahead_offset: Vector2<f32>,
+
+    view_size: Vector2<f32>,
+    dead_zone: Vector2<f32>,
+    look_ahead: f32,
+    smoothing: f32,
+    bounds: Option<(Vector2<f32>, Vector2<f32>)>,
+    pixels_per_unit: f32,
+}
+
+impl Camera2D {
+    /// Creates a camera centered at ```position``` with no dead zone, look-ahead, smoothing, bounds or pixel snapping.
+    pub fn new(position: Vector2<f32>) -> Self {
+        Self {
+            position,
+            focus: position,
+            look_ahead_offset: Vector2::zeros(),
+
+            view_size: Vector2::zeros(),
+            dead_zone: Vector2::zeros(),
+            look_ahead: 0.0,
+            smoothing: 0.0,
+            bounds: None,
+            pixels_per_unit: 0.0,
+        }
+    }
+
+    /// Sets the size of the visible area in world units. Used only for world-bounds clamping.
+    pub fn with_view_size(mut self, view_size: Vector2<f32>) -> Self {
+        self.view_size = view_size;
+        self
+    }
+    /// Sets the dead-zone box size in world units.
+    /// While the target is inside this box (centered on the camera) the camera doesn't move.
+    pub fn with_dead_zone(mut self, dead_zone: Vector2<f32>) -> Self {
+        self.dead_zone = dead_zone;
+        self
+    }
+    /// Sets how many seconds ahead of the target (by its velocity) the camera looks.
+    pub fn with_look_ahead(mut self, look_ahead: f32) -> Self {
+        self.look_ahead = look_ahead;
+        self
+    }
+    /// Sets how fast the camera catches up with its desired position. 0.0 means instantly.
+    /// The bigger the value - the faster the camera is. It's framerate-independent.
+    pub fn with_smoothing(mut self, smoothing: f32) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+    /// Restricts the visible area to the ```min```..```max``` world rectangle.
+    /// If view is bigger than bounds - camera would be centered inside them.
+    pub fn with_bounds(mut self, min: Vector2<f32>, max: Vector2<f32>) -> Self {
+        self.bounds = Some((min, max));
+        self
+    }
+
+    /// Snaps the view to the virtual pixel grid with ```pixels_per_unit``` art pixels per world unit, so low-res art doesn't shimmer while the camera moves.
+    /// Camera still moves smoothly inside, only [Camera2D::get_view_matrix] is rounded. 0.0 disables snapping.
+    /// # Example
+    /// ```rust
+    /// use tinystorm::{camera::Camera2D, nalgebra::Vector2};
+    ///
+    /// // 16x16 pixel tiles, one tile per world unit.
+    /// let mut camera = Camera2D::new(Vector2::zeros()).with_smoothing(8.0).with_pixel_snapping(16.0);
+    /// ...
+    /// shader.set_mat4("u_View", &camera.get_view_matrix());
+    /// shader.set_vec2("u_Position", &camera.snap(&sprite_position)); // Snap sprites too.
+    /// ```
+    pub fn with_pixel_snapping(mut self, pixels_per_unit: f32) -> Self {
+        self.pixels_per_unit = pixels_per_unit;
+        self
+    }
+
+    /// Sets the size of the visible area in world units. Call it when your window is resized.
+    pub fn set_view_size(&mut self, view_size: Vector2<f32>) {
+        self.view_size = view_size;
+    }
+    /// Moves the camera to ```position``` immediately, without any smoothing.
+    /// Useful for teleports and level changes.
+    pub fn set_position(&mut self, position: Vector2<f32>) {
+        self.focus = position;
+        self.look_ahead_offset = Vector2::zeros();
+        self.position = self.clamp_to_bounds(position);
+    }
+
+    /// Moves the camera towards the ```target```. Call it once per frame.
+    pub fn update(&mut self, target: &Vector2<f32>, target_velocity: &Vector2<f32>, delta: f32) {
+        let half_dead_zone = self.dead_zone * 0.5;
+        for axis in 0..2 {
+            let difference = target[axis] - self.focus[axis];
+            if difference > half_dead_zone[axis] {
+                self.focus[axis] += difference - half_dead_zone[axis];
+            } else if difference < -half_dead_zone[axis] {
+                self.focus[axis] += difference + half_dead_zone[axis];
+            }
+        }
+
+        let factor = self.smoothing_factor(delta);
+        self.look_ahead_offset += (target_velocity * self.look_ahead - self.look_ahead_offset) * factor;
+
+        let desired = self.clamp_to_bounds(self.focus + self.look_ahead_offset);
+        self.position += (desired - self.position) * factor;
+    }
+
+    /// Gets current camera center position in world units.
+    pub fn get_position(&self) -> Vector2<f32> {
+        self.position
+    }
+    /// Gets view matrix that moves the world so camera position is at the origin.
+    /// Position is snapped to the pixel grid if [Camera2D::with_pixel_snapping] is used.
+    pub fn get_view_matrix(&self) -> Matrix4<f32> {
+        let position = self.snap(&self.position);
+        Matrix4::new_translation(&Vector3::new(-position.x, -position.y, 0.0))
+    }
+    /// Rounds ```position``` to the nearest virtual pixel. Returns it as is if pixel snapping is disabled.
+    pub fn snap(&self, position: &Vector2<f32>) -> Vector2<f32> {
+        if self.pixels_per_unit <= 0.0 {
+            return *position;
+        }
+        position.map(|value| (value * self.pixels_per_unit).round() / self.pixels_per_unit)
+    }
+    /// Gets number of virtual pixels per world unit. 0.0 means pixel snapping is disabled.
+    pub fn get_pixels_per_unit(&self) -> f32 {
+        self.pixels_per_unit
+    }
+
+    fn smoothing_factor(&self, delta: f32) -> f32 {
+        if self.smoothing <= 0.0 { 1.0 } else { 1.0 - (-self.smoothing * delta).exp() }
+    }
+    fn clamp_to_bounds(&self, position: Vector2<f32>) -> Vector2<f32> {
+        let Some((min, max)) = self.bounds else { return position; };
+
+        let half_view = self.view_size * 0.5;
+        let mut result = position;
+        for axis in 0..2 {
+            let low = min[axis] + half_view[axis];
+            let high = max[axis] - half_view[axis];
+            result[axis] = if low > high { (min[axis] + max[axis]) * 0.5 } else { position[axis].clamp(low, high) };
+        }
+
+        result
+    }
+}
+
+/// Creates an orthographic projection from pixels to normalized device coordinates: (0, 0) is the top-left corner of a
+/// ```width``` x ```height``` viewport and Y goes down, like [crate::window::Window::get_mouse_y]. Z is kept as is, use it for sorting.
+/// # Example
+/// ```rust
+/// use tinystorm::camera;
+///
+/// let projection = camera::pixel_projection(window.get_width() as f32, window.get_height() as f32);
+/// shader.set_mat4("u_Projection", &projection);
+/// ```
+pub fn pixel_projection(width: f32, height: f32) -> Matrix4<f32> {
+    let (width, height) = (width.max(1.0), height.max(1.0));
+
+    Matrix4::new(
+        2.0 / width, 0.0, 0.0, -1.0,
+        0.0, -2.0 / height, 0.0, 1.0,
+        0.0, 0.0, 1.0, 0.0,
+        0.0, 0.0, 0.0, 1.0,
+    )
+}
+/// Creates a perspective projection for reverse-Z depth (see [crate::window::WindowBuilder::with_reverse_z]):
+/// ```near``` plane maps to depth 1.0 and ```far``` plane maps to 0.0 in [0; 1] clip range.
+/// ```fovy``` is vertical field of view in radians.
+/// # Example
+/// ```rust
+/// use tinystorm::camera;
+///
+/// let projection = camera::reverse_z_perspective(70.0_f32.to_radians(), window.get_aspect(), 0.1, 100000.0);
+/// shader.set_mat4("u_Projection", &projection);
+/// ```
+pub fn reverse_z_perspective(fovy: f32, aspect: f32, near: f32, far: f32) -> Matrix4<f32> {
+    let focal = 1.0 / (fovy * 0.5).tan();
+    let range = far - near;
+
+    Matrix4::new(
+        focal / aspect, 0.0, 0.0, 0.0,
+        0.0, focal, 0.0, 0.0,
+        0.0, 0.0, near / range, far * near / range,
+        0.0, 0.0, -1.0, 0.0,
+    )
+}
+/// Creates a perspective projection without a far plane, so nothing is ever clipped by distance (space, flight sims).
+/// Depth is in regular OpenGL [-1; 1] clip range. Precision is poor far away, pair it with reverse-Z if possible,
+/// see [reverse_z_infinite_perspective].
+pub fn infinite_perspective(fovy: f32, aspect: f32, near: f32) -> Matrix4<f32> {
+    let focal = 1.0 / (fovy * 0.5).tan();
+
+    Matrix4::new(
+        focal / aspect, 0.0, 0.0, 0.0,
+        0.0, focal, 0.0, 0.0,
+        0.0, 0.0, -1.0, -2.0 * near,
+        0.0, 0.0, -1.0, 0.0,
+    )
+}
+/// Creates a reverse-Z perspective projection without a far plane: ```near``` plane maps to depth 1.0, infinity maps to 0.0.
+/// It's the best depth precision you can get for extreme view distances.
+/// # Example
+/// ```rust
+/// use tinystorm::{camera, window::WindowBuilder};
+///
+/// let window = WindowBuilder::default().with_reverse_z(true).build();
+/// let projection = camera::reverse_z_infinite_perspective(70.0_f32.to_radians(), window.get_aspect(), 0.01);
+/// ```
+pub fn reverse_z_infinite_perspective(fovy: f32, aspect: f32, near: f32) -> Matrix4<f32> {
+    let focal = 1.0 / (fovy * 0.5).tan();
+
+    Matrix4::new(
+        focal / aspect, 0.0, 0.0, 0.0,
+        0.0, focal, 0.0, 0.0,
+        0.0, 0.0, 0.0, near,
+        0.0, 0.0, -1.0, 0.0,
+    )
+}
+
+/// A half-line in 3D: from ```origin``` along ```direction``` (normalized). Used for mouse picking.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Ray {
+    pub origin: Vector3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    /// Creates a ray, ```direction``` is normalized.
+    pub fn new(origin: Vector3<f32>, direction: Vector3<f32>) -> Self {
+        Self { origin, direction: direction.try_normalize(f32::EPSILON).unwrap_or(-Vector3::z()) }
+    }
+    /// Creates a ray from the camera through ```mouse``` position (in pixels from the top-left corner of a ```screen_size``` window).
+    /// Works with any perspective projection, including reverse-Z and infinite ones.
+    /// # Example
+    /// ```rust
+    /// use tinystorm::camera::Ray;
+    ///
+    /// let ray = Ray::from_screen(
+    ///     &Vector2::new(window.get_mouse_x(), window.get_mouse_y()),
+    ///     &Vector2::new(window.get_width() as f32, window.get_height() as f32),
+    ///     &view, &projection,
+    /// );
+    /// ```
+    pub fn from_screen(mouse: &Vector2<f32>, screen_size: &Vector2<f32>, view: &Matrix4<f32>, projection: &Matrix4<f32>) -> Self {
+        let inverse_view = view.try_inverse().unwrap_or_else(Matrix4::identity);
+        let inverse_projection = projection.try_inverse().unwrap_or_else(Matrix4::identity);
+
+        let ndc = Vector2::new(mouse.x / screen_size.x * 2.0 - 1.0, 1.0 - mouse.y / screen_size.y * 2.0);
+        // Any depth between the planes works, 0.5 is finite for regular, reverse-Z and infinite projections.
+        let point = inverse_projection * nalgebra::Vector4::new(ndc.x, ndc.y, 0.5, 1.0);
+        let mut direction = point.xyz() / point.w;
+        if direction.z > 0.0 {
+            direction = -direction;
+        }
+
+        Self::new(
+            inverse_view.column(3).xyz(),
+            inverse_view.fixed_view::<3, 3>(0, 0) * direction,
+        )
+    }
+
+    /// Gets a point at ```distance``` along the ray.
+    pub fn at(&self, distance: f32) -> Vector3<f32> {
+        self.origin + self.direction * distance
+    }
+    /// Gets distance to a plane going through ```point``` with ```normal```, or ```None``` if the ray doesn't hit it.
+    pub fn intersect_plane(&self, point: &Vector3<f32>, normal: &Vector3<f32>) -> Option<f32> {
+        let facing = self.direction.dot(normal);
+        if facing.abs() < f32::EPSILON {
+            return None;
+        }
+        let distance = (point - self.origin).dot(normal) / facing;
+        (distance >= 0.0).then_some(distance)
+    }
+}
@@ -0,0 +1,302 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant, SystemTime},
+};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+/// Player-facing window settings you can load from and save to a RON or TOML file.
+/// Format is picked by the file extension (```.ron``` - RON, anything else - TOML).
+///
+/// Missing fields are filled with defaults, so old config files keep working after you add new settings.
+/// # Example
+/// ```toml
+/// width = 1280
+/// height = 720
+/// vsync = false
+/// msaa = 4
+/// fullscreen = false
+/// max_fps = 144
+///
+/// [key_bindings]
+/// jump = "Space"
+/// shoot = "F"
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub width: u32,
+    pub height: u32,
+    pub vsync: bool,
+    pub msaa: u32,
+    pub fullscreen: bool,
+    pub max_fps: u32,
+    /// Action name to key map, e.g. ```"jump" = "Space"```.
+    pub key_bindings: BTreeMap<String, glfw::Key>,
+}
+
+impl WindowConfig {
+    /// Loads config from a file at ```path```.
+    /// If file doesn't exist - default config is returned.
+    /// If file can't be read or is broken - error is returned, so you can tell the player and fall back to ```WindowConfig::default()```
+    /// (but don't [WindowConfig::save] defaults over a file you couldn't read).
+    /// # Example
+    /// ```rust
+    /// let config = WindowConfig::load("./settings.toml").unwrap_or_else(|error| {
+    ///     show_error_popup(&error);
+    ///     WindowConfig::default()
+    /// });
+    /// ```
+    pub fn load(path: &str) -> Result<Self, String> {
+        let source = match std::fs::read_to_string(path) {
+            Ok(value) => value,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => return Err(format!("Failed to read config at: {}. Error: {}.", path, error)),
+        };
+
+        match parse::<Self>(path, &source) {
+            Ok(value) => Ok(value.sanitized()),
+            Err(error) => Err(format!("Failed to parse config at: {}. Error: {}.", path, error)),
+        }
+    }
+    /// Saves config to a file at ```path```. Useful for settings menus.
+    /// Returns an error if it can't be written (e.g. read-only directory or full disk), so the game can tell the player.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let source = if is_ron(path) {
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).map_err(|error| error.to_string())
+        } else {
+            toml::to_string_pretty(self).map_err(|error| error.to_string())
+        };
+
+        let source = source.map_err(|error| format!("Failed to serialize config for: {}. Error: {}.", path, error))?;
+        std::fs::write(path, source).map_err(|error| format!("Failed to save config at: {}. Error: {}.", path, error))
+    }
+
+    fn sanitized(mut self) -> Self {
+        let default = Self::default();
+        if self.width == 0 || self.height == 0 {
+            self.width = default.width;
+            self.height = default.height;
+        }
+        self
+    }
+}
+
+/// # Default values
+/// ```
+/// width: 960
+/// height: 540
+/// vsync: true
+/// msaa: 0
+/// fullscreen: false
+/// max_fps: 0
+/// key_bindings: {}
+/// ```
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            width: 960,
+            height: 540,
+            vsync: true,
+            msaa: 0,
+            fullscreen: false,
+            max_fps: 0,
+            key_bindings: BTreeMap::new(),
+        }
+    }
+}
+
+/// Window position, size and state that can be saved and restored between runs. See [crate::window::Window::geometry].
+/// # Example
+/// ```ron
+/// (x: 120, y: 80, width: 1600, height: 900, maximized: false, monitor: Some("DELL U2719D"))
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowGeometry {
+    /// Window X position in screen coordinates.
+    pub x: i32,
+    /// Window Y position in screen coordinates.
+    pub y: i32,
+    /// Window width in screen coordinates (not framebuffer pixels).
+    pub width: u32,
+    /// Window height in screen coordinates (not framebuffer pixels).
+    pub height: u32,
+    pub maximized: bool,
+    /// Name of the monitor the window was on.
+    pub monitor: Option<String>,
+}
+
+/// # Default values
+/// ```
+/// x: 0
+/// y: 0
+/// width: 960
+/// height: 540
+/// maximized: false
+/// monitor: None
+/// ```
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        Self { x: 0, y: 0, width: 960, height: 540, maximized: false, monitor: None }
+    }
+}
+
+/// A data file (e.g. gameplay tuning) that is reloaded automatically when it changes on disk, so values can be tweaked while the game runs.
+/// Format is picked by the file extension like in [WindowConfig] (```.ron``` - RON, anything else - TOML).
+///
+/// The file is checked in [Watched::update] at most every [Watched::with_interval] (0.25 seconds by default).
+/// If a changed file is broken, the previous value is kept, so a typo doesn't crash the game. The error is available from [Watched::get_error].
+/// # Example
+/// ```rust
+/// use tinystorm::config::Watched;
+///
+/// #[derive(Deserialize)]
+/// struct Tuning { jump_height: f32, gravity: f32 }
+///
+/// let mut tuning = Watched::<Tuning>::new("assets/tuning.ron");
+/// while window.is_running() {
+///     window.poll_events();
+///     if tuning.update() {
+///         println!("Tuning reloaded.");
+///     } else if let Some(error) = tuning.get_error() {
+///         draw_error_overlay(error);
+///     }
+///     player.update(&tuning, window.get_delta());
+/// }
+/// ```
+pub struct Watched<T> {
+    path: String,
+    value: T,
+    modified: Option<SystemTime>,
+    error: Option<String>,
+    interval: Duration,
+    last_check: Instant,
+}
+
+impl<T: DeserializeOwned> Watched<T> {
+    /// Loads a file at ```path``` and starts watching it. Panics if the file can't be read or parsed.
+    pub fn new(path: &str) -> Self {
+        let source = match std::fs::read_to_string(path) {
+            Ok(value) => value,
+            Err(error) => panic!("Failed to read watched file at: {}. Error: {}.", path, error),
+        };
+        let value = match parse(path, &source) {
+            Ok(value) => value,
+            Err(error) => panic!("Failed to parse watched file at: {}. Error: {}.", path, error),
+        };
+
+        Self { path: path.to_string(), value, modified: Self::modified(path), error: None, interval: Duration::from_millis(250), last_check: Instant::now() }
+    }
+    /// Sets how often the file is checked for changes.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Reloads the file if it was changed. Call it once per frame.
+    /// Returns ```true``` if the value was reloaded, and ```false``` if nothing changed or the changed file is broken (see [Watched::get_error]).
+    pub fn update(&mut self) -> bool {
+        if self.last_check.elapsed() < self.interval {
+            return false;
+        }
+        self.last_check = Instant::now();
+
+        let modified = Self::modified(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        // Remembered even if parsing fails, so a broken file is reported once, not every check.
+        self.modified = modified;
+
+        let source = match std::fs::read_to_string(&self.path) {
+            Ok(value) => value,
+            Err(error) => {
+                self.error = Some(format!("Failed to reload watched file at: {}. Error: {}.", self.path, error));
+                return false;
+            }
+        };
+        match parse(&self.path, &source) {
+            Ok(value) => {
+                self.value = value;
+                self.error = None;
+                true
+            }
+            Err(error) => {
+                self.error = Some(format!("Failed to parse watched file at: {}. Error: {}.", self.path, error));
+                false
+            }
+        }
+    }
+
+    /// Gets the current value.
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+    /// Gets the error of the last reload, ```None``` if it succeeded. The value is left as it was before the broken change.
+    pub fn get_error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+    /// Gets path of the watched file.
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    fn modified(path: &str) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+    }
+}
+impl<T> std::ops::Deref for Watched<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+fn is_ron(path: &str) -> bool {
+    std::path::Path::new(path).extension().is_some_and(|extension| extension.eq_ignore_ascii_case("ron"))
+}
+fn parse<T: DeserializeOwned>(path: &str, source: &str) -> Result<T, String> {
+    if is_ron(path) {
+        ron::from_str(source).map_err(|error| error.to_string())
+    } else {
+        toml::from_str(source).map_err(|error| error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        let directory = std::env::temp_dir().join(format!("tinystorm_config_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        directory.join(name).to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        for name in ["settings.toml", "settings.ron"] {
+            let path = temp_path(name);
+            let config = WindowConfig { width: 1280, height: 720, vsync: false, max_fps: 144, ..Default::default() };
+            config.save(&path).unwrap();
+            assert_eq!(WindowConfig::load(&path).unwrap(), config);
+        }
+    }
+
+    #[test]
+    fn only_missing_file_gives_defaults() {
+        assert_eq!(WindowConfig::load(&temp_path("missing.toml")).unwrap(), WindowConfig::default());
+
+        // A directory exists but can't be read as a file.
+        let directory = temp_path("directory.toml");
+        std::fs::create_dir_all(&directory).unwrap();
+        assert!(WindowConfig::load(&directory).is_err());
+        assert!(WindowConfig::default().save(&directory).is_err());
+
+        let broken = temp_path("broken.toml");
+        std::fs::write(&broken, "width = ").unwrap();
+        assert!(WindowConfig::load(&broken).is_err());
+    }
+}
@@ -0,0 +1,283 @@
+use gl::types::{GLint, GLsizeiptr, GLuint};
+use nalgebra::{Matrix4, Vector4};
+
+use crate::{bvh::Frustum, hiz::DepthPyramid, mesh::IndexedMesh, render, shader::Shader};
+
+const COMPUTE_SOURCE: &str = "#version 430 core
+layout(local_size_x = 64) in;
+
+layout(std430, binding = 0) readonly buffer Bounds {
+    vec4 u_Bounds[];
+};
+layout(std430, binding = 1) readonly buffer Input {
+    uint u_Input[];
+};
+layout(std430, binding = 2) writeonly buffer Output {
+    uint u_Output[];
+};
+// DrawElementsIndirectCommand.
+layout(std430, binding = 3) buffer Command {
+    uint u_Count;
+    uint u_InstanceCount;
+    uint u_FirstIndex;
+    int u_BaseVertex;
+    uint u_BaseInstance;
+};
+
+uniform int u_Instances;
+// Size of one instance in uints.
+uniform int u_Stride;
+uniform vec4 u_Planes[6];
+uniform mat4 u_ViewProjection;
+uniform bool u_Occlusion;
+uniform bool u_ReverseZ;
+uniform sampler2D u_Pyramid;
+
+float farthest(float a, float b) {
+    return u_ReverseZ ? min(a, b) : max(a, b);
+}
+
+bool is_occluded(vec3 center, float radius) {
+    vec3 ndc_min = vec3(1e30);
+    vec3 ndc_max = vec3(-1e30);
+    for (int i = 0; i < 8; i++) {
+        vec3 corner = center + radius * vec3((i & 1) == 0 ? -1.0 : 1.0, (i & 2) == 0 ? -1.0 : 1.0, (i & 4) == 0 ? -1.0 : 1.0);
+        vec4 clip = u_ViewProjection * vec4(corner, 1.0);
+        // Crosses the camera plane, so it can't be projected and is kept.
+        if (clip.w <= 0.0) {
+            return false;
+        }
+        vec3 ndc = clip.xyz / clip.w;
+        ndc_min = min(ndc_min, ndc);
+        ndc_max = max(ndc_max, ndc);
+    }
+
+    vec2 uv_min = clamp(ndc_min.xy * 0.5 + 0.5, 0.0, 1.0);
+    vec2 uv_max = clamp(ndc_max.xy * 0.5 + 0.5, 0.0, 1.0);
+    // Regular depth maps -1.0 - 1.0 to 0.0 - 1.0, reverse-Z uses 0.0 - 1.0 clip control with 1.0 being near.
+    float nearest = u_ReverseZ ? ndc_max.z : ndc_min.z * 0.5 + 0.5;
+
+    // The level where the box covers at most 2x2 texels, so 4 fetches see all of it.
+    vec2 size = (uv_max - uv_min) * vec2(textureSize(u_Pyramid, 0));
+    int levels = textureQueryLevels(u_Pyramid);
+    int level = clamp(int(ceil(log2(max(max(size.x, size.y), 1.0)))), 0, levels - 1);
+    ivec2 min_texel, max_texel;
+    for (; level < levels; level++) {
+        ivec2 level_size = textureSize(u_Pyramid, level);
+        min_texel = clamp(ivec2(uv_min * vec2(level_size)), ivec2(0), level_size - 1);
+        max_texel = clamp(ivec2(uv_max * vec2(level_size)), ivec2(0), level_size - 1);
+        if (all(lessThanEqual(max_texel - min_texel, ivec2(1)))) {
+            break;
+        }
+    }
+    if (level == levels) {
+        return false;
+    }
+
+    float depth = farthest(
+        farthest(texelFetch(u_Pyramid, min_texel, level).r, texelFetch(u_Pyramid, ivec2(max_texel.x, min_texel.y), level).r),
+        farthest(texelFetch(u_Pyramid, ivec2(min_texel.x, max_texel.y), level).r, texelFetch(u_Pyramid, max_texel, level).r)
+    );
+    return u_ReverseZ ? nearest < depth : nearest > depth;
+}
+
+void main() {
+    int index = int(gl_GlobalInvocationID.x);
+    if (index >= u_Instances) {
+        return;
+    }
+
+    vec4 bounds = u_Bounds[index];
+    for (int i = 0; i < 6; i++) {
+        if (dot(u_Planes[i].xyz, bounds.xyz) + u_Planes[i].w < -bounds.w) {
+            return;
+        }
+    }
+    if (u_Occlusion && is_occluded(bounds.xyz, bounds.w)) {
+        return;
+    }
+
+    uint slot = atomicAdd(u_InstanceCount, 1u);
+    for (int i = 0; i < u_Stride; i++) {
+        u_Output[slot * uint(u_Stride) + uint(i)] = u_Input[index * u_Stride + i];
+    }
+}
+";
+const COMMAND_SIZE: usize = 5 * std::mem::size_of::<u32>();
+
+/// Culls instances of an [IndexedMesh] on the GPU: a compute shader tests bounding spheres of all instances against the camera frustum
+/// (and optionally a [DepthPyramid] for occlusion), then copies visible ones into the mesh instance buffer and draws them
+/// with one indirect call. The CPU never touches per-instance data after [InstanceCuller::set_instances], so it scales to
+/// hundreds of thousands of instances. Requires OpenGL 4.3.
+///
+/// Occlusion culling uses the pyramid built from the previous frame's depth, so objects that just came out from behind
+/// an occluder can show up one frame late. It's usually unnoticeable, but skip the pyramid on camera cuts.
+/// # Example
+/// ```rust
+/// use tinystorm::{culling::InstanceCuller, hiz::DepthPyramid, mesh::{Attribute, IndexedMesh, Layout}};
+///
+/// let layout = Layout::default_3d()
+///     .next_instanced_attribute(Attribute::Vec4, 1) // Model matrix [layout(location=3..6)]
+///     .next_instanced_attribute(Attribute::Vec4, 1)
+///     .next_instanced_attribute(Attribute::Vec4, 1)
+///     .next_instanced_attribute(Attribute::Vec4, 1);
+/// let tree = IndexedMesh::new(&indices, &vertices, &layout, gl::TRIANGLES);
+///
+/// let mut culler = InstanceCuller::new();
+/// // World-space bounding spheres: center in XYZ, radius in W.
+/// culler.set_instances(&tree_transforms, &tree_bounds);
+///
+/// while window.is_running() {
+///     window.poll_events();
+///
+///     culler.cull(&tree, &(projection * view), Some(&pyramid));
+///     scene.bind();
+///     unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }
+///     culler.draw(&tree);
+///     Framebuffer::unbind(window.get_width(), window.get_height());
+///
+///     pyramid.build(scene.get_depth_texture_id()); // Used for occlusion in the next frame.
+///     ...
+/// }
+/// ```
+pub struct InstanceCuller {
+    shader: Shader,
+    bounds: GLuint,
+    instances: GLuint,
+    command: GLuint,
+
+    count: usize,
+    stride: usize,
+    generation: u64,
+}
+
+impl InstanceCuller {
+    /// Creates a culler without instances. Panics if compute shaders aren't supported.
+    pub fn new() -> Self {
+        let shader = Shader::from_compute_source(COMPUTE_SOURCE);
+
+        let mut buffers: [GLuint; 3] = [0; 3];
+        unsafe {
+            gl::GenBuffers(3, buffers.as_mut_ptr());
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, buffers[2]);
+            gl::BufferData(gl::SHADER_STORAGE_BUFFER, COMMAND_SIZE as GLsizeiptr, std::ptr::null(), gl::DYNAMIC_DRAW);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+        }
+
+        Self { shader, bounds: buffers[0], instances: buffers[1], command: buffers[2], count: 0, stride: 0, generation: render::context_generation() }
+    }
+
+    /// Replaces all instances: ```instances``` laid out like per-instance attributes of the meshes you cull
+    /// (see [crate::mesh::Layout::next_instanced_attribute]) and their world-space bounding spheres
+    /// (center in XYZ, radius in W). Panics if their lengths differ or instance size isn't a multiple of 4 bytes.
+    pub fn set_instances<T>(&mut self, instances: &[T], bounds: &[Vector4<f32>]) {
+        if instances.len() != bounds.len() {
+            panic!("Failed to set culled instances. Got {} instances, but {} bounds.", instances.len(), bounds.len());
+        }
+        if !std::mem::size_of::<T>().is_multiple_of(4) {
+            panic!("Failed to set culled instances. Instance size must be a multiple of 4 bytes, got {}.", std::mem::size_of::<T>());
+        }
+
+        unsafe {
+            for (buffer, size, data) in [
+                (self.bounds, std::mem::size_of_val(bounds), bounds.as_ptr() as *const std::ffi::c_void),
+                (self.instances, std::mem::size_of_val(instances), instances.as_ptr() as *const std::ffi::c_void),
+            ] {
+                gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, buffer);
+                gl::BufferData(gl::SHADER_STORAGE_BUFFER, size as GLsizeiptr, data, gl::DYNAMIC_DRAW);
+                render::count_buffer_upload(size);
+            }
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+        }
+
+        self.count = instances.len();
+        self.stride = std::mem::size_of::<T>();
+    }
+
+    /// Writes instances inside the frustum of ```view_projection``` (and not hidden behind depth of ```pyramid```, if it's given)
+    /// into instance buffer of ```mesh``` for [InstanceCuller::draw]. Bound shader is restored after that.
+    /// Panics if instance size doesn't match per-instance attributes of the mesh layout.
+    pub fn cull(&self, mesh: &IndexedMesh, view_projection: &Matrix4<f32>, pyramid: Option<&DepthPyramid>) {
+        if self.count > 0 && mesh.get_layout().get_instance_stride() != self.stride {
+            panic!(
+                "Failed to cull instances. Instance size is {} bytes, but the mesh layout expects {}.",
+                self.stride,
+                mesh.get_layout().get_instance_stride()
+            );
+        }
+        let output = mesh.reserve_instances(self.count * self.stride);
+        let command: [u32; 5] = [mesh.get_index_count() as u32, 0, 0, 0, 0];
+
+        unsafe {
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.command);
+            gl::BufferSubData(gl::SHADER_STORAGE_BUFFER, 0, COMMAND_SIZE as GLsizeiptr, command.as_ptr() as *const std::ffi::c_void);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+            if self.count == 0 {
+                return;
+            }
+
+            let mut previous_program: GLint = 0;
+            gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut previous_program);
+
+            for (binding, buffer) in [self.bounds, self.instances, output, self.command].into_iter().enumerate() {
+                gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding as GLuint, buffer);
+            }
+
+            self.shader.bind();
+            self.shader.set_int("u_Instances", self.count as i32);
+            self.shader.set_int("u_Stride", (self.stride / 4) as i32);
+            self.shader.set_mat4("u_ViewProjection", view_projection);
+            for (i, plane) in Frustum::from_matrix(view_projection).get_planes().iter().enumerate() {
+                self.shader.set_vec4(&format!("u_Planes[{}]", i), plane);
+            }
+
+            self.shader.set_bool("u_Occlusion", pyramid.is_some());
+            if let Some(pyramid) = pyramid {
+                pyramid.bind(0);
+                self.shader.set_int("u_Pyramid", 0);
+                self.shader.set_bool("u_ReverseZ", pyramid.is_reverse_z());
+            }
+            self.shader.dispatch((self.count as u32).div_ceil(64), 1, 1);
+
+            gl::MemoryBarrier(gl::COMMAND_BARRIER_BIT | gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT | gl::BUFFER_UPDATE_BARRIER_BIT);
+            gl::UseProgram(previous_program as GLuint);
+        }
+    }
+    /// Draws instances left by the last [InstanceCuller::cull] of ```mesh``` with one indirect call.
+    /// Bind your shader before that, just like for [IndexedMesh::draw_instanced].
+    pub fn draw(&self, mesh: &IndexedMesh) {
+        mesh.draw_indirect(self.command);
+    }
+
+    /// Gets number of instances left by the last [InstanceCuller::cull]. It waits for the GPU, so use it for debugging only.
+    pub fn get_visible_count(&self) -> usize {
+        let mut count: u32 = 0;
+        unsafe {
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.command);
+            gl::GetBufferSubData(gl::SHADER_STORAGE_BUFFER, 4, 4, &mut count as *mut u32 as *mut std::ffi::c_void);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+        }
+        count as usize
+    }
+    /// Gets number of instances given to [InstanceCuller::set_instances].
+    pub fn get_instance_count(&self) -> usize {
+        self.count
+    }
+}
+impl Default for InstanceCuller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Drop for InstanceCuller {
+    /// You don't need to manually free OpenGL resources, it's done automatically.
+    fn drop(&mut self) {
+        if !render::is_current_context(self.generation) {
+            return;
+        }
+        unsafe {
+            let buffers = [self.bounds, self.instances, self.command];
+            gl::DeleteBuffers(3, buffers.as_ptr());
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+
+/// A key of an [AnimationCurve]: ```value``` at ```time``` with tangents (slopes, value change per time unit) on both sides.
+/// Tangents default to 0.0 (flat) when missing in a file.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub time: f32,
+    pub value: f32,
+    /// Slope on the left side of the key.
+    #[serde(default)]
+    pub in_tangent: f32,
+    /// Slope on the right side of the key.
+    #[serde(default)]
+    pub out_tangent: f32,
+}
+
+impl Keyframe {
+    /// Creates a key with flat tangents.
+    pub fn new(time: f32, value: f32) -> Self {
+        Self { time, value, in_tangent: 0.0, out_tangent: 0.0 }
+    }
+    /// Sets slopes on the left and the right side of the key. Different slopes make a sharp corner.
+    pub fn with_tangents(mut self, in_tangent: f32, out_tangent: f32) -> Self {
+        self.in_tangent = in_tangent;
+        self.out_tangent = out_tangent;
+        self
+    }
+}
+
+/// A curve made of keys connected with cubic Hermite segments, like animation curves in game editors.
+/// Use it as data for anything tuned over time: tween easing, particle size over lifetime, post-effect intensity, etc.
+///
+/// Before the first key and after the last one the curve holds their values. An empty curve is 0.0 everywhere.
+/// It's serialized as a plain list of keys, so designers can tweak it in a RON file.
+/// # Example
+/// ```ron
+/// // assets/curves/explosion_size.ron
+/// [
+///     (time: 0.0, value: 0.0, out_tangent: 8.0),
+///     (time: 0.2, value: 1.2),
+///     (time: 1.0, value: 0.0),
+/// ]
+/// ```
+/// ```rust
+/// use tinystorm::curve::AnimationCurve;
+///
+/// let size: AnimationCurve = ron::from_str(&std::fs::read_to_string("assets/curves/explosion_size.ron").unwrap()).unwrap();
+/// let scale = size.evaluate(age / lifetime);
+/// ```
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<Keyframe>", into = "Vec<Keyframe>")]
+pub struct AnimationCurve {
+    // Always sorted by time.
+    keys: Vec<Keyframe>,
+}
+
+impl AnimationCurve {
+    /// Creates an empty curve.
+    pub fn new() -> Self {
+        Self { keys: Vec::new() }
+    }
+    /// Creates a curve that is ```value``` everywhere.
+    pub fn constant(value: f32) -> Self {
+        Self::new().with_key(Keyframe::new(0.0, value))
+    }
+    /// Creates a straight line from ```from``` at 0.0 to ```to``` at 1.0.
+    pub fn linear(from: f32, to: f32) -> Self {
+        let slope = to - from;
+        Self::new()
+            .with_key(Keyframe::new(0.0, from).with_tangents(slope, slope))
+            .with_key(Keyframe::new(1.0, to).with_tangents(slope, slope))
+    }
+    /// Creates a curve from ```from``` at 0.0 to ```to``` at 1.0 that starts and ends smoothly (smoothstep).
+    pub fn ease_in_out(from: f32, to: f32) -> Self {
+        Self::new().with_key(Keyframe::new(0.0, from)).with_key(Keyframe::new(1.0, to))
+    }
+
+    /// Adds a ```key```. A key with the same time is replaced.
+    pub fn with_key(mut self, key: Keyframe) -> Self {
+        self.add_key(key);
+        self
+    }
+    /// Adds a ```key```. A key with the same time is replaced.
+    pub fn add_key(&mut self, key: Keyframe) {
+        match self.keys.binary_search_by(|other| other.time.total_cmp(&key.time)) {
+            Ok(index) => self.keys[index] = key,
+            Err(index) => self.keys.insert(index, key),
+        }
+    }
+    /// Removes the key at ```index``` (in time order). Panics if there's no such key.
+    pub fn remove_key(&mut self, index: usize) -> Keyframe {
+        if index >= self.keys.len() {
+            panic!("Failed to remove curve key {}. Curve has only {} keys.", index, self.keys.len());
+        }
+        self.keys.remove(index)
+    }
+    /// Sets tangents of all keys so the curve goes smoothly through them (Catmull-Rom). First and last keys get flat tangents.
+    pub fn smooth_tangents(&mut self) {
+        for i in 1..self.keys.len().saturating_sub(1) {
+            let (previous, next) = (self.keys[i - 1], self.keys[i + 1]);
+            let slope = (next.value - previous.value) / (next.time - previous.time);
+            self.keys[i].in_tangent = slope;
+            self.keys[i].out_tangent = slope;
+        }
+    }
+
+    /// Gets the curve value at ```time```. Times outside the keys (and NaN) give the value of the first or the last key.
+    pub fn evaluate(&self, time: f32) -> f32 {
+        let (first, last) = match (self.keys.first(), self.keys.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return 0.0,
+        };
+        if time.is_nan() || time <= first.time {
+            return first.value;
+        }
+        if time >= last.time {
+            return last.value;
+        }
+
+        let index = self.keys.partition_point(|key| key.time <= time);
+        let (a, b) = (&self.keys[index - 1], &self.keys[index]);
+        let duration = b.time - a.time;
+        let t = (time - a.time) / duration;
+        let (t2, t3) = (t * t, t * t * t);
+
+        (2.0 * t3 - 3.0 * t2 + 1.0) * a.value
+            + (t3 - 2.0 * t2 + t) * duration * a.out_tangent
+            + (-2.0 * t3 + 3.0 * t2) * b.value
+            + (t3 - t2) * duration * b.in_tangent
+    }
+
+    /// Gets all keys in time order.
+    pub fn get_keys(&self) -> &[Keyframe] {
+        &self.keys
+    }
+    /// Gets time of the first and the last key, or ```None``` if the curve is empty.
+    pub fn get_time_range(&self) -> Option<(f32, f32)> {
+        Some((self.keys.first()?.time, self.keys.last()?.time))
+    }
+}
+impl From<Vec<Keyframe>> for AnimationCurve {
+    fn from(mut keys: Vec<Keyframe>) -> Self {
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        // Later keys with the same time win, like in add_key.
+        keys.dedup_by(|later, earlier| {
+            let same = later.time == earlier.time;
+            if same {
+                *earlier = *later;
+            }
+            same
+        });
+        Self { keys }
+    }
+}
+impl From<AnimationCurve> for Vec<Keyframe> {
+    fn from(curve: AnimationCurve) -> Self {
+        curve.keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluate_clamps_outside_and_nan() {
+        let curve = AnimationCurve::linear(2.0, 6.0);
+        let (start, end) = curve.get_time_range().unwrap();
+
+        assert_eq!(curve.evaluate(start - 1.0), 2.0);
+        assert_eq!(curve.evaluate(end + 1.0), 6.0);
+        assert_eq!(curve.evaluate(f32::NAN), 2.0);
+        assert!((curve.evaluate((start + end) * 0.5) - 4.0).abs() < 1e-5);
+        assert_eq!(AnimationCurve::new().evaluate(f32::NAN), 0.0);
+    }
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use gl::types::{GLint, GLsizei, GLsync};
+
+use crate::render;
+
+/// A point in the GPU command stream you can wait for or check, so streaming and readbacks only wait for the commands they need
+/// instead of stalling everything with ```glFinish```.
+/// # Example
+/// ```rust
+/// use tinystorm::fence::FenceSync;
+///
+/// // Frame N: write into a part of a ring buffer and remember when the GPU is done reading it.
+/// particles.update_vertices_range::<f32>(part * part_size, &new_particles);
+/// particles.draw_partial(part * part_vertices, part_vertices);
+/// fences[part] = Some(FenceSync::insert());
+///
+/// // Frame N + 3: the same part is reused only after the GPU is done with it.
+/// if let Some(fence) = fences[part].take() {
+///     fence.client_wait(Duration::from_millis(100));
+/// }
+/// ```
+pub struct FenceSync {
+    sync: GLsync,
+    generation: u64,
+}
+
+impl FenceSync {
+    /// Inserts a fence after all OpenGL commands issued so far. It's signaled when the GPU finishes all of them.
+    pub fn insert() -> Self {
+        let sync = unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) };
+        if sync.is_null() {
+            panic!("Failed to insert fence sync. glFenceSync returned null.");
+        }
+        Self { sync, generation: render::context_generation() }
+    }
+
+    /// Checks if the GPU has passed the fence without waiting.
+    /// Commands must reach the GPU first, which happens on [crate::window::Window::swap_buffers] or [FenceSync::client_wait].
+    pub fn is_signaled(&self) -> bool {
+        let mut status: GLint = 0;
+        unsafe {
+            gl::GetSynciv(self.sync, gl::SYNC_STATUS, 1, std::ptr::null_mut::<GLsizei>(), &mut status);
+        }
+        status == gl::SIGNALED as GLint
+    }
+    /// Blocks the CPU until the GPU passes the fence or ```timeout``` runs out (zero only flushes and checks).
+    /// Returns ```true``` if the fence was signaled. Panics if waiting failed.
+    pub fn client_wait(&self, timeout: Duration) -> bool {
+        let result = unsafe {
+            gl::ClientWaitSync(self.sync, gl::SYNC_FLUSH_COMMANDS_BIT, timeout.as_nanos().min(u64::MAX as u128) as u64)
+        };
+        match result {
+            gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => true,
+            gl::TIMEOUT_EXPIRED => false,
+            _ => panic!("Failed to wait for fence sync. glClientWaitSync returned 0x{:X}.", result),
+        }
+    }
+    /// Makes the GPU (not the CPU) wait for the fence before running commands issued after this call.
+    /// Useful only with multiple contexts sharing objects, a single context runs commands in order anyway.
+    pub fn gpu_wait(&self) {
+        unsafe {
+            gl::WaitSync(self.sync, 0, gl::TIMEOUT_IGNORED);
+        }
+    }
+}
+impl Drop for FenceSync {
+    /// You don't need to manually free OpenGL resources, it's done automatically.
+    fn drop(&mut self) {
+        if !render::is_current_context(self.generation) {
+            return;
+        }
+        unsafe {
+            gl::DeleteSync(self.sync);
+        }
+    }
+}
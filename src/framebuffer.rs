@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+
+use crate::render;
+
+/// A simple OpenGL framebuffer (aka. render target) with a color texture and a depth-stencil texture.
+/// Render into it instead of the screen and use its color texture afterwards (post-processing, minimaps, etc.).
+/// # Example
+/// ```rust
+/// use tinystorm::framebuffer::Framebuffer;
+///
+/// let framebuffer = Framebuffer::new(window.get_width(), window.get_height());
+/// while window.is_running() {
+///     window.poll_events();
+///
+///     framebuffer.bind();
+///     unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }
+///     mesh.draw();
+///     Framebuffer::unbind(window.get_width(), window.get_height());
+///
+///     framebuffer.bind_color_texture(0);
+///     fullscreen_triangle.draw();
+///     window.swap_buffers();
+/// }
+/// ```
+pub struct Framebuffer {
+    id: GLuint,
+    color: GLuint,
+    depth_stencil: GLuint,
+
+    width: u32,
+    height: u32,
+    generation: u64,
+}
+
+impl Framebuffer {
+    /// Creates a framebuffer with ```width``` x ```height``` RGBA8 color texture and 24/8 depth-stencil texture.
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut id = 0;
+        unsafe { gl::GenFramebuffers(1, &mut id); }
+
+        let mut framebuffer = Self { id, color: 0, depth_stencil: 0, width: 0, height: 0, generation: render::context_generation() };
+        framebuffer.resize(width, height);
+        framebuffer
+    }
+
+    /// Recreates attachments with a new size. All the previous content is lost.
+    /// Does nothing if size hasn't changed. Size is clamped to at least 1x1.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if width == self.width && height == self.height {
+            return;
+        }
+
+        self.width = width;
+        self.height = height;
+
+        unsafe {
+            self.delete_attachments();
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+
+            gl::GenTextures(1, &mut self.color);
+            gl::BindTexture(gl::TEXTURE_2D, self.color);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, self.color, 0);
+
+            gl::GenTextures(1, &mut self.depth_stencil);
+            gl::BindTexture(gl::TEXTURE_2D, self.depth_stencil);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::DEPTH24_STENCIL8 as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                0,
+                gl::DEPTH_STENCIL,
+                gl::UNSIGNED_INT_24_8,
+                std::ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::TEXTURE_2D, self.depth_stencil, 0);
+
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                panic!("Failed to create {}x{} framebuffer. Status: 0x{:X}.", width, height, status);
+            }
+        }
+    }
+
+    /// Makes OpenGL render into this framebuffer and sets viewport to its size.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            gl::Viewport(0, 0, self.width as GLsizei, self.height as GLsizei);
+        }
+    }
+    /// Makes OpenGL render to the screen again and sets viewport to ```width``` x ```height``` (usually window size).
+    pub fn unbind(width: u32, height: u32) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, width as GLsizei, height as GLsizei);
+        }
+    }
+    /// Binds the color texture to certain slot, just like [crate::texture::Texture::bind].
+    pub fn bind_color_texture(&self, slot: GLenum) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + slot);
+            gl::BindTexture(gl::TEXTURE_2D, self.color);
+        }
+        render::count_texture_bind();
+    }
+
+    /// Binds the depth-stencil texture to certain slot. Sampling it returns depth in the red channel.
+    pub fn bind_depth_texture(&self, slot: GLenum) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + slot);
+            gl::BindTexture(gl::TEXTURE_2D, self.depth_stencil);
+        }
+        render::count_texture_bind();
+    }
+
+    /// Gets framebuffer width in pixels.
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+    /// Gets framebuffer height in pixels.
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+    /// Gets OpenGL id of the color texture for more precise control.
+    pub fn get_color_texture_id(&self) -> GLuint {
+        self.color
+    }
+    /// Gets OpenGL id of the depth-stencil texture for more precise control.
+    pub fn get_depth_texture_id(&self) -> GLuint {
+        self.depth_stencil
+    }
+
+    unsafe fn delete_attachments(&mut self) {
+        // Attachments of a lost context died with it.
+        if !render::is_current_context(self.generation) {
+            self.color = 0;
+            self.depth_stencil = 0;
+            return;
+        }
+        unsafe {
+            if self.color != 0 {
+                gl::DeleteTextures(1, &self.color);
+                self.color = 0;
+            }
+            if self.depth_stencil != 0 {
+                gl::DeleteTextures(1, &self.depth_stencil);
+                self.depth_stencil = 0;
+            }
+        }
+    }
+}
+impl Drop for Framebuffer {
+    /// You don't need to manually free OpenGL resources, it's done automatically.
+    fn drop(&mut self) {
+        if !render::is_current_context(self.generation) {
+            return;
+        }
+        unsafe {
+            self.delete_attachments();
+            gl::DeleteFramebuffers(1, &self.id);
+        }
+    }
+}
+
+/// A pair of equally sized [Framebuffer]s where one is read while the other is written, then they're swapped.
+/// Used for blur chains, simulations (Game of Life, fluids) and feedback effects.
+/// # Example
+/// ```rust
+/// use tinystorm::framebuffer::PingPong;
+///
+/// let mut blur = PingPong::new(window.get_width() / 2, window.get_height() / 2);
+/// for _ in 0..4 {
+///     blur.write().bind();
+///     blur.read().bind_color_texture(0);
+///     blur_shader.bind();
+///     fullscreen_triangle.draw();
+///     blur.swap();
+/// }
+/// Framebuffer::unbind(window.get_width(), window.get_height());
+/// blur.read().bind_color_texture(0); // The latest result.
+/// ```
+pub struct PingPong {
+    targets: [Framebuffer; 2],
+    read: usize,
+}
+
+impl PingPong {
+    /// Creates two ```width``` x ```height``` framebuffers.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { targets: [Framebuffer::new(width, height), Framebuffer::new(width, height)], read: 0 }
+    }
+
+    /// Swaps targets, so the one that was just written becomes the one to read.
+    pub fn swap(&mut self) {
+        self.read = 1 - self.read;
+    }
+    /// Gets the framebuffer to read from (the latest result).
+    pub fn read(&self) -> &Framebuffer {
+        &self.targets[self.read]
+    }
+    /// Gets the framebuffer to render into.
+    pub fn write(&self) -> &Framebuffer {
+        &self.targets[1 - self.read]
+    }
+
+    /// Resizes both framebuffers. All the previous content is lost.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        for target in &mut self.targets {
+            target.resize(width, height);
+        }
+    }
+    /// Gets width of both framebuffers in pixels.
+    pub fn get_width(&self) -> u32 {
+        self.targets[0].get_width()
+    }
+    /// Gets height of both framebuffers in pixels.
+    pub fn get_height(&self) -> u32 {
+        self.targets[0].get_height()
+    }
+}
+
+/// A registry of named [Framebuffer]s sized relatively to the window (e.g. "half-res" = 0.5 scale).
+/// [crate::window::Window] owns one and resizes all of them automatically when the window is resized.
+/// # Example
+/// ```rust
+/// window.register_render_target("scene", 1.0);
+/// window.register_render_target("bloom", 0.5); // Half of the window resolution.
+///
+/// while window.is_running() {
+///     window.poll_events();
+///     window.get_render_target("scene").unwrap().bind();
+///     ...
+/// }
+/// ```
+#[derive(Default)]
+pub struct RenderTargets {
+    targets: HashMap<String, (f32, Framebuffer)>,
+    width: u32,
+    height: u32,
+}
+
+impl RenderTargets {
+    /// Creates an empty registry for a ```width``` x ```height``` window.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { targets: HashMap::new(), width, height }
+    }
+
+    /// Creates a framebuffer with window size multiplied by ```scale``` and stores it under ```name```.
+    /// If there's already one with the same name, it's replaced.
+    pub fn register(&mut self, name: &str, scale: f32) {
+        let (width, height) = Self::scaled(self.width, self.height, scale);
+        self.targets.insert(String::from(name), (scale, Framebuffer::new(width, height)));
+    }
+    /// Removes a framebuffer stored under ```name``` and returns it.
+    pub fn unregister(&mut self, name: &str) -> Option<Framebuffer> {
+        self.targets.remove(name).map(|(_, framebuffer)| framebuffer)
+    }
+    /// Gets a framebuffer stored under ```name```.
+    pub fn get(&self, name: &str) -> Option<&Framebuffer> {
+        self.targets.get(name).map(|(_, framebuffer)| framebuffer)
+    }
+
+    /// Resizes all framebuffers according to their scales. Called by the window automatically.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+
+        for (scale, framebuffer) in self.targets.values_mut() {
+            let (width, height) = Self::scaled(width, height, *scale);
+            framebuffer.resize(width, height);
+        }
+    }
+
+    /// Creates all framebuffers again, used after the OpenGL context was lost.
+    /// Old objects died with the context and aren't deleted when dropped (their ids could belong to new objects).
+    pub(crate) fn recreate(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+
+        for (scale, framebuffer) in self.targets.values_mut() {
+            let (width, height) = Self::scaled(self.width, self.height, *scale);
+            *framebuffer = Framebuffer::new(width, height);
+        }
+    }
+
+    fn scaled(width: u32, height: u32, scale: f32) -> (u32, u32) {
+        ((width as f32 * scale).round() as u32, (height as f32 * scale).round() as u32)
+    }
+}
@@ -0,0 +1,303 @@
+use nalgebra::{UnitQuaternion, Unit, Vector3};
+
+use crate::{
+    camera::Ray,
+    mesh::{Attribute, Layout, Mesh},
+};
+
+const RING_SEGMENTS: usize = 48;
+
+/// What a [Gizmo] edits.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+/// A change made by dragging a [Gizmo] during one [Gizmo::update]. Apply it to the edited object.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GizmoDelta {
+    /// Offset to add to the position.
+    Translate(Vector3<f32>),
+    /// Rotation to apply on top of the current one (```rotation = delta * rotation```).
+    Rotate(UnitQuaternion<f32>),
+    /// Factors to multiply the scale by, 1.0 for axes that didn't change.
+    Scale(Vector3<f32>),
+}
+
+struct Drag {
+    axis: usize,
+    // Gizmo position when dragging started, so translation is measured from a fixed point.
+    origin: Vector3<f32>,
+    // Mouse parameter at start: distance along the axis or angle on the ring.
+    start: f32,
+    // Snapped value already returned as deltas.
+    applied: f32,
+}
+
+/// A translate/rotate/scale manipulator for level editors: X, Y and Z handles hit-tested with a mouse [Ray] and dragged with optional snapping.
+/// It doesn't own the edited object, it returns [GizmoDelta]s for you to apply.
+///
+/// Handles are ```size``` world units long, scale it with camera distance to keep the gizmo the same size on screen.
+/// Axes are red (X), green (Y) and blue (Z), the hovered or dragged one is yellow.
+/// # Example
+/// ```rust
+/// use tinystorm::{camera::Ray, gizmo::{Gizmo, GizmoDelta, GizmoMode}};
+///
+/// let mut gizmo = Gizmo::new(GizmoMode::Translate).with_translate_snap(0.5).with_rotate_snap(15.0_f32.to_radians());
+///
+/// while window.is_running() {
+///     window.poll_events();
+///     let ray = Ray::from_screen(&mouse, &screen_size, &view, &projection);
+///
+///     gizmo.set_position(selected.position);
+///     gizmo.set_size((camera.position - selected.position).norm() * 0.15);
+///     match gizmo.update(&ray, window.is_mouse_button_pressed(glfw::MouseButton::Button1)) {
+///         Some(GizmoDelta::Translate(offset)) => selected.position += offset,
+///         Some(GizmoDelta::Rotate(rotation)) => selected.rotation = rotation * selected.rotation,
+///         Some(GizmoDelta::Scale(factors)) => selected.scale.component_mul_assign(&factors),
+///         None => {}
+///     }
+///
+///     // Draw the scene, then the gizmo on top.
+///     gizmo_shader.bind();
+///     gizmo.debug_draw();
+/// }
+/// ```
+pub struct Gizmo {
+    mode: GizmoMode,
+    position: Vector3<f32>,
+    size: f32,
+    translate_snap: f32,
+    rotate_snap: f32,
+    scale_snap: f32,
+
+    hovered: Option<usize>,
+    drag: Option<Drag>,
+}
+
+impl Gizmo {
+    /// Creates a gizmo at the origin with 1.0 long handles and no snapping.
+    pub fn new(mode: GizmoMode) -> Self {
+        Self {
+            mode,
+            position: Vector3::zeros(),
+            size: 1.0,
+            translate_snap: 0.0,
+            rotate_snap: 0.0,
+            scale_snap: 0.0,
+            hovered: None,
+            drag: None,
+        }
+    }
+    /// Snaps translation to multiples of ```step``` world units. 0.0 disables snapping.
+    pub fn with_translate_snap(mut self, step: f32) -> Self {
+        self.translate_snap = step;
+        self
+    }
+    /// Snaps rotation to multiples of ```step``` radians. 0.0 disables snapping.
+    pub fn with_rotate_snap(mut self, step: f32) -> Self {
+        self.rotate_snap = step;
+        self
+    }
+    /// Snaps scale factor to multiples of ```step```. 0.0 disables snapping.
+    pub fn with_scale_snap(mut self, step: f32) -> Self {
+        self.scale_snap = step;
+        self
+    }
+
+    /// Hit-tests handles with mouse ```ray``` and drags the grabbed one while ```pressed``` is ```true```.
+    /// Returns a change since the last call, or ```None``` if nothing changed. Call it once per frame.
+    pub fn update(&mut self, ray: &Ray, pressed: bool) -> Option<GizmoDelta> {
+        if !pressed {
+            self.drag = None;
+            self.hovered = self.hit_test(ray);
+            return None;
+        }
+
+        let Some(drag) = &self.drag else {
+            // Dragging starts only if the button was pressed over a handle.
+            let axis = self.hovered.take()?;
+            let start = self.drag_parameter(ray, axis, &self.position)?;
+            self.drag = Some(Drag { axis, origin: self.position, start, applied: if self.mode == GizmoMode::Scale { 1.0 } else { 0.0 } });
+            self.hovered = Some(axis);
+            return None;
+        };
+
+        let (axis, origin, start, applied) = (drag.axis, drag.origin, drag.start, drag.applied);
+        let current = self.drag_parameter(ray, axis, &origin)?;
+        let direction = Vector3::ith(axis, 1.0);
+
+        let (value, delta) = match self.mode {
+            GizmoMode::Translate => {
+                let value = snap(current - start, self.translate_snap);
+                let offset = direction * (value - applied);
+                self.position += offset;
+                (value, GizmoDelta::Translate(offset))
+            }
+            GizmoMode::Rotate => {
+                let value = snap(wrap_angle(current - start), self.rotate_snap);
+                (value, GizmoDelta::Rotate(UnitQuaternion::from_axis_angle(&Unit::new_unchecked(direction), value - applied)))
+            }
+            GizmoMode::Scale => {
+                let value = snap(1.0 + (current - start) / self.size, self.scale_snap).max(0.01);
+                let mut factors = Vector3::repeat(1.0);
+                factors[axis] = value / applied;
+                (value, GizmoDelta::Scale(factors))
+            }
+        };
+
+        if value == applied {
+            return None;
+        }
+        if let Some(drag) = &mut self.drag {
+            drag.applied = value;
+        }
+        Some(delta)
+    }
+
+    /// Draws handles as lines. Position is at ```layout(location = 0)``` as ```vec3``` and color at ```layout(location = 1)``` as ```vec3```.
+    /// It creates a mesh every call, so use it only for editors and debugging. Disable depth test to draw it on top of the scene.
+    pub fn debug_draw(&self) {
+        let highlighted = self.drag.as_ref().map(|drag| drag.axis).or(self.hovered);
+        let mut vertices: Vec<f32> = Vec::new();
+
+        for axis in 0..3 {
+            let color = if highlighted == Some(axis) { Vector3::new(1.0, 0.9, 0.1) } else { Vector3::ith(axis, 1.0) };
+            let mut line = |a: Vector3<f32>, b: Vector3<f32>| {
+                vertices.extend_from_slice(&[a.x, a.y, a.z, color.x, color.y, color.z, b.x, b.y, b.z, color.x, color.y, color.z]);
+            };
+
+            let direction = Vector3::ith(axis, self.size);
+            match self.mode {
+                GizmoMode::Translate | GizmoMode::Scale => {
+                    let end = self.position + direction;
+                    line(self.position, end);
+                    // A small cross marks the handle end.
+                    let (u, v) = (Vector3::ith((axis + 1) % 3, self.size * 0.05), Vector3::ith((axis + 2) % 3, self.size * 0.05));
+                    line(end - u, end + u);
+                    line(end - v, end + v);
+                }
+                GizmoMode::Rotate => {
+                    let (u, v) = (Vector3::ith((axis + 1) % 3, self.size), Vector3::ith((axis + 2) % 3, self.size));
+                    for segment in 0..RING_SEGMENTS {
+                        let [a, b] = [segment, segment + 1].map(|i| {
+                            let (sin, cos) = (i as f32 / RING_SEGMENTS as f32 * std::f32::consts::TAU).sin_cos();
+                            self.position + u * cos + v * sin
+                        });
+                        line(a, b);
+                    }
+                }
+            }
+        }
+
+        let layout = Layout::default().next_attribute(Attribute::Vec3).next_attribute(Attribute::Vec3);
+        Mesh::new::<f32>(&vertices, &layout, gl::LINES).draw();
+    }
+
+    /// Sets what the gizmo edits. Cancels dragging.
+    pub fn set_mode(&mut self, mode: GizmoMode) {
+        self.mode = mode;
+        self.drag = None;
+    }
+    /// Moves the gizmo, usually to the selected object. Ignored while dragging, the gizmo moves by itself then.
+    pub fn set_position(&mut self, position: Vector3<f32>) {
+        if self.drag.is_none() {
+            self.position = position;
+        }
+    }
+    /// Sets handle length in world units.
+    pub fn set_size(&mut self, size: f32) {
+        self.size = size;
+    }
+
+    /// Gets what the gizmo edits.
+    pub fn get_mode(&self) -> GizmoMode {
+        self.mode
+    }
+    /// Gets gizmo position.
+    pub fn get_position(&self) -> Vector3<f32> {
+        self.position
+    }
+    /// Gets handle length in world units.
+    pub fn get_size(&self) -> f32 {
+        self.size
+    }
+    /// Gets the axis (0 - X, 1 - Y, 2 - Z) under the mouse or being dragged.
+    pub fn get_active_axis(&self) -> Option<usize> {
+        self.drag.as_ref().map(|drag| drag.axis).or(self.hovered)
+    }
+    /// Checks if a handle is being dragged. Use it to ignore the click for object selection.
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    // Closest handle to the ray, if it's close enough to grab.
+    fn hit_test(&self, ray: &Ray) -> Option<usize> {
+        let tolerance = self.size * 0.08;
+        let mut best: Option<(usize, f32)> = None;
+
+        for axis in 0..3 {
+            let direction = Vector3::ith(axis, 1.0);
+            let distance = match self.mode {
+                GizmoMode::Translate | GizmoMode::Scale => {
+                    let Some((along_ray, along_axis)) = closest_parameters(ray, &self.position, &direction) else { continue; };
+                    if along_ray < 0.0 || !(0.0..=self.size).contains(&along_axis) {
+                        continue;
+                    }
+                    (ray.at(along_ray) - (self.position + direction * along_axis)).norm()
+                }
+                GizmoMode::Rotate => {
+                    let Some(along_ray) = ray.intersect_plane(&self.position, &direction) else { continue; };
+                    ((ray.at(along_ray) - self.position).norm() - self.size).abs()
+                }
+            };
+
+            if distance <= tolerance && best.is_none_or(|(_, best_distance)| distance < best_distance) {
+                best = Some((axis, distance));
+            }
+        }
+
+        best.map(|(axis, _)| axis)
+    }
+    // Distance along the axis from origin (translate, scale) or angle around the axis (rotate) under the mouse.
+    fn drag_parameter(&self, ray: &Ray, axis: usize, origin: &Vector3<f32>) -> Option<f32> {
+        let direction = Vector3::ith(axis, 1.0);
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Scale => closest_parameters(ray, origin, &direction).map(|(_, along_axis)| along_axis),
+            GizmoMode::Rotate => {
+                let offset = ray.at(ray.intersect_plane(origin, &direction)?) - origin;
+                let (u, v) = (Vector3::ith((axis + 1) % 3, 1.0), Vector3::ith((axis + 2) % 3, 1.0));
+                Some(offset.dot(&v).atan2(offset.dot(&u)))
+            }
+        }
+    }
+}
+
+/// Rounds ```value``` to the nearest multiple of ```step```. 0.0 or negative ```step``` returns ```value``` as is.
+pub fn snap(value: f32, step: f32) -> f32 {
+    if step <= 0.0 { value } else { (value / step).round() * step }
+}
+/// Rounds every component of ```value``` to the nearest multiple of ```step```, e.g. to place objects on a grid.
+pub fn snap_vector(value: &Vector3<f32>, step: f32) -> Vector3<f32> {
+    value.map(|component| snap(component, step))
+}
+
+// Parameters of the closest points between the ray and an infinite line through point along direction,
+// or None if they're parallel.
+fn closest_parameters(ray: &Ray, point: &Vector3<f32>, direction: &Vector3<f32>) -> Option<(f32, f32)> {
+    let offset = ray.origin - point;
+    let cosine = ray.direction.dot(direction);
+    let denominator = 1.0 - cosine * cosine;
+    if denominator < 1e-6 {
+        return None;
+    }
+
+    let (ray_offset, line_offset) = (ray.direction.dot(&offset), direction.dot(&offset));
+    Some(((cosine * line_offset - ray_offset) / denominator, (line_offset - cosine * ray_offset) / denominator))
+}
+fn wrap_angle(angle: f32) -> f32 {
+    use std::f32::consts::{PI, TAU};
+    (angle + PI).rem_euclid(TAU) - PI
+}
@@ -0,0 +1,252 @@
+use gl::types::{GLboolean, GLenum, GLint, GLsizei, GLuint};
+
+use crate::{mesh::Mesh, render, shader::Shader};
+
+const VERTEX_SOURCE: &str = "#version 330 core
+layout(location = 0) in vec2 a_Position;
+
+void main() {
+    gl_Position = vec4(a_Position, 0.0, 1.0);
+}
+";
+// Base level of the source is always the level we read from, so every fetch uses lod 0.
+const REDUCE_SOURCE: &str = "#version 330 core
+out float f_Depth;
+
+uniform sampler2D u_Source;
+uniform bool u_Copy;
+uniform bool u_ReverseZ;
+
+float reduce(float a, float b) {
+    return u_ReverseZ ? min(a, b) : max(a, b);
+}
+float fetch(ivec2 coord, ivec2 size) {
+    return texelFetch(u_Source, min(coord, size - 1), 0).r;
+}
+
+void main() {
+    ivec2 coord = ivec2(gl_FragCoord.xy);
+    if (u_Copy) {
+        f_Depth = texelFetch(u_Source, coord, 0).r;
+        return;
+    }
+
+    ivec2 size = textureSize(u_Source, 0);
+    ivec2 base = coord * 2;
+    float depth = reduce(
+        reduce(fetch(base, size), fetch(base + ivec2(1, 0), size)),
+        reduce(fetch(base + ivec2(0, 1), size), fetch(base + ivec2(1, 1), size))
+    );
+
+    // With odd sizes the last column/row of the smaller level also has to cover one extra texel.
+    bool extra_x = (size.x & 1) == 1 && base.x + 2 == size.x - 1;
+    bool extra_y = (size.y & 1) == 1 && base.y + 2 == size.y - 1;
+    if (extra_x) {
+        depth = reduce(depth, reduce(fetch(base + ivec2(2, 0), size), fetch(base + ivec2(2, 1), size)));
+    }
+    if (extra_y) {
+        depth = reduce(depth, reduce(fetch(base + ivec2(0, 2), size), fetch(base + ivec2(1, 2), size)));
+    }
+    if (extra_x && extra_y) {
+        depth = reduce(depth, fetch(base + ivec2(2, 2), size));
+    }
+
+    f_Depth = depth;
+}
+";
+
+/// A hierarchical depth (aka. Hi-Z) pyramid: scene depth copied into an R32F texture,
+/// where every next mip level keeps the farthest depth of 2x2 texels of the previous one.
+/// It's used for GPU occlusion culling (see [crate::culling::InstanceCuller]) and screen-space reflections.
+/// # Example
+/// ```rust
+/// use tinystorm::{framebuffer::Framebuffer, hiz::DepthPyramid};
+///
+/// let scene = Framebuffer::new(window.get_width(), window.get_height());
+/// let mut pyramid = DepthPyramid::new(window.get_width(), window.get_height());
+///
+/// while window.is_running() {
+///     window.poll_events();
+///
+///     scene.bind();
+///     // Draw your scene here...
+///     Framebuffer::unbind(window.get_width(), window.get_height());
+///
+///     pyramid.build(scene.get_depth_texture_id());
+///     pyramid.bind(3); // Sample it in your shaders with textureLod/texelFetch.
+///     ...
+/// }
+/// ```
+pub struct DepthPyramid {
+    texture: GLuint,
+    framebuffer: GLuint,
+
+    shader: Shader,
+    mesh: Mesh,
+
+    width: u32,
+    height: u32,
+    levels: u32,
+    reverse_z: bool,
+    generation: u64,
+}
+
+impl DepthPyramid {
+    /// Creates a pyramid for a ```width``` x ```height``` depth texture with a full mip chain down to 1x1.
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut framebuffer = 0;
+        unsafe { gl::GenFramebuffers(1, &mut framebuffer); }
+
+        let mut pyramid = Self {
+            texture: 0,
+            framebuffer,
+
+            shader: Shader::from_source(VERTEX_SOURCE, REDUCE_SOURCE),
+            mesh: Mesh::fullscreen_triangle(),
+
+            width: 0,
+            height: 0,
+            levels: 0,
+            reverse_z: false,
+            generation: render::context_generation(),
+        };
+        pyramid.resize(width, height);
+        pyramid
+    }
+    /// Keeps the nearest depth instead of the farthest one, for scenes rendered with reversed depth (1.0 is near).
+    pub fn with_reverse_z(mut self, reverse_z: bool) -> Self {
+        self.reverse_z = reverse_z;
+        self
+    }
+
+    /// Recreates the mip chain for a new depth texture size. Does nothing if size hasn't changed.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if width == self.width && height == self.height {
+            return;
+        }
+
+        self.width = width;
+        self.height = height;
+        self.levels = 32 - width.max(height).leading_zeros();
+
+        unsafe {
+            if self.texture != 0 && render::is_current_context(self.generation) {
+                gl::DeleteTextures(1, &self.texture);
+            }
+
+            gl::GenTextures(1, &mut self.texture);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            for level in 0..self.levels {
+                let (level_width, level_height) = self.get_level_size(level);
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    level as GLint,
+                    gl::R32F as GLint,
+                    level_width as GLsizei,
+                    level_height as GLsizei,
+                    0,
+                    gl::RED,
+                    gl::FLOAT,
+                    std::ptr::null(),
+                );
+            }
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST_MIPMAP_NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, self.levels as GLint - 1);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    /// Rebuilds the whole pyramid from ```depth_texture``` (e.g. [crate::framebuffer::Framebuffer::get_depth_texture_id]).
+    /// Depth texture must have the same size as the pyramid. Call it once per frame after the scene is drawn.
+    /// Bound framebuffer, viewport, shader and depth test state are restored after that.
+    pub fn build(&self, depth_texture: GLuint) {
+        unsafe {
+            let mut previous_framebuffer: GLint = 0;
+            let mut previous_viewport: [GLint; 4] = [0; 4];
+            let mut previous_program: GLint = 0;
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous_framebuffer);
+            gl::GetIntegerv(gl::VIEWPORT, previous_viewport.as_mut_ptr());
+            gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut previous_program);
+            let depth_test: GLboolean = gl::IsEnabled(gl::DEPTH_TEST);
+
+            gl::Disable(gl::DEPTH_TEST);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+
+            self.shader.bind();
+            self.shader.set_int("u_Source", 0);
+            self.shader.set_bool("u_ReverseZ", self.reverse_z);
+            gl::ActiveTexture(gl::TEXTURE0);
+
+            for level in 0..self.levels {
+                let (width, height) = self.get_level_size(level);
+                gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, self.texture, level as GLint);
+                gl::Viewport(0, 0, width as GLsizei, height as GLsizei);
+
+                if level == 0 {
+                    gl::BindTexture(gl::TEXTURE_2D, depth_texture);
+                    self.shader.set_bool("u_Copy", true);
+                } else {
+                    // Reading only the previous level makes it legal to write the current one of the same texture.
+                    gl::BindTexture(gl::TEXTURE_2D, self.texture);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_BASE_LEVEL, level as GLint - 1);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, level as GLint - 1);
+                    self.shader.set_bool("u_Copy", false);
+                }
+
+                self.mesh.draw();
+            }
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_BASE_LEVEL, 0);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, self.levels as GLint - 1);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous_framebuffer as GLuint);
+            gl::Viewport(previous_viewport[0], previous_viewport[1], previous_viewport[2], previous_viewport[3]);
+            gl::UseProgram(previous_program as GLuint);
+            if depth_test == gl::TRUE { gl::Enable(gl::DEPTH_TEST); }
+        }
+    }
+
+    /// Binds the pyramid texture to certain slot, just like [crate::texture::Texture::bind].
+    pub fn bind(&self, slot: GLenum) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + slot);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+        }
+        render::count_texture_bind();
+    }
+
+    /// Checks if the pyramid keeps the nearest depth, see [DepthPyramid::with_reverse_z].
+    pub fn is_reverse_z(&self) -> bool {
+        self.reverse_z
+    }
+    /// Gets number of mip levels in the pyramid.
+    pub fn get_levels(&self) -> u32 {
+        self.levels
+    }
+    /// Gets size of certain mip ```level``` in pixels.
+    pub fn get_level_size(&self, level: u32) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+    /// Gets OpenGL id of the pyramid texture for more precise control.
+    pub fn get_texture_id(&self) -> GLuint {
+        self.texture
+    }
+}
+impl Drop for DepthPyramid {
+    /// You don't need to manually free OpenGL resources, it's done automatically.
+    fn drop(&mut self) {
+        if !render::is_current_context(self.generation) {
+            return;
+        }
+        unsafe {
+            gl::DeleteTextures(1, &self.texture);
+            gl::DeleteFramebuffers(1, &self.framebuffer);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use gl::types::{GLboolean, GLenum, GLfloat, GLint, GLsizei, GLsizeiptr, GLuint};
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+
+use crate::{bvh::Aabb, framebuffer::Framebuffer, render, shader::Shader};
+
+const VERTEX_SOURCE: &str = "#version 330 core
+layout(location = 0) in vec2 a_Corner;
+layout(location = 1) in vec4 a_Instance;
+
+out vec2 v_Uv;
+
+uniform mat4 u_ViewProjection;
+uniform vec3 u_CameraPosition;
+uniform vec3 u_Center;
+uniform float u_Radius;
+uniform int u_Views;
+uniform int u_Columns;
+
+void main() {
+    vec3 center = a_Instance.xyz + u_Center * a_Instance.w;
+    vec3 toCamera = vec3(u_CameraPosition.x - center.x, 0.0, u_CameraPosition.z - center.z);
+    toCamera = dot(toCamera, toCamera) > 1e-8 ? normalize(toCamera) : vec3(0.0, 0.0, 1.0);
+
+    // The same view order as in Imposter::bake.
+    float angle = atan(toCamera.x, toCamera.z);
+    int view = int(floor(angle / 6.28318530718 * float(u_Views) + 0.5));
+    view = ((view % u_Views) + u_Views) % u_Views;
+
+    vec3 right = vec3(toCamera.z, 0.0, -toCamera.x);
+    vec3 world = center + (right * a_Corner.x + vec3(0.0, a_Corner.y, 0.0)) * u_Radius * 2.0 * a_Instance.w;
+
+    v_Uv = (vec2(view % u_Columns, view / u_Columns) + a_Corner + 0.5) / float(u_Columns);
+    gl_Position = u_ViewProjection * vec4(world, 1.0);
+}
+";
+const FRAGMENT_SOURCE: &str = "#version 330 core
+in vec2 v_Uv;
+out vec4 f_Color;
+
+uniform sampler2D u_Atlas;
+
+void main() {
+    vec4 color = texture(u_Atlas, v_Uv);
+    if (color.a < 0.5) {
+        discard;
+    }
+    f_Color = vec4(color.rgb, 1.0);
+}
+";
+
+/// A flat stand-in for a detailed mesh far away (trees of a forest, people of a crowd): the mesh is rendered
+/// from ```views``` directions around the Y axis into an atlas once, then drawn as camera-facing quads
+/// showing the closest baked view. Thousands of them are drawn with a single instanced call.
+///
+/// Quads turn only around the Y axis, so imposters look right from the side and at moderate angles, not from straight above.
+/// Lighting is baked in, draw the mesh with the lighting you want while baking.
+/// # Example
+/// ```rust
+/// use tinystorm::imposter::Imposter;
+///
+/// let tree_data = MeshData::lathe(&tree_profile, 16);
+/// let tree = tree_data.build();
+///
+/// // 16 views, 256x256 pixels each. The closure draws the mesh with a given view-projection matrix.
+/// let imposter = Imposter::bake(&tree_data.get_bounds(), 16, 256, |view_projection| {
+///     tree_shader.bind();
+///     tree_shader.set_mat4("u_ViewProjection", view_projection);
+///     tree.draw();
+/// });
+///
+/// // Far trees: XYZ - position, W - scale.
+/// let far_trees: Vec<Vector4<f32>> = trees.iter().filter(|tree| tree.distance > 80.0).map(|tree| tree.instance).collect();
+/// imposter.draw(&(projection * view), &camera_position, &far_trees);
+/// ```
+pub struct Imposter {
+    atlas: Framebuffer,
+    shader: Shader,
+    vao: GLuint,
+    quad_buffer: GLuint,
+    instance_buffer: GLuint,
+
+    views: u32,
+    columns: u32,
+    frame_size: u32,
+    center: Vector3<f32>,
+    radius: f32,
+    generation: u64,
+}
+
+impl Imposter {
+    /// Renders a mesh with ```bounds``` (in its local space) from ```views``` directions around the Y axis
+    /// into an atlas of ```frame_size``` x ```frame_size``` frames.
+    /// ```draw``` is called once per view with an orthographic view-projection matrix, it must bind a shader and draw the mesh
+    /// with alpha 1.0 (the background is transparent). Previously bound framebuffer, viewport and render state are restored.
+    pub fn bake(bounds: &Aabb, views: u32, frame_size: u32, mut draw: impl FnMut(&Matrix4<f32>)) -> Self {
+        if views == 0 || bounds.is_empty() {
+            panic!("Failed to bake imposter. Got {} views and {:?} bounds, but at least 1 view and non-empty bounds are required.", views, bounds);
+        }
+
+        let columns = (views as f32).sqrt().ceil() as u32;
+        let center = bounds.get_center();
+        let radius = (bounds.get_size().norm() * 0.5).max(f32::EPSILON);
+        let atlas = Framebuffer::new(columns * frame_size, columns * frame_size);
+
+        unsafe {
+            let mut previous_framebuffer: GLint = 0;
+            let mut previous_viewport: [GLint; 4] = [0; 4];
+            let mut clear_color: [GLfloat; 4] = [0.0; 4];
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous_framebuffer);
+            gl::GetIntegerv(gl::VIEWPORT, previous_viewport.as_mut_ptr());
+            gl::GetFloatv(gl::COLOR_CLEAR_VALUE, clear_color.as_mut_ptr());
+            let depth_test: GLboolean = gl::IsEnabled(gl::DEPTH_TEST);
+            let blend: GLboolean = gl::IsEnabled(gl::BLEND);
+
+            atlas.bind();
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+
+            let projection = Matrix4::new_orthographic(-radius, radius, -radius, radius, radius, radius * 3.0);
+            for view in 0..views {
+                let (x, y) = (view % columns, view / columns);
+                gl::Viewport((x * frame_size) as GLint, (y * frame_size) as GLint, frame_size as GLsizei, frame_size as GLsizei);
+
+                let (sin, cos) = (view as f32 / views as f32 * std::f32::consts::TAU).sin_cos();
+                let eye = center + Vector3::new(sin, 0.0, cos) * radius * 2.0;
+                let look_at = Matrix4::look_at_rh(&Point3::from(eye), &Point3::from(center), &Vector3::y());
+                draw(&(projection * look_at));
+            }
+
+            if depth_test != gl::TRUE { gl::Disable(gl::DEPTH_TEST); }
+            if blend == gl::TRUE { gl::Enable(gl::BLEND); }
+            gl::ClearColor(clear_color[0], clear_color[1], clear_color[2], clear_color[3]);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous_framebuffer as GLuint);
+            gl::Viewport(previous_viewport[0], previous_viewport[1], previous_viewport[2], previous_viewport[3]);
+        }
+
+        let (mut vao, mut quad_buffer, mut instance_buffer) = (0, 0, 0);
+        let corners: [f32; 8] = [-0.5, -0.5, 0.5, -0.5, 0.5, 0.5, -0.5, 0.5];
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+
+            gl::GenBuffers(1, &mut quad_buffer);
+            gl::BindBuffer(gl::ARRAY_BUFFER, quad_buffer);
+            gl::BufferData(gl::ARRAY_BUFFER, size_of_val(&corners) as GLsizeiptr, corners.as_ptr() as *const _, gl::STATIC_DRAW);
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+
+            gl::GenBuffers(1, &mut instance_buffer);
+            gl::BindBuffer(gl::ARRAY_BUFFER, instance_buffer);
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(1, 4, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+            gl::VertexAttribDivisor(1, 1);
+
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        render::count_buffer_upload(size_of_val(&corners));
+
+        Self {
+            atlas,
+            shader: Shader::from_source(VERTEX_SOURCE, FRAGMENT_SOURCE),
+            vao,
+            quad_buffer,
+            instance_buffer,
+            views,
+            columns,
+            frame_size,
+            center,
+            radius,
+            generation: render::context_generation(),
+        }
+    }
+
+    /// Draws imposters at ```instances```: XYZ is the mesh origin in world space, W is its scale. Uses its own shader and texture slot 0.
+    pub fn draw(&self, view_projection: &Matrix4<f32>, camera_position: &Vector3<f32>, instances: &[Vector4<f32>]) {
+        if instances.is_empty() {
+            return;
+        }
+
+        self.shader.bind();
+        self.shader.set_mat4("u_ViewProjection", view_projection);
+        self.shader.set_vec3("u_CameraPosition", camera_position);
+        self.shader.set_vec3("u_Center", &self.center);
+        self.shader.set_float("u_Radius", self.radius);
+        self.shader.set_int("u_Views", self.views as i32);
+        self.shader.set_int("u_Columns", self.columns as i32);
+        self.shader.set_int("u_Atlas", 0);
+        self.atlas.bind_color_texture(0);
+
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.instance_buffer);
+            gl::BufferData(gl::ARRAY_BUFFER, size_of_val(instances) as GLsizeiptr, instances.as_ptr() as *const _, gl::STREAM_DRAW);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            gl::BindVertexArray(self.vao);
+            gl::DrawArraysInstanced(gl::TRIANGLE_FAN, 0, 4, instances.len() as GLsizei);
+        }
+        render::count_buffer_upload(size_of_val(instances));
+        render::count_draw(gl::TRIANGLES, instances.len() * 6);
+    }
+
+    /// Gets index of the baked view used for an imposter at ```position``` seen from ```camera_position```, the same way the shader picks it.
+    pub fn get_view(&self, position: &Vector3<f32>, camera_position: &Vector3<f32>) -> u32 {
+        let to_camera = camera_position - (position + self.center);
+        let angle = to_camera.x.atan2(to_camera.z);
+        let view = (angle / std::f32::consts::TAU * self.views as f32 + 0.5).floor() as i64;
+        view.rem_euclid(self.views as i64) as u32
+    }
+    /// Binds the atlas to certain slot, e.g. to blend imposters with real meshes in your own shader.
+    pub fn bind_atlas(&self, slot: GLenum) {
+        self.atlas.bind_color_texture(slot);
+    }
+    /// Gets the atlas framebuffer. View ```i``` is in column ```i % columns``` and row ```i / columns``` from the bottom-left corner.
+    pub fn get_atlas(&self) -> &Framebuffer {
+        &self.atlas
+    }
+    /// Gets number of baked views.
+    pub fn get_views(&self) -> u32 {
+        self.views
+    }
+    /// Gets number of atlas columns (and rows).
+    pub fn get_columns(&self) -> u32 {
+        self.columns
+    }
+    /// Gets size of one view in pixels.
+    pub fn get_frame_size(&self) -> u32 {
+        self.frame_size
+    }
+}
+impl Drop for Imposter {
+    /// You don't need to manually free OpenGL resources, it's done automatically.
+    fn drop(&mut self) {
+        if !render::is_current_context(self.generation) {
+            return;
+        }
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteBuffers(1, &self.quad_buffer);
+            gl::DeleteBuffers(1, &self.instance_buffer);
+        }
+    }
+}
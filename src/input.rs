@@ -0,0 +1,314 @@
+use nalgebra::{Vector2, Vector4};
+
+/// A copy of the window input state at certain frame. See [crate::window::Window::input_snapshot].
+///
+/// It doesn't borrow the window, so you can pass it to your game systems by value
+/// while the window is still used by rendering code.
+/// # Example
+/// ```rust
+/// use tinystorm::input::InputState;
+///
+/// fn update_player(player: &mut Player, input: InputState, delta: f32) {
+///     if input.is_key_pressed(Key::W) {
+///         player.position.z -= player.speed * delta;
+///     }
+/// }
+///
+/// while window.is_running() {
+///     window.poll_events();
+///     update_player(&mut player, window.input_snapshot(), window.get_delta());
+///     ...
+/// }
+/// ```
+#[derive(Clone, Copy)]
+pub struct InputState {
+    pub(crate) keys: [u64; glfw::ffi::KEY_LAST as usize + 1],
+    pub(crate) mouse_buttons: [u64; glfw::ffi::MOUSE_BUTTON_LAST as usize + 1],
+    pub(crate) current_frame: u64,
+
+    pub(crate) mouse_x: f32,
+    pub(crate) mouse_y: f32,
+    pub(crate) mouse_dx: f32,
+    pub(crate) mouse_dy: f32,
+    pub(crate) scroll_dx: f32,
+    pub(crate) scroll_dy: f32,
+}
+
+impl InputState {
+    /// Checks if specific key was pressed. Same as [crate::window::Window::is_key_pressed].
+    pub fn is_key_pressed(&self, key: glfw::Key) -> bool {
+        self.keys[key as usize] > 0
+    }
+    /// Checks if specific key was just pressed. Same as [crate::window::Window::is_key_just_pressed].
+    pub fn is_key_just_pressed(&self, key: glfw::Key) -> bool {
+        self.keys[key as usize] == self.current_frame
+    }
+    /// Checks if specific mouse button was pressed. Same as [crate::window::Window::is_mouse_button_pressed].
+    pub fn is_mouse_button_pressed(&self, button: glfw::MouseButton) -> bool {
+        self.mouse_buttons[button as usize] > 0
+    }
+    /// Checks if specific mouse button was just pressed. Same as [crate::window::Window::is_mouse_button_just_pressed].
+    pub fn is_mouse_button_just_pressed(&self, button: glfw::MouseButton) -> bool {
+        self.mouse_buttons[button as usize] == self.current_frame
+    }
+
+    /// Gets mouse cursor X position in pixels from top-left corner relative to window.
+    pub fn get_mouse_x(&self) -> f32 {
+        self.mouse_x
+    }
+    /// Gets mouse cursor Y position in pixels from top-left corner relative to window.
+    pub fn get_mouse_y(&self) -> f32 {
+        self.mouse_y
+    }
+    /// Gets horizontal mouse cursor movement in pixels.
+    pub fn get_mouse_dx(&self) -> f32 {
+        self.mouse_dx
+    }
+    /// Gets vertical mouse cursor movement in pixels.
+    pub fn get_mouse_dy(&self) -> f32 {
+        self.mouse_dy
+    }
+    /// Gets horizontal scroll offset.
+    pub fn get_scroll_dx(&self) -> f32 {
+        self.scroll_dx
+    }
+    /// Gets vertical scroll offset.
+    pub fn get_scroll_dy(&self) -> f32 {
+        self.scroll_dy
+    }
+}
+
+/// Filtering applied to a gamepad axis before it's reported by [crate::window::Window::get_gamepad_axis].
+/// # Example
+/// ```rust
+/// use tinystorm::{input::GamepadAxisConfig, glfw::GamepadAxis};
+///
+/// // Precise aiming: bigger deadzone for worn sticks and a quadratic response curve.
+/// let aim = GamepadAxisConfig::default().with_deadzone(0.2).with_exponent(2.0);
+/// window.set_gamepad_axis_config(GamepadAxis::AxisRightX, aim);
+/// window.set_gamepad_axis_config(GamepadAxis::AxisRightY, aim);
+/// ```
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GamepadAxisConfig {
+    deadzone: f32,
+    saturation: f32,
+    exponent: f32,
+}
+
+impl GamepadAxisConfig {
+    /// Sets axis magnitude below which the value is reported as 0.0 (stick drift).
+    pub fn with_deadzone(mut self, deadzone: f32) -> Self {
+        self.deadzone = deadzone;
+        self
+    }
+    /// Sets axis magnitude above which the value is reported as 1.0 (sticks that never reach the edge).
+    pub fn with_saturation(mut self, saturation: f32) -> Self {
+        self.saturation = saturation;
+        self
+    }
+    /// Sets response curve exponent. 1.0 is linear, bigger values give more precision near the center.
+    pub fn with_exponent(mut self, exponent: f32) -> Self {
+        self.exponent = exponent;
+        self
+    }
+
+    /// Applies deadzone, saturation and response curve to a raw axis ```value```, keeping its sign.
+    pub fn apply(&self, value: f32) -> f32 {
+        let magnitude = value.abs();
+        if magnitude <= self.deadzone {
+            return 0.0;
+        }
+
+        let range = (self.saturation - self.deadzone).max(f32::EPSILON);
+        let normalized = ((magnitude - self.deadzone) / range).clamp(0.0, 1.0);
+        normalized.powf(self.exponent).copysign(value)
+    }
+
+    /// Gets deadzone of the axis.
+    pub fn get_deadzone(&self) -> f32 {
+        self.deadzone
+    }
+    /// Gets saturation of the axis.
+    pub fn get_saturation(&self) -> f32 {
+        self.saturation
+    }
+    /// Gets response curve exponent of the axis.
+    pub fn get_exponent(&self) -> f32 {
+        self.exponent
+    }
+}
+
+/// # Default values
+/// ```
+/// deadzone: 0.1  
+/// saturation: 1.0  
+/// exponent: 1.0
+/// ```
+impl Default for GamepadAxisConfig {
+    fn default() -> Self {
+        Self { deadzone: 0.1, saturation: 1.0, exponent: 1.0 }
+    }
+}
+
+/// A mouse cursor moved by a gamepad stick, so mouse-driven menus work from the couch. See [crate::window::Window::set_virtual_cursor].
+///
+/// It feeds the regular mouse API: [crate::window::Window::get_mouse_x], [crate::window::Window::get_mouse_y] and
+/// the click button reported as ```MouseButton::Left```. The real mouse keeps working and takes over as soon as it moves.
+/// The cursor speeds up while the stick is held and, once the stick is released, snaps to the center of the nearest interactive rect
+/// (see [crate::window::Window::set_interactive_rects]), so small buttons are easy to hit.
+/// # Example
+/// ```rust
+/// use tinystorm::input::VirtualCursor;
+///
+/// window.set_virtual_cursor(Some(VirtualCursor::default().with_speed(500.0)));
+/// window.set_interactive_rects(&menu.buttons.iter().map(|button| button.rect).collect::<Vec<_>>());
+///
+/// // Works the same with a mouse and a gamepad.
+/// if window.is_mouse_button_just_pressed(MouseButton::Left) {
+///     menu.click(window.get_mouse_x(), window.get_mouse_y());
+/// }
+/// ```
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct VirtualCursor {
+    gamepad: glfw::JoystickId,
+    axes: (glfw::GamepadAxis, glfw::GamepadAxis),
+    click_button: glfw::GamepadButton,
+    speed: f32,
+    acceleration: f32,
+    max_speed: f32,
+    snap_radius: f32,
+    snap_speed: f32,
+
+    // Time the stick has been held, for acceleration.
+    move_time: f32,
+    // Whether the gamepad (not the mouse) moved the cursor last, snapping is done only then.
+    active: bool,
+    clicking: bool,
+}
+
+impl VirtualCursor {
+    /// Sets gamepad that moves the cursor.
+    pub fn with_gamepad(mut self, gamepad: glfw::JoystickId) -> Self {
+        self.gamepad = gamepad;
+        self
+    }
+    /// Sets horizontal and vertical axes that move the cursor. Filtered by their [GamepadAxisConfig].
+    pub fn with_axes(mut self, x: glfw::GamepadAxis, y: glfw::GamepadAxis) -> Self {
+        self.axes = (x, y);
+        self
+    }
+    /// Sets gamepad button reported as the left mouse button.
+    pub fn with_click_button(mut self, button: glfw::GamepadButton) -> Self {
+        self.click_button = button;
+        self
+    }
+    /// Sets cursor speed in pixels per second at full stick tilt, right after the stick is pushed.
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+    /// Sets how fast cursor speed grows while the stick is held, in pixels per second squared, up to ```max_speed```.
+    pub fn with_acceleration(mut self, acceleration: f32, max_speed: f32) -> Self {
+        self.acceleration = acceleration;
+        self.max_speed = max_speed;
+        self
+    }
+    /// Sets how far (in pixels) an interactive rect can be to attract the released cursor, and how fast it moves there
+    /// (fraction of the remaining distance per 1/60 s). Radius 0.0 disables snapping.
+    pub fn with_snapping(mut self, radius: f32, speed: f32) -> Self {
+        self.snap_radius = radius;
+        self.snap_speed = speed;
+        self
+    }
+
+    /// Gets gamepad that moves the cursor.
+    pub fn get_gamepad(&self) -> glfw::JoystickId {
+        self.gamepad
+    }
+    /// Gets horizontal and vertical axes that move the cursor.
+    pub fn get_axes(&self) -> (glfw::GamepadAxis, glfw::GamepadAxis) {
+        self.axes
+    }
+    /// Gets gamepad button reported as the left mouse button.
+    pub fn get_click_button(&self) -> glfw::GamepadButton {
+        self.click_button
+    }
+    /// Checks if the gamepad moved the cursor last (and not the mouse). Useful to show button prompts instead of mouse ones.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Moves cursor at ```position``` by filtered ```stick``` value over ```delta``` seconds, snapping it to ```rects```
+    /// (XY is top-left corner, ZW is size, both in pixels) when the stick is released. Returns the new position.
+    pub(crate) fn step(&mut self, position: Vector2<f32>, stick: Vector2<f32>, delta: f32, rects: &[Vector4<f32>]) -> Vector2<f32> {
+        let tilt = stick.norm().min(1.0);
+        if tilt > 0.0 {
+            self.active = true;
+            self.move_time += delta;
+            let speed = (self.speed + self.acceleration * self.move_time).min(self.max_speed.max(self.speed));
+            return position + stick / stick.norm().max(1.0) * speed * delta;
+        }
+        self.move_time = 0.0;
+        if !self.active || self.snap_radius <= 0.0 {
+            return position;
+        }
+
+        let target = rects.iter()
+            .map(|rect| {
+                let closest = Vector2::new(position.x.clamp(rect.x, rect.x + rect.z), position.y.clamp(rect.y, rect.y + rect.w));
+                ((closest - position).norm(), Vector2::new(rect.x + rect.z * 0.5, rect.y + rect.w * 0.5))
+            })
+            .filter(|(distance, _)| *distance <= self.snap_radius)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, center)| center);
+        let Some(target) = target else { return position; };
+
+        // Frame rate independent exponential approach, stops at the center instead of creeping forever.
+        let factor = 1.0 - (1.0 - self.snap_speed.clamp(0.0, 1.0)).powf(delta * 60.0);
+        let moved = position + (target - position) * factor;
+        if (target - moved).norm() < 0.5 { target } else { moved }
+    }
+    // Called when the real mouse moved, so it takes over the cursor.
+    pub(crate) fn deactivate(&mut self) {
+        self.active = false;
+        self.move_time = 0.0;
+    }
+    // Updates click state from the click button, returns Some(pressed) when it changed.
+    pub(crate) fn update_click(&mut self, pressed: bool) -> Option<bool> {
+        if pressed == self.clicking {
+            return None;
+        }
+        self.clicking = pressed;
+        Some(pressed)
+    }
+}
+
+/// # Default values
+/// ```
+/// gamepad: JoystickId::Joystick1  
+/// axes: (GamepadAxis::AxisLeftX, GamepadAxis::AxisLeftY)  
+/// click_button: GamepadButton::ButtonA  
+/// speed: 400.0  
+/// acceleration: 800.0  
+/// max_speed: 1600.0  
+/// snap_radius: 48.0  
+/// snap_speed: 0.25
+/// ```
+impl Default for VirtualCursor {
+    fn default() -> Self {
+        Self {
+            gamepad: glfw::JoystickId::Joystick1,
+            axes: (glfw::GamepadAxis::AxisLeftX, glfw::GamepadAxis::AxisLeftY),
+            click_button: glfw::GamepadButton::ButtonA,
+            speed: 400.0,
+            acceleration: 800.0,
+            max_speed: 1600.0,
+            snap_radius: 48.0,
+            snap_speed: 0.25,
+
+            move_time: 0.0,
+            active: false,
+            clicking: false,
+        }
+    }
+}
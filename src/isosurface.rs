@@ -0,0 +1,216 @@
+use nalgebra::Vector3;
+
+use crate::mesh::MeshData;
+
+/// A 3D grid of scalar values (density) that can be turned into a smooth mesh with surface nets.
+/// Values above the iso level are inside the surface. Good for smooth voxel terrain, caves and metaballs.
+/// # Example
+/// ```rust
+/// use tinystorm::{isosurface::ScalarField, nalgebra::Vector3};
+///
+/// // Two metaballs.
+/// let balls = [Vector3::new(10.0, 10.0, 10.0), Vector3::new(16.0, 12.0, 10.0)];
+/// let field = ScalarField::from_fn(32, 32, 32, |x, y, z| {
+///     let point = Vector3::new(x as f32, y as f32, z as f32);
+///     balls.iter().map(|ball| 16.0 / (point - ball).norm_squared().max(0.001)).sum()
+/// });
+///
+/// let mesh = field.polygonize(1.0).build(); // Positions are in grid units.
+/// ```
+pub struct ScalarField {
+    width: usize,
+    height: usize,
+    depth: usize,
+    values: Vec<f32>,
+}
+
+impl ScalarField {
+    /// Creates a ```width``` x ```height``` x ```depth``` field filled with zeros.
+    pub fn new(width: usize, height: usize, depth: usize) -> Self {
+        Self { width, height, depth, values: vec![0.0; width * height * depth] }
+    }
+    /// Creates a field with values computed by ```f(x, y, z)``` at every grid point.
+    pub fn from_fn(width: usize, height: usize, depth: usize, f: impl Fn(usize, usize, usize) -> f32) -> Self {
+        let mut field = Self::new(width, height, depth);
+        for z in 0..depth {
+            for y in 0..height {
+                for x in 0..width {
+                    field.set(x, y, z, f(x, y, z));
+                }
+            }
+        }
+        field
+    }
+
+    /// Sets a value at ```x```, ```y```, ```z```. Panics if it's outside the field.
+    pub fn set(&mut self, x: usize, y: usize, z: usize, value: f32) {
+        let index = self.index(x, y, z);
+        self.values[index] = value;
+    }
+    /// Gets a value at ```x```, ```y```, ```z```. Panics if it's outside the field.
+    pub fn get(&self, x: usize, y: usize, z: usize) -> f32 {
+        self.values[self.index(x, y, z)]
+    }
+    /// Gets field size in grid points.
+    pub fn get_size(&self) -> (usize, usize, usize) {
+        (self.width, self.height, self.depth)
+    }
+    /// Gets trilinearly interpolated value at ```position``` in grid units (clamped to the field).
+    pub fn sample(&self, position: &Vector3<f32>) -> f32 {
+        let size = [self.width, self.height, self.depth];
+        let mut base = [0; 3];
+        let mut fraction = [0.0; 3];
+        for axis in 0..3 {
+            let max = (size[axis] - 1) as f32;
+            let coordinate = position[axis].clamp(0.0, max);
+            base[axis] = (coordinate.floor() as usize).min(size[axis].saturating_sub(2));
+            fraction[axis] = coordinate - base[axis] as f32;
+        }
+
+        let mut value = 0.0;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let mut weight = 1.0;
+            for axis in 0..3 {
+                weight *= if offset[axis] == 1 { fraction[axis] } else { 1.0 - fraction[axis] };
+            }
+            let x = (base[0] + offset[0]).min(size[0] - 1);
+            let y = (base[1] + offset[1]).min(size[1] - 1);
+            let z = (base[2] + offset[2]).min(size[2] - 1);
+            value += weight * self.get(x, y, z);
+        }
+        value
+    }
+
+    /// Extracts the surface where field crosses ```iso``` level with surface nets.
+    /// Normals point outside (from bigger values to smaller ones), UV is vertex XZ position.
+    pub fn polygonize(&self, iso: f32) -> MeshData {
+        let mut data = MeshData::default();
+        self.polygonize_into(iso, &mut data);
+        data
+    }
+    /// Same as [ScalarField::polygonize], but writes into ```data``` (cleared first), reusing its memory.
+    /// Use it when the surface is rebuilt often (e.g. sculpting), so it's not allocated every time.
+    /// # Example
+    /// ```rust
+    /// let mut data = MeshData::default();
+    /// while window.is_running() {
+    ///     ...
+    ///     if field_changed {
+    ///         field.polygonize_into(1.0, &mut data);
+    ///         mesh = data.build();
+    ///     }
+    /// }
+    /// ```
+    pub fn polygonize_into(&self, iso: f32, data: &mut MeshData) {
+        let size = [self.width, self.height, self.depth];
+        data.clear();
+        if size.iter().any(|&axis| axis < 2) {
+            return;
+        }
+
+        // One vertex per cell the surface passes through.
+        let cells = [size[0] - 1, size[1] - 1, size[2] - 1];
+        let mut cell_vertices = vec![u32::MAX; cells[0] * cells[1] * cells[2]];
+        let mut vertex_count = 0;
+        for z in 0..cells[2] {
+            for y in 0..cells[1] {
+                for x in 0..cells[0] {
+                    let Some(position) = self.cell_vertex(x, y, z, iso) else { continue; };
+
+                    let normal = -self.gradient(&position).try_normalize(f32::EPSILON).unwrap_or(Vector3::y());
+                    data.vertices.extend_from_slice(&[
+                        position.x, position.y, position.z,
+                        position.x, position.z,
+                        normal.x, normal.y, normal.z,
+                    ]);
+                    cell_vertices[(z * cells[1] + y) * cells[0] + x] = vertex_count;
+                    vertex_count += 1;
+                }
+            }
+        }
+
+        // One quad per grid edge crossing the surface, connecting 4 cells around it.
+        for z in 0..size[2] {
+            for y in 0..size[1] {
+                for x in 0..size[0] {
+                    let point = [x, y, z];
+                    let inside = self.get(x, y, z) > iso;
+
+                    for d in 0..3 {
+                        let (u, v) = ((d + 1) % 3, (d + 2) % 3);
+                        if point[d] + 1 >= size[d] || point[u] == 0 || point[v] == 0 || point[u] >= cells[u] || point[v] >= cells[v] {
+                            continue;
+                        }
+
+                        let mut next = point;
+                        next[d] += 1;
+                        if inside == (self.get(next[0], next[1], next[2]) > iso) {
+                            continue;
+                        }
+
+                        let cell = |du: usize, dv: usize| {
+                            let mut cell = point;
+                            cell[u] -= du;
+                            cell[v] -= dv;
+                            cell_vertices[(cell[2] * cells[1] + cell[1]) * cells[0] + cell[0]]
+                        };
+                        let quad = [cell(1, 1), cell(0, 1), cell(0, 0), cell(1, 0)];
+                        if quad.contains(&u32::MAX) {
+                            continue;
+                        }
+
+                        if inside {
+                            data.indices.extend_from_slice(&[quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]);
+                        } else {
+                            data.indices.extend_from_slice(&[quad[0], quad[2], quad[1], quad[0], quad[3], quad[2]]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn cell_vertex(&self, x: usize, y: usize, z: usize, iso: f32) -> Option<Vector3<f32>> {
+        let corners: [f32; 8] = std::array::from_fn(|corner| self.get(x + (corner & 1), y + ((corner >> 1) & 1), z + ((corner >> 2) & 1)));
+        let inside = corners.map(|value| value > iso);
+        if inside.iter().all(|&value| value) || inside.iter().all(|&value| !value) {
+            return None;
+        }
+
+        // Average of all points where cell edges cross the surface.
+        let mut sum = Vector3::zeros();
+        let mut count = 0;
+        for a in 0..8 {
+            for axis in 0..3 {
+                let b = a | (1 << axis);
+                if b == a || inside[a] == inside[b] {
+                    continue;
+                }
+
+                let t = (iso - corners[a]) / (corners[b] - corners[a]);
+                let start = Vector3::new((a & 1) as f32, ((a >> 1) & 1) as f32, ((a >> 2) & 1) as f32);
+                let mut end = start;
+                end[axis] = 1.0;
+                sum += start.lerp(&end, t);
+                count += 1;
+            }
+        }
+
+        Some(Vector3::new(x as f32, y as f32, z as f32) + sum / count as f32)
+    }
+    fn gradient(&self, position: &Vector3<f32>) -> Vector3<f32> {
+        let step = 0.5;
+        Vector3::new(
+            self.sample(&(position + Vector3::x() * step)) - self.sample(&(position - Vector3::x() * step)),
+            self.sample(&(position + Vector3::y() * step)) - self.sample(&(position - Vector3::y() * step)),
+            self.sample(&(position + Vector3::z() * step)) - self.sample(&(position - Vector3::z() * step)),
+        )
+    }
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        if x >= self.width || y >= self.height || z >= self.depth {
+            panic!("Point ({}, {}, {}) is outside of {}x{}x{} scalar field.", x, y, z, self.width, self.height, self.depth);
+        }
+        (z * self.height + y) * self.width + x
+    }
+}
@@ -22,6 +22,7 @@
 //!     .with_vsync(false) // Default: true
 //!     .with_max_fps(144 * 5) // Default: WindowBuilder::NO_MAX_FPS
 //!     .with_msaa(4) // (aka. 4 samples per pixel) Default: WindowBuilder::NO_MSAA
+//!     .with_centered(true) // Default: false
 //!     .build();
 //! ```
 //! 
@@ -35,6 +36,16 @@
 //!    You can use ``WindowBuilder::NO_MAX_FPS`` for better readability.  
 //!  - ``with_msaa(msaa_quality: u32)`` - if greater than 0, enables msaa with ``msaa_quality`` samples.  
 //!    You can use ``WindowBuilder::NO_MSAA`` for better readability.
+//!  - ``with_centered(centered: bool)`` - places the window at the center of the monitor.  
+//!    Use ``with_position(x: i32, y: i32)`` instead if you want to place it yourself.
+//! 
+//! Player settings (size, vsync, msaa, fullscreen, max FPS and key bindings) can be loaded from a RON or TOML file:
+//! 
+//! ```rust
+//! let mut window: Window = WindowBuilder::from_config("./settings.toml").build(); // Defaults are used if file is missing.
+//! if window.is_action_just_pressed("jump") { ... }
+//! window.get_config().save("./settings.toml"); // Saves current settings back.
+//! ```
 //! ### Working with the Window
 //! Here's a simple example of working with certain parts of the Window
 //! 
@@ -77,6 +88,7 @@
 //!     // Working with time
 //!     // ps. You can also do window.get_delta_raw().as_secs_f32()
 //!     println!("Current frame delta time in seconds: {}.", window.get_delta());
+//!     println!("Average FPS over the last second: {}.", window.get_fps()); // Or window.set_title_fps(true) to see it in the title.
 //! 
 //!     // Getting window info
 //!     println!("Window size in pixels: Width {}, Height {}.", window.get_width(), window.get_height());
@@ -88,6 +100,7 @@
 //! 
 //!     println!("Window title: {}.", window.get_title());
 //!     println!("Window aspect: {}.", window.get_aspect());
+//!     println!("Is window focused: {}, is window iconified: {}.", window.is_focused(), window.is_iconified());
 //! 
 //!     window.swap_buffers();
 //! }
@@ -209,14 +222,14 @@
 //! To render the mesh you can just call ``yourmesh.draw();``  
 //!   
 //! But it's not enough, to see anything on your screen you need to call:  
-//! ``unsafe { gl::Clear(gl::COLOR_BUFFER_BIT); }``  
-//! Or, if you want to have a 3D game:  
-//! ``unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }``
+//! ``unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }``  
+//! Depth test is enabled by default (with ``gl::LEQUAL``), so clear depth too, even in 2D games, or nothing new is drawn after the first frame.
+//! If you don't need it at all, use ``WindowBuilder::with_depth_bits(0)`` and clear only ``gl::COLOR_BUFFER_BIT``.
 //! #### Example:
 //! ```rust
 //! while window.is_running() {
 //!     window.poll_events();
-//!     unsafe { gl::Clear(gl::COLOR_BUFFER_BIT); }
+//!     unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }
 //! 
 //!     mesh1.draw();
 //!     mesh2.draw();
@@ -241,7 +254,7 @@
 //! ```rust
 //! while window.is_running() {
 //!     window.poll_events();
-//!     unsafe { gl::Clear(gl::COLOR_BUFFER_BIT); }
+//!     unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }
 //! 
 //!     shader.bind();
 //!     shader.set_float("u_Aspect", window.get_aspect()); // There's many other uniform types you can use.
@@ -272,7 +285,7 @@
 //! ```rust
 //! while window.is_running() {
 //!     window.poll_events();
-//!     unsafe { gl::Clear(gl::COLOR_BUFFER_BIT); }
+//!     unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }
 //! 
 //!     shader.bind();
 //!     shader.set_int("u_ColorSampler", 0); // Just bind uniform sampler2D u_ColorSampler; to texture slot 0.
@@ -282,11 +295,118 @@
 //!     Texture::unbind(); // You can use that if you really need to have no textures bound at all.
 //! }
 //! ```
+//! 
+//! ## Camera
+//! ### 2D follow camera
+//! A platformer-style camera with dead zone, look-ahead, smoothing and world bounds:
+//! ```rust
+//! use tinystorm::{camera::Camera2D, nalgebra::Vector2};
+//! 
+//! let mut camera = Camera2D::new(Vector2::zeros())
+//!     .with_view_size(Vector2::new(16.0, 9.0))
+//!     .with_dead_zone(Vector2::new(2.0, 1.0)) // Camera won't move while player is inside this box.
+//!     .with_look_ahead(0.5) // Looks half a second ahead of the player.
+//!     .with_smoothing(8.0); // 0.0 means no smoothing at all.
+//! 
+//! while window.is_running() {
+//!     window.poll_events();
+//!     camera.update(&player_position, &player_velocity, window.get_delta());
+//! 
+//!     shader.bind();
+//!     shader.set_mat4("u_View", &camera.get_view_matrix());
+//!     mesh.draw();
+//! 
+//!     window.swap_buffers();
+//! }
+//! ```
+//! 
+//! ## Transition
+//! ### Changing scenes
+//! ```rust
+//! use tinystorm::{transition::{Transition, TransitionDirection}, nalgebra::Vector3};
+//! 
+//! // There's also Transition::circle_wipe, Transition::dissolve and Transition::custom with your own shader.
+//! let mut transition = Transition::fade(Vector3::zeros(), 0.5); // Fade to black in half a second.
+//! transition.start(TransitionDirection::Out);
+//! 
+//! while window.is_running() {
+//!     window.poll_events();
+//!     unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }
+//! 
+//!     transition.update(window.get_delta());
+//!     if transition.is_finished() && transition.get_direction() == TransitionDirection::Out {
+//!         // Screen is fully covered, it's time to change the scene.
+//!         transition.start(TransitionDirection::In);
+//!     }
+//! 
+//!     mesh.draw();
+//!     transition.draw(window.get_aspect()); // Always draw it last.
+//!     window.swap_buffers();
+//! }
+//! ```
+//! 
+//! ## State
+//! ### Structuring your game with states
+//! Instead of writing the main loop yourself, you can split your game into states (menu, game, pause, etc.):
+//! ```rust
+//! use tinystorm::{state::{State, StateAction, StateStack}, window::{Window, WindowBuilder}, glfw::Key};
+//! 
+//! struct Game;
+//! impl State for Game {
+//!     fn update(&mut self, delta: f32, window: &mut Window) -> StateAction {
+//!         if window.is_key_just_pressed(Key::Escape) {
+//!             return StateAction::Push(Box::new(Pause)); // Pause would be drawn over the game if it's an overlay.
+//!         }
+//!         StateAction::None
+//!     }
+//!     fn draw(&mut self, window: &Window) {
+//!         unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }
+//!     }
+//! }
+//! 
+//! let window = WindowBuilder::default().build();
+//! StateStack::new(Box::new(Game)).run(window); // Runs until window is closed or StateAction::Quit is returned.
+//! ```
 
 pub mod window;
+pub mod input;
+pub mod config;
 pub mod shader;
 pub mod mesh;
 pub mod texture;
+pub mod streaming;
+pub mod loader;
+pub mod upload;
+pub mod fence;
+pub mod framebuffer;
+pub mod vat;
+pub mod imposter;
+pub mod hiz;
+pub mod culling;
+pub mod shadow;
+pub mod lightmap;
+pub mod oit;
+pub mod overdraw;
+pub mod render;
+pub mod camera;
+pub mod bvh;
+pub mod gizmo;
+pub mod origin;
+pub mod spline;
+pub mod curve;
+pub mod voxel;
+pub mod isosurface;
+pub mod nav;
+pub mod random;
+pub mod light2d;
+pub mod sim;
+pub mod analysis;
+pub mod profiler;
+#[cfg(feature = "bench")]
+pub mod bench;
+pub mod transition;
+pub mod state;
+pub mod storage;
 
 pub use glfw;
 pub use gl;
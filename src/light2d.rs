@@ -0,0 +1,292 @@
+use std::cell::RefCell;
+
+use gl::types::{GLboolean, GLfloat, GLint, GLuint};
+use nalgebra::{Matrix4, Vector2, Vector3};
+
+use crate::{framebuffer::Framebuffer, mesh::{Layout, Mesh}, shader::Shader, texture::Texture};
+
+const LIGHT_VERTEX_SOURCE: &str = "#version 330 core
+layout(location = 0) in vec2 a_Position;
+
+uniform mat4 u_ViewProjection;
+uniform vec2 u_Center;
+uniform float u_Radius;
+
+out vec2 v_Local;
+
+void main() {
+    v_Local = a_Position;
+    gl_Position = u_ViewProjection * vec4(u_Center + a_Position * u_Radius, 0.0, 1.0);
+}
+";
+const LIGHT_FRAGMENT_SOURCE: &str = "#version 330 core
+in vec2 v_Local;
+out vec4 f_Color;
+
+uniform vec3 u_Color;
+uniform float u_Intensity;
+uniform bool u_Textured;
+uniform sampler2D u_Texture;
+
+void main() {
+    float falloff;
+    if (u_Textured) {
+        falloff = texture(u_Texture, v_Local * 0.5 + 0.5).r;
+    } else {
+        float distance = clamp(1.0 - length(v_Local), 0.0, 1.0);
+        falloff = distance * distance;
+    }
+
+    f_Color = vec4(u_Color * u_Intensity * falloff, 1.0);
+}
+";
+const COMPOSITE_VERTEX_SOURCE: &str = "#version 330 core
+layout(location = 0) in vec2 a_Position;
+
+out vec2 v_Uv;
+
+void main() {
+    v_Uv = a_Position * 0.5 + 0.5;
+    gl_Position = vec4(a_Position, 0.0, 1.0);
+}
+";
+const COMPOSITE_FRAGMENT_SOURCE: &str = "#version 330 core
+in vec2 v_Uv;
+out vec4 f_Color;
+
+uniform sampler2D u_LightMap;
+
+void main() {
+    f_Color = vec4(texture(u_LightMap, v_Uv).rgb, 1.0);
+}
+";
+
+/// A point light for [LightMap].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Light2D {
+    /// Light center in world units.
+    pub position: Vector2<f32>,
+    /// Distance at which light fades out completely.
+    pub radius: f32,
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+    /// Whether occluders added with [LightMap::add_occluder] cast shadows from this light.
+    pub shadows: bool,
+}
+
+impl Light2D {
+    /// Creates a white light with intensity 1.0 that casts shadows.
+    pub fn new(position: Vector2<f32>, radius: f32) -> Self {
+        Self { position, radius, color: Vector3::new(1.0, 1.0, 1.0), intensity: 1.0, shadows: true }
+    }
+}
+
+/// A 2D lighting system: lights are additively blended into a light map (its own render target),
+/// then the light map is multiplied over your already drawn sprites.
+/// Polygon occluders (walls, crates) cast hard shadows using stencil shadow geometry.
+/// # Example
+/// ```rust
+/// use tinystorm::{light2d::{Light2D, LightMap}, nalgebra::{Vector2, Vector3}};
+///
+/// let mut light_map = LightMap::new(window.get_width(), window.get_height())
+///     .with_ambient(Vector3::new(0.1, 0.1, 0.15));
+/// light_map.add_occluder(&[Vector2::new(2.0, 2.0), Vector2::new(4.0, 2.0), Vector2::new(4.0, 4.0), Vector2::new(2.0, 4.0)]);
+///
+/// while window.is_running() {
+///     window.poll_events();
+///     // Draw your sprites here...
+///
+///     let torch = Light2D { color: Vector3::new(1.0, 0.7, 0.4), ..Light2D::new(player_position, 8.0) };
+///     light_map.render(&[torch], &(projection * camera.get_view_matrix()));
+///     light_map.composite(); // Darkens everything outside of the light.
+///     window.swap_buffers();
+/// }
+/// ```
+pub struct LightMap {
+    framebuffer: Framebuffer,
+    light_shader: Shader,
+    composite_shader: Shader,
+    quad: Mesh,
+    fullscreen_triangle: Mesh,
+    texture: Option<Texture>,
+
+    ambient: Vector3<f32>,
+    occluders: Vec<Vec<Vector2<f32>>>,
+    // Reused for shadow geometry of every light, so rendering doesn't allocate each frame.
+    shadow_vertices: RefCell<Vec<f32>>,
+}
+
+impl LightMap {
+    // Shadow quads are extruded this many radii away from the light, far enough to cover its whole area.
+    const SHADOW_LENGTH: f32 = 100.0;
+
+    /// Creates a ```width``` x ```height``` light map. It can be smaller than the window for softer and cheaper lighting.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            framebuffer: Framebuffer::new(width, height),
+            light_shader: Shader::from_source(LIGHT_VERTEX_SOURCE, LIGHT_FRAGMENT_SOURCE),
+            composite_shader: Shader::from_source(COMPOSITE_VERTEX_SOURCE, COMPOSITE_FRAGMENT_SOURCE),
+            quad: Mesh::new::<f32>(&[
+                -1.0, -1.0,
+                 1.0, -1.0,
+                 1.0,  1.0,
+                -1.0,  1.0,
+            ], &Layout::basic_2d(), gl::TRIANGLE_FAN),
+            fullscreen_triangle: Mesh::fullscreen_triangle(),
+            texture: None,
+
+            ambient: Vector3::zeros(),
+            occluders: Vec::new(),
+            shadow_vertices: RefCell::new(Vec::new()),
+        }
+    }
+    /// Sets light color where no lights reach. Black by default.
+    pub fn with_ambient(mut self, ambient: Vector3<f32>) -> Self {
+        self.ambient = ambient;
+        self
+    }
+    /// Uses red channel of ```texture``` as light shape (cookie) instead of the default quadratic falloff.
+    /// Texture covers the whole light radius square.
+    pub fn with_light_texture(mut self, texture: Texture) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    /// Resizes the light map. Call it when your window is resized.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.framebuffer.resize(width, height);
+    }
+    /// Sets light color where no lights reach.
+    pub fn set_ambient(&mut self, ambient: Vector3<f32>) {
+        self.ambient = ambient;
+    }
+
+    /// Adds a polygon (in world units) that casts shadows. Polygon is treated as closed.
+    pub fn add_occluder(&mut self, polygon: &[Vector2<f32>]) {
+        if polygon.len() >= 2 {
+            self.occluders.push(polygon.to_vec());
+        }
+    }
+    /// Removes all occluders.
+    pub fn clear_occluders(&mut self) {
+        self.occluders.clear();
+    }
+
+    /// Renders ```lights``` into the light map with ```view_projection``` (the same one your sprites use).
+    /// Bound framebuffer, viewport, shader, clear color, blending, depth and stencil test state are restored after that.
+    pub fn render(&self, lights: &[Light2D], view_projection: &Matrix4<f32>) {
+        unsafe {
+            let mut previous_framebuffer: GLint = 0;
+            let mut previous_viewport: [GLint; 4] = [0; 4];
+            let mut previous_program: GLint = 0;
+            let mut previous_clear_color: [GLfloat; 4] = [0.0; 4];
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous_framebuffer);
+            gl::GetIntegerv(gl::VIEWPORT, previous_viewport.as_mut_ptr());
+            gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut previous_program);
+            gl::GetFloatv(gl::COLOR_CLEAR_VALUE, previous_clear_color.as_mut_ptr());
+            let blend: GLboolean = gl::IsEnabled(gl::BLEND);
+            let depth_test: GLboolean = gl::IsEnabled(gl::DEPTH_TEST);
+            let stencil_test: GLboolean = gl::IsEnabled(gl::STENCIL_TEST);
+
+            self.framebuffer.bind();
+            gl::ClearColor(self.ambient.x, self.ambient.y, self.ambient.z, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::ONE, gl::ONE);
+
+            self.light_shader.bind();
+            self.light_shader.set_mat4("u_ViewProjection", view_projection);
+            self.light_shader.set_bool("u_Textured", self.texture.is_some());
+            if let Some(texture) = &self.texture {
+                texture.bind(0);
+                self.light_shader.set_int("u_Texture", 0);
+            }
+
+            for light in lights {
+                let shadowed = light.shadows && !self.occluders.is_empty();
+                if shadowed {
+                    self.draw_shadows(light);
+                }
+
+                self.light_shader.set_vec2("u_Center", &light.position);
+                self.light_shader.set_float("u_Radius", light.radius);
+                self.light_shader.set_vec3("u_Color", &light.color);
+                self.light_shader.set_float("u_Intensity", light.intensity);
+                self.quad.draw();
+
+                if shadowed {
+                    gl::Disable(gl::STENCIL_TEST);
+                }
+            }
+
+            gl::BlendFunc(gl::ONE, gl::ZERO);
+            if blend != gl::TRUE { gl::Disable(gl::BLEND); }
+            if depth_test == gl::TRUE { gl::Enable(gl::DEPTH_TEST); }
+            if stencil_test == gl::TRUE { gl::Enable(gl::STENCIL_TEST); }
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous_framebuffer as GLuint);
+            gl::Viewport(previous_viewport[0], previous_viewport[1], previous_viewport[2], previous_viewport[3]);
+            gl::UseProgram(previous_program as GLuint);
+            gl::ClearColor(previous_clear_color[0], previous_clear_color[1], previous_clear_color[2], previous_clear_color[3]);
+        }
+    }
+    /// Multiplies the currently bound framebuffer by the light map. Call it after all lit sprites are drawn.
+    pub fn composite(&self) {
+        unsafe {
+            let blend: GLboolean = gl::IsEnabled(gl::BLEND);
+            let depth_test: GLboolean = gl::IsEnabled(gl::DEPTH_TEST);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::DST_COLOR, gl::ZERO);
+
+            self.framebuffer.bind_color_texture(0);
+            self.composite_shader.bind();
+            self.composite_shader.set_int("u_LightMap", 0);
+            self.fullscreen_triangle.draw();
+            Shader::unbind();
+
+            gl::BlendFunc(gl::ONE, gl::ZERO);
+            if blend != gl::TRUE { gl::Disable(gl::BLEND); }
+            if depth_test == gl::TRUE { gl::Enable(gl::DEPTH_TEST); }
+        }
+    }
+
+    /// Gets the light map framebuffer, e.g. to use it in your own sprite shader instead of [LightMap::composite].
+    pub fn get_framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    // Marks shadowed area in the stencil buffer and leaves stencil test on, so the light is drawn only outside of it.
+    unsafe fn draw_shadows(&self, light: &Light2D) {
+        let mut vertices = self.shadow_vertices.borrow_mut();
+        vertices.clear();
+        for polygon in &self.occluders {
+            for (i, &a) in polygon.iter().enumerate() {
+                let b = polygon[(i + 1) % polygon.len()];
+                let far_a = a + (a - light.position) * Self::SHADOW_LENGTH;
+                let far_b = b + (b - light.position) * Self::SHADOW_LENGTH;
+                vertices.extend_from_slice(&[a.x, a.y, b.x, b.y, far_b.x, far_b.y, a.x, a.y, far_b.x, far_b.y, far_a.x, far_a.y]);
+            }
+        }
+        let shadows = Mesh::new::<f32>(&vertices, &Layout::basic_2d(), gl::TRIANGLES);
+
+        unsafe {
+            gl::Enable(gl::STENCIL_TEST);
+            gl::ClearStencil(0);
+            gl::Clear(gl::STENCIL_BUFFER_BIT);
+            gl::StencilFunc(gl::ALWAYS, 1, 0xFF);
+            gl::StencilOp(gl::KEEP, gl::KEEP, gl::REPLACE);
+            gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+
+            self.light_shader.set_vec2("u_Center", &Vector2::zeros());
+            self.light_shader.set_float("u_Radius", 1.0);
+            shadows.draw();
+
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+            gl::StencilFunc(gl::EQUAL, 0, 0xFF);
+            gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP);
+        }
+    }
+}
+
@@ -0,0 +1,445 @@
+use nalgebra::{Vector2, Vector3};
+
+use crate::{
+    bvh::{Aabb, Bvh},
+    camera::Ray,
+    mesh::{self, Attribute, IndexedMesh, Layout, MeshData},
+    random::Random,
+    texture::Texture,
+};
+
+// Offset of ray origins from surfaces, so they don't hit the triangle they start on.
+const BIAS: f32 = 1e-3;
+// Empty texels between UV charts, so linear filtering doesn't bleed neighbours in.
+const PADDING: u32 = 2;
+
+/// A light used by [LightmapBaker].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BakeLight {
+    /// Sun-like light coming from infinitely far away along ```direction```.
+    Directional { direction: Vector3<f32>, color: Vector3<f32> },
+    /// Light at ```position``` fading out to zero at ```range```.
+    Point { position: Vector3<f32>, color: Vector3<f32>, range: f32 },
+}
+
+/// Result of [LightmapBaker::bake]: the mesh with unique lightmap UVs and the lightmap itself.
+///
+/// Vertices are split where lightmap UV charts meet, so the mesh usually has more vertices than the source one.
+pub struct BakedLightmap {
+    /// Position, UV, normal and lightmap UV of every vertex, see [BakedLightmap::layout].
+    pub vertices: Vec<f32>,
+    /// Triangle indices into ```vertices```.
+    pub indices: Vec<u32>,
+    pub width: u32,
+    pub height: u32,
+    /// Linear RGB light of every texel, row by row from the bottom.
+    pub pixels: Vec<f32>,
+}
+
+impl BakedLightmap {
+    /// Layout of baked vertices.
+    /// # Layout
+    /// position: [Attribute::Vec3]
+    /// uv: [Attribute::Vec2]
+    /// normal: [Attribute::Vec3]
+    /// lightmap uv: [Attribute::Vec2]
+    pub fn layout() -> Layout {
+        Layout::lightmap_3d()
+    }
+    /// Uploads the mesh to GPU with [BakedLightmap::layout] and ```gl::TRIANGLES```.
+    pub fn build(&self) -> IndexedMesh {
+        IndexedMesh::new::<f32>(&self.indices, &self.vertices, &Self::layout(), gl::TRIANGLES)
+    }
+    /// Uploads the lightmap to GPU, see [Texture::from_hdr].
+    pub fn to_texture(&self) -> Texture {
+        Texture::from_hdr(self.width, self.height, &self.pixels)
+    }
+}
+
+/// Bakes static lighting of a mesh into a lightmap on the CPU: direct light with ray traced shadows,
+/// plus sky light and one bounce of indirect light from hemisphere samples. It's slow, bake at load time or in a tool and save results.
+///
+/// Lightmap UVs are generated automatically with [unwrap_uvs](crate::mesh::unwrap_uvs), so every surface gets the same texel density.
+///
+/// In your fragment shader multiply albedo by ```texture(u_Lightmap, v_LightmapUv).rgb``` instead of computing lighting.
+/// # Example
+/// ```rust
+/// use tinystorm::lightmap::{BakeLight, LightmapBaker};
+///
+/// let baked = LightmapBaker::new(512)
+///     .with_samples(64)
+///     .with_sky(Vector3::new(0.3, 0.35, 0.45))
+///     .with_light(BakeLight::Directional { direction: Vector3::new(-0.3, -1.0, -0.2), color: Vector3::new(1.0, 0.95, 0.8) })
+///     .bake(&level_data);
+///
+/// let level = baked.build();
+/// let lightmap = baked.to_texture();
+/// ...
+/// lightmap.bind(1);
+/// shader.set_int("u_Lightmap", 1);
+/// level.draw();
+/// ```
+pub struct LightmapBaker {
+    resolution: u32,
+    samples: u32,
+    bounce: bool,
+    albedo: f32,
+    sky: Vector3<f32>,
+    lights: Vec<BakeLight>,
+    seed: u64,
+}
+
+impl LightmapBaker {
+    /// Creates a baker for ```resolution``` x ```resolution``` lightmaps.
+    pub fn new(resolution: u32) -> Self {
+        Self::default().with_resolution(resolution)
+    }
+    /// Sets lightmap width and height in texels.
+    pub fn with_resolution(mut self, resolution: u32) -> Self {
+        self.resolution = resolution.max(4);
+        self
+    }
+    /// Sets number of hemisphere samples per texel for sky and bounced light. More is smoother and slower, 0 disables indirect light.
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples = samples;
+        self
+    }
+    /// Enables/disables one bounce of light from surfaces with ```albedo``` (0.0 - 1.0, how much light they reflect).
+    pub fn with_bounce(mut self, bounce: bool, albedo: f32) -> Self {
+        self.bounce = bounce;
+        self.albedo = albedo;
+        self
+    }
+    /// Sets color of light coming from the sky (rays that don't hit anything).
+    pub fn with_sky(mut self, sky: Vector3<f32>) -> Self {
+        self.sky = sky;
+        self
+    }
+    /// Adds a light.
+    pub fn with_light(mut self, light: BakeLight) -> Self {
+        self.lights.push(light);
+        self
+    }
+    /// Sets seed of the random sample directions, the same seed gives the same lightmap.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Generates lightmap UVs for ```data``` and bakes its lighting. The mesh also shadows itself.
+    pub fn bake(&self, data: &MeshData) -> BakedLightmap {
+        let vertex = |index: usize| &data.vertices[index * 8..index * 8 + 8];
+        let position = |index: usize| Vector3::new(vertex(index)[0], vertex(index)[1], vertex(index)[2]);
+        let normal = |index: usize| Vector3::new(vertex(index)[5], vertex(index)[6], vertex(index)[7]);
+
+        let positions: Vec<Vector3<f32>> = (0..data.vertices.len() / 8).map(position).collect();
+        let unwrap = mesh::unwrap_uvs(&positions, &data.indices, self.resolution, PADDING);
+        let triangles: Vec<[usize; 3]> = unwrap.indices.chunks_exact(3).map(|t| [t[0] as usize, t[1] as usize, t[2] as usize]).collect();
+        let source = |index: usize| unwrap.vertex_map[index] as usize;
+
+        let scene = Scene::new(triangles.iter().map(|t| t.map(|index| positions[source(index)])).collect());
+        let size = self.resolution as f32;
+
+        let mut vertices = Vec::with_capacity(unwrap.uvs.len() * 10);
+        for (&index, uv) in unwrap.vertex_map.iter().zip(&unwrap.uvs) {
+            vertices.extend_from_slice(vertex(index as usize));
+            vertices.extend_from_slice(uv.as_slice());
+        }
+
+        let mut pixels = vec![0.0; (self.resolution * self.resolution * 3) as usize];
+        let mut covered = vec![false; (self.resolution * self.resolution) as usize];
+        let mut random = Random::new(self.seed);
+
+        for (i, triangle) in triangles.iter().enumerate() {
+            let corners = triangle.map(|index| unwrap.uvs[index] * size);
+
+            // Shades texels whose centers are inside the triangle in lightmap space.
+            let (min, max) = (corners[0].inf(&corners[1]).inf(&corners[2]), corners[0].sup(&corners[1]).sup(&corners[2]));
+            for y in min.y.floor() as u32..(max.y.ceil() as u32).min(self.resolution) {
+                for x in min.x.floor() as u32..(max.x.ceil() as u32).min(self.resolution) {
+                    let Some(weights) = barycentric(&Vector2::new(x as f32 + 0.5, y as f32 + 0.5), &corners) else { continue; };
+                    let point = triangle.iter().zip(weights.iter()).map(|(&index, weight)| positions[source(index)] * *weight).sum::<Vector3<f32>>();
+                    let surface_normal = triangle.iter().zip(weights.iter()).map(|(&index, weight)| normal(source(index)) * *weight).sum::<Vector3<f32>>();
+                    let surface_normal = surface_normal.try_normalize(f32::EPSILON).unwrap_or(scene.normals[i]);
+
+                    let light = self.shade(&scene, &point, &surface_normal, &mut random);
+                    let texel = (y * self.resolution + x) as usize;
+                    pixels[texel * 3..texel * 3 + 3].copy_from_slice(light.as_slice());
+                    covered[texel] = true;
+                }
+            }
+        }
+
+        dilate(&mut pixels, &mut covered, self.resolution, PADDING);
+        BakedLightmap {
+            vertices,
+            indices: unwrap.indices,
+            width: self.resolution,
+            height: self.resolution,
+            pixels,
+        }
+    }
+
+    fn shade(&self, scene: &Scene, point: &Vector3<f32>, normal: &Vector3<f32>, random: &mut Random) -> Vector3<f32> {
+        let mut light = self.direct(scene, point, normal);
+        if self.samples == 0 {
+            return light;
+        }
+
+        let mut indirect = Vector3::zeros();
+        for _ in 0..self.samples {
+            // Cosine-weighted hemisphere direction.
+            let direction = (normal + random.on_unit_sphere()).try_normalize(f32::EPSILON).unwrap_or(*normal);
+            let ray = Ray::new(point + normal * BIAS, direction);
+            match scene.cast(&ray, f32::INFINITY) {
+                None => indirect += self.sky,
+                Some((triangle, distance)) if self.bounce => {
+                    let hit_normal = scene.normals[triangle] * -scene.normals[triangle].dot(&direction).signum();
+                    indirect += self.direct(scene, &ray.at(distance), &hit_normal) * self.albedo;
+                }
+                Some(_) => {}
+            }
+        }
+        light += indirect / self.samples as f32;
+        light
+    }
+    fn direct(&self, scene: &Scene, point: &Vector3<f32>, normal: &Vector3<f32>) -> Vector3<f32> {
+        let origin = point + normal * BIAS;
+        self.lights.iter().map(|light| {
+            let (direction, distance, color) = match *light {
+                BakeLight::Directional { direction, color } => (-direction.normalize(), f32::INFINITY, color),
+                BakeLight::Point { position, color, range } => {
+                    let offset = position - origin;
+                    let distance = offset.norm();
+                    let falloff = (1.0 - distance / range).clamp(0.0, 1.0);
+                    (offset / distance.max(f32::EPSILON), distance, color * falloff * falloff)
+                }
+            };
+
+            let facing = normal.dot(&direction);
+            if facing <= 0.0 || color == Vector3::zeros() || scene.cast(&Ray::new(origin, direction), distance).is_some() {
+                return Vector3::zeros();
+            }
+            color * facing
+        }).sum()
+    }
+}
+/// # Default values
+/// ```
+/// resolution: 256
+/// samples: 32
+/// bounce: true
+/// albedo: 0.5
+/// sky: (0.0, 0.0, 0.0)
+/// lights: []
+/// seed: 0
+/// ```
+impl Default for LightmapBaker {
+    fn default() -> Self {
+        Self { resolution: 256, samples: 32, bounce: true, albedo: 0.5, sky: Vector3::zeros(), lights: Vec::new(), seed: 0 }
+    }
+}
+
+/// Bakes ambient occlusion of a mesh into its vertices on the CPU: how much of the hemisphere above every vertex
+/// is blocked by nearby geometry, 0.0 (fully occluded) - 1.0 (open). Cheaper than [LightmapBaker] and needs no lightmap UVs,
+/// but it's only as detailed as the mesh.
+///
+/// In your shaders multiply ambient light (or all of it) by the AO attribute.
+/// # Example
+/// ```rust
+/// use tinystorm::lightmap::AoBaker;
+///
+/// let baker = AoBaker::new().with_samples(128).with_distance(2.0);
+/// let level = baker.build(&level_data);
+///
+/// // Vertex shader:
+/// layout(location = 3) in float a_Occlusion;
+/// // Fragment shader:
+/// vec3 ambient = u_Ambient * v_Occlusion;
+/// ```
+pub struct AoBaker {
+    samples: u32,
+    distance: f32,
+    seed: u64,
+}
+
+impl AoBaker {
+    /// Creates a baker with default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets number of hemisphere rays per vertex. More is smoother and slower.
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+    /// Sets how far geometry occludes. Hits further than that don't count, closer ones count more.
+    pub fn with_distance(mut self, distance: f32) -> Self {
+        self.distance = distance;
+        self
+    }
+    /// Sets seed of the random sample directions, the same seed gives the same result.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Layout of vertices made by [AoBaker::bake_vertices].
+    /// # Layout
+    /// position: [Attribute::Vec3]  
+    /// uv: [Attribute::Vec2]  
+    /// normal: [Attribute::Vec3]  
+    /// occlusion: [Attribute::Float]
+    pub fn layout() -> Layout {
+        Layout::default_3d().next_attribute(Attribute::Float)
+    }
+    /// Bakes AO of every vertex of ```data```. The mesh occludes itself.
+    pub fn bake(&self, data: &MeshData) -> Vec<f32> {
+        let vertex = |index: usize| &data.vertices[index * 8..index * 8 + 8];
+        let position = |index: usize| Vector3::new(vertex(index)[0], vertex(index)[1], vertex(index)[2]);
+        let scene = Scene::new(data.indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]].map(|index| position(index as usize))).collect());
+
+        // Vertices without normals use the average of their triangle normals.
+        let mut face_normals = vec![Vector3::zeros(); data.vertices.len() / 8];
+        for (triangle, normal) in data.indices.chunks_exact(3).zip(&scene.normals) {
+            for &index in triangle {
+                face_normals[index as usize] += normal;
+            }
+        }
+
+        let mut random = Random::new(self.seed);
+        (0..data.vertices.len() / 8).map(|index| {
+            let normal = Vector3::new(vertex(index)[5], vertex(index)[6], vertex(index)[7]).try_normalize(f32::EPSILON)
+                .or(face_normals[index].try_normalize(f32::EPSILON));
+            let Some(normal) = normal else { return 1.0; };
+
+            let origin = position(index) + normal * BIAS;
+            let mut occlusion = 0.0;
+            for _ in 0..self.samples {
+                // Cosine-weighted hemisphere direction.
+                let direction = (normal + random.on_unit_sphere()).try_normalize(f32::EPSILON).unwrap_or(normal);
+                if let Some((_, distance)) = scene.cast(&Ray::new(origin, direction), self.distance) {
+                    occlusion += 1.0 - distance / self.distance;
+                }
+            }
+            1.0 - occlusion / self.samples as f32
+        }).collect()
+    }
+    /// Bakes AO (see [AoBaker::bake]) and appends it to every vertex of ```data```. Returns vertices with [AoBaker::layout].
+    pub fn bake_vertices(&self, data: &MeshData) -> Vec<f32> {
+        let occlusion = self.bake(data);
+        let mut vertices = Vec::with_capacity(occlusion.len() * 9);
+        for (vertex, occlusion) in data.vertices.chunks_exact(8).zip(occlusion) {
+            vertices.extend_from_slice(vertex);
+            vertices.push(occlusion);
+        }
+        vertices
+    }
+    /// Bakes AO and uploads the mesh to GPU with [AoBaker::layout] and ```gl::TRIANGLES```.
+    pub fn build(&self, data: &MeshData) -> IndexedMesh {
+        IndexedMesh::new::<f32>(&data.indices, &self.bake_vertices(data), &Self::layout(), gl::TRIANGLES)
+    }
+}
+/// # Default values
+/// ```
+/// samples: 64
+/// distance: 1.0
+/// seed: 0
+/// ```
+impl Default for AoBaker {
+    fn default() -> Self {
+        Self { samples: 64, distance: 1.0, seed: 0 }
+    }
+}
+
+// Triangles of the baked mesh in a BVH, for shadow, sample and occlusion rays.
+struct Scene {
+    triangles: Vec<[Vector3<f32>; 3]>,
+    normals: Vec<Vector3<f32>>,
+    bvh: Bvh<()>,
+}
+
+impl Scene {
+    fn new(triangles: Vec<[Vector3<f32>; 3]>) -> Self {
+        let normals = triangles.iter()
+            .map(|[a, b, c]| (b - a).cross(&(c - a)).try_normalize(f32::EPSILON).unwrap_or(Vector3::y()))
+            .collect();
+        let bvh = Bvh::new(triangles.iter().map(|triangle| (Aabb::from_points(*triangle), ())).collect());
+        Self { triangles, normals, bvh }
+    }
+    fn cast(&self, ray: &Ray, max_distance: f32) -> Option<(usize, f32)> {
+        self.bvh.cast_ray(ray, max_distance, |index, _| intersect_triangle(ray, &self.triangles[index]))
+    }
+}
+
+// Möller-Trumbore ray-triangle intersection, both sides.
+fn intersect_triangle(ray: &Ray, [a, b, c]: &[Vector3<f32>; 3]) -> Option<f32> {
+    let (edge1, edge2) = (b - a, c - a);
+    let p = ray.direction.cross(&edge2);
+    let determinant = edge1.dot(&p);
+    if determinant.abs() < 1e-8 {
+        return None;
+    }
+
+    let inverse = 1.0 / determinant;
+    let offset = ray.origin - a;
+    let u = offset.dot(&p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = offset.cross(&edge1);
+    let v = ray.direction.dot(&q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let distance = edge2.dot(&q) * inverse;
+    (distance > 0.0).then_some(distance)
+}
+// Barycentric weights of point in a 2D triangle, or None if it's outside.
+fn barycentric(point: &Vector2<f32>, [a, b, c]: &[Vector2<f32>; 3]) -> Option<[f32; 3]> {
+    let (v0, v1, v2) = (b - a, c - a, point - a);
+    let denominator = v0.x * v1.y - v1.x * v0.y;
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let v = (v2.x * v1.y - v1.x * v2.y) / denominator;
+    let w = (v0.x * v2.y - v2.x * v0.y) / denominator;
+    let u = 1.0 - v - w;
+    // A small tolerance, so texels on shared edges aren't lost.
+    (u >= -1e-4 && v >= -1e-4 && w >= -1e-4).then_some([u, v, w])
+}
+// Fills uncovered texels with the average of covered neighbours, a texel ring per pass.
+fn dilate(pixels: &mut [f32], covered: &mut [bool], size: u32, passes: u32) {
+    let size = size as i64;
+    for _ in 0..passes {
+        let mut filled = Vec::new();
+        for y in 0..size {
+            for x in 0..size {
+                if covered[(y * size + x) as usize] {
+                    continue;
+                }
+
+                let (mut sum, mut count) = (Vector3::zeros(), 0);
+                for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)] {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if nx < 0 || ny < 0 || nx >= size || ny >= size || !covered[(ny * size + nx) as usize] {
+                        continue;
+                    }
+                    let texel = (ny * size + nx) as usize * 3;
+                    sum += Vector3::new(pixels[texel], pixels[texel + 1], pixels[texel + 2]);
+                    count += 1;
+                }
+                if count > 0 {
+                    filled.push(((y * size + x) as usize, sum / count as f32));
+                }
+            }
+        }
+
+        for (texel, color) in filled {
+            pixels[texel * 3..texel * 3 + 3].copy_from_slice(color.as_slice());
+            covered[texel] = true;
+        }
+    }
+}
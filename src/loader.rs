@@ -0,0 +1,524 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, mpsc::{self, Receiver, Sender}},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+use image::GenericImageView;
+
+use crate::render;
+
+// EXT_texture_compression_s3tc, not included in gl bindings.
+const COMPRESSED_RGBA_S3TC_DXT1: GLenum = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT5: GLenum = 0x83F3;
+
+/// Pixel format of a [TranscodedTexture]. Block-compressed formats are uploaded as is, without decompression.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TextureFormat {
+    /// Uncompressed 8-bit RGBA.
+    Rgba8,
+    /// BC1 (DXT1), RGB with 1-bit alpha. Decoded on the CPU if ```EXT_texture_compression_s3tc``` isn't supported.
+    Bc1,
+    /// BC3 (DXT5), RGBA. Decoded on the CPU if ```EXT_texture_compression_s3tc``` isn't supported.
+    Bc3,
+    /// BC4 (RGTC1), single channel.
+    Bc4,
+    /// BC5 (RGTC2), two channels, good for normal maps.
+    Bc5,
+    /// BC7 (BPTC), high quality RGBA.
+    Bc7,
+    /// ETC2 RGB, mostly for mobile GPUs.
+    Etc2Rgb,
+    /// ETC2 RGBA (with EAC alpha), mostly for mobile GPUs.
+    Etc2Rgba,
+}
+
+impl TextureFormat {
+    /// Gets OpenGL internal format.
+    pub fn internal_format(&self) -> GLenum {
+        match self {
+            Self::Rgba8 => gl::RGBA8,
+            Self::Bc1 => COMPRESSED_RGBA_S3TC_DXT1,
+            Self::Bc3 => COMPRESSED_RGBA_S3TC_DXT5,
+            Self::Bc4 => gl::COMPRESSED_RED_RGTC1,
+            Self::Bc5 => gl::COMPRESSED_RG_RGTC2,
+            Self::Bc7 => gl::COMPRESSED_RGBA_BPTC_UNORM,
+            Self::Etc2Rgb => gl::COMPRESSED_RGB8_ETC2,
+            Self::Etc2Rgba => gl::COMPRESSED_RGBA8_ETC2_EAC,
+        }
+    }
+    /// Checks if it's a block-compressed format.
+    pub fn is_compressed(&self) -> bool {
+        *self != Self::Rgba8
+    }
+}
+
+impl TranscodedTexture {
+    /// Decodes BC1 or BC3 mips into [TextureFormat::Rgba8]. Returns an error for other formats or truncated data.
+    pub fn decode_s3tc(&self) -> Result<Self, String> {
+        let block_size = match self.format {
+            TextureFormat::Bc1 => 8,
+            TextureFormat::Bc3 => 16,
+            format => return Err(format!("{:?} isn't an S3TC format", format)),
+        };
+
+        let mut mips = Vec::with_capacity(self.mips.len());
+        for (level, data) in self.mips.iter().enumerate() {
+            let width = (self.width >> level).max(1) as usize;
+            let height = (self.height >> level).max(1) as usize;
+            let (blocks_x, blocks_y) = (width.div_ceil(4), height.div_ceil(4));
+            if data.len() < blocks_x * blocks_y * block_size {
+                return Err(format!("Mip level {} is truncated", level));
+            }
+
+            let mut pixels = vec![0; width * height * 4];
+            for (i, block) in data.chunks_exact(block_size).take(blocks_x * blocks_y).enumerate() {
+                let decoded = match self.format {
+                    TextureFormat::Bc1 => decode_bc1_block(block, true),
+                    _ => {
+                        let mut decoded = decode_bc1_block(&block[8..], false);
+                        for (pixel, alpha) in decoded.iter_mut().zip(decode_bc3_alpha(&block[..8])) {
+                            pixel[3] = alpha;
+                        }
+                        decoded
+                    }
+                };
+
+                let (block_x, block_y) = (i % blocks_x * 4, i / blocks_x * 4);
+                for (j, pixel) in decoded.iter().enumerate() {
+                    let (x, y) = (block_x + j % 4, block_y + j / 4);
+                    if x < width && y < height {
+                        pixels[(y * width + x) * 4..][..4].copy_from_slice(pixel);
+                    }
+                }
+            }
+            mips.push(pixels);
+        }
+        Ok(Self { width: self.width, height: self.height, format: TextureFormat::Rgba8, mips })
+    }
+}
+
+// 16 RGBA pixels of a BC1 color block, row by row. BC3 color blocks always use four colors.
+fn decode_bc1_block(block: &[u8], punch_through: bool) -> [[u8; 4]; 16] {
+    let endpoints = [u16::from_le_bytes([block[0], block[1]]), u16::from_le_bytes([block[2], block[3]])];
+    let [c0, c1] = endpoints.map(|color| {
+        let (r, g, b) = ((color >> 11) & 31, (color >> 5) & 63, color & 31);
+        [(r << 3 | r >> 2) as u32, (g << 2 | g >> 4) as u32, (b << 3 | b >> 2) as u32]
+    });
+    let mix = |a: u32, b: u32, c: u32| [0, 1, 2].map(|i| ((c0[i] * a + c1[i] * b) / c) as u8);
+
+    let palette = if !punch_through || endpoints[0] > endpoints[1] {
+        let [a, b, c, d] = [mix(1, 0, 1), mix(0, 1, 1), mix(2, 1, 3), mix(1, 2, 3)];
+        [[a[0], a[1], a[2], 255], [b[0], b[1], b[2], 255], [c[0], c[1], c[2], 255], [d[0], d[1], d[2], 255]]
+    } else {
+        let [a, b, c] = [mix(1, 0, 1), mix(0, 1, 1), mix(1, 1, 2)];
+        [[a[0], a[1], a[2], 255], [b[0], b[1], b[2], 255], [c[0], c[1], c[2], 255], [0, 0, 0, 0]]
+    };
+
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    std::array::from_fn(|i| palette[(indices >> (i * 2)) as usize & 3])
+}
+// 16 alpha values of a BC3 alpha block, row by row.
+fn decode_bc3_alpha(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+    let palette: [u8; 8] = std::array::from_fn(|i| match i {
+        0 => a0 as u8,
+        1 => a1 as u8,
+        _ if a0 > a1 => ((a0 * (8 - i as u32) + a1 * (i as u32 - 1)) / 7) as u8,
+        6 => 0,
+        7 => 255,
+        _ => ((a0 * (6 - i as u32) + a1 * (i as u32 - 1)) / 5) as u8,
+    });
+
+    let indices = block[2..8].iter().rev().fold(0u64, |indices, byte| indices << 8 | *byte as u64);
+    std::array::from_fn(|i| palette[(indices >> (i * 3)) as usize & 7])
+}
+
+/// A decoded (or transcoded) texture ready to be uploaded by [TextureLoader].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TranscodedTexture {
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    /// Mip levels from the biggest one. If there's only one uncompressed level, mipmaps are generated on upload.
+    pub mips: Vec<Vec<u8>>,
+}
+
+/// A handle of a texture loaded by [TextureLoader].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct LoadedTexture(usize);
+
+type Transcoder = dyn Fn(&str, &[u8]) -> Result<TranscodedTexture, String> + Send + Sync;
+type Job<R> = (usize, Box<dyn FnOnce() -> R + Send>);
+
+// A fixed number of worker threads running jobs in the background, their results are polled from the main thread.
+// Threads are started on the first job and stopped (after their current jobs) when the pool is dropped.
+pub(crate) struct WorkerPool<R: Send + 'static> {
+    threads: usize,
+    workers: Vec<JoinHandle<()>>,
+    job_sender: Option<Sender<Job<R>>>,
+    result_sender: Sender<(usize, R)>,
+    result_receiver: Receiver<(usize, R)>,
+}
+
+impl<R: Send + 'static> WorkerPool<R> {
+    // One thread per CPU core.
+    pub(crate) fn new() -> Self {
+        let (result_sender, result_receiver) = mpsc::channel();
+        Self {
+            threads: std::thread::available_parallelism().map(|threads| threads.get()).unwrap_or(4),
+            workers: Vec::new(),
+            job_sender: None,
+            result_sender,
+            result_receiver,
+        }
+    }
+    // Takes effect only before the first job.
+    pub(crate) fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+    // Runs job on a worker, its result comes back from try_recv with the same index.
+    pub(crate) fn submit(&mut self, index: usize, job: impl FnOnce() -> R + Send + 'static) {
+        if self.job_sender.is_none() {
+            self.start_workers();
+        }
+        if let Some(sender) = &self.job_sender {
+            let _ = sender.send((index, Box::new(job)));
+        }
+    }
+    pub(crate) fn try_recv(&self) -> Option<(usize, R)> {
+        self.result_receiver.try_recv().ok()
+    }
+
+    fn start_workers(&mut self) {
+        let (job_sender, job_receiver) = mpsc::channel::<Job<R>>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        for _ in 0..self.threads {
+            let job_receiver = job_receiver.clone();
+            let result_sender = self.result_sender.clone();
+
+            self.workers.push(std::thread::spawn(move || loop {
+                let job = job_receiver.lock().unwrap().recv();
+                let Ok((index, job)) = job else { break; };
+
+                if result_sender.send((index, job())).is_err() {
+                    break;
+                }
+            }));
+        }
+        self.job_sender = Some(job_sender);
+    }
+}
+impl<R: Send + 'static> Drop for WorkerPool<R> {
+    fn drop(&mut self) {
+        // Closing the job channel stops workers after their current jobs.
+        self.job_sender = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Loads textures using a pool of worker threads (one per CPU core by default) for reading and decoding,
+/// while OpenGL uploads are made on the main thread in [TextureLoader::update] under a per-frame time budget,
+/// so a big scene loads fast without freezing the game.
+///
+/// Files are decoded with the ```image``` crate by default. Plug your own transcoder with [TextureLoader::with_transcoder]
+/// to load GPU-compressed textures (Basis Universal, KTX2, DDS) with the crate of your choice.
+/// # Example
+/// ```rust
+/// use tinystorm::loader::{TextureLoader, TextureFormat, TranscodedTexture};
+///
+/// let mut loader = TextureLoader::new()
+///     .with_transcoder(|path, bytes| {
+///         if !path.ends_with(".basis") { return TextureLoader::decode_image(path, bytes); }
+///         let mips = my_basis_transcoder::transcode_bc7(bytes)?; // Any crate you like.
+///         Ok(TranscodedTexture { width: mips.width, height: mips.height, format: TextureFormat::Bc7, mips: mips.levels })
+///     })
+///     .with_upload_budget(std::time::Duration::from_millis(2));
+///
+/// let textures: Vec<_> = level.texture_paths.iter().map(|path| loader.load(path)).collect();
+/// while window.is_running() {
+///     window.poll_events();
+///     loader.update();
+///     if loader.get_pending() > 0 { draw_loading_screen(); } else { draw_scene(); }
+///     window.swap_buffers();
+/// }
+/// ```
+pub struct TextureLoader {
+    transcoder: Arc<Transcoder>,
+    pool: WorkerPool<Result<TranscodedTexture, String>>,
+
+    paths: Vec<String>,
+    textures: Vec<GLuint>,
+    errors: Vec<Option<String>>,
+    ready: VecDeque<(usize, TranscodedTexture)>,
+    pending: usize,
+    // Checked on the first BC1/BC3 upload.
+    s3tc_supported: Option<bool>,
+
+    upload_budget: Duration,
+    filter: GLenum,
+    wrap: GLenum,
+    generation: u64,
+}
+
+impl TextureLoader {
+    /// Creates a loader with the default ```image``` crate decoder, one worker per CPU core and 2ms upload budget.
+    /// Workers are started on the first [TextureLoader::load].
+    pub fn new() -> Self {
+        Self {
+            transcoder: Arc::new(Self::decode_image),
+            pool: WorkerPool::new(),
+
+            paths: Vec::new(),
+            textures: Vec::new(),
+            errors: Vec::new(),
+            ready: VecDeque::new(),
+            pending: 0,
+            s3tc_supported: None,
+
+            upload_budget: Duration::from_millis(2),
+            filter: gl::LINEAR,
+            wrap: gl::REPEAT,
+            generation: render::context_generation(),
+        }
+    }
+    /// Sets a function that turns file bytes into a [TranscodedTexture]. It's called from worker threads.
+    pub fn with_transcoder(mut self, transcoder: impl Fn(&str, &[u8]) -> Result<TranscodedTexture, String> + Send + Sync + 'static) -> Self {
+        self.transcoder = Arc::new(transcoder);
+        self
+    }
+    /// Sets number of worker threads.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.pool.set_threads(threads);
+        self
+    }
+    /// Sets how much time [TextureLoader::update] can spend on uploads per frame. At least one texture is uploaded per frame anyway.
+    pub fn with_upload_budget(mut self, budget: Duration) -> Self {
+        self.upload_budget = budget;
+        self
+    }
+    /// Sets ```filter``` and ```wrap``` of all loaded textures, just like [crate::texture::Texture::load_from_file].
+    pub fn with_sampling(mut self, filter: GLenum, wrap: GLenum) -> Self {
+        self.filter = filter;
+        self.wrap = wrap;
+        self
+    }
+
+    /// Default transcoder: decodes any image format supported by the ```image``` crate into [TextureFormat::Rgba8].
+    pub fn decode_image(_path: &str, bytes: &[u8]) -> Result<TranscodedTexture, String> {
+        let image = image::load_from_memory(bytes).map_err(|error| error.to_string())?.flipv();
+        let (width, height) = image.dimensions();
+        Ok(TranscodedTexture { width, height, format: TextureFormat::Rgba8, mips: vec![image.to_rgba8().into_raw()] })
+    }
+
+    /// Queues a file at ```path``` to be read and decoded by workers.
+    pub fn load(&mut self, path: &str) -> LoadedTexture {
+        let index = self.paths.len();
+        self.paths.push(String::from(path));
+        self.textures.push(0);
+        self.errors.push(None);
+        self.pending += 1;
+
+        let (path, transcoder) = (String::from(path), self.transcoder.clone());
+        self.pool.submit(index, move || {
+            std::fs::read(&path).map_err(|error| error.to_string()).and_then(|bytes| transcoder(&path, &bytes))
+        });
+        LoadedTexture(index)
+    }
+    /// Uploads decoded textures until the upload budget is spent. Call it once per frame.
+    /// Textures that can't be read or decoded don't stop the game, they're marked as failed, see [TextureLoader::get_error].
+    pub fn update(&mut self) {
+        while let Some((index, result)) = self.pool.try_recv() {
+            match result {
+                Ok(texture) => self.ready.push_back((index, texture)),
+                Err(error) => self.fail(index, error),
+            }
+        }
+
+        let start = Instant::now();
+        while let Some((index, mut texture)) = self.ready.pop_front() {
+            if matches!(texture.format, TextureFormat::Bc1 | TextureFormat::Bc3) && !self.is_s3tc_supported() {
+                match texture.decode_s3tc() {
+                    Ok(decoded) => texture = decoded,
+                    Err(error) => {
+                        self.fail(index, error);
+                        continue;
+                    }
+                }
+            }
+            self.textures[index] = self.upload(&texture);
+            self.pending -= 1;
+
+            if start.elapsed() >= self.upload_budget {
+                break;
+            }
+        }
+    }
+
+    /// Binds ```texture``` to certain slot, just like [crate::texture::Texture::bind]. Binds nothing if it's not loaded yet.
+    pub fn bind(&self, texture: LoadedTexture, slot: GLenum) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + slot);
+            gl::BindTexture(gl::TEXTURE_2D, self.textures[texture.0]);
+        }
+        render::count_texture_bind();
+    }
+    /// Checks if ```texture``` is uploaded and ready to use.
+    pub fn is_loaded(&self, texture: LoadedTexture) -> bool {
+        self.textures[texture.0] != 0
+    }
+    /// Checks if ```texture``` couldn't be read or decoded. Failed textures are never uploaded and bind nothing.
+    pub fn is_failed(&self, texture: LoadedTexture) -> bool {
+        self.errors[texture.0].is_some()
+    }
+    /// Gets the reason ```texture``` failed to load, ```None``` if it's loaded or still pending.
+    pub fn get_error(&self, texture: LoadedTexture) -> Option<&str> {
+        self.errors[texture.0].as_deref()
+    }
+    /// Gets OpenGL id of ```texture```, 0 if it's not loaded yet.
+    pub fn get_texture_id(&self, texture: LoadedTexture) -> GLuint {
+        self.textures[texture.0]
+    }
+    /// Gets number of textures that aren't uploaded yet. Failed textures aren't counted.
+    pub fn get_pending(&self) -> usize {
+        self.pending
+    }
+
+    fn fail(&mut self, index: usize, error: String) {
+        self.errors[index] = Some(format!("Failed to load texture at: {}. Error: {}.", self.paths[index], error));
+        self.pending -= 1;
+    }
+    fn is_s3tc_supported(&mut self) -> bool {
+        *self.s3tc_supported.get_or_insert_with(|| render::has_extension(b"GL_EXT_texture_compression_s3tc"))
+    }
+
+    fn upload(&self, texture: &TranscodedTexture) -> GLuint {
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, self.wrap as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, self.wrap as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, (self.filter + gl::NEAREST_MIPMAP_LINEAR - gl::NEAREST) as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, self.filter as GLint);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+
+            let generate_mipmaps = texture.mips.len() == 1 && !texture.format.is_compressed();
+            if !generate_mipmaps {
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, texture.mips.len().saturating_sub(1) as GLint);
+            }
+
+            for (level, data) in texture.mips.iter().enumerate() {
+                let width = (texture.width >> level).max(1) as GLsizei;
+                let height = (texture.height >> level).max(1) as GLsizei;
+                if texture.format.is_compressed() {
+                    gl::CompressedTexImage2D(
+                        gl::TEXTURE_2D,
+                        level as GLint,
+                        texture.format.internal_format(),
+                        width,
+                        height,
+                        0,
+                        data.len() as GLsizei,
+                        data.as_ptr() as *const std::ffi::c_void,
+                    );
+                } else {
+                    gl::TexImage2D(
+                        gl::TEXTURE_2D,
+                        level as GLint,
+                        gl::RGBA8 as GLint,
+                        width,
+                        height,
+                        0,
+                        gl::RGBA,
+                        gl::UNSIGNED_BYTE,
+                        data.as_ptr() as *const std::ffi::c_void,
+                    );
+                }
+            }
+            if generate_mipmaps {
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+            }
+
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        id
+    }
+}
+impl Default for TextureLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Drop for TextureLoader {
+    /// You don't need to manually free OpenGL resources, it's done automatically.
+    fn drop(&mut self) {
+        if !render::is_current_context(self.generation) {
+            return;
+        }
+        unsafe {
+            for texture in &self.textures {
+                if *texture != 0 {
+                    gl::DeleteTextures(1, texture);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texture(format: TextureFormat, width: u32, height: u32, data: Vec<u8>) -> TranscodedTexture {
+        TranscodedTexture { width, height, format, mips: vec![data] }
+    }
+
+    #[test]
+    fn decode_bc1_four_colors() {
+        // Red and blue endpoints, rows use indices 0, 1, 2 and 3.
+        let block = vec![0x00, 0xF8, 0x1F, 0x00, 0x00, 0x55, 0xAA, 0xFF];
+        let decoded = texture(TextureFormat::Bc1, 4, 4, block).decode_s3tc().unwrap();
+
+        assert_eq!(decoded.format, TextureFormat::Rgba8);
+        let pixels = &decoded.mips[0];
+        assert_eq!(&pixels[0..4], &[255, 0, 0, 255]);
+        assert_eq!(&pixels[16..20], &[0, 0, 255, 255]);
+        assert_eq!(&pixels[32..36], &[170, 0, 85, 255]);
+        assert_eq!(&pixels[48..52], &[85, 0, 170, 255]);
+    }
+
+    #[test]
+    fn decode_bc1_punch_through_alpha() {
+        // First endpoint is smaller, so index 3 is transparent.
+        let block = vec![0x1F, 0x00, 0x00, 0xF8, 0xFF, 0xFF, 0xFF, 0xFF];
+        let decoded = texture(TextureFormat::Bc1, 4, 4, block).decode_s3tc().unwrap();
+        assert!(decoded.mips[0].chunks_exact(4).all(|pixel| pixel == [0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn decode_bc3_alpha_and_partial_blocks() {
+        // Alpha endpoints 255 and 0, first pixel uses index 0, the rest index 1. Color block is white.
+        let mut block = vec![255, 0, 0b0000_1000, 0, 0, 0, 0, 0];
+        block.extend([0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]);
+        let decoded = texture(TextureFormat::Bc3, 2, 2, block).decode_s3tc().unwrap();
+
+        let pixels = &decoded.mips[0];
+        assert_eq!(pixels.len(), 2 * 2 * 4);
+        assert_eq!(&pixels[0..4], &[255, 255, 255, 255]);
+        assert_eq!(&pixels[4..8], &[255, 255, 255, 0]);
+    }
+
+    #[test]
+    fn decode_s3tc_rejects_truncated_data() {
+        assert!(texture(TextureFormat::Bc1, 8, 8, vec![0; 8]).decode_s3tc().is_err());
+        assert!(texture(TextureFormat::Bc7, 4, 4, vec![0; 16]).decode_s3tc().is_err());
+    }
+}
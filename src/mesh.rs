@@ -1,7 +1,15 @@
-use std::f32::consts::PI;
-use gl::types::{GLenum, GLint, GLsizei, GLsizeiptr, GLuint};
+use std::{cell::Cell, cmp::Reverse, collections::{BinaryHeap, HashMap}, f32::consts::PI, hash::{BuildHasherDefault, Hasher}, rc::Rc, time::Duration};
+use gl::types::{GLenum, GLint, GLintptr, GLsizei, GLsizeiptr, GLuint};
+use nalgebra::{Matrix3, Matrix4, Point3, Vector2, Vector3, Vector4};
+
+use crate::{bvh::Aabb, fence::FenceSync, render};
 
 /// Just a vertex attribute types enum. Float, Vec2, etc.
+///
+/// Packed types take less memory than floats: ```*Norm``` ones are read as floats in 0.0 - 1.0 (unsigned) or -1.0 - 1.0 (signed) range,
+/// ```Half*``` ones are 16-bit floats, and ```Short*```/```UByte4``` are read as ```ivec```/```uvec```.
+/// To mix them with floats in one vertex, pass vertices as bytes (```Mesh::new::<u8>```).
+/// Strides of all packed types are multiples of 4 bytes, so attributes after them stay aligned.
 #[repr(u8)]
 #[derive(Clone, Copy)]
 pub enum Attribute {
@@ -24,6 +32,27 @@ pub enum Attribute {
     UVec2,
     UVec3,
     UVec4,
+
+    /// 4 unsigned bytes read as ```vec4``` in 0.0 - 1.0, e.g. colors.
+    UByte4Norm,
+    /// 4 signed bytes read as ```vec4``` in -1.0 - 1.0, e.g. normals and tangents.
+    Byte4Norm,
+    /// 4 unsigned bytes read as ```uvec4```, e.g. bone indices.
+    UByte4,
+    /// 2 unsigned shorts read as ```vec2``` in 0.0 - 1.0, e.g. UVs.
+    UShort2Norm,
+    /// 2 signed shorts read as ```vec2``` in -1.0 - 1.0.
+    Short2Norm,
+    /// 4 signed shorts read as ```vec4``` in -1.0 - 1.0, e.g. quantized positions scaled in the shader.
+    Short4Norm,
+    /// 2 signed shorts read as ```ivec2```, e.g. tile coordinates.
+    Short2,
+    /// 4 signed shorts read as ```ivec4```.
+    Short4,
+    /// 2 half floats read as ```vec2```.
+    Half2,
+    /// 4 half floats read as ```vec4```.
+    Half4,
 }
 impl Attribute {
     /// Returns size in bytes of current attribute.
@@ -51,14 +80,22 @@ impl Attribute {
             Attribute::UVec2 => 2 * std::mem::size_of::<u32>(),
             Attribute::UVec3 => 3 * std::mem::size_of::<u32>(),
             Attribute::UVec4 => 4 * std::mem::size_of::<u32>(),
+
+            Attribute::UByte4Norm | Attribute::Byte4Norm | Attribute::UByte4 => 4 * std::mem::size_of::<u8>(),
+            Attribute::UShort2Norm | Attribute::Short2Norm | Attribute::Short2 => 2 * std::mem::size_of::<u16>(),
+            Attribute::Short4Norm | Attribute::Short4 => 4 * std::mem::size_of::<u16>(),
+            Attribute::Half2 => 2 * std::mem::size_of::<u16>(),
+            Attribute::Half4 => 4 * std::mem::size_of::<u16>(),
         }
     }
 }
 
 /// A system for creating custom layouts for meshes.
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct Layout {
-    attributes: Vec<Attribute>
+    attributes: Vec<Attribute>,
+    // Divisor of every attribute, 0 for per-vertex ones.
+    divisors: Vec<u32>,
 }
 impl Layout {
     /// Best for 3D games with more improved graphics.
@@ -67,14 +104,32 @@ impl Layout {
     /// uv: [Attribute::Vec2]  
     /// normal: [Attribute::Vec3]
     pub fn default_3d() -> Self {
-        Self { attributes: vec![Attribute::Vec3, Attribute::Vec2, Attribute::Vec3] }
+        Self::per_vertex(vec![Attribute::Vec3, Attribute::Vec2, Attribute::Vec3])
+    }
+    /// Best for 3D games with normal mapping. Use [compute_tangents] or [MeshData::build_with_tangents] to get tangents.
+    /// # Layout
+    /// position: [Attribute::Vec3]  
+    /// uv: [Attribute::Vec2]  
+    /// normal: [Attribute::Vec3]  
+    /// tangent: [Attribute::Vec4] (W is bitangent sign: ```bitangent = cross(normal, tangent.xyz) * tangent.w```)
+    pub fn tangent_3d() -> Self {
+        Self::per_vertex(vec![Attribute::Vec3, Attribute::Vec2, Attribute::Vec3, Attribute::Vec4])
+    }
+    /// Best for 3D games with lightmaps or detail maps. Use [MeshData::build_with_lightmap_uvs] to generate the second UV set.
+    /// # Layout
+    /// position: [Attribute::Vec3]  
+    /// uv: [Attribute::Vec2]  
+    /// normal: [Attribute::Vec3]  
+    /// uv2: [Attribute::Vec2]
+    pub fn lightmap_3d() -> Self {
+        Self::per_vertex(vec![Attribute::Vec3, Attribute::Vec2, Attribute::Vec3, Attribute::Vec2])
     }
     /// Best for 3D games with workbench graphics.
     /// # Layout
     /// position: [Attribute::Vec3]  
     /// normal: [Attribute::Vec3]
     pub fn simple_3d() -> Self {
-        Self { attributes: vec![Attribute::Vec3, Attribute::Vec3] }
+        Self::per_vertex(vec![Attribute::Vec3, Attribute::Vec3])
     }
 
     /// Best for 2D games with simple graphics.
@@ -82,12 +137,12 @@ impl Layout {
     /// position: [Attribute::Vec2]  
     /// uv: [Attribute::Vec2]
     pub fn default_2d() -> Self {
-        Self { attributes: vec![Attribute::Vec2, Attribute::Vec2] }
+        Self::per_vertex(vec![Attribute::Vec2, Attribute::Vec2])
     }
     /// Best for 2D games with workbench graphics.
     /// position: [Attribute::Vec2]
     pub fn basic_2d() -> Self {
-        Self { attributes: vec![Attribute::Vec2] }
+        Self::per_vertex(vec![Attribute::Vec2])
     }
     
     /// Set next vertex attribute.
@@ -99,26 +154,90 @@ impl Layout {
     /// ```
     pub fn next_attribute(mut self, attribute: Attribute) -> Self {
         self.attributes.push(attribute);
+        self.divisors.push(0);
+        self
+    }
+    /// Set next per-instance attribute: it advances once per ```divisor``` instances instead of once per vertex.
+    /// Per-instance attributes live in a separate instance buffer, filled with [Mesh::set_instances] or [IndexedMesh::set_instances]
+    /// and laid out one after another just like vertex attributes. Matrices take 4 [Attribute::Vec4] attributes (one per column).
+    /// # Example
+    /// ```
+    /// let layout = Layout::default_3d()
+    ///     .next_instanced_attribute(Attribute::Vec4, 1) // Model matrix [layout(location=3..6)]
+    ///     .next_instanced_attribute(Attribute::Vec4, 1)
+    ///     .next_instanced_attribute(Attribute::Vec4, 1)
+    ///     .next_instanced_attribute(Attribute::Vec4, 1)
+    ///     .next_instanced_attribute(Attribute::UByte4Norm, 1); // Color [layout(location=7)]
+    /// ```
+    pub fn next_instanced_attribute(mut self, attribute: Attribute, divisor: u32) -> Self {
+        if divisor == 0 {
+            panic!("Failed to add instanced attribute. Divisor must be at least 1, use next_attribute for per-vertex attributes.");
+        }
+        self.attributes.push(attribute);
+        self.divisors.push(divisor);
         self
     }
-    /// Returns all attributes built using [Layout::next_attribute()]
+    /// Returns all attributes built using [Layout::next_attribute()] and [Layout::next_instanced_attribute()]
     pub fn attributes(&self) -> &[Attribute] {
         &self.attributes
     }
-}
+    /// Returns divisor of every attribute, 0 for per-vertex ones.
+    pub fn divisors(&self) -> &[u32] {
+        &self.divisors
+    }
+    /// Gets size of one vertex in bytes.
+    pub fn get_vertex_stride(&self) -> usize {
+        self.stride(false)
+    }
+    /// Gets size of one instance in bytes, 0 if there are no per-instance attributes.
+    pub fn get_instance_stride(&self) -> usize {
+        self.stride(true)
+    }
 
-fn build_attributes_and_get_stride(layout: &Layout) -> usize {
-    let mut stride = 0;
-    for attribute in layout.attributes() {
-        stride += attribute.size_in_bytes();
+    fn per_vertex(attributes: Vec<Attribute>) -> Self {
+        Self { divisors: vec![0; attributes.len()], attributes }
     }
-    
+    fn stride(&self, instanced: bool) -> usize {
+        self.attributes.iter().zip(&self.divisors)
+            .filter(|(_, divisor)| (**divisor > 0) == instanced)
+            .map(|(attribute, _)| attribute.size_in_bytes())
+            .sum()
+    }
+}
+
+// Sets up attributes of the bound VAO: per-instance ones read from a new instance buffer, per-vertex ones from vertex_buffers:
+// one interleaved buffer, or one tightly packed buffer per per-vertex attribute (planar).
+// Returns the instance buffer, or 0 if the layout has no per-instance attributes.
+fn build_attributes(layout: &Layout, vertex_buffers: &[GLuint]) -> GLuint {
+    let (vertex_stride, instance_stride) = (layout.get_vertex_stride(), layout.get_instance_stride());
+    let mut instance_buffer: GLuint = 0;
+
     unsafe {
-        let mut offset: GLuint = 0;
-        for (i, attribute) in layout.attributes().iter().enumerate() {
+        if instance_stride > 0 {
+            gl::GenBuffers(1, &mut instance_buffer);
+        }
+
+        let planar = vertex_buffers.len() > 1;
+        let (mut vertex_offset, mut instance_offset, mut stream): (GLuint, GLuint, usize) = (0, 0, 0);
+        let mut planar_offset: GLuint;
+        for (i, (attribute, &divisor)) in layout.attributes().iter().zip(layout.divisors()).enumerate() {
             let index = i as GLuint;
             gl::EnableVertexAttribArray(index);
 
+            let (stride, offset) = if divisor > 0 {
+                gl::BindBuffer(gl::ARRAY_BUFFER, instance_buffer);
+                gl::VertexAttribDivisor(index, divisor);
+                (instance_stride, &mut instance_offset)
+            } else if planar {
+                gl::BindBuffer(gl::ARRAY_BUFFER, vertex_buffers[stream]);
+                stream += 1;
+                planar_offset = 0;
+                (attribute.size_in_bytes(), &mut planar_offset)
+            } else {
+                gl::BindBuffer(gl::ARRAY_BUFFER, vertex_buffers[0]);
+                (vertex_stride, &mut vertex_offset)
+            };
+
             match attribute {
                 Attribute::Float | Attribute::Vec2 | Attribute::Vec3 | Attribute::Vec4 => {
                     gl::VertexAttribPointer(
@@ -127,7 +246,7 @@ fn build_attributes_and_get_stride(layout: &Layout) -> usize {
                         gl::FLOAT,
                         gl::FALSE,
                         stride as GLsizei,
-                        offset as *const _,
+                        *offset as *const _,
                     );
                 }
                 Attribute::Double | Attribute::DVec2 | Attribute::DVec3 | Attribute::DVec4 => {
@@ -136,7 +255,7 @@ fn build_attributes_and_get_stride(layout: &Layout) -> usize {
                         *attribute as GLint - Attribute::Double as GLint + 1,
                         gl::FLOAT,
                         stride as GLsizei,
-                        offset as *const _,
+                        *offset as *const _,
                     );
                 }
                 Attribute::Int | Attribute::IVec2 | Attribute::IVec3 | Attribute::IVec4 => {
@@ -145,7 +264,7 @@ fn build_attributes_and_get_stride(layout: &Layout) -> usize {
                         *attribute as GLint - Attribute::Int as GLint + 1,
                         gl::INT,
                         stride as GLsizei,
-                        offset as *const _,
+                        *offset as *const _,
                     );
                 }
                 Attribute::UInt | Attribute::UVec2 | Attribute::UVec3 | Attribute::UVec4 => {
@@ -154,19 +273,41 @@ fn build_attributes_and_get_stride(layout: &Layout) -> usize {
                         *attribute as GLint - Attribute::UInt as GLint + 1,
                         gl::UNSIGNED_INT,
                         stride as GLsizei,
-                        offset as *const _,
+                        *offset as *const _,
                     );
                 }
+                Attribute::UByte4Norm | Attribute::Byte4Norm | Attribute::UShort2Norm | Attribute::Short2Norm | Attribute::Short4Norm
+                | Attribute::Half2 | Attribute::Half4 => {
+                    let (size, kind, normalized) = match attribute {
+                        Attribute::UByte4Norm => (4, gl::UNSIGNED_BYTE, gl::TRUE),
+                        Attribute::Byte4Norm => (4, gl::BYTE, gl::TRUE),
+                        Attribute::UShort2Norm => (2, gl::UNSIGNED_SHORT, gl::TRUE),
+                        Attribute::Short2Norm => (2, gl::SHORT, gl::TRUE),
+                        Attribute::Short4Norm => (4, gl::SHORT, gl::TRUE),
+                        Attribute::Half2 => (2, gl::HALF_FLOAT, gl::FALSE),
+                        _ => (4, gl::HALF_FLOAT, gl::FALSE),
+                    };
+                    gl::VertexAttribPointer(i as GLuint, size, kind, normalized, stride as GLsizei, *offset as *const _);
+                }
+                Attribute::UByte4 | Attribute::Short2 | Attribute::Short4 => {
+                    let (size, kind) = match attribute {
+                        Attribute::UByte4 => (4, gl::UNSIGNED_BYTE),
+                        Attribute::Short2 => (2, gl::SHORT),
+                        _ => (4, gl::SHORT),
+                    };
+                    gl::VertexAttribIPointer(i as GLuint, size, kind, stride as GLsizei, *offset as *const _);
+                }
             }
 
-            offset += attribute.size_in_bytes() as GLuint;
+            *offset += attribute.size_in_bytes() as GLuint;
         }
     }
 
-    stride
+    instance_buffer
 }
 
 /// Just a mesh you can render on your screen.
+/// Cloning is cheap: clones share the same GPU buffers, which are freed when the last clone is dropped.
 /// # Example
 /// ```rust
 /// use tinystorm::{window::WindowBuilder, mesh::{Layout, Mesh}, gl};
@@ -181,7 +322,7 @@ fn build_attributes_and_get_stride(layout: &Layout) -> usize {
 /// 
 /// while window.is_running() {
 ///     window.poll_events();
-///     unsafe { gl::Clear(gl::COLOR_BUFFER_BIT); }
+///     unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }
 /// 
 ///     mesh.draw();
 ///     window.swap_buffers();
@@ -189,8 +330,7 @@ fn build_attributes_and_get_stride(layout: &Layout) -> usize {
 /// ```
 #[derive(Clone)]
 pub struct Mesh {
-    vao: GLuint,
-    vbo: GLuint,
+    buffers: Rc<MeshBuffers>,
 
     num_vertices: GLsizei,
     render_mode: GLenum,
@@ -199,18 +339,16 @@ impl Mesh {
     /// Returns a sphere with certain number of horizontal and vertical divisions in [Layout::simple_3d] layout.  
     /// Origin is located at it's center. Radius is 1.0
     pub fn simple_sphere(x_divisions: usize, y_divisions: usize) -> Self {
-        let mut vertices = Vec::new();
-        
+        let mut vertices = Vec::with_capacity((x_divisions + 1) * (y_divisions + 1) * 6);
+        // Longitude is the same for every ring, so its sine and cosine are computed once.
+        let longitudes: Vec<(f32, f32)> = (0..=x_divisions).map(|j| (2.0 * PI * (j as f32 / x_divisions as f32)).sin_cos()).collect();
+
         for i in 0..=y_divisions {
             let latitude = PI * (i as f32 / y_divisions as f32);
             let sin_latitude = latitude.sin();
             let cos_latitude = latitude.cos();
     
-            for j in 0..=x_divisions {
-                let longitude = 2.0 * PI * (j as f32 / x_divisions as f32);
-                let sin_longitude = longitude.sin();
-                let cos_longitude = longitude.cos();
-    
+            for &(sin_longitude, cos_longitude) in &longitudes {
                 let x = sin_latitude * cos_longitude;
                 let y = sin_latitude * sin_longitude;
                 let z = cos_latitude;
@@ -255,18 +393,16 @@ impl Mesh {
     /// Returns a sphere with certain number of horizontal and vertical divisions in [Layout::default_3d] layout.  
     /// Origin is located at it's center. Radius is 1.0
     pub fn default_sphere(x_divisions: usize, y_divisions: usize) -> Self {
-        let mut vertices = Vec::new();
-        
+        let mut vertices = Vec::with_capacity((x_divisions + 1) * (y_divisions + 1) * 8);
+        // Longitude is the same for every ring, so its sine and cosine are computed once.
+        let longitudes: Vec<(f32, f32)> = (0..=x_divisions).map(|j| (2.0 * PI * (j as f32 / x_divisions as f32)).sin_cos()).collect();
+
         for i in 0..=y_divisions {
             let latitude = PI * (i as f32 / y_divisions as f32);
             let sin_latitude = latitude.sin();
             let cos_latitude = latitude.cos();
     
-            for j in 0..=x_divisions {
-                let longitude = 2.0 * PI * (j as f32 / x_divisions as f32);
-                let sin_longitude = longitude.sin();
-                let cos_longitude = longitude.cos();
-    
+            for (j, &(sin_longitude, cos_longitude)) in longitudes.iter().enumerate() {
                 let x = sin_latitude * cos_longitude;
                 let y = sin_latitude * sin_longitude;
                 let z = cos_latitude;
@@ -313,6 +449,32 @@ impl Mesh {
     
         Self::new::<f32>(&result, &Layout::default_3d(), gl::TRIANGLES)
     }
+    /// Returns a cylinder along Y axis with caps and certain number of segments around it in [Layout::default_3d] layout.  
+    /// Origin is located at it's center. Radius is 1.0, height is 2.0
+    pub fn default_cylinder(segments: usize) -> Self {
+        MeshData::cylinder(segments).to_mesh()
+    }
+    /// Returns a cone along Y axis with a base cap and certain number of segments around it in [Layout::default_3d] layout.  
+    /// Origin is located at it's center. Base radius is 1.0 at Y = -1.0, apex is at Y = 1.0
+    pub fn default_cone(segments: usize) -> Self {
+        MeshData::cone(segments).to_mesh()
+    }
+    /// Returns a torus around Y axis with certain number of segments around the axis and sides around the tube in [Layout::default_3d] layout.  
+    /// Origin is located at it's center. Radius is 1.0 (to the tube center), tube radius is 0.25
+    pub fn default_torus(segments: usize, sides: usize) -> Self {
+        MeshData::torus(segments, sides).to_mesh()
+    }
+    /// Returns a capsule along Y axis with certain number of segments around it and rings in each hemisphere in [Layout::default_3d] layout.  
+    /// Origin is located at it's center. Radius is 1.0, height is 4.0 (2.0 without hemispheres)
+    pub fn default_capsule(segments: usize, rings: usize) -> Self {
+        MeshData::capsule(segments, rings).to_mesh()
+    }
+    /// Returns a flat plane on XZ plane facing up, split into ```columns``` x ```rows``` cells, in [Layout::default_3d] layout.  
+    /// Origin is located at it's center. Half-Size is 1.0. UVs go 0.0 - 1.0 over the whole grid, or over every cell if ```uv_repeat``` is true
+    /// (use ```gl::REPEAT``` wrapping to tile a texture).
+    pub fn grid(columns: usize, rows: usize, uv_repeat: bool) -> Self {
+        MeshData::grid(columns, rows, uv_repeat).to_mesh()
+    }
     /// Returns a cube in [Layout::default_3d] layout.  
     /// Origin is located at it's center. Half-Size is 1.0
     pub fn default_cube() -> Self {
@@ -432,6 +594,131 @@ impl Mesh {
         ], &Layout::simple_3d(), gl::TRIANGLES)
     }
 
+    /// Returns a single triangle covering the whole screen in [Layout::basic_2d] layout.  
+    /// Vertex positions are already in normalized device coordinates, so it's the cheapest way to draw fullscreen effects.
+    pub fn fullscreen_triangle() -> Self {
+        Self::new::<f32>(&[
+            -1.0, -1.0,
+             3.0, -1.0,
+            -1.0,  3.0,
+        ], &Layout::basic_2d(), gl::TRIANGLES)
+    }
+    /// Returns a ```width``` x ```height``` pixels quad for sprites in [Layout::default_2d] layout, drawn as ```gl::TRIANGLE_FAN```.
+    /// Positions are in pixels with Y going down, to be drawn with [crate::camera::pixel_projection].
+    /// ```pivot``` is the origin in 0.0 - 1.0 of the quad size: (0.0, 0.0) is the top-left corner, (0.5, 1.0) is the bottom center (feet of a character).
+    /// UVs cover the whole texture, upright.
+    /// # Example
+    /// ```rust
+    /// use tinystorm::{camera, mesh::Mesh};
+    ///
+    /// let player = Mesh::sprite_quad(32.0, 48.0, Vector2::new(0.5, 1.0));
+    /// shader.set_mat4("u_Projection", &camera::pixel_projection(window.get_width() as f32, window.get_height() as f32));
+    /// shader.set_mat4("u_Model", &Matrix4::new_translation(&Vector3::new(player_x, player_y, 0.0)));
+    /// player.draw();
+    /// ```
+    pub fn sprite_quad(width: f32, height: f32, pivot: Vector2<f32>) -> Self {
+        let (left, top) = (-pivot.x * width, -pivot.y * height);
+        let (right, bottom) = (left + width, top + height);
+        // Textures are flipped on load, so the top edge gets V = 1.0.
+        Self::new::<f32>(&[
+            left,  bottom, 0.0, 0.0,
+            right, bottom, 1.0, 0.0,
+            right, top,    1.0, 1.0,
+            left,  top,    0.0, 1.0,
+        ], &Layout::default_2d(), gl::TRIANGLE_FAN)
+    }
+    /// Returns a circle with certain number of segments in [Layout::default_2d] layout, drawn as ```gl::TRIANGLE_FAN```.  
+    /// Origin is located at it's center. Radius is 1.0, UVs go 0.0 - 1.0 over its bounding square.
+    pub fn circle(segments: usize) -> Self {
+        let segments = segments.max(3);
+        let mut vertices = vec![0.0, 0.0, 0.5, 0.5];
+        for i in 0..=segments {
+            let (sin, cos) = (2.0 * PI * i as f32 / segments as f32).sin_cos();
+            vertices.extend_from_slice(&[cos, sin, cos * 0.5 + 0.5, sin * 0.5 + 0.5]);
+        }
+        Self::new::<f32>(&vertices, &Layout::default_2d(), gl::TRIANGLE_FAN)
+    }
+    /// Returns a ring between ```inner``` and ```outer``` radiuses with certain number of segments in [Layout::default_2d] layout,
+    /// drawn as ```gl::TRIANGLE_STRIP```.  
+    /// Origin is located at it's center. UVs go 0.0 - 1.0 over its bounding square.
+    pub fn ring(inner: f32, outer: f32, segments: usize) -> Self {
+        let segments = segments.max(3);
+        let mut vertices = Vec::with_capacity((segments + 1) * 8);
+        for i in 0..=segments {
+            let (sin, cos) = (2.0 * PI * i as f32 / segments as f32).sin_cos();
+            for radius in [inner, outer] {
+                let uv_scale = radius / outer.max(f32::EPSILON) * 0.5;
+                vertices.extend_from_slice(&[cos * radius, sin * radius, cos * uv_scale + 0.5, sin * uv_scale + 0.5]);
+            }
+        }
+        Self::new::<f32>(&vertices, &Layout::default_2d(), gl::TRIANGLE_STRIP)
+    }
+    /// Returns a ```width``` x ```height``` rectangle with corners rounded by ```radius``` and certain number of segments per corner
+    /// in [Layout::default_2d] layout, drawn as ```gl::TRIANGLE_FAN```. Useful for buttons and panels.  
+    /// Origin is located at it's center. Radius is clamped to half of the shorter side, UVs go 0.0 - 1.0 over the rectangle.
+    pub fn rounded_rect(width: f32, height: f32, radius: f32, segments: usize) -> Self {
+        let segments = segments.max(1);
+        let (half_width, half_height) = (width * 0.5, height * 0.5);
+        let radius = radius.clamp(0.0, half_width.min(half_height));
+
+        let mut vertices = vec![0.0, 0.0, 0.5, 0.5];
+        let corners = [(1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0), (1.0, -1.0)];
+        for (corner, (x, y)) in corners.iter().enumerate() {
+            let center = Vector2::new(x * (half_width - radius), y * (half_height - radius));
+            for i in 0..=segments {
+                let (sin, cos) = (PI * 0.5 * (corner as f32 + i as f32 / segments as f32)).sin_cos();
+                let point = center + Vector2::new(cos, sin) * radius;
+                vertices.extend_from_slice(&[point.x, point.y, point.x / width.max(f32::EPSILON) + 0.5, point.y / height.max(f32::EPSILON) + 0.5]);
+            }
+        }
+        // Closes the outline at the first perimeter vertex.
+        vertices.extend_from_within(4..8);
+        Self::new::<f32>(&vertices, &Layout::default_2d(), gl::TRIANGLE_FAN)
+    }
+    /// Returns a thick line through ```points``` in [Layout::default_2d] layout, drawn as ```gl::TRIANGLE_STRIP```.
+    /// Corners are mitered (cut off when they're too sharp), ends are flat. Use it instead of ```gl::LINES```, whose width
+    /// isn't reliable across drivers. UV.x is distance along the line (for dashes or repeating textures), UV.y goes 0.0 - 1.0 across it.
+    /// Panics if there are less than 2 distinct points.
+    /// # Example
+    /// ```rust
+    /// let path = Mesh::polyline_2d(&[Vector2::new(-0.5, 0.0), Vector2::new(0.0, 0.4), Vector2::new(0.5, 0.0)], 0.05);
+    /// ```
+    pub fn polyline_2d(points: &[Vector2<f32>], thickness: f32) -> Self {
+        let mut points = points.to_vec();
+        points.dedup_by(|a, b| (*a - *b).norm_squared() <= f32::EPSILON);
+        if points.len() < 2 {
+            panic!("Failed to create polyline. Got {} distinct points, but at least 2 are required.", points.len());
+        }
+
+        let half = thickness * 0.5;
+        let directions: Vec<Vector2<f32>> = points.windows(2).map(|pair| (pair[1] - pair[0]).normalize()).collect();
+        let mut vertices = Vec::with_capacity(points.len() * 8);
+        let mut distance = 0.0;
+        for (i, point) in points.iter().enumerate() {
+            let previous = directions[i.saturating_sub(1)];
+            let next = directions[i.min(directions.len() - 1)];
+            let normal = Vector2::new(-previous.y, previous.x);
+
+            // Miter is along the average normal, longer at sharper corners. Too long ones are limited to avoid spikes.
+            let tangent = (previous + next).try_normalize(f32::EPSILON).unwrap_or(next);
+            let miter = Vector2::new(-tangent.y, tangent.x);
+            let offset = miter * (half / miter.dot(&normal).max(0.25));
+
+            if i > 0 {
+                distance += (point - points[i - 1]).norm();
+            }
+            let (left, right) = (point + offset, point - offset);
+            vertices.extend_from_slice(&[left.x, left.y, distance, 0.0, right.x, right.y, distance, 1.0]);
+        }
+        Self::new::<f32>(&vertices, &Layout::default_2d(), gl::TRIANGLE_STRIP)
+    }
+    /// Returns a line through ```points``` drawn as ```gl::LINE_STRIP```, with only a position attribute ([Attribute::Vec3]).
+    /// Line width depends on the driver (usually 1 pixel), use [Mesh::polyline_2d] or [MeshData::extrude] for thick lines.
+    pub fn line_strip_3d(points: &[Vector3<f32>]) -> Self {
+        let vertices: Vec<f32> = points.iter().flat_map(|point| [point.x, point.y, point.z]).collect();
+        Self::new::<f32>(&vertices, &Layout::default().next_attribute(Attribute::Vec3), gl::LINE_STRIP)
+    }
+
     /// Creates a mesh with your vertices, custom vertex layout and render mode.
     /// # Example
     /// ```
@@ -454,9 +741,27 @@ impl Mesh {
             gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
             gl::BufferData(gl::ARRAY_BUFFER, std::mem::size_of_val(vertices) as GLsizeiptr, vertices.as_ptr() as *const _, gl::STATIC_DRAW);
         }
+        render::count_buffer_upload(std::mem::size_of_val(vertices));
         
-        let stride = build_attributes_and_get_stride(layout);
-        Self { vao, vbo, num_vertices: (std::mem::size_of_val(vertices) / stride) as GLsizei, render_mode }
+        let instance_vbo = build_attributes(layout, &[vbo]);
+        let num_vertices = (std::mem::size_of_val(vertices) / layout.get_vertex_stride().max(1)) as GLsizei;
+        Self { buffers: Rc::new(MeshBuffers { vao, vbo, ebo: 0, instance_vbo, vertex_bytes: std::mem::size_of_val(vertices), planar_vbos: Vec::new(), instance_bytes: Cell::new(0), layout: layout.clone(), generation: render::context_generation() }), num_vertices, render_mode }
+    }
+    /// Creates a mesh from separate per-attribute slices (positions, UVs, normals...), each uploaded into its own vertex buffer,
+    /// so data you already keep split (e.g. from a loader or a simulation) doesn't need to be interleaved first.
+    /// ```streams``` go in the order of per-vertex attributes of ```layout``` and must all hold the same number of vertices,
+    /// per-instance attributes still come from [Mesh::set_instances].
+    /// Planar meshes can't be updated with [Mesh::update_vertices_range].
+    /// # Example
+    /// ```rust
+    /// let mesh = Mesh::from_planar::<f32>(&[&positions, &uvs, &normals], &Layout::default_3d(), gl::TRIANGLES);
+    /// ```
+    pub fn from_planar<T>(streams: &[&[T]], layout: &Layout, render_mode: GLenum) -> Self {
+        let (vao, mut vbos, num_vertices) = MeshBuffers::upload_planar(streams, layout);
+        let instance_vbo = build_attributes(layout, &vbos);
+        let vbo = vbos.remove(0);
+        let vertex_bytes = streams.iter().map(|stream| std::mem::size_of_val(*stream)).sum();
+        Self { buffers: Rc::new(MeshBuffers { vao, vbo, ebo: 0, instance_vbo, vertex_bytes, planar_vbos: vbos, instance_bytes: Cell::new(0), layout: layout.clone(), generation: render::context_generation() }), num_vertices: num_vertices as GLsizei, render_mode }
     }
 
     /// Draws the mesh itself.
@@ -464,7 +769,7 @@ impl Mesh {
     /// ```
     /// // You must clear the framebuffer before rendering meshes on it,
     /// // else your mesh won't appear on screen.
-    /// unsafe { gl::Clear(gl::COLOR_BUFFER_BIT); }
+    /// unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }
     /// ...
     /// mesh.draw();
     /// other_mesh.draw();
@@ -474,22 +779,200 @@ impl Mesh {
     /// ```
     pub fn draw(&self) {
         unsafe {
-            gl::BindVertexArray(self.vao);
+            gl::BindVertexArray(self.buffers.vao);
             gl::DrawArrays(self.render_mode, 0, self.num_vertices);
         }
+        render::count_draw(self.render_mode, self.num_vertices as usize);
+    }
+    /// Draws the mesh as wireframe, polygon mode is restored afterwards. See [render::set_polygon_mode].
+    pub fn draw_wireframe(&self) {
+        render::with_polygon_mode(render::PolygonMode::Wireframe, || self.draw());
+    }
+    /// Draws only ```count``` vertices starting at ```first_vertex```, e.g. the part of a ring buffer written this frame.
+    /// The range is clamped to the mesh.
+    /// # Example
+    /// ```rust
+    /// particles.update_vertices_range::<f32>(head * 8, &new_particles);
+    /// particles.draw_partial(head, new_particles.len() / 8);
+    /// ```
+    pub fn draw_partial(&self, first_vertex: usize, count: usize) {
+        let count = count.min((self.num_vertices as usize).saturating_sub(first_vertex));
+        if count == 0 {
+            return;
+        }
+        unsafe {
+            gl::BindVertexArray(self.buffers.vao);
+            gl::DrawArrays(self.render_mode, first_vertex as GLint, count as GLsizei);
+        }
+        render::count_draw(self.render_mode, count);
+    }
+
+    /// Draws several ```(first, count)``` vertex ranges with one call (```glMultiDrawArrays```),
+    /// e.g. visible chunks of a world stored in one buffer. Ranges are clamped to the mesh, like in [Mesh::draw_partial].
+    /// # Example
+    /// ```rust
+    /// let ranges: Vec<(usize, usize)> = chunks.iter().filter(|chunk| chunk.is_visible).map(|chunk| chunk.range).collect();
+    /// world.multi_draw(&ranges);
+    /// ```
+    pub fn multi_draw(&self, ranges: &[(usize, usize)]) {
+        let ranges = clamp_ranges(ranges, self.num_vertices as usize);
+        if ranges.is_empty() {
+            return;
+        }
+        let firsts: Vec<GLint> = ranges.iter().map(|&(first, _)| first as GLint).collect();
+        let counts: Vec<GLsizei> = ranges.iter().map(|&(_, count)| count as GLsizei).collect();
+        unsafe {
+            gl::BindVertexArray(self.buffers.vao);
+            gl::MultiDrawArrays(self.render_mode, firsts.as_ptr(), counts.as_ptr(), ranges.len() as GLsizei);
+        }
+        render::count_draw(self.render_mode, counts.iter().sum::<GLsizei>() as usize);
+    }
+    /// Replaces a part of vertex data starting at ```offset``` (counted in ```T```s, like ```vertices```) with ```vertices```,
+    /// so only the changed part of a big dynamic mesh (e.g. one chunk of a tilemap) is uploaded. Vertex count stays the same.
+    /// Panics if it goes past the end of the vertex buffer or the mesh is planar.
+    /// # Example
+    /// ```rust
+    /// // Tile 37 changed: 4 vertices of 4 floats each.
+    /// tilemap.update_vertices_range::<f32>(37 * 16, &tile_vertices);
+    /// ```
+    pub fn update_vertices_range<T>(&self, offset: usize, vertices: &[T]) {
+        let (start, size) = (offset * std::mem::size_of::<T>(), std::mem::size_of_val(vertices));
+        if !self.buffers.planar_vbos.is_empty() {
+            panic!("Failed to update mesh vertices. Planar meshes can't be updated.");
+        }
+        if start + size > self.buffers.vertex_bytes {
+            panic!("Failed to update mesh vertices. Range {}..{} is out of the {} bytes vertex buffer.", start, start + size, self.buffers.vertex_bytes);
+        }
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.buffers.vbo);
+            gl::BufferSubData(gl::ARRAY_BUFFER, start as GLintptr, size as GLsizeiptr, vertices.as_ptr() as *const _);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        render::count_buffer_upload(size);
+    }
+    /// Replaces per-instance data: instances one after another, laid out like per-instance attributes of the layout
+    /// (see [Layout::next_instanced_attribute]). Panics if the layout has no per-instance attributes.
+    pub fn set_instances<T>(&self, instances: &[T]) {
+        if self.buffers.instance_vbo == 0 {
+            panic!("Failed to set Mesh instances. Its layout has no per-instance attributes.");
+        }
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.buffers.instance_vbo);
+            gl::BufferData(gl::ARRAY_BUFFER, std::mem::size_of_val(instances) as GLsizeiptr, instances.as_ptr() as *const _, gl::DYNAMIC_DRAW);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        self.buffers.instance_bytes.set(std::mem::size_of_val(instances));
+        render::count_buffer_upload(std::mem::size_of_val(instances));
+    }
+    /// Draws ```instances``` copies of the mesh with one call. Per-instance attributes come from [Mesh::set_instances].
+    pub fn draw_instanced(&self, instances: usize) {
+        unsafe {
+            gl::BindVertexArray(self.buffers.vao);
+            gl::DrawArraysInstanced(self.render_mode, 0, self.num_vertices, instances as GLsizei);
+        }
+        render::count_draw(self.render_mode, self.num_vertices as usize * instances);
+    }
+
+    /// Gets number of vertices.
+    pub fn get_vertex_count(&self) -> usize {
+        self.num_vertices as usize
+    }
+    /// Gets number of triangles, 0 if the mesh isn't drawn as triangles.
+    pub fn get_triangle_count(&self) -> usize {
+        match self.render_mode {
+            gl::TRIANGLES => self.get_vertex_count() / 3,
+            gl::TRIANGLE_STRIP | gl::TRIANGLE_FAN => self.get_vertex_count().saturating_sub(2),
+            _ => 0,
+        }
+    }
+    /// Gets render mode, e.g. ```gl::TRIANGLES```.
+    pub fn get_render_mode(&self) -> GLenum {
+        self.render_mode
+    }
+    /// Gets layout the mesh was created with.
+    pub fn get_layout(&self) -> &Layout {
+        &self.buffers.layout
+    }
+    /// Gets size of one vertex in bytes.
+    pub fn get_vertex_stride(&self) -> usize {
+        self.buffers.layout.get_vertex_stride()
+    }
+    /// Gets estimated GPU memory taken by the mesh in bytes: vertex and instance buffers. Drivers may allocate a bit more.
+    pub fn get_gpu_memory(&self) -> usize {
+        self.buffers.vertex_bytes + self.buffers.instance_bytes.get()
+    }
+}
+
+impl MeshBuffers {
+    // Creates and binds a VAO and uploads each planar stream into its own buffer.
+    // Returns the VAO, the buffers and the number of vertices. Panics if streams don't match per-vertex attributes of the layout.
+    fn upload_planar<T>(streams: &[&[T]], layout: &Layout) -> (GLuint, Vec<GLuint>, usize) {
+        let attributes: Vec<Attribute> = layout.attributes().iter().zip(layout.divisors())
+            .filter(|(_, divisor)| **divisor == 0)
+            .map(|(attribute, _)| *attribute)
+            .collect();
+        if streams.len() != attributes.len() || streams.is_empty() {
+            panic!("Failed to create planar mesh. Got {} streams, but the layout has {} per-vertex attributes.", streams.len(), attributes.len());
+        }
+        let vertex_count = std::mem::size_of_val(streams[0]) / attributes[0].size_in_bytes();
+        for (i, (stream, attribute)) in streams.iter().zip(&attributes).enumerate() {
+            if std::mem::size_of_val(*stream) != vertex_count * attribute.size_in_bytes() {
+                panic!("Failed to create planar mesh. Stream {} has {} bytes, but {} vertices need {}.", i, std::mem::size_of_val(*stream), vertex_count, vertex_count * attribute.size_in_bytes());
+            }
+        }
+
+        let mut vao: GLuint = 0;
+        let mut vbos: Vec<GLuint> = vec![0; streams.len()];
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+            gl::GenBuffers(vbos.len() as GLsizei, vbos.as_mut_ptr());
+            for (stream, &vbo) in streams.iter().zip(&vbos) {
+                gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+                gl::BufferData(gl::ARRAY_BUFFER, std::mem::size_of_val(*stream) as GLsizeiptr, stream.as_ptr() as *const _, gl::STATIC_DRAW);
+                render::count_buffer_upload(std::mem::size_of_val(*stream));
+            }
+        }
+        (vao, vbos, vertex_count)
     }
 }
-impl Drop for Mesh {
+
+// GPU objects of a mesh, shared by all its clones and deleted when the last one is dropped.
+// Rc and not Arc: they belong to the OpenGL context's thread and can't be deleted from another one.
+struct MeshBuffers {
+    vao: GLuint,
+    vbo: GLuint,
+    // 0 for non-indexed meshes, deleting it does nothing.
+    ebo: GLuint,
+    // 0 if the layout has no per-instance attributes.
+    instance_vbo: GLuint,
+    // Vertex buffers of attributes after the first one of planar meshes, empty for interleaved ones.
+    planar_vbos: Vec<GLuint>,
+    // Size of the vertex buffer (all of them for planar meshes) in bytes.
+    vertex_bytes: usize,
+    // Size of the instance buffer in bytes, changed by set_instances.
+    instance_bytes: Cell<usize>,
+    layout: Layout,
+    generation: u64,
+}
+impl Drop for MeshBuffers {
     /// You don't need to manually free OpenGL resources, it's done automatically.
     fn drop(&mut self) {
+        if !render::is_current_context(self.generation) {
+            return;
+        }
         unsafe {
             gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteBuffers(1, &self.ebo);
             gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteBuffers(1, &self.instance_vbo);
+            gl::DeleteBuffers(self.planar_vbos.len() as GLsizei, self.planar_vbos.as_ptr());
         }
     }
 }
 
 /// Just a mesh you can render on your screen.
+/// Cloning is cheap: clones share the same GPU buffers, which are freed when the last clone is dropped.
 /// # Example
 /// ```rust
 /// use tinystorm::{window::WindowBuilder, mesh::{Layout, Mesh}, gl};
@@ -504,7 +987,7 @@ impl Drop for Mesh {
 /// 
 /// while window.is_running() {
 ///     window.poll_events();
-///     unsafe { gl::Clear(gl::COLOR_BUFFER_BIT); }
+///     unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }
 /// 
 ///     mesh.draw();
 ///     window.swap_buffers();
@@ -512,29 +995,26 @@ impl Drop for Mesh {
 /// ```
 #[derive(Clone)]
 pub struct IndexedMesh {
-    vao: GLuint,
-    ebo: GLuint,
-    vbo: GLuint,
+    buffers: Rc<MeshBuffers>,
 
     num_indices: GLsizei,
     render_mode: GLenum,
+    ranges: Vec<DrawRange>,
 }
 impl IndexedMesh {
     /// Returns a sphere with certain number of horizontal and vertical divisions in [Layout::simple_3d] layout.  
     /// Origin is located at it's center. Radius is 1.0
     pub fn simple_sphere(x_divisions: usize, y_divisions: usize) -> Self {
-        let mut vertices = Vec::new();
-        
+        let mut vertices = Vec::with_capacity((x_divisions + 1) * (y_divisions + 1) * 6);
+        // Longitude is the same for every ring, so its sine and cosine are computed once.
+        let longitudes: Vec<(f32, f32)> = (0..=x_divisions).map(|j| (2.0 * PI * (j as f32 / x_divisions as f32)).sin_cos()).collect();
+
         for i in 0..=y_divisions {
             let latitude = PI * (i as f32 / y_divisions as f32);
             let sin_latitude = latitude.sin();
             let cos_latitude = latitude.cos();
     
-            for j in 0..=x_divisions {
-                let longitude = 2.0 * PI * (j as f32 / x_divisions as f32);
-                let sin_longitude = longitude.sin();
-                let cos_longitude = longitude.cos();
-    
+            for &(sin_longitude, cos_longitude) in &longitudes {
                 let x = sin_latitude * cos_longitude;
                 let y = sin_latitude * sin_longitude;
                 let z = cos_latitude;
@@ -573,18 +1053,16 @@ impl IndexedMesh {
     /// Returns a sphere with certain number of horizontal and vertical divisions in [Layout::default_3d] layout.  
     /// Origin is located at it's center. Radius is 1.0
     pub fn default_sphere(x_divisions: usize, y_divisions: usize) -> Self {
-        let mut vertices = Vec::new();
-        
+        let mut vertices = Vec::with_capacity((x_divisions + 1) * (y_divisions + 1) * 8);
+        // Longitude is the same for every ring, so its sine and cosine are computed once.
+        let longitudes: Vec<(f32, f32)> = (0..=x_divisions).map(|j| (2.0 * PI * (j as f32 / x_divisions as f32)).sin_cos()).collect();
+
         for i in 0..=y_divisions {
             let latitude = PI * (i as f32 / y_divisions as f32);
             let sin_latitude = latitude.sin();
             let cos_latitude = latitude.cos();
     
-            for j in 0..=x_divisions {
-                let longitude = 2.0 * PI * (j as f32 / x_divisions as f32);
-                let sin_longitude = longitude.sin();
-                let cos_longitude = longitude.cos();
-    
+            for (j, &(sin_longitude, cos_longitude)) in longitudes.iter().enumerate() {
                 let x = sin_latitude * cos_longitude;
                 let y = sin_latitude * sin_longitude;
                 let z = cos_latitude;
@@ -625,6 +1103,32 @@ impl IndexedMesh {
     
         Self::new::<f32>(&indices, &vertices, &Layout::default_3d(), gl::TRIANGLES)
     }
+    /// Returns a cylinder along Y axis with caps and certain number of segments around it in [Layout::default_3d] layout.  
+    /// Origin is located at it's center. Radius is 1.0, height is 2.0
+    pub fn default_cylinder(segments: usize) -> Self {
+        MeshData::cylinder(segments).build()
+    }
+    /// Returns a cone along Y axis with a base cap and certain number of segments around it in [Layout::default_3d] layout.  
+    /// Origin is located at it's center. Base radius is 1.0 at Y = -1.0, apex is at Y = 1.0
+    pub fn default_cone(segments: usize) -> Self {
+        MeshData::cone(segments).build()
+    }
+    /// Returns a torus around Y axis with certain number of segments around the axis and sides around the tube in [Layout::default_3d] layout.  
+    /// Origin is located at it's center. Radius is 1.0 (to the tube center), tube radius is 0.25
+    pub fn default_torus(segments: usize, sides: usize) -> Self {
+        MeshData::torus(segments, sides).build()
+    }
+    /// Returns a capsule along Y axis with certain number of segments around it and rings in each hemisphere in [Layout::default_3d] layout.  
+    /// Origin is located at it's center. Radius is 1.0, height is 4.0 (2.0 without hemispheres)
+    pub fn default_capsule(segments: usize, rings: usize) -> Self {
+        MeshData::capsule(segments, rings).build()
+    }
+    /// Returns a flat plane on XZ plane facing up, split into ```columns``` x ```rows``` cells, in [Layout::default_3d] layout.  
+    /// Origin is located at it's center. Half-Size is 1.0. UVs go 0.0 - 1.0 over the whole grid, or over every cell if ```uv_repeat``` is true
+    /// (use ```gl::REPEAT``` wrapping to tile a texture).
+    pub fn grid(columns: usize, rows: usize, uv_repeat: bool) -> Self {
+        MeshData::grid(columns, rows, uv_repeat).build()
+    }
     /// Returns a cube in [Layout::default_3d] layout.  
     /// Origin is located at it's center. Half-Size is 1.0
     pub fn default_cube() -> Self {
@@ -760,9 +1264,32 @@ impl IndexedMesh {
             gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
             gl::BufferData(gl::ARRAY_BUFFER, std::mem::size_of_val(vertices) as GLsizeiptr, vertices.as_ptr() as *const _, gl::STATIC_DRAW);
         }
+        render::count_buffer_upload(std::mem::size_of_val(indices));
+        render::count_buffer_upload(std::mem::size_of_val(vertices));
         
-        build_attributes_and_get_stride(layout);
-        Self { vao, vbo, ebo, num_indices: std::mem::size_of_val(indices) as GLsizei, render_mode }
+        let instance_vbo = build_attributes(layout, &[vbo]);
+        Self { buffers: Rc::new(MeshBuffers { vao, vbo, ebo, instance_vbo, vertex_bytes: std::mem::size_of_val(vertices), planar_vbos: Vec::new(), instance_bytes: Cell::new(0), layout: layout.clone(), generation: render::context_generation() }), num_indices: indices.len() as GLsizei, render_mode, ranges: Vec::new() }
+    }
+    /// Creates an indexed mesh from separate per-attribute slices, each uploaded into its own vertex buffer, see [Mesh::from_planar].
+    /// Planar meshes can't be updated with [IndexedMesh::update_vertices_range] or simplified with [IndexedMesh::simplified].
+    /// # Example
+    /// ```rust
+    /// let terrain = IndexedMesh::from_planar::<f32>(&indices, &[&positions, &uvs, &normals], &Layout::default_3d(), gl::TRIANGLES);
+    /// ```
+    pub fn from_planar<T>(indices: &[u32], streams: &[&[T]], layout: &Layout, render_mode: GLenum) -> Self {
+        let (vao, mut vbos, _) = MeshBuffers::upload_planar(streams, layout);
+        let mut ebo: GLuint = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut ebo);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+            gl::BufferData(gl::ELEMENT_ARRAY_BUFFER, std::mem::size_of_val(indices) as GLsizeiptr, indices.as_ptr() as *const _, gl::STATIC_DRAW);
+        }
+        render::count_buffer_upload(std::mem::size_of_val(indices));
+
+        let instance_vbo = build_attributes(layout, &vbos);
+        let vbo = vbos.remove(0);
+        let vertex_bytes = streams.iter().map(|stream| std::mem::size_of_val(*stream)).sum();
+        Self { buffers: Rc::new(MeshBuffers { vao, vbo, ebo, instance_vbo, vertex_bytes, planar_vbos: vbos, instance_bytes: Cell::new(0), layout: layout.clone(), generation: render::context_generation() }), num_indices: indices.len() as GLsizei, render_mode, ranges: Vec::new() }
     }
 
     /// Draws the mesh itself.
@@ -770,7 +1297,7 @@ impl IndexedMesh {
     /// ```
     /// // You must clear the framebuffer before rendering meshes on it,
     /// // else your mesh won't appear on screen.
-    /// unsafe { gl::Clear(gl::COLOR_BUFFER_BIT); }
+    /// unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }
     /// ...
     /// mesh.draw();
     /// other_mesh.draw();
@@ -780,18 +1307,1580 @@ impl IndexedMesh {
     /// ```
     pub fn draw(&self) {
         unsafe {
-            gl::BindVertexArray(self.vao);
+            gl::BindVertexArray(self.buffers.vao);
             gl::DrawElements(self.render_mode, self.num_indices, gl::UNSIGNED_INT, std::ptr::null());
         }
+        render::count_draw(self.render_mode, self.num_indices as usize);
     }
-}
-impl Drop for IndexedMesh {
-    /// You don't need to manually free OpenGL resources, it's done automatically.
-    fn drop(&mut self) {
+    /// Draws the mesh as wireframe, polygon mode is restored afterwards. See [render::set_polygon_mode].
+    pub fn draw_wireframe(&self) {
+        render::with_polygon_mode(render::PolygonMode::Wireframe, || self.draw());
+    }
+    /// Draws only ```count``` indices starting at ```first_index```, e.g. the part of a ring buffer written this frame.
+    /// The range is clamped to the mesh. See also [IndexedMesh::draw_range].
+    pub fn draw_partial(&self, first_index: usize, count: usize) {
+        let count = count.min((self.num_indices as usize).saturating_sub(first_index));
+        if count == 0 {
+            return;
+        }
         unsafe {
-            gl::DeleteVertexArrays(1, &self.vao);
-            gl::DeleteBuffers(1, &self.ebo);
-            gl::DeleteBuffers(1, &self.vbo);
+            gl::BindVertexArray(self.buffers.vao);
+            gl::DrawElements(self.render_mode, count as GLsizei, gl::UNSIGNED_INT, (first_index * std::mem::size_of::<u32>()) as *const std::ffi::c_void);
+        }
+        render::count_draw(self.render_mode, count);
+    }
+
+    /// Draws several ```(offset, count)``` index ranges with one call (```glMultiDrawElements```), offsets are counted in indices.
+    /// Ranges added with [IndexedMesh::with_range] can be passed as ```(range.offset, range.count)```.
+    /// Ranges are clamped to the mesh, like in [IndexedMesh::draw_partial].
+    pub fn multi_draw(&self, ranges: &[(usize, usize)]) {
+        let ranges = clamp_ranges(ranges, self.num_indices as usize);
+        if ranges.is_empty() {
+            return;
+        }
+        let offsets: Vec<*const std::ffi::c_void> = ranges.iter()
+            .map(|&(offset, _)| (offset * std::mem::size_of::<u32>()) as *const std::ffi::c_void)
+            .collect();
+        let counts: Vec<GLsizei> = ranges.iter().map(|&(_, count)| count as GLsizei).collect();
+        unsafe {
+            gl::BindVertexArray(self.buffers.vao);
+            gl::MultiDrawElements(self.render_mode, counts.as_ptr(), gl::UNSIGNED_INT, offsets.as_ptr(), ranges.len() as GLsizei);
+        }
+        render::count_draw(self.render_mode, counts.iter().sum::<GLsizei>() as usize);
+    }
+    /// Replaces a part of vertex data starting at ```offset``` (counted in ```T```s, like ```vertices```) with ```vertices```,
+    /// so only the changed part of a big dynamic mesh (e.g. one chunk of a tilemap) is uploaded. Vertex count stays the same.
+    /// Panics if it goes past the end of the vertex buffer or the mesh is planar.
+    /// # Example
+    /// ```rust
+    /// // Tile 37 changed: 4 vertices of 4 floats each.
+    /// tilemap.update_vertices_range::<f32>(37 * 16, &tile_vertices);
+    /// ```
+    pub fn update_vertices_range<T>(&self, offset: usize, vertices: &[T]) {
+        let (start, size) = (offset * std::mem::size_of::<T>(), std::mem::size_of_val(vertices));
+        if !self.buffers.planar_vbos.is_empty() {
+            panic!("Failed to update indexed mesh vertices. Planar meshes can't be updated.");
+        }
+        if start + size > self.buffers.vertex_bytes {
+            panic!("Failed to update indexed mesh vertices. Range {}..{} is out of the {} bytes vertex buffer.", start, start + size, self.buffers.vertex_bytes);
+        }
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.buffers.vbo);
+            gl::BufferSubData(gl::ARRAY_BUFFER, start as GLintptr, size as GLsizeiptr, vertices.as_ptr() as *const _);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        render::count_buffer_upload(size);
+    }
+    /// Replaces per-instance data: instances one after another, laid out like per-instance attributes of the layout
+    /// (see [Layout::next_instanced_attribute]). Panics if the layout has no per-instance attributes.
+    pub fn set_instances<T>(&self, instances: &[T]) {
+        if self.buffers.instance_vbo == 0 {
+            panic!("Failed to set IndexedMesh instances. Its layout has no per-instance attributes.");
+        }
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.buffers.instance_vbo);
+            gl::BufferData(gl::ARRAY_BUFFER, std::mem::size_of_val(instances) as GLsizeiptr, instances.as_ptr() as *const _, gl::DYNAMIC_DRAW);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        self.buffers.instance_bytes.set(std::mem::size_of_val(instances));
+        render::count_buffer_upload(std::mem::size_of_val(instances));
+    }
+    /// Draws ```instances``` copies of the mesh with one call. Per-instance attributes come from [IndexedMesh::set_instances].
+    pub fn draw_instanced(&self, instances: usize) {
+        unsafe {
+            gl::BindVertexArray(self.buffers.vao);
+            gl::DrawElementsInstanced(self.render_mode, self.num_indices, gl::UNSIGNED_INT, std::ptr::null(), instances as GLsizei);
         }
+        render::count_draw(self.render_mode, self.num_indices as usize * instances);
     }
-}
\ No newline at end of file
+    // Grows the instance buffer to at least bytes without uploading anything, for instances written on the GPU.
+    pub(crate) fn reserve_instances(&self, bytes: usize) -> GLuint {
+        if self.buffers.instance_vbo == 0 {
+            panic!("Failed to reserve IndexedMesh instances. Its layout has no per-instance attributes.");
+        }
+        if self.buffers.instance_bytes.get() < bytes {
+            unsafe {
+                gl::BindBuffer(gl::ARRAY_BUFFER, self.buffers.instance_vbo);
+                gl::BufferData(gl::ARRAY_BUFFER, bytes as GLsizeiptr, std::ptr::null(), gl::DYNAMIC_COPY);
+                gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            }
+            self.buffers.instance_bytes.set(bytes);
+        }
+        self.buffers.instance_vbo
+    }
+    // Draws with a DrawElementsIndirectCommand from command_buffer. Instance count is only known to the GPU,
+    // so statistics count a single instance.
+    pub(crate) fn draw_indirect(&self, command_buffer: GLuint) {
+        unsafe {
+            gl::BindVertexArray(self.buffers.vao);
+            gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, command_buffer);
+            gl::DrawElementsIndirect(self.render_mode, gl::UNSIGNED_INT, std::ptr::null());
+            gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, 0);
+        }
+        render::count_draw(self.render_mode, self.num_indices as usize);
+    }
+    /// Combines ```parts``` with their transforms into one mesh on CPU and uploads it, see [MeshData::merge].
+    /// Use [IndexedMesh::merge_meshes] when parts are only on GPU.
+    pub fn merge(parts: &[(&MeshData, Matrix4<f32>)]) -> Self {
+        MeshData::merge(parts).build()
+    }
+    /// Same as [IndexedMesh::merge], but parts are read back from GPU with [IndexedMesh::to_data] first.
+    /// # Example
+    /// ```rust
+    /// let crates = IndexedMesh::merge_meshes(&[
+    ///     (&crate_mesh, Matrix4::new_translation(&Vector3::new(0.0, 0.0, 0.0))),
+    ///     (&crate_mesh, Matrix4::new_translation(&Vector3::new(1.5, 0.0, 0.0))),
+    /// ]);
+    /// ```
+    pub fn merge_meshes(parts: &[(&IndexedMesh, Matrix4<f32>)]) -> Self {
+        let data: Vec<MeshData> = parts.iter().map(|(mesh, _)| mesh.to_data()).collect();
+        let parts: Vec<(&MeshData, Matrix4<f32>)> = data.iter().zip(parts).map(|(data, (_, transform))| (data, *transform)).collect();
+        MeshData::merge(&parts).build()
+    }
+    /// Reads vertices and indices back from GPU into a [MeshData]. Panics if the mesh isn't ```gl::TRIANGLES```,
+    /// is planar or doesn't have [Layout::default_3d].
+    pub fn to_data(&self) -> MeshData {
+        let layout = &self.buffers.layout;
+        if self.render_mode != gl::TRIANGLES
+            || !matches!(layout.attributes(), [Attribute::Vec3, Attribute::Vec2, Attribute::Vec3])
+            || layout.divisors().iter().any(|&divisor| divisor != 0)
+        {
+            panic!("Failed to read back indexed mesh. It must be drawn with gl::TRIANGLES and have Layout::default_3d.");
+        }
+        if !self.buffers.planar_vbos.is_empty() {
+            panic!("Failed to read back indexed mesh. Planar meshes can't be read back.");
+        }
+
+        let (indices, vertices) = self.read_back();
+        MeshData { vertices: floats_from_bytes(&vertices), indices }
+    }
+    // Copies the index and vertex buffers to CPU.
+    fn read_back(&self) -> (Vec<u32>, Vec<u8>) {
+        let mut indices = vec![0u32; self.num_indices as usize];
+        let mut vertices = vec![0u8; self.buffers.vertex_bytes];
+        unsafe {
+            // COPY_READ_BUFFER doesn't touch bindings of any vertex array.
+            gl::BindBuffer(gl::COPY_READ_BUFFER, self.buffers.ebo);
+            gl::GetBufferSubData(gl::COPY_READ_BUFFER, 0, std::mem::size_of_val(indices.as_slice()) as GLsizeiptr, indices.as_mut_ptr() as *mut _);
+            gl::BindBuffer(gl::COPY_READ_BUFFER, self.buffers.vbo);
+            gl::GetBufferSubData(gl::COPY_READ_BUFFER, 0, vertices.len() as GLsizeiptr, vertices.as_mut_ptr() as *mut _);
+            gl::BindBuffer(gl::COPY_READ_BUFFER, 0);
+        }
+        (indices, vertices)
+    }
+    /// Creates a copy of the mesh with about ```target_ratio``` (0.0 - 1.0) of its triangles, see [MeshData::simplified].
+    /// Vertices and indices are read back from GPU, so no CPU copy needs to be kept. The copy has the same layout and render mode,
+    /// but no draw ranges. Panics if the mesh isn't ```gl::TRIANGLES```, is planar or the first attribute isn't a [Attribute::Vec3] position.
+    /// # Example
+    /// ```rust
+    /// let statue = LodMesh::new()
+    ///     .with_level(statue_mesh.simplified(1.0), 15.0)
+    ///     .with_level(statue_mesh.simplified(0.25), 50.0)
+    ///     .with_level(statue_mesh.simplified(0.05), 150.0);
+    /// ```
+    pub fn simplified(&self, target_ratio: f32) -> Self {
+        let layout = &self.buffers.layout;
+        if self.render_mode != gl::TRIANGLES || !matches!(layout.attributes().first(), Some(Attribute::Vec3)) || layout.divisors()[0] != 0 {
+            panic!("Failed to simplify indexed mesh. It must be drawn with gl::TRIANGLES and have a per-vertex Vec3 position as the first attribute.");
+        }
+        if !self.buffers.planar_vbos.is_empty() {
+            panic!("Failed to simplify indexed mesh. Planar meshes can't be read back.");
+        }
+
+        let stride = layout.get_vertex_stride();
+        let (indices, vertices) = self.read_back();
+
+        let positions: Vec<Vector3<f32>> = vertices.chunks_exact(stride).map(|vertex| {
+            let value = |i: usize| f32::from_ne_bytes([vertex[i * 4], vertex[i * 4 + 1], vertex[i * 4 + 2], vertex[i * 4 + 3]]);
+            Vector3::new(value(0), value(1), value(2))
+        }).collect();
+        let (used, indices) = compact_vertices(&simplify(&positions, &indices, target_ratio));
+        let vertices: Vec<u8> = used.iter().flat_map(|&index| &vertices[index * stride..(index + 1) * stride]).copied().collect();
+        Self::new::<u8>(&indices, &vertices, layout, self.render_mode)
+    }
+
+    /// Adds a named range of indices, e.g. a part of a model with its own material. Panics if it's out of the index buffer.
+    /// # Example
+    /// ```rust
+    /// let model = IndexedMesh::new::<f32>(&indices, &vertices, &Layout::default_3d(), gl::TRIANGLES)
+    ///     .with_range(DrawRange::new("body", 0, 3600).with_material(0))
+    ///     .with_range(DrawRange::new("glass", 3600, 480).with_material(1));
+    ///
+    /// for range in model.ranges() {
+    ///     materials[range.material.unwrap_or(0)].bind();
+    ///     model.draw_range(range);
+    /// }
+    /// ```
+    pub fn with_range(mut self, range: DrawRange) -> Self {
+        if range.offset + range.count > self.num_indices as usize {
+            panic!(
+                "Failed to add draw range \"{}\". Indices {}..{} are out of the mesh with {} indices.",
+                range.name, range.offset, range.offset + range.count, self.num_indices,
+            );
+        }
+        self.ranges.push(range);
+        self
+    }
+    /// Gets a range by its ```name```.
+    pub fn get_range(&self, name: &str) -> Option<&DrawRange> {
+        self.ranges.iter().find(|range| range.name == name)
+    }
+    /// Gets all ranges in the order they were added.
+    pub fn ranges(&self) -> &[DrawRange] {
+        &self.ranges
+    }
+    /// Draws only indices of ```range```. It doesn't have to be added to this mesh, but must fit in its index buffer.
+    pub fn draw_range(&self, range: &DrawRange) {
+        self.draw_partial(range.offset, range.count);
+    }
+
+    /// Gets number of vertices.
+    pub fn get_vertex_count(&self) -> usize {
+        self.buffers.vertex_bytes / self.buffers.layout.get_vertex_stride().max(1)
+    }
+    /// Gets number of indices.
+    pub fn get_index_count(&self) -> usize {
+        self.num_indices as usize
+    }
+    /// Gets number of triangles, 0 if the mesh isn't drawn as triangles.
+    pub fn get_triangle_count(&self) -> usize {
+        match self.render_mode {
+            gl::TRIANGLES => self.get_index_count() / 3,
+            gl::TRIANGLE_STRIP | gl::TRIANGLE_FAN => self.get_index_count().saturating_sub(2),
+            _ => 0,
+        }
+    }
+    /// Gets render mode, e.g. ```gl::TRIANGLES```.
+    pub fn get_render_mode(&self) -> GLenum {
+        self.render_mode
+    }
+    /// Gets layout the mesh was created with.
+    pub fn get_layout(&self) -> &Layout {
+        &self.buffers.layout
+    }
+    /// Gets size of one vertex in bytes.
+    pub fn get_vertex_stride(&self) -> usize {
+        self.buffers.layout.get_vertex_stride()
+    }
+    /// Gets estimated GPU memory taken by the mesh in bytes: vertex, index and instance buffers. Drivers may allocate a bit more.
+    /// # Example
+    /// ```rust
+    /// let model = MeshData::lathe(&profile, 64).build();
+    /// println!("{} vertices, {} triangles, {:.1} KiB", model.get_vertex_count(), model.get_triangle_count(), model.get_gpu_memory() as f32 / 1024.0);
+    /// ```
+    pub fn get_gpu_memory(&self) -> usize {
+        self.buffers.vertex_bytes + self.get_index_count() * std::mem::size_of::<u32>() + self.buffers.instance_bytes.get()
+    }
+}
+
+/// A named range of indices of an [IndexedMesh] (a sub-mesh), so multi-material models can share one vertex array.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DrawRange {
+    pub name: String,
+    /// First index of the range.
+    pub offset: usize,
+    /// Number of indices in the range.
+    pub count: usize,
+    /// Material of the range, an index in whatever material list you use.
+    pub material: Option<usize>,
+}
+impl DrawRange {
+    /// Creates a range of ```count``` indices starting at ```offset```, without material.
+    pub fn new(name: &str, offset: usize, count: usize) -> Self {
+        Self { name: String::from(name), offset, count, material: None }
+    }
+    /// Sets material index of the range.
+    pub fn with_material(mut self, material: usize) -> Self {
+        self.material = Some(material);
+        self
+    }
+}
+/// Several versions of a mesh with less and less detail, each drawn up to a certain distance from the camera,
+/// so dense scenes don't waste triangles on far objects. Nothing is drawn beyond the last level.
+/// # Example
+/// ```rust
+/// use tinystorm::mesh::LodMesh;
+///
+/// let rock = LodMesh::new()
+///     .with_level(rock_data.build(), 20.0)
+///     .with_level(rock_medium_data.build(), 60.0)
+///     .with_level(rock_low_data.build(), 200.0)
+///     .with_bias(settings.lod_bias);
+///
+/// for position in &rock_positions {
+///     shader.set_mat4("u_Model", &Matrix4::new_translation(position));
+///     rock.draw_for_camera(position, &camera_position);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct LodMesh {
+    levels: Vec<(IndexedMesh, f32)>,
+    bias: f32,
+}
+impl LodMesh {
+    /// Creates a LOD mesh without levels.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds a level drawn up to ```max_distance```. Levels are kept sorted by distance, add them in any order.
+    pub fn with_level(mut self, mesh: IndexedMesh, max_distance: f32) -> Self {
+        let index = self.levels.partition_point(|(_, distance)| *distance <= max_distance);
+        self.levels.insert(index, (mesh, max_distance));
+        self
+    }
+    /// Sets multiplier of all level distances, e.g. 0.5 on low quality settings switches to simpler levels twice as close.
+    pub fn with_bias(mut self, bias: f32) -> Self {
+        self.bias = bias;
+        self
+    }
+
+    /// Gets index of the level used at ```distance```, or ```None``` if it's beyond the last one.
+    pub fn get_level(&self, distance: f32) -> Option<usize> {
+        self.levels.iter().position(|(_, max_distance)| distance <= max_distance * self.bias)
+    }
+    /// Gets mesh of a level.
+    pub fn get_mesh(&self, level: usize) -> Option<&IndexedMesh> {
+        self.levels.get(level).map(|(mesh, _)| mesh)
+    }
+    /// Gets number of levels.
+    pub fn get_level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Draws the level for ```distance``` from the camera. Returns the drawn level, if any.
+    pub fn draw_for_distance(&self, distance: f32) -> Option<usize> {
+        let level = self.get_level(distance)?;
+        self.levels[level].0.draw();
+        Some(level)
+    }
+    /// Draws the level for distance between ```position``` and ```camera_position```. Returns the drawn level, if any.
+    pub fn draw_for_camera(&self, position: &Vector3<f32>, camera_position: &Vector3<f32>) -> Option<usize> {
+        self.draw_for_distance(position.metric_distance(camera_position))
+    }
+}
+/// # Default values
+/// ```
+/// levels: []
+/// bias: 1.0
+/// ```
+impl Default for LodMesh {
+    fn default() -> Self {
+        Self { levels: Vec::new(), bias: 1.0 }
+    }
+}
+
+/// A mesh whose vertices are rewritten every frame (particles, immediate-mode UI, debug lines) without ```glBufferData``` stalls.
+///
+/// Its buffer is split into 3 parts mapped once with ```glBufferStorage```: the CPU writes into one part while the GPU still draws
+/// from the previous ones, and a [FenceSync] makes [StreamingMesh::write] wait only if the GPU is 3 frames behind.
+/// Per-instance attributes aren't supported.
+/// # Example
+/// ```rust
+/// use tinystorm::mesh::StreamingMesh;
+///
+/// let mut particles = StreamingMesh::new(10_000, &Layout::default_2d(), gl::POINTS);
+/// while window.is_running() {
+///     window.poll_events();
+///     particles.write::<f32>(&system.vertices());
+///     particles.draw();
+///     window.swap_buffers();
+/// }
+/// ```
+pub struct StreamingMesh {
+    vao: GLuint,
+    vbo: GLuint,
+    mapped: *mut u8,
+    layout: Layout,
+    render_mode: GLenum,
+
+    capacity: usize,
+    segment: usize,
+    fences: [Option<FenceSync>; STREAMING_SEGMENTS],
+    num_vertices: GLsizei,
+    generation: u64,
+}
+
+const STREAMING_SEGMENTS: usize = 3;
+// How long StreamingMesh::write waits for the GPU before giving up. A healthy GPU is never seconds behind.
+const STREAMING_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl StreamingMesh {
+    /// Creates a streaming mesh that can hold up to ```capacity``` vertices per frame, with custom vertex layout and render mode.
+    /// It's empty until the first [StreamingMesh::write].
+    /// Panics if persistently mapped buffers aren't supported (OpenGL 4.4 or ARB_buffer_storage) or the layout has per-instance attributes.
+    pub fn new(capacity: usize, layout: &Layout, render_mode: GLenum) -> Self {
+        if !gl::BufferStorage::is_loaded() {
+            panic!("Failed to create streaming mesh. Persistently mapped buffers require OpenGL 4.4 or ARB_buffer_storage.");
+        }
+        if layout.get_instance_stride() > 0 {
+            panic!("Failed to create streaming mesh. Per-instance attributes aren't supported.");
+        }
+
+        let size = (capacity * layout.get_vertex_stride() * STREAMING_SEGMENTS) as GLsizeiptr;
+        let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+        let mut vao: GLuint = 0;
+        let mut vbo: GLuint = 0;
+        let mapped = unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferStorage(gl::ARRAY_BUFFER, size, std::ptr::null(), flags);
+            gl::MapBufferRange(gl::ARRAY_BUFFER, 0, size, flags) as *mut u8
+        };
+        if mapped.is_null() && size > 0 {
+            panic!("Failed to create streaming mesh. glMapBufferRange returned null.");
+        }
+
+        build_attributes(layout, &[vbo]);
+        Self { vao, vbo, mapped, layout: layout.clone(), render_mode, capacity, segment: 0, fences: [None, None, None], num_vertices: 0, generation: render::context_generation() }
+    }
+
+    /// Replaces vertices drawn by the next [StreamingMesh::draw] calls. Call it once per frame, before drawing.
+    /// Blocks only if the GPU still draws the part written 3 calls ago. Panics if there are more than ```capacity``` vertices
+    /// or the GPU doesn't finish with that part in 5 seconds (e.g. the context was lost).
+    pub fn write<T>(&mut self, vertices: &[T]) {
+        let segment_bytes = self.capacity * self.layout.get_vertex_stride();
+        let size = std::mem::size_of_val(vertices);
+        if size > segment_bytes {
+            panic!("Failed to write streaming mesh vertices. {} bytes don't fit into {} bytes.", size, segment_bytes);
+        }
+
+        // The previous part is done being drawn by everything issued so far.
+        self.fences[self.segment] = Some(FenceSync::insert());
+        self.segment = (self.segment + 1) % STREAMING_SEGMENTS;
+        if let Some(fence) = self.fences[self.segment].take()
+            && !fence.client_wait(STREAMING_TIMEOUT) {
+            panic!("Failed to write streaming mesh vertices. The GPU didn't finish drawing them in {} seconds, the context may be lost.", STREAMING_TIMEOUT.as_secs());
+        }
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(vertices.as_ptr() as *const u8, self.mapped.add(self.segment * segment_bytes), size);
+        }
+        self.num_vertices = (size / self.layout.get_vertex_stride().max(1)) as GLsizei;
+        render::count_buffer_upload(size);
+    }
+
+    /// Draws vertices of the last [StreamingMesh::write].
+    pub fn draw(&self) {
+        if self.num_vertices == 0 {
+            return;
+        }
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(self.render_mode, (self.segment * self.capacity) as GLint, self.num_vertices);
+        }
+        render::count_draw(self.render_mode, self.num_vertices as usize);
+    }
+
+    /// Gets number of vertices written by the last [StreamingMesh::write].
+    pub fn get_vertex_count(&self) -> usize {
+        self.num_vertices as usize
+    }
+    /// Gets maximum number of vertices per [StreamingMesh::write].
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+    /// Gets render mode of the mesh (e.g. ```gl::POINTS```).
+    pub fn get_render_mode(&self) -> GLenum {
+        self.render_mode
+    }
+    /// Gets vertex layout of the mesh.
+    pub fn get_layout(&self) -> &Layout {
+        &self.layout
+    }
+    /// Gets estimated GPU memory taken by the mesh in bytes, all 3 parts included.
+    pub fn get_gpu_memory(&self) -> usize {
+        self.capacity * self.layout.get_vertex_stride() * STREAMING_SEGMENTS
+    }
+}
+impl Drop for StreamingMesh {
+    /// You don't need to manually free OpenGL resources, it's done automatically.
+    fn drop(&mut self) {
+        if !render::is_current_context(self.generation) {
+            return;
+        }
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::UnmapBuffer(gl::ARRAY_BUFFER);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteBuffers(1, &self.vbo);
+        }
+    }
+}
+
+/// Vertices and indices of a mesh in [Layout::default_3d] layout, generated on CPU.
+/// Use it for procedural props and call [MeshData::build] to upload it to GPU.
+/// # Example
+/// ```rust
+/// use tinystorm::{mesh::MeshData, nalgebra::Vector2};
+///
+/// // A vase: (radius, height) profile from bottom to top, rotated around Y axis.
+/// let vase = MeshData::lathe(&[
+///     Vector2::new(0.3, 0.0),
+///     Vector2::new(0.5, 0.4),
+///     Vector2::new(0.2, 0.9),
+///     Vector2::new(0.3, 1.0),
+/// ], 32).build();
+/// ```
+#[derive(Clone, Default)]
+pub struct MeshData {
+    /// Position, UV and normal of every vertex, one after another.
+    pub vertices: Vec<f32>,
+    /// Triangle indices into ```vertices```.
+    pub indices: Vec<u32>,
+}
+impl MeshData {
+    /// Sweeps 2D ```profile``` along 3D ```path``` points. Profile X goes to the right of the path, Y goes up.
+    /// UV.x goes along the profile from 0.0 to 1.0, UV.y is distance along the path.
+    /// Order profile points from left to right for flat shapes (roads), or clockwise for tubes (pipes), so normals face outside.
+    pub fn extrude(profile: &[Vector2<f32>], path: &[Vector3<f32>]) -> Self {
+        if profile.len() < 2 || path.len() < 2 {
+            panic!("Failed to extrude mesh. Got {} profile and {} path points, but at least 2 of each are required.", profile.len(), path.len());
+        }
+
+        let mut data = Self::default();
+        let mut distance = 0.0;
+        for (i, position) in path.iter().enumerate() {
+            if i > 0 {
+                distance += (position - path[i - 1]).norm();
+            }
+
+            let tangent = (path[(i + 1).min(path.len() - 1)] - path[i.saturating_sub(1)])
+                .try_normalize(f32::EPSILON)
+                .unwrap_or(Vector3::z());
+            let right = tangent.cross(&Vector3::y()).try_normalize(f32::EPSILON).unwrap_or(Vector3::x());
+            let up = right.cross(&tangent);
+
+            for (j, point) in profile.iter().enumerate() {
+                let normal = Self::profile_normal(profile, j);
+                data.push_vertex(
+                    position + right * point.x + up * point.y,
+                    Vector2::new(j as f32 / (profile.len() - 1) as f32, distance),
+                    right * normal.x + up * normal.y,
+                );
+            }
+        }
+
+        data.push_grid(path.len(), profile.len());
+        data
+    }
+    /// Rotates 2D ```profile``` of (radius, height) points around Y axis with ```segments``` steps.
+    /// Order profile points from bottom to top, so normals face outside.
+    /// UV.x goes around the axis from 0.0 to 1.0, UV.y goes along the profile from 0.0 to 1.0.
+    pub fn lathe(profile: &[Vector2<f32>], segments: usize) -> Self {
+        if profile.len() < 2 {
+            panic!("Failed to lathe mesh. Got {} profile points, but at least 2 are required.", profile.len());
+        }
+
+        let segments = segments.max(3);
+        let mut data = Self::default();
+        for i in 0..=segments {
+            // The last ring repeats the first one exactly (sin(2PI) isn't 0.0), so welding by position sees no seam.
+            let angle = 2.0 * PI * (i % segments) as f32 / segments as f32;
+            let (sin, cos) = angle.sin_cos();
+
+            for (j, point) in profile.iter().enumerate() {
+                let normal = Self::profile_normal(profile, j);
+                data.push_vertex(
+                    Vector3::new(point.x * cos, point.y, point.x * sin),
+                    Vector2::new(i as f32 / segments as f32, j as f32 / (profile.len() - 1) as f32),
+                    Vector3::new(-normal.x * cos, -normal.y, -normal.x * sin),
+                );
+            }
+        }
+
+        data.push_grid(segments + 1, profile.len());
+        data
+    }
+
+    fn cylinder(segments: usize) -> Self {
+        let mut data = Self::default();
+        data.revolve(&[(Vector2::new(0.0, -1.0), -Vector2::y(), 0.0), (Vector2::new(1.0, -1.0), -Vector2::y(), 1.0)], segments, true);
+        data.revolve(&[(Vector2::new(1.0, -1.0), Vector2::x(), 0.0), (Vector2::new(1.0, 1.0), Vector2::x(), 1.0)], segments, false);
+        data.revolve(&[(Vector2::new(1.0, 1.0), Vector2::y(), 0.0), (Vector2::new(0.0, 1.0), Vector2::y(), 1.0)], segments, true);
+        data
+    }
+    fn cone(segments: usize) -> Self {
+        // Side slope is 2 up for 1 in, so its normal is (2, 1) normalized.
+        let normal = Vector2::new(2.0, 1.0).normalize();
+        let mut data = Self::default();
+        data.revolve(&[(Vector2::new(0.0, -1.0), -Vector2::y(), 0.0), (Vector2::new(1.0, -1.0), -Vector2::y(), 1.0)], segments, true);
+        data.revolve(&[(Vector2::new(1.0, -1.0), normal, 0.0), (Vector2::new(0.0, 1.0), normal, 1.0)], segments, false);
+        data
+    }
+    fn torus(segments: usize, sides: usize) -> Self {
+        let sides = sides.max(3);
+        let profile: Vec<_> = (0..=sides).map(|i| {
+            let v = i as f32 / sides as f32;
+            let (sin, cos) = (2.0 * PI * (i % sides) as f32 / sides as f32).sin_cos();
+            (Vector2::new(1.0 + cos * 0.25, sin * 0.25), Vector2::new(cos, sin), v)
+        }).collect();
+
+        let mut data = Self::default();
+        data.revolve(&profile, segments, false);
+        data
+    }
+    fn capsule(segments: usize, rings: usize) -> Self {
+        let rings = rings.max(1);
+        // UV.y follows the surface length: two quarter circles and the cylinder between them.
+        let length = PI + 2.0;
+
+        let mut profile = Vec::new();
+        for (center, start, distance) in [(-1.0, -PI * 0.5, 0.0), (1.0, 0.0, PI * 0.5 + 2.0)] {
+            for i in 0..=rings {
+                let step = PI * 0.5 * i as f32 / rings as f32;
+                let normal = Vector2::new((start + step).cos(), (start + step).sin());
+                profile.push((normal + Vector2::new(0.0, center), normal, (distance + step) / length));
+            }
+        }
+
+        let mut data = Self::default();
+        data.revolve(&profile, segments, false);
+        data
+    }
+    fn grid(columns: usize, rows: usize, uv_repeat: bool) -> Self {
+        let (columns, rows) = (columns.max(1), rows.max(1));
+        let uv_scale = if uv_repeat { Vector2::new(columns as f32, rows as f32) } else { Vector2::repeat(1.0) };
+
+        let mut data = Self::default();
+        for z in 0..=rows {
+            for x in 0..=columns {
+                let (u, v) = (x as f32 / columns as f32, z as f32 / rows as f32);
+                // V goes to -Z, so textures aren't flipped when looking along -Z.
+                data.vertices.extend_from_slice(&[u * 2.0 - 1.0, 0.0, v * 2.0 - 1.0, u * uv_scale.x, (1.0 - v) * uv_scale.y, 0.0, 1.0, 0.0]);
+            }
+        }
+        for z in 0..rows {
+            for x in 0..columns {
+                let current = (z * (columns + 1) + x) as u32;
+                let next = current + columns as u32 + 1;
+                data.indices.extend_from_slice(&[current, next, next + 1, current, next + 1, current + 1]);
+            }
+        }
+        data
+    }
+
+    /// Replaces normals of all vertices. Smooth normals are averaged between triangles sharing a position (even across UV seams),
+    /// flat normals are per triangle, so vertices are split and every triangle gets its own 3 vertices.
+    pub fn generate_normals(&mut self, smooth: bool) {
+        let positions: Vec<Vector3<f32>> = self.vertices.chunks_exact(8).map(|vertex| Vector3::new(vertex[0], vertex[1], vertex[2])).collect();
+
+        if smooth {
+            for (vertex, normal) in self.vertices.chunks_exact_mut(8).zip(compute_normals(&positions, &self.indices)) {
+                vertex[5..8].copy_from_slice(normal.as_slice());
+            }
+            return;
+        }
+
+        let mut vertices = Vec::with_capacity(self.indices.len() * 8);
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| positions[triangle[i] as usize]);
+            let normal = (b - a).cross(&(c - a)).try_normalize(f32::EPSILON).unwrap_or(Vector3::y());
+            for &index in triangle {
+                let base = index as usize * 8;
+                vertices.extend_from_slice(&self.vertices[base..base + 5]);
+                vertices.extend_from_slice(normal.as_slice());
+            }
+        }
+        self.indices = (0..(vertices.len() / 8) as u32).collect();
+        self.vertices = vertices;
+    }
+
+    /// Removes all vertices and indices, but keeps allocated memory, so the same [MeshData] can be refilled every frame without allocations.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+    }
+    /// Gets the box around all vertices, e.g. to put the mesh into a [crate::bvh::Bvh].
+    pub fn get_bounds(&self) -> Aabb {
+        Aabb::from_points(self.vertices.chunks_exact(8).map(|vertex| Vector3::new(vertex[0], vertex[1], vertex[2])))
+    }
+    /// Combines ```parts```, each with its own transform, into one mesh, so static geometry (e.g. level props) can be drawn with one call.
+    /// Normals are transformed too, and triangles of mirrored parts are flipped to keep facing outside.
+    /// # Example
+    /// ```rust
+    /// let rock = MeshData::lathe(&rock_profile, 12);
+    /// let parts: Vec<_> = rock_transforms.iter().map(|transform| (&rock, *transform)).collect();
+    /// let rocks = MeshData::merge(&parts).build();
+    /// ```
+    pub fn merge(parts: &[(&MeshData, Matrix4<f32>)]) -> Self {
+        let mut builder = MeshBuilder::new();
+        builder.data.vertices.reserve(parts.iter().map(|(data, _)| data.vertices.len()).sum());
+        builder.data.indices.reserve(parts.iter().map(|(data, _)| data.indices.len()).sum());
+
+        for (data, transform) in parts {
+            builder.push_transform(transform);
+            builder.push_data(data);
+            builder.pop_transform();
+        }
+        builder.into_data()
+    }
+
+    /// Reduces number of triangles to about ```target_ratio``` (0.0 - 1.0) of the current one by collapsing edges that change
+    /// the shape the least (quadric error metric), e.g. to make LOD levels. Open borders are kept in place, vertices aren't moved,
+    /// only removed, so UVs and normals of the rest stay correct. It may stop above the target if nothing can be removed without flipping triangles.
+    /// # Example
+    /// ```rust
+    /// let rock = MeshData::lathe(&profile, 64);
+    /// let rocks = LodMesh::new()
+    ///     .with_level(rock.build(), 20.0)
+    ///     .with_level(rock.simplified(0.3).build(), 60.0)
+    ///     .with_level(rock.simplified(0.08).build(), 200.0);
+    /// ```
+    pub fn simplified(&self, target_ratio: f32) -> Self {
+        let positions: Vec<Vector3<f32>> = self.vertices.chunks_exact(8).map(|vertex| Vector3::new(vertex[0], vertex[1], vertex[2])).collect();
+        let (used, indices) = compact_vertices(&simplify(&positions, &self.indices, target_ratio));
+        Self {
+            vertices: used.iter().flat_map(|&index| &self.vertices[index * 8..index * 8 + 8]).copied().collect(),
+            indices,
+        }
+    }
+
+    /// Uploads vertices and indices to GPU as an [IndexedMesh] with [Layout::default_3d] and ```gl::TRIANGLES```.
+    pub fn build(&self) -> IndexedMesh {
+        IndexedMesh::new::<f32>(&self.indices, &self.vertices, &Layout::default_3d(), gl::TRIANGLES)
+    }
+
+    /// Computes tangents with [compute_tangents] and uploads the mesh to GPU as an [IndexedMesh] with [Layout::tangent_3d] and ```gl::TRIANGLES```.
+    pub fn build_with_tangents(&self) -> IndexedMesh {
+        let vertices: Vec<&[f32]> = self.vertices.chunks_exact(8).collect();
+        let tangents = compute_tangents(
+            &vertices.iter().map(|vertex| Vector3::new(vertex[0], vertex[1], vertex[2])).collect::<Vec<_>>(),
+            &vertices.iter().map(|vertex| Vector2::new(vertex[3], vertex[4])).collect::<Vec<_>>(),
+            &vertices.iter().map(|vertex| Vector3::new(vertex[5], vertex[6], vertex[7])).collect::<Vec<_>>(),
+            &self.indices,
+        );
+
+        let mut data = Vec::with_capacity(vertices.len() * 12);
+        for (vertex, tangent) in vertices.iter().zip(tangents) {
+            data.extend_from_slice(vertex);
+            data.extend_from_slice(tangent.as_slice());
+        }
+        IndexedMesh::new::<f32>(&self.indices, &data, &Layout::tangent_3d(), gl::TRIANGLES)
+    }
+
+    /// Generates non-overlapping second UVs with [unwrap_uvs] for a ```resolution``` x ```resolution``` lightmap.
+    /// Returns vertices with [Layout::lightmap_3d] and their indices. Vertices are split where UV charts meet.
+    pub fn unwrap_lightmap_uvs(&self, resolution: u32) -> (Vec<f32>, Vec<u32>) {
+        let positions: Vec<Vector3<f32>> = self.vertices.chunks_exact(8).map(|vertex| Vector3::new(vertex[0], vertex[1], vertex[2])).collect();
+        let unwrap = unwrap_uvs(&positions, &self.indices, resolution, 2);
+
+        let mut vertices = Vec::with_capacity(unwrap.uvs.len() * 10);
+        for (&index, uv) in unwrap.vertex_map.iter().zip(&unwrap.uvs) {
+            let base = index as usize * 8;
+            vertices.extend_from_slice(&self.vertices[base..base + 8]);
+            vertices.extend_from_slice(uv.as_slice());
+        }
+        (vertices, unwrap.indices)
+    }
+    /// Generates second UVs (see [MeshData::unwrap_lightmap_uvs]) and uploads the mesh to GPU as an [IndexedMesh]
+    /// with [Layout::lightmap_3d] and ```gl::TRIANGLES```.
+    pub fn build_with_lightmap_uvs(&self, resolution: u32) -> IndexedMesh {
+        let (vertices, indices) = self.unwrap_lightmap_uvs(resolution);
+        IndexedMesh::new::<f32>(&indices, &vertices, &Layout::lightmap_3d(), gl::TRIANGLES)
+    }
+
+    fn to_mesh(&self) -> Mesh {
+        let mut vertices = Vec::with_capacity(self.indices.len() * 8);
+        for &index in &self.indices {
+            let base = index as usize * 8;
+            vertices.extend_from_slice(&self.vertices[base..base + 8]);
+        }
+        Mesh::new::<f32>(&vertices, &Layout::default_3d(), gl::TRIANGLES)
+    }
+
+    // Same as lathe, but with explicit (position, normal, UV.y) profile points, so hard edges and curves are exact.
+    // Planar UVs map X and Z from -1.0 - 1.0 to 0.0 - 1.0, for caps.
+    fn revolve(&mut self, profile: &[(Vector2<f32>, Vector2<f32>, f32)], segments: usize, planar: bool) {
+        let segments = segments.max(3);
+        let first = (self.vertices.len() / 8) as u32;
+        for i in 0..=segments {
+            let u = i as f32 / segments as f32;
+            // The last ring repeats the first one exactly, like in lathe.
+            let (sin, cos) = (2.0 * PI * (i % segments) as f32 / segments as f32).sin_cos();
+
+            for &(point, normal, v) in profile {
+                let position = Vector3::new(point.x * cos, point.y, point.x * sin);
+                let uv = if planar { Vector2::new(position.x * 0.5 + 0.5, position.z * 0.5 + 0.5) } else { Vector2::new(u, v) };
+                self.push_vertex(position, uv, Vector3::new(normal.x * cos, normal.y, normal.x * sin));
+            }
+        }
+
+        let start = self.indices.len();
+        self.push_grid(segments + 1, profile.len());
+        for index in &mut self.indices[start..] {
+            *index += first;
+        }
+    }
+    fn push_vertex(&mut self, position: Vector3<f32>, uv: Vector2<f32>, normal: Vector3<f32>) {
+        self.vertices.extend_from_slice(&[position.x, position.y, position.z, uv.x, uv.y, normal.x, normal.y, normal.z]);
+    }
+    fn push_grid(&mut self, rows: usize, columns: usize) {
+        for row in 0..rows - 1 {
+            for column in 0..columns - 1 {
+                let a = (row * columns + column) as u32;
+                let b = a + 1;
+                let c = a + columns as u32;
+                let d = c + 1;
+                self.indices.extend_from_slice(&[a, b, c, b, d, c]);
+            }
+        }
+    }
+    // Points to the left of the profile direction.
+    fn profile_normal(profile: &[Vector2<f32>], index: usize) -> Vector2<f32> {
+        let direction = profile[(index + 1).min(profile.len() - 1)] - profile[index.saturating_sub(1)];
+        Vector2::new(-direction.y, direction.x).try_normalize(f32::EPSILON).unwrap_or(Vector2::y())
+    }
+}
+
+/// Builds a mesh in [Layout::default_3d] layout piece by piece with typed vertices, triangles and quads,
+/// instead of keeping track of float arrays and index offsets by hand.
+/// Everything pushed is transformed by the current transform, so parts can be placed with [MeshBuilder::push_transform].
+/// # Example
+/// ```rust
+/// use tinystorm::{mesh::MeshBuilder, nalgebra::{Matrix4, Vector2, Vector3}};
+///
+/// let uvs = [Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.0), Vector2::new(1.0, 1.0), Vector2::new(0.0, 1.0)];
+/// let mut builder = MeshBuilder::new();
+/// for step in 0..10 {
+///     builder.push_transform(&Matrix4::new_translation(&Vector3::new(0.0, step as f32 * 0.2, step as f32 * 0.3)));
+///     // Top of a stair step, counter-clockwise when looking from above.
+///     builder.push_quad([
+///         Vector3::new(0.0, 0.2, 0.3),
+///         Vector3::new(1.0, 0.2, 0.3),
+///         Vector3::new(1.0, 0.2, 0.0),
+///         Vector3::new(0.0, 0.2, 0.0),
+///     ], uvs);
+///     builder.pop_transform();
+/// }
+/// let stairs = builder.build();
+/// ```
+#[derive(Clone)]
+pub struct MeshBuilder {
+    data: MeshData,
+    // Transform and its normal matrix. The first one is identity and can't be popped.
+    transforms: Vec<(Matrix4<f32>, Matrix3<f32>)>,
+}
+impl MeshBuilder {
+    /// Creates an empty builder with identity transform.
+    pub fn new() -> Self {
+        Self { data: MeshData::default(), transforms: vec![(Matrix4::identity(), Matrix3::identity())] }
+    }
+
+    /// Multiplies current transform by ```transform``` until [MeshBuilder::pop_transform] is called.
+    pub fn push_transform(&mut self, transform: &Matrix4<f32>) {
+        let transform = self.transforms.last().unwrap().0 * transform;
+        let normal_matrix = transform.fixed_view::<3, 3>(0, 0).try_inverse().unwrap_or_else(Matrix3::identity).transpose();
+        self.transforms.push((transform, normal_matrix));
+    }
+    /// Goes back to the transform before the last [MeshBuilder::push_transform]. Panics if there's nothing to pop.
+    pub fn pop_transform(&mut self) {
+        if self.transforms.len() == 1 {
+            panic!("Failed to pop mesh builder transform. Every push_transform was already popped.");
+        }
+        self.transforms.pop();
+    }
+
+    /// Adds a vertex and returns its index for [MeshBuilder::push_indices].
+    pub fn push_vertex(&mut self, position: &Vector3<f32>, uv: &Vector2<f32>, normal: &Vector3<f32>) -> u32 {
+        let (transform, normal_matrix) = self.transforms.last().unwrap();
+        let index = (self.data.vertices.len() / 8) as u32;
+        let normal = (normal_matrix * normal).try_normalize(f32::EPSILON).unwrap_or(*normal);
+        self.data.push_vertex(transform.transform_point(&Point3::from(*position)).coords, *uv, normal);
+        index
+    }
+    /// Adds triangle indices of vertices returned by [MeshBuilder::push_vertex]. Indices are used as is, without transform.
+    pub fn push_indices(&mut self, indices: &[u32]) {
+        self.data.indices.extend_from_slice(indices);
+    }
+    /// Adds a triangle with counter-clockwise ```positions``` (when looking at its front side) and a flat normal.
+    pub fn push_triangle(&mut self, positions: [Vector3<f32>; 3], uvs: [Vector2<f32>; 3]) {
+        let normal = (positions[1] - positions[0]).cross(&(positions[2] - positions[0])).try_normalize(f32::EPSILON).unwrap_or(Vector3::y());
+        let indices = [0, 1, 2].map(|i| self.push_vertex(&positions[i], &uvs[i], &normal));
+        self.push_faces(&[indices[0], indices[1], indices[2]]);
+    }
+    /// Adds a quad with counter-clockwise ```positions``` (when looking at its front side) and a flat normal, as 2 triangles.
+    pub fn push_quad(&mut self, positions: [Vector3<f32>; 4], uvs: [Vector2<f32>; 4]) {
+        let normal = (positions[2] - positions[0]).cross(&(positions[3] - positions[1])).try_normalize(f32::EPSILON).unwrap_or(Vector3::y());
+        let indices = [0, 1, 2, 3].map(|i| self.push_vertex(&positions[i], &uvs[i], &normal));
+        self.push_faces(&[indices[0], indices[1], indices[2], indices[0], indices[2], indices[3]]);
+    }
+    /// Adds all vertices and triangles of ```data``` (e.g. a primitive or a lathed shape).
+    pub fn push_data(&mut self, data: &MeshData) {
+        let first = (self.data.vertices.len() / 8) as u32;
+        for vertex in data.vertices.chunks_exact(8) {
+            self.push_vertex(
+                &Vector3::new(vertex[0], vertex[1], vertex[2]),
+                &Vector2::new(vertex[3], vertex[4]),
+                &Vector3::new(vertex[5], vertex[6], vertex[7]),
+            );
+        }
+        let indices: Vec<u32> = data.indices.iter().map(|index| first + index).collect();
+        self.push_faces(&indices);
+    }
+
+    /// Replaces normals of everything pushed so far, see [MeshData::generate_normals].
+    pub fn generate_normals(&mut self, smooth: bool) {
+        self.data.generate_normals(smooth);
+    }
+    /// Removes all vertices, indices and transforms, but keeps allocated memory for the next mesh.
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.transforms.truncate(1);
+    }
+
+    /// Gets number of vertices pushed so far.
+    pub fn get_vertex_count(&self) -> usize {
+        self.data.vertices.len() / 8
+    }
+    /// Gets number of indices pushed so far.
+    pub fn get_index_count(&self) -> usize {
+        self.data.indices.len()
+    }
+    /// Gets vertices and indices pushed so far.
+    pub fn get_data(&self) -> &MeshData {
+        &self.data
+    }
+
+    /// Uploads the mesh to GPU as an [IndexedMesh], see [MeshData::build].
+    pub fn build(&self) -> IndexedMesh {
+        self.data.build()
+    }
+    /// Uploads the mesh to GPU as a non-indexed [Mesh] with ```gl::TRIANGLES```.
+    pub fn build_mesh(&self) -> Mesh {
+        self.data.to_mesh()
+    }
+    /// Takes vertices and indices out of the builder.
+    pub fn into_data(self) -> MeshData {
+        self.data
+    }
+
+    // Mirroring transforms (negative scale) flip winding, so triangles are reversed to keep facing the same way.
+    fn push_faces(&mut self, indices: &[u32]) {
+        if self.transforms.last().unwrap().0.fixed_view::<3, 3>(0, 0).determinant() < 0.0 {
+            self.data.indices.extend(indices.chunks_exact(3).flat_map(|triangle| [triangle[0], triangle[2], triangle[1]]));
+        } else {
+            self.data.indices.extend_from_slice(indices);
+        }
+    }
+}
+impl Default for MeshBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Computes smooth normals of a triangle list: every vertex gets an average of normals of triangles around its position,
+/// weighted by their area. Vertices with equal positions (UV seams, duplicated vertices of OBJ files) get the same normal.
+/// Triangles are expected to be counter-clockwise when looking at their front side.
+/// # Example
+/// ```rust
+/// use tinystorm::mesh;
+///
+/// let normals = mesh::compute_normals(&positions, &indices);
+/// ```
+pub fn compute_normals(positions: &[Vector3<f32>], indices: &[u32]) -> Vec<Vector3<f32>> {
+    let (groups, group_count) = weld_positions(positions);
+
+    // Cross product length is twice the triangle area, so bigger triangles have more influence.
+    let mut sums = vec![Vector3::zeros(); group_count];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+        let normal = (positions[b] - positions[a]).cross(&(positions[c] - positions[a]));
+        for index in [a, b, c] {
+            sums[groups[index]] += normal;
+        }
+    }
+
+    groups.iter().map(|&group| sums[group].try_normalize(f32::EPSILON).unwrap_or(Vector3::y())).collect()
+}
+
+/// Computes per-vertex tangents of a triangle list for normal mapping: tangent points where UV.x grows,
+/// W is +1.0 or -1.0 for mirrored UVs (```bitangent = cross(normal, tangent.xyz) * tangent.w```).
+/// Tangents of triangles around a vertex are averaged and made perpendicular to its normal.
+/// # Example
+/// ```rust
+/// use tinystorm::mesh;
+///
+/// let tangents = mesh::compute_tangents(&positions, &uvs, &normals, &indices);
+/// ```
+/// ```glsl
+/// // Vertex shader, with Layout::tangent_3d.
+/// vec3 bitangent = cross(a_Normal, a_Tangent.xyz) * a_Tangent.w;
+/// v_TBN = mat3(u_Model) * mat3(a_Tangent.xyz, bitangent, a_Normal);
+/// // Fragment shader.
+/// vec3 normal = normalize(v_TBN * (texture(u_NormalMap, v_Uv).xyz * 2.0 - 1.0));
+/// ```
+pub fn compute_tangents(positions: &[Vector3<f32>], uvs: &[Vector2<f32>], normals: &[Vector3<f32>], indices: &[u32]) -> Vec<Vector4<f32>> {
+    if positions.len() != uvs.len() || positions.len() != normals.len() {
+        panic!("Failed to compute tangents. Got {} positions, {} UVs and {} normals, but they must be the same count.", positions.len(), uvs.len(), normals.len());
+    }
+
+    let mut tangents = vec![Vector3::zeros(); positions.len()];
+    let mut bitangents = vec![Vector3::zeros(); positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+        let (edge_1, edge_2) = (positions[b] - positions[a], positions[c] - positions[a]);
+        let (delta_1, delta_2) = (uvs[b] - uvs[a], uvs[c] - uvs[a]);
+
+        // Not normalized, so bigger triangles have more influence, like in compute_normals.
+        let determinant = delta_1.x * delta_2.y - delta_2.x * delta_1.y;
+        if determinant.abs() <= f32::EPSILON {
+            continue;
+        }
+        let tangent = (edge_1 * delta_2.y - edge_2 * delta_1.y) * determinant.signum();
+        let bitangent = (edge_2 * delta_1.x - edge_1 * delta_2.x) * determinant.signum();
+        for index in [a, b, c] {
+            tangents[index] += tangent;
+            bitangents[index] += bitangent;
+        }
+    }
+
+    normals.iter().enumerate().map(|(i, normal)| {
+        // Gram-Schmidt, any perpendicular direction if UVs are degenerate.
+        let tangent = (tangents[i] - normal * normal.dot(&tangents[i]))
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(|| {
+                let axis = if normal.x.abs() < 0.9 { Vector3::x() } else { Vector3::y() };
+                (axis - normal * normal.dot(&axis)).normalize()
+            });
+        let sign = if normal.cross(&tangent).dot(&bitangents[i]) < 0.0 { -1.0 } else { 1.0 };
+        Vector4::new(tangent.x, tangent.y, tangent.z, sign)
+    }).collect()
+}
+
+/// Lightmap UVs made by [unwrap_uvs]. Vertices are split where charts meet, so there are usually more of them than in the input.
+pub struct UvUnwrap {
+    /// Input vertex index of every output vertex, to copy its other attributes.
+    pub vertex_map: Vec<u32>,
+    /// UV of every output vertex in 0.0 - 1.0 range.
+    pub uvs: Vec<Vector2<f32>>,
+    /// Triangle indices into output vertices, in the same order as input triangles.
+    pub indices: Vec<u32>,
+}
+
+/// Generates non-overlapping UVs (e.g. for lightmaps) for a ```resolution``` x ```resolution``` texture with ```padding``` texels between charts.
+/// Panics if the charts can't fit into the texture, even at the smallest scale.
+///
+/// Connected triangles facing the same axis direction are grouped into charts and projected along that axis,
+/// then charts are packed into the texture with the same texel density everywhere. Welded vertices are detected by position,
+/// so UV seams and hard edges of the input don't split charts.
+/// # Example
+/// ```rust
+/// use tinystorm::mesh;
+///
+/// let unwrap = mesh::unwrap_uvs(&positions, &indices, 1024, 2);
+/// let lightmap_uvs: Vec<Vector2<f32>> = unwrap.uvs;
+/// let positions: Vec<Vector3<f32>> = unwrap.vertex_map.iter().map(|&index| positions[index as usize]).collect();
+/// ```
+pub fn unwrap_uvs(positions: &[Vector3<f32>], indices: &[u32], resolution: u32, padding: u32) -> UvUnwrap {
+    let triangles: Vec<[usize; 3]> = indices.chunks_exact(3).map(|t| [t[0] as usize, t[1] as usize, t[2] as usize]).collect();
+    let (groups, _) = weld_positions(positions);
+
+    // Axis (0 - 2) and its sign the triangle faces the most.
+    let directions: Vec<(usize, bool)> = triangles.iter().map(|&[a, b, c]| {
+        let normal = (positions[b] - positions[a]).cross(&(positions[c] - positions[a]));
+        let axis = normal.abs().imax();
+        (axis, normal[axis] >= 0.0)
+    }).collect();
+
+    let mut edges: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (i, triangle) in triangles.iter().enumerate() {
+        for j in 0..3 {
+            let (a, b) = (groups[triangle[j]], groups[triangle[(j + 1) % 3]]);
+            edges.entry((a.min(b), a.max(b))).or_default().push(i);
+        }
+    }
+
+    // Flood fill of charts across shared edges.
+    let mut charts: Vec<Vec<usize>> = Vec::new();
+    let mut chart_of = vec![usize::MAX; triangles.len()];
+    for start in 0..triangles.len() {
+        if chart_of[start] != usize::MAX {
+            continue;
+        }
+        let mut chart = vec![start];
+        chart_of[start] = charts.len();
+        let mut next = 0;
+        while next < chart.len() {
+            let triangle = triangles[chart[next]];
+            next += 1;
+            for j in 0..3 {
+                let (a, b) = (groups[triangle[j]], groups[triangle[(j + 1) % 3]]);
+                for &neighbour in &edges[&(a.min(b), a.max(b))] {
+                    if chart_of[neighbour] == usize::MAX && directions[neighbour] == directions[start] {
+                        chart_of[neighbour] = charts.len();
+                        chart.push(neighbour);
+                    }
+                }
+            }
+        }
+        charts.push(chart);
+    }
+
+    // Planar projection of every chart, moved to start at (0, 0).
+    let projected: Vec<_> = charts.iter().map(|chart| {
+        let (axis, positive) = directions[chart[0]];
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut points: Vec<[Vector2<f32>; 3]> = chart.iter().map(|&triangle| {
+            // Mirrored for back-facing directions, so charts aren't flipped.
+            triangles[triangle].map(|index| Vector2::new(if positive { positions[index][u] } else { -positions[index][u] }, positions[index][v]))
+        }).collect();
+
+        let min = points.iter().flatten().fold(Vector2::repeat(f32::INFINITY), |min, point| min.inf(point));
+        let max = points.iter().flatten().fold(Vector2::repeat(f32::NEG_INFINITY), |max, point| max.sup(point));
+        for point in points.iter_mut().flatten() {
+            *point -= min;
+        }
+        (points, max - min)
+    }).collect();
+
+    // Shelf packing, shrinking the scale (texels per world unit) until everything fits.
+    let mut order: Vec<usize> = (0..charts.len()).collect();
+    order.sort_by(|&a, &b| projected[b].1.y.total_cmp(&projected[a].1.y));
+    // Every chart takes at least one texel plus padding however small the scale gets.
+    let minimum_area = charts.len() as u64 * (padding as u64 + 1).pow(2);
+    if minimum_area > (resolution as u64).pow(2) {
+        panic!(
+            "Failed to unwrap UVs. Error: {} charts with {} texels of padding need at least {} texels, but the texture has {}x{}.",
+            charts.len(), padding, minimum_area, resolution, resolution,
+        );
+    }
+    let (size, padding) = (resolution as f32, padding as f32);
+    let area: f32 = projected.iter().map(|(_, extent)| extent.x * extent.y).sum::<f32>().max(f32::EPSILON);
+    let mut scale = (size * size * 0.7 / area).sqrt();
+    let mut attempts = 0;
+    let offsets = loop {
+        let mut offsets = vec![Vector2::zeros(); charts.len()];
+        let (mut x, mut y, mut shelf) = (padding, padding, 0.0_f32);
+        let mut fits = true;
+        for &chart in &order {
+            let extent = projected[chart].1 * scale;
+            if x + extent.x + padding > size {
+                (x, y, shelf) = (padding, y + shelf + padding, 0.0);
+            }
+            if x + extent.x + padding > size || y + extent.y + padding > size {
+                fits = false;
+                break;
+            }
+            offsets[chart] = Vector2::new(x, y);
+            x += extent.x.ceil() + padding;
+            shelf = shelf.max(extent.y.ceil());
+        }
+        if fits {
+            break offsets;
+        }
+        // Shelves waste space, so charts can still not fit after the check above. 0.9^256 leaves every chart at its minimum size.
+        attempts += 1;
+        if attempts > 256 {
+            panic!("Failed to unwrap UVs. Error: {} charts don't fit into {}x{} texels with {} texels of padding.", charts.len(), resolution, resolution, padding);
+        }
+        scale *= 0.9;
+    };
+
+    let mut unwrap = UvUnwrap { vertex_map: Vec::new(), uvs: Vec::new(), indices: vec![0; triangles.len() * 3] };
+    for (chart, (points, _)) in charts.iter().zip(&projected) {
+        // One output vertex per input vertex in a chart.
+        let mut vertices: HashMap<usize, u32> = HashMap::new();
+        for (&triangle, corners) in chart.iter().zip(points) {
+            for (j, (&index, corner)) in triangles[triangle].iter().zip(corners).enumerate() {
+                let output = *vertices.entry(index).or_insert_with(|| {
+                    unwrap.vertex_map.push(index as u32);
+                    unwrap.uvs.push((corner * scale + offsets[chart_of[triangle]]) / size);
+                    unwrap.uvs.len() as u32 - 1
+                });
+                unwrap.indices[triangle * 3 + j] = output;
+            }
+        }
+    }
+    unwrap
+}
+
+// Edge-collapse decimation: returns indices of the remaining triangles into the same vertices.
+// Vertices with the same position are collapsed together (as groups), so UV seams and hard edges don't stop it.
+fn simplify(positions: &[Vector3<f32>], indices: &[u32], target_ratio: f32) -> Vec<u32> {
+    let mut triangles: Vec<[u32; 3]> = indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect();
+    let target = (triangles.len() as f32 * target_ratio.clamp(0.0, 1.0)).round() as usize;
+    let (groups, group_count) = weld_positions(positions);
+    let group_positions: Vec<Vector3<f64>> = {
+        let mut group_positions = vec![Vector3::zeros(); group_count];
+        for (position, &group) in positions.iter().zip(&groups) {
+            group_positions[group] = position.cast();
+        }
+        group_positions
+    };
+    let group_of = |index: u32| groups[index as usize];
+    let plane_quadric = |normal: Vector3<f64>, point: &Vector3<f64>, weight: f64| {
+        let plane = Vector4::new(normal.x, normal.y, normal.z, -normal.dot(point));
+        plane * plane.transpose() * weight
+    };
+
+    // Quadrics of triangle planes weighted by area, plus planes perpendicular to open borders, so they don't shrink.
+    let mut quadrics = vec![Matrix4::<f64>::zeros(); group_count];
+    let mut triangles_of: Vec<Vec<usize>> = vec![Vec::new(); group_count];
+    let mut edge_uses: HashMap<(usize, usize), usize> = HashMap::new();
+    for (i, triangle) in triangles.iter().enumerate() {
+        let [a, b, c] = triangle.map(group_of);
+        if a == b || b == c || c == a {
+            continue;
+        }
+        let cross = (group_positions[b] - group_positions[a]).cross(&(group_positions[c] - group_positions[a]));
+        let Some(normal) = cross.try_normalize(f64::EPSILON) else { continue; };
+        let quadric = plane_quadric(normal, &group_positions[a], cross.norm() * 0.5);
+        for group in [a, b, c] {
+            quadrics[group] += quadric;
+            triangles_of[group].push(i);
+        }
+        for (from, to) in [(a, b), (b, c), (c, a)] {
+            *edge_uses.entry((from.min(to), from.max(to))).or_default() += 1;
+        }
+    }
+    for triangle in &triangles {
+        let [a, b, c] = triangle.map(group_of);
+        let cross = (group_positions[b] - group_positions[a]).cross(&(group_positions[c] - group_positions[a]));
+        let Some(normal) = cross.try_normalize(f64::EPSILON) else { continue; };
+        for (from, to) in [(a, b), (b, c), (c, a)] {
+            if edge_uses.get(&(from.min(to), from.max(to))) == Some(&1) {
+                let edge = group_positions[to] - group_positions[from];
+                if let Some(border_normal) = edge.cross(&normal).try_normalize(f64::EPSILON) {
+                    let quadric = plane_quadric(border_normal, &group_positions[from], edge.norm_squared() * 100.0);
+                    quadrics[from] += quadric;
+                    quadrics[to] += quadric;
+                }
+            }
+        }
+    }
+
+    // Error of moving group ```from``` into position of group ```to```.
+    let cost = |quadrics: &[Matrix4<f64>], from: usize, to: usize| {
+        let point = group_positions[to].push(1.0);
+        (point.transpose() * (quadrics[from] + quadrics[to]) * point)[0].max(0.0)
+    };
+    // Costs are stored as ordered bits (non-negative floats compare like integers), stale entries are skipped by versions.
+    let mut versions = vec![0u32; group_count];
+    let mut queue = BinaryHeap::new();
+    for &(a, b) in edge_uses.keys() {
+        for (from, to) in [(a, b), (b, a)] {
+            queue.push(Reverse((cost(&quadrics, from, to).to_bits(), from, to, 0, 0)));
+        }
+    }
+
+    let mut alive = vec![true; triangles.len()];
+    let mut remaining = alive.len();
+    let mut removed = vec![false; group_count];
+    while remaining > target {
+        let Some(Reverse((_, from, to, from_version, to_version))) = queue.pop() else { break; };
+        if removed[from] || removed[to] || versions[from] != from_version || versions[to] != to_version {
+            continue;
+        }
+
+        // Triangles around the edge disappear, others must not flip.
+        let (shared, moved): (Vec<usize>, Vec<usize>) = triangles_of[from].iter().copied()
+            .filter(|&t| alive[t])
+            .partition(|&t| triangles[t].iter().any(|&index| group_of(index) == to));
+        if shared.is_empty() {
+            continue;
+        }
+        let flips = moved.iter().any(|&t| {
+            let corners = triangles[t].map(|index| group_positions[group_of(index)]);
+            let moved_corners = triangles[t].map(|index| if group_of(index) == from { group_positions[to] } else { group_positions[group_of(index)] });
+            let before = (corners[1] - corners[0]).cross(&(corners[2] - corners[0]));
+            let after = (moved_corners[1] - moved_corners[0]).cross(&(moved_corners[2] - moved_corners[0]));
+            after.dot(&before) <= before.norm() * after.norm() * 0.2
+        });
+        if flips {
+            continue;
+        }
+
+        // Moved corners take a vertex of ```to``` from a removed triangle they shared, so UV seams stay where they were.
+        let mut replacements: HashMap<u32, u32> = HashMap::new();
+        for &t in &shared {
+            let from_vertex = triangles[t].iter().copied().find(|&index| group_of(index) == from);
+            let to_vertex = triangles[t].iter().copied().find(|&index| group_of(index) == to);
+            if let (Some(from_vertex), Some(to_vertex)) = (from_vertex, to_vertex) {
+                replacements.entry(from_vertex).or_insert(to_vertex);
+            }
+            alive[t] = false;
+            remaining -= 1;
+        }
+        let fallback = *replacements.values().next().unwrap();
+        for &t in &moved {
+            for index in &mut triangles[t] {
+                if group_of(*index) == from {
+                    *index = replacements.get(index).copied().unwrap_or(fallback);
+                }
+            }
+            triangles_of[to].push(t);
+        }
+
+        removed[from] = true;
+        quadrics[to] = quadrics[from] + quadrics[to];
+        versions[to] += 1;
+        triangles_of[to].retain(|&t| alive[t]);
+
+        let mut neighbours: Vec<usize> = triangles_of[to].iter().flat_map(|&t| triangles[t].map(group_of)).filter(|&group| group != to).collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        for neighbour in neighbours {
+            for (from, to) in [(neighbour, to), (to, neighbour)] {
+                queue.push(Reverse((cost(&quadrics, from, to).to_bits(), from, to, versions[from], versions[to])));
+            }
+        }
+    }
+
+    triangles.iter().zip(&alive).filter(|&(_, &alive)| alive).flat_map(|(triangle, _)| *triangle).collect()
+}
+// Drops unused vertices. Returns old index of every kept vertex and indices remapped to them.
+fn compact_vertices(indices: &[u32]) -> (Vec<usize>, Vec<u32>) {
+    let mut remap: HashMap<u32, u32> = HashMap::new();
+    let mut used = Vec::new();
+    let indices = indices.iter().map(|&index| *remap.entry(index).or_insert_with(|| {
+        used.push(index as usize);
+        used.len() as u32 - 1
+    })).collect();
+    (used, indices)
+}
+// Reinterprets vertex bytes read back from GPU as floats.
+fn floats_from_bytes(bytes: &[u8]) -> Vec<f32> {
+    bytes.chunks_exact(4).map(|value| f32::from_ne_bytes([value[0], value[1], value[2], value[3]])).collect()
+}
+// Clamps (first, count) ranges to total elements, dropping empty ones.
+fn clamp_ranges(ranges: &[(usize, usize)], total: usize) -> Vec<(usize, usize)> {
+    ranges.iter()
+        .map(|&(first, count)| (first, count.min(total.saturating_sub(first))))
+        .filter(|&(_, count)| count > 0)
+        .collect()
+}
+// Gives the same group to vertices with the same position. Returns group of every vertex and number of groups.
+fn weld_positions(positions: &[Vector3<f32>]) -> (Vec<usize>, usize) {
+    let mut shared = HashMap::with_capacity_and_hasher(positions.len(), BuildHasherDefault::<PositionHasher>::default());
+    let groups = positions.iter().map(|position| {
+        let count = shared.len();
+        // Adding 0.0 turns -0.0 into 0.0, so they're the same position.
+        *shared.entry(position.map(|value| (value + 0.0).to_bits())).or_insert(count)
+    }).collect();
+    (groups, shared.len())
+}
+
+// A fast hasher for welding vertices by position bits, about 3 times faster than std's SipHash there.
+// It's not DoS-resistant, which doesn't matter for mesh data.
+#[derive(Default)]
+struct PositionHasher(u64);
+impl Hasher for PositionHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_u32(byte as u32);
+        }
+    }
+    fn write_u32(&mut self, value: u32) {
+        self.0 = (self.0.rotate_left(5) ^ value as u64).wrapping_mul(0x517C_C1B7_2722_0A95);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions_of(data: &MeshData) -> Vec<Vector3<f32>> {
+        data.vertices.chunks_exact(8).map(|vertex| Vector3::new(vertex[0], vertex[1], vertex[2])).collect()
+    }
+    fn sphere(segments: usize) -> MeshData {
+        let profile: Vec<Vector2<f32>> = (0..=segments / 2)
+            .map(|i| {
+                let (sin, cos) = (PI * i as f32 / (segments / 2) as f32 - PI * 0.5).sin_cos();
+                Vector2::new(cos, sin)
+            })
+            .collect();
+        MeshData::lathe(&profile, segments)
+    }
+
+    // Separating axis test of two 2D triangles, touching ones don't overlap.
+    fn triangles_overlap(a: &[Vector2<f32>; 3], b: &[Vector2<f32>; 3]) -> bool {
+        for triangle in [a, b] {
+            for i in 0..3 {
+                let edge = triangle[(i + 1) % 3] - triangle[i];
+                let axis = Vector2::new(-edge.y, edge.x);
+                let project = |t: &[Vector2<f32>; 3]| t.iter().map(|point| point.dot(&axis)).fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| (min.min(value), max.max(value)));
+                let ((a_min, a_max), (b_min, b_max)) = (project(a), project(b));
+                let epsilon = 1e-6 * axis.norm();
+                if a_max <= b_min + epsilon || b_max <= a_min + epsilon {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn check_unwrap(positions: &[Vector3<f32>], indices: &[u32], unwrap: &UvUnwrap) {
+        assert_eq!(unwrap.indices.len(), indices.len());
+        assert_eq!(unwrap.vertex_map.len(), unwrap.uvs.len());
+        for (output, input) in unwrap.indices.iter().zip(indices) {
+            assert_eq!(positions[unwrap.vertex_map[*output as usize] as usize], positions[*input as usize]);
+        }
+        for uv in &unwrap.uvs {
+            assert!((0.0..=1.0).contains(&uv.x) && (0.0..=1.0).contains(&uv.y), "UV {:?} is out of the texture", uv);
+        }
+
+        let triangles: Vec<[Vector2<f32>; 3]> = unwrap.indices.chunks_exact(3)
+            .map(|t| [unwrap.uvs[t[0] as usize], unwrap.uvs[t[1] as usize], unwrap.uvs[t[2] as usize]])
+            .collect();
+        for i in 0..triangles.len() {
+            for j in i + 1..triangles.len() {
+                assert!(!triangles_overlap(&triangles[i], &triangles[j]), "UV triangles {} and {} overlap", i, j);
+            }
+        }
+    }
+
+    #[test]
+    fn merge_of_read_back_meshes_matches_cpu_merge() {
+        let a = sphere(8);
+        let b = MeshData::lathe(&[Vector2::new(0.5, 0.0), Vector2::new(0.2, 1.0)], 6);
+        let transforms = [Matrix4::new_translation(&Vector3::new(2.0, 0.0, 0.0)), Matrix4::new_nonuniform_scaling(&Vector3::new(-1.0, 1.0, 1.0))];
+
+        // What IndexedMesh::to_data gets from GetBufferSubData of a built mesh.
+        let read_back = |data: &MeshData| MeshData {
+            vertices: floats_from_bytes(&data.vertices.iter().flat_map(|value| value.to_ne_bytes()).collect::<Vec<u8>>()),
+            indices: data.indices.clone(),
+        };
+        let (a_back, b_back) = (read_back(&a), read_back(&b));
+        assert_eq!(a_back.vertices, a.vertices);
+
+        let expected = MeshData::merge(&[(&a, transforms[0]), (&b, transforms[1])]);
+        let merged = MeshData::merge(&[(&a_back, transforms[0]), (&b_back, transforms[1])]);
+        assert_eq!(merged.vertices, expected.vertices);
+        assert_eq!(merged.indices, expected.indices);
+        assert_eq!(merged.indices.len(), a.indices.len() + b.indices.len());
+    }
+
+    #[test]
+    fn clamp_ranges_cuts_ranges_at_the_end() {
+        assert_eq!(clamp_ranges(&[(0, 6), (4, 10), (12, 3), (9, 0)], 10), vec![(0, 6), (4, 6)]);
+    }
+
+    #[test]
+    fn unwrap_uvs_cylinder_has_no_overlaps() {
+        let data = MeshData::cylinder(16);
+        let positions = positions_of(&data);
+        let unwrap = unwrap_uvs(&positions, &data.indices, 256, 2);
+        check_unwrap(&positions, &data.indices, &unwrap);
+    }
+    #[test]
+    fn unwrap_uvs_sphere_has_no_overlaps() {
+        let data = sphere(24);
+        let positions = positions_of(&data);
+        let unwrap = unwrap_uvs(&positions, &data.indices, 512, 2);
+        check_unwrap(&positions, &data.indices, &unwrap);
+    }
+    #[test]
+    fn unwrap_uvs_flat_grid_is_one_chart() {
+        let data = MeshData::grid(8, 8, false);
+        let positions = positions_of(&data);
+        let unwrap = unwrap_uvs(&positions, &data.indices, 128, 2);
+        check_unwrap(&positions, &data.indices, &unwrap);
+        // No seams, so no vertex is split.
+        assert_eq!(unwrap.uvs.len(), positions.len());
+    }
+    #[test]
+    #[should_panic(expected = "Failed to unwrap UVs")]
+    fn unwrap_uvs_zero_resolution_panics() {
+        let data = MeshData::cylinder(8);
+        unwrap_uvs(&positions_of(&data), &data.indices, 0, 2);
+    }
+    #[test]
+    #[should_panic(expected = "Failed to unwrap UVs")]
+    fn unwrap_uvs_too_many_charts_panics() {
+        let data = sphere(64);
+        unwrap_uvs(&positions_of(&data), &data.indices, 8, 2);
+    }
+
+    // Normal of every triangle (not normalized).
+    fn face_normals(data: &MeshData) -> Vec<Vector3<f32>> {
+        let positions = positions_of(data);
+        data.indices.chunks_exact(3).map(|t| {
+            let [a, b, c] = [t[0], t[1], t[2]].map(|index| positions[index as usize]);
+            (b - a).cross(&(c - a))
+        }).collect()
+    }
+    // Number of triangles using every edge, with vertices welded by position rounded to 1e-4, so lathe seams and poles match.
+    // Degenerate triangles (like at lathe poles) are skipped.
+    fn edge_uses(data: &MeshData) -> HashMap<([i32; 3], [i32; 3]), usize> {
+        let positions = positions_of(data);
+        let mut uses = HashMap::new();
+        for t in data.indices.chunks_exact(3) {
+            let [a, b, c]: [[i32; 3]; 3] = [t[0], t[1], t[2]].map(|index| positions[index as usize].map(|value| (value * 1e4).round() as i32).into());
+            if a == b || b == c || c == a {
+                continue;
+            }
+            for (from, to) in [(a, b), (b, c), (c, a)] {
+                *uses.entry((from.min(to), from.max(to))).or_insert(0) += 1;
+            }
+        }
+        uses
+    }
+    fn check_vertices_kept(original: &MeshData, simplified: &MeshData) {
+        let original_vertices: Vec<&[f32]> = original.vertices.chunks_exact(8).collect();
+        for vertex in simplified.vertices.chunks_exact(8) {
+            assert!(original_vertices.contains(&vertex), "Vertex {:?} was moved or made up", vertex);
+        }
+        assert!(simplified.indices.iter().all(|&index| (index as usize) < simplified.vertices.len() / 8));
+    }
+
+    #[test]
+    fn simplify_reaches_target_ratio() {
+        let data = sphere(32);
+        let triangles = data.indices.len() / 3;
+        for ratio in [0.5, 0.25, 0.1] {
+            let simplified = data.simplified(ratio);
+            let target = (triangles as f32 * ratio).round() as usize;
+            let remaining = simplified.indices.len() / 3;
+            assert!(remaining <= target + 2 && remaining * 10 >= target * 8, "Got {} triangles for target {}", remaining, target);
+            check_vertices_kept(&data, &simplified);
+        }
+    }
+    #[test]
+    fn simplify_full_ratio_keeps_everything() {
+        let data = sphere(16);
+        let simplified = data.simplified(1.0);
+        assert_eq!(simplified.indices.len(), data.indices.len());
+        assert_eq!(simplified.vertices.len(), data.vertices.len());
+    }
+    #[test]
+    fn simplify_sphere_stays_closed_without_flips() {
+        let data = sphere(32);
+        let simplified = data.simplified(0.2);
+
+        // Sphere is centered at the origin and its faces point outwards. Slivers along a meridian end up edge-on (about 0.0), flipped faces point inwards.
+        let positions = positions_of(&simplified);
+        for (normal, t) in face_normals(&simplified).iter().zip(simplified.indices.chunks_exact(3)) {
+            let centroid = (positions[t[0] as usize] + positions[t[1] as usize] + positions[t[2] as usize]) / 3.0;
+            if let Some(normal) = normal.try_normalize(1e-6) {
+                assert!(normal.dot(&centroid.normalize()) > -0.2, "Triangle {:?} is flipped", t);
+            }
+        }
+
+        assert!(edge_uses(&data).values().all(|&uses| uses == 2));
+        assert!(edge_uses(&simplified).values().all(|&uses| uses == 2), "Simplified sphere isn't closed");
+    }
+    #[test]
+    fn simplify_grid_keeps_borders() {
+        let data = MeshData::grid(16, 16, false);
+        let simplified = data.simplified(0.1);
+        assert!(simplified.indices.len() < data.indices.len() / 2);
+        check_vertices_kept(&data, &simplified);
+
+        // Corners and the outline stay in place, and the plane isn't folded.
+        assert_eq!(simplified.get_bounds(), data.get_bounds());
+        let reference = face_normals(&data)[0].normalize();
+        for normal in face_normals(&simplified) {
+            assert!(normal.normalize().dot(&reference) > 0.99, "Grid triangle normal {:?} left the plane", normal);
+        }
+        let area = |data: &MeshData| face_normals(data).iter().map(|normal| normal.norm() * 0.5).sum::<f32>();
+        assert!((area(&simplified) - area(&data)).abs() < area(&data) * 1e-4, "Grid area changed");
+    }
+}
@@ -0,0 +1,487 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use nalgebra::Vector2;
+
+use crate::mesh::{Layout, Mesh};
+
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+/// A 2D grid of walkable/blocked cells for pathfinding: A*, jump point search and flow fields.
+/// Diagonal moves are allowed only when both adjacent straight cells are walkable, so paths never cut corners.
+/// # Example
+/// ```rust
+/// use tinystorm::nav::NavGrid;
+///
+/// let mut grid = NavGrid::new(32, 32);
+/// for y in 0..20 { grid.set_walkable(10, y, false); } // A wall.
+///
+/// let path = grid.find_path((0, 0), (20, 5)); // Some(vec![(0, 0), (1, 1), ...]) or None if unreachable.
+///
+/// // For many units going to the same goal (RTS), compute the flow field once.
+/// let field = grid.flow_field((20, 5));
+/// for unit in &mut units {
+///     if let Some(direction) = field.get_direction(unit.cell.0, unit.cell.1) {
+///         unit.velocity = direction * unit.speed;
+///     }
+/// }
+/// ```
+pub struct NavGrid {
+    width: usize,
+    height: usize,
+    walkable: Vec<bool>,
+    diagonal: bool,
+}
+
+impl NavGrid {
+    /// Creates a ```width``` x ```height``` grid where every cell is walkable and diagonal moves are allowed.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height, walkable: vec![true; width * height], diagonal: true }
+    }
+    /// Allows/disallows diagonal moves. Jump point search always uses them.
+    pub fn with_diagonal(mut self, diagonal: bool) -> Self {
+        self.diagonal = diagonal;
+        self
+    }
+
+    /// Makes a cell at ```x```, ```y``` walkable or blocked. Panics if it's outside the grid.
+    pub fn set_walkable(&mut self, x: usize, y: usize, walkable: bool) {
+        if x >= self.width || y >= self.height {
+            panic!("Cell ({}, {}) is outside of {}x{} navigation grid.", x, y, self.width, self.height);
+        }
+        self.walkable[y * self.width + x] = walkable;
+    }
+    /// Checks if a cell at ```x```, ```y``` is walkable. Cells outside the grid are blocked.
+    pub fn is_walkable(&self, x: i64, y: i64) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height && self.walkable[y as usize * self.width + x as usize]
+    }
+    /// Gets grid size in cells.
+    pub fn get_size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Finds the shortest path from ```start``` to ```goal``` with A*.
+    /// Returns every cell of the path including both ends, or [None] if goal is unreachable.
+    pub fn find_path(&self, start: (usize, usize), goal: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+        self.search(start, goal, self.diagonal, |grid, cell, _| grid.neighbors(cell))
+    }
+    /// Finds the shortest path from ```start``` to ```goal``` with jump point search.
+    /// It's much faster than [NavGrid::find_path] on big open maps and returns the same kind of path (every cell).
+    pub fn find_path_jps(&self, start: (usize, usize), goal: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+        let goal_i = (goal.0 as i64, goal.1 as i64);
+        let jump_points = self.search(start, goal, true, |grid, cell, parent| {
+            grid.jps_neighbors(cell, parent)
+                .into_iter()
+                .filter_map(|next| grid.jump(next, cell, goal_i))
+                .map(|point| (point, Self::distance(cell, point)))
+                .collect()
+        })?;
+
+        // Jump points are connected with straight or diagonal lines, so walk them cell by cell.
+        let mut path = vec![jump_points[0]];
+        for pair in jump_points.windows(2) {
+            let (mut x, mut y) = (pair[0].0 as i64, pair[0].1 as i64);
+            let (end_x, end_y) = (pair[1].0 as i64, pair[1].1 as i64);
+            while (x, y) != (end_x, end_y) {
+                x += (end_x - x).signum();
+                y += (end_y - y).signum();
+                path.push((x as usize, y as usize));
+            }
+        }
+        Some(path)
+    }
+    /// Computes directions towards ```goal``` for every cell at once. See [FlowField].
+    pub fn flow_field(&self, goal: (usize, usize)) -> FlowField {
+        let mut costs = vec![u32::MAX; self.width * self.height];
+        let mut queue = BinaryHeap::new();
+        if self.is_walkable(goal.0 as i64, goal.1 as i64) {
+            costs[goal.1 * self.width + goal.0] = 0;
+            queue.push(Reverse((0, goal)));
+        }
+
+        while let Some(Reverse((cost, cell))) = queue.pop() {
+            if cost > costs[cell.1 * self.width + cell.0] {
+                continue;
+            }
+            for (next, step) in self.neighbors(cell) {
+                let next_cost = cost + step;
+                let index = next.1 * self.width + next.0;
+                if next_cost < costs[index] {
+                    costs[index] = next_cost;
+                    queue.push(Reverse((next_cost, next)));
+                }
+            }
+        }
+
+        let directions = (0..self.width * self.height).map(|index| {
+            let cell = (index % self.width, index / self.width);
+            if costs[index] == 0 || costs[index] == u32::MAX {
+                return None;
+            }
+            self.neighbors(cell)
+                .into_iter()
+                .min_by_key(|(next, _)| costs[next.1 * self.width + next.0])
+                .map(|(next, _)| Vector2::new(next.0 as f32 - cell.0 as f32, next.1 as f32 - cell.1 as f32).normalize())
+        }).collect();
+
+        FlowField { width: self.width, height: self.height, costs, directions }
+    }
+
+    fn search(
+        &self,
+        start: (usize, usize),
+        goal: (usize, usize),
+        diagonal: bool,
+        successors: impl Fn(&Self, (usize, usize), Option<(usize, usize)>) -> Vec<((usize, usize), u32)>,
+    ) -> Option<Vec<(usize, usize)>> {
+        if !self.is_walkable(start.0 as i64, start.1 as i64) || !self.is_walkable(goal.0 as i64, goal.1 as i64) {
+            return None;
+        }
+
+        let mut costs = vec![u32::MAX; self.width * self.height];
+        let mut parents: Vec<Option<(usize, usize)>> = vec![None; self.width * self.height];
+        let mut queue = BinaryHeap::new();
+        costs[start.1 * self.width + start.0] = 0;
+        queue.push(Reverse((Self::heuristic(start, goal, diagonal), 0, start)));
+
+        while let Some(Reverse((_, cost, cell))) = queue.pop() {
+            if cell == goal {
+                let mut path = vec![goal];
+                while let Some(parent) = parents[path[path.len() - 1].1 * self.width + path[path.len() - 1].0] {
+                    path.push(parent);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if cost > costs[cell.1 * self.width + cell.0] {
+                continue;
+            }
+
+            for (next, step) in successors(self, cell, parents[cell.1 * self.width + cell.0]) {
+                let next_cost = cost + step;
+                let index = next.1 * self.width + next.0;
+                if next_cost < costs[index] {
+                    costs[index] = next_cost;
+                    parents[index] = Some(cell);
+                    queue.push(Reverse((next_cost + Self::heuristic(next, goal, diagonal), next_cost, next)));
+                }
+            }
+        }
+
+        None
+    }
+    fn neighbors(&self, cell: (usize, usize)) -> Vec<((usize, usize), u32)> {
+        self.neighbors_with(cell, self.diagonal)
+    }
+    fn neighbors_with(&self, cell: (usize, usize), diagonal_moves: bool) -> Vec<((usize, usize), u32)> {
+        let (x, y) = (cell.0 as i64, cell.1 as i64);
+        let mut result = Vec::with_capacity(8);
+        for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)] {
+            let diagonal = dx != 0 && dy != 0;
+            if diagonal && (!diagonal_moves || !self.is_walkable(x + dx, y) || !self.is_walkable(x, y + dy)) {
+                continue;
+            }
+            if self.is_walkable(x + dx, y + dy) {
+                result.push((((x + dx) as usize, (y + dy) as usize), if diagonal { DIAGONAL_COST } else { STRAIGHT_COST }));
+            }
+        }
+        result
+    }
+    fn jps_neighbors(&self, cell: (usize, usize), parent: Option<(usize, usize)>) -> Vec<(i64, i64)> {
+        let (x, y) = (cell.0 as i64, cell.1 as i64);
+        let Some(parent) = parent else {
+            return self.neighbors_with(cell, true).into_iter().map(|(next, _)| (next.0 as i64, next.1 as i64)).collect();
+        };
+
+        let dx = (x - parent.0 as i64).signum();
+        let dy = (y - parent.1 as i64).signum();
+        let walkable = |x: i64, y: i64| self.is_walkable(x, y);
+        let mut result = Vec::with_capacity(5);
+
+        if dx != 0 && dy != 0 {
+            if walkable(x, y + dy) { result.push((x, y + dy)); }
+            if walkable(x + dx, y) { result.push((x + dx, y)); }
+            if walkable(x, y + dy) && walkable(x + dx, y) { result.push((x + dx, y + dy)); }
+        } else if dx != 0 {
+            let (next, top, bottom) = (walkable(x + dx, y), walkable(x, y + 1), walkable(x, y - 1));
+            if next {
+                result.push((x + dx, y));
+                if top { result.push((x + dx, y + 1)); }
+                if bottom { result.push((x + dx, y - 1)); }
+            }
+            if top { result.push((x, y + 1)); }
+            if bottom { result.push((x, y - 1)); }
+        } else {
+            let (next, right, left) = (walkable(x, y + dy), walkable(x + 1, y), walkable(x - 1, y));
+            if next {
+                result.push((x, y + dy));
+                if right { result.push((x + 1, y + dy)); }
+                if left { result.push((x - 1, y + dy)); }
+            }
+            if right { result.push((x + 1, y)); }
+            if left { result.push((x - 1, y)); }
+        }
+
+        result
+    }
+    fn jump(&self, cell: (i64, i64), parent: (usize, usize), goal: (i64, i64)) -> Option<(usize, usize)> {
+        let (mut x, mut y) = cell;
+        let (dx, dy) = ((x - parent.0 as i64).signum(), (y - parent.1 as i64).signum());
+
+        loop {
+            if !self.is_walkable(x, y) {
+                return None;
+            }
+            if (x, y) == goal {
+                return Some((x as usize, y as usize));
+            }
+
+            let forced = if dx != 0 && dy != 0 {
+                self.jump((x + dx, y), (x as usize, y as usize), goal).is_some()
+                    || self.jump((x, y + dy), (x as usize, y as usize), goal).is_some()
+            } else if dx != 0 {
+                (self.is_walkable(x, y - 1) && !self.is_walkable(x - dx, y - 1))
+                    || (self.is_walkable(x, y + 1) && !self.is_walkable(x - dx, y + 1))
+            } else {
+                (self.is_walkable(x - 1, y) && !self.is_walkable(x - 1, y - dy))
+                    || (self.is_walkable(x + 1, y) && !self.is_walkable(x + 1, y - dy))
+            };
+            if forced {
+                return Some((x as usize, y as usize));
+            }
+
+            if !self.is_walkable(x + dx, y) || !self.is_walkable(x, y + dy) {
+                return None;
+            }
+            x += dx;
+            y += dy;
+        }
+    }
+    fn distance(a: (usize, usize), b: (usize, usize)) -> u32 {
+        let dx = a.0.abs_diff(b.0) as u32;
+        let dy = a.1.abs_diff(b.1) as u32;
+        STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+    }
+    fn heuristic(a: (usize, usize), b: (usize, usize), diagonal: bool) -> u32 {
+        if diagonal {
+            Self::distance(a, b)
+        } else {
+            STRAIGHT_COST * (a.0.abs_diff(b.0) + a.1.abs_diff(b.1)) as u32
+        }
+    }
+}
+
+/// Directions towards a single goal for every cell of a [NavGrid], built by [NavGrid::flow_field].
+/// Any number of units can follow it without running pathfinding for each one.
+pub struct FlowField {
+    width: usize,
+    height: usize,
+    costs: Vec<u32>,
+    directions: Vec<Option<Vector2<f32>>>,
+}
+
+impl FlowField {
+    /// Gets normalized direction to the next cell on the way to the goal.
+    /// Returns [None] at the goal itself, in blocked or unreachable cells and outside the grid.
+    pub fn get_direction(&self, x: usize, y: usize) -> Option<Vector2<f32>> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.directions[y * self.width + x]
+    }
+    /// Gets path cost from the cell to the goal (10 per straight step, 14 per diagonal one).
+    /// Returns [None] in blocked or unreachable cells and outside the grid.
+    pub fn get_cost(&self, x: usize, y: usize) -> Option<u32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.costs[y * self.width + x]).filter(|&cost| cost != u32::MAX)
+    }
+
+    /// Draws a line from the center of every cell along its direction, cells are ```cell_size``` world units wide.
+    /// Position is at ```layout(location = 0)``` as ```vec2```. It creates a mesh every call, so use it only for debugging.
+    pub fn debug_draw(&self, cell_size: f32) {
+        let mut vertices: Vec<f32> = Vec::new();
+        for (index, direction) in self.directions.iter().enumerate() {
+            let Some(direction) = direction else { continue; };
+            let center = Vector2::new((index % self.width) as f32 + 0.5, (index / self.width) as f32 + 0.5) * cell_size;
+            let end = center + direction * cell_size * 0.4;
+            vertices.extend_from_slice(&[center.x, center.y, end.x, end.y]);
+        }
+
+        Mesh::new::<f32>(&vertices, &Layout::basic_2d(), gl::LINES).draw();
+    }
+}
+
+/// Draws ```path``` through cell centers as a line strip, cells are ```cell_size``` world units wide.
+/// Position is at ```layout(location = 0)``` as ```vec2```. It creates a mesh every call, so use it only for debugging.
+pub fn debug_draw_path(path: &[(usize, usize)], cell_size: f32) {
+    let vertices: Vec<f32> = path.iter()
+        .flat_map(|&(x, y)| [(x as f32 + 0.5) * cell_size, (y as f32 + 0.5) * cell_size])
+        .collect();
+
+    Mesh::new::<f32>(&vertices, &Layout::basic_2d(), gl::LINE_STRIP).draw();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Random;
+
+    // Checks that every step goes to an adjacent walkable cell without cutting corners, and returns the path cost.
+    fn check_path(grid: &NavGrid, path: &[(usize, usize)], diagonal: bool) -> u32 {
+        let mut cost = 0;
+        for pair in path.windows(2) {
+            let (x, y) = (pair[0].0 as i64, pair[0].1 as i64);
+            let (dx, dy) = (pair[1].0 as i64 - x, pair[1].1 as i64 - y);
+            assert!(dx.abs() <= 1 && dy.abs() <= 1 && (dx, dy) != (0, 0), "Path jumps from {:?} to {:?}", pair[0], pair[1]);
+            assert!(grid.is_walkable(x + dx, y + dy), "Path goes through blocked cell {:?}", pair[1]);
+
+            if dx != 0 && dy != 0 {
+                assert!(diagonal, "Diagonal step from {:?} with diagonal moves disabled", pair[0]);
+                assert!(grid.is_walkable(x + dx, y) && grid.is_walkable(x, y + dy), "Path cuts a corner at {:?}", pair[0]);
+                cost += DIAGONAL_COST;
+            } else {
+                cost += STRAIGHT_COST;
+            }
+        }
+        cost
+    }
+
+    fn wall_grid() -> NavGrid {
+        let mut grid = NavGrid::new(16, 16);
+        for y in 0..12 {
+            grid.set_walkable(8, y, false);
+        }
+        grid
+    }
+
+    fn random_grid(random: &mut Random, size: usize) -> NavGrid {
+        let mut grid = NavGrid::new(size, size);
+        for y in 0..size {
+            for x in 0..size {
+                if random.chance(0.3) {
+                    grid.set_walkable(x, y, false);
+                }
+            }
+        }
+        grid.set_walkable(0, 0, true);
+        grid.set_walkable(size - 1, size - 1, true);
+        grid
+    }
+
+    #[test]
+    fn a_star_open_grid() {
+        let grid = NavGrid::new(10, 10);
+        let path = grid.find_path((0, 0), (9, 4)).unwrap();
+
+        assert_eq!(path.first(), Some(&(0, 0)));
+        assert_eq!(path.last(), Some(&(9, 4)));
+        assert_eq!(check_path(&grid, &path, true), 4 * DIAGONAL_COST + 5 * STRAIGHT_COST);
+    }
+
+    #[test]
+    fn a_star_goes_around_walls() {
+        let grid = wall_grid();
+        let path = grid.find_path((2, 2), (14, 2)).unwrap();
+
+        assert!(path.iter().any(|&(_, y)| y >= 12), "Path doesn't go around the wall");
+        assert_eq!(check_path(&grid, &path, true), grid.flow_field((14, 2)).get_cost(2, 2).unwrap());
+    }
+
+    #[test]
+    fn a_star_without_diagonals() {
+        let grid = NavGrid::new(10, 10).with_diagonal(false);
+        let path = grid.find_path((1, 1), (6, 8)).unwrap();
+        assert_eq!(check_path(&grid, &path, false), (5 + 7) * STRAIGHT_COST);
+    }
+
+    #[test]
+    fn a_star_unreachable() {
+        let mut grid = NavGrid::new(8, 8);
+        for x in 0..8 {
+            grid.set_walkable(x, 4, false);
+        }
+        assert_eq!(grid.find_path((0, 0), (0, 7)), None);
+        assert_eq!(grid.find_path((0, 4), (0, 0)), None);
+        assert_eq!(grid.find_path((0, 0), (0, 0)), Some(vec![(0, 0)]));
+    }
+
+    #[test]
+    fn jps_matches_a_star() {
+        let mut random = Random::new(7);
+        let mut found = 0;
+        for _ in 0..50 {
+            let grid = random_grid(&mut random, 24);
+            let (start, goal) = ((0, 0), (23, 23));
+
+            match (grid.find_path(start, goal), grid.find_path_jps(start, goal)) {
+                (Some(a_star), Some(jps)) => {
+                    assert_eq!(jps.first(), Some(&start));
+                    assert_eq!(jps.last(), Some(&goal));
+                    assert_eq!(check_path(&grid, &jps, true), check_path(&grid, &a_star, true));
+                    found += 1;
+                }
+                (None, None) => {}
+                (a_star, jps) => panic!("A* found {:?}, but JPS found {:?}", a_star.is_some(), jps.is_some()),
+            }
+        }
+        assert!(found >= 10, "Only {} of the random grids have a path", found);
+    }
+
+    #[test]
+    fn jps_goes_around_walls() {
+        let grid = wall_grid();
+        let path = grid.find_path_jps((2, 2), (14, 2)).unwrap();
+        assert_eq!(check_path(&grid, &path, true), check_path(&grid, &grid.find_path((2, 2), (14, 2)).unwrap(), true));
+    }
+
+    #[test]
+    fn flow_field_leads_to_goal() {
+        let grid = wall_grid();
+        let goal = (14, 2);
+        let field = grid.flow_field(goal);
+
+        assert_eq!(field.get_cost(goal.0, goal.1), Some(0));
+        assert_eq!(field.get_direction(goal.0, goal.1), None);
+        assert_eq!(field.get_cost(8, 0), None);
+        assert_eq!(field.get_direction(8, 0), None);
+        assert_eq!(field.get_cost(16, 0), None);
+
+        // Following directions from every cell must reach the goal with strictly decreasing cost.
+        for y in 0..16 {
+            for x in 0..16 {
+                let Some(mut cost) = field.get_cost(x, y) else { continue; };
+                let mut cell = (x, y);
+                while cell != goal {
+                    let direction = field.get_direction(cell.0, cell.1).unwrap();
+                    cell = ((cell.0 as f32 + direction.x.round()) as usize, (cell.1 as f32 + direction.y.round()) as usize);
+                    let next_cost = field.get_cost(cell.0, cell.1).unwrap();
+                    assert!(next_cost < cost, "Flow field doesn't get closer to the goal at {:?}", cell);
+                    cost = next_cost;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn flow_field_costs_match_a_star() {
+        let mut random = Random::new(11);
+        let grid = random_grid(&mut random, 20);
+        let field = grid.flow_field((19, 19));
+
+        for (x, y) in [(0, 0), (5, 3), (10, 17), (18, 2)] {
+            let path_cost = grid.find_path((x, y), (19, 19)).map(|path| check_path(&grid, &path, true));
+            assert_eq!(field.get_cost(x, y), path_cost);
+        }
+    }
+
+    #[test]
+    fn flow_field_unreachable_goal() {
+        let mut grid = NavGrid::new(4, 4);
+        grid.set_walkable(3, 3, false);
+        let field = grid.flow_field((3, 3));
+        assert!((0..4).all(|y| (0..4).all(|x| field.get_cost(x, y).is_none() && field.get_direction(x, y).is_none())));
+    }
+}
@@ -0,0 +1,247 @@
+use std::cell::Cell;
+
+use gl::types::{GLboolean, GLenum, GLint, GLsizei, GLuint};
+
+use crate::{mesh::Mesh, render::{self, BlendState}, shader::Shader};
+
+const VERTEX_SOURCE: &str = "#version 330 core
+layout(location = 0) in vec2 a_Position;
+
+void main() {
+    gl_Position = vec4(a_Position, 0.0, 1.0);
+}
+";
+const COMPOSITE_SOURCE: &str = "#version 330 core
+out vec4 f_Color;
+
+uniform sampler2D u_Accumulation;
+uniform sampler2D u_Weight;
+
+void main() {
+    ivec2 coord = ivec2(gl_FragCoord.xy);
+    vec4 accumulation = texelFetch(u_Accumulation, coord, 0);
+    float revealage = accumulation.a;
+    if (revealage >= 1.0) {
+        discard;
+    }
+
+    float weight = texelFetch(u_Weight, coord, 0).r;
+    f_Color = vec4(accumulation.rgb / max(weight, 0.00001), 1.0 - revealage);
+}
+";
+
+/// Weighted blended order-independent transparency (McGuire & Bavoil).
+/// Transparent meshes can be drawn in any order, which helps a lot when sorting fails (particles, foliage, glass).
+///
+/// Transparent pass renders into two targets (accumulation with revealage in its alpha, and weight), then [WeightedOit::composite] blends
+/// the result over the opaque scene. It needs only OpenGL 3.3: both targets share one ```glBlendFuncSeparate```,
+/// color is summed and alpha is multiplied.
+/// # Example
+/// ```rust
+/// use tinystorm::{framebuffer::Framebuffer, oit::WeightedOit};
+///
+/// let scene = Framebuffer::new(window.get_width(), window.get_height());
+/// let oit = WeightedOit::new(window.get_width(), window.get_height());
+///
+/// while window.is_running() {
+///     window.poll_events();
+///
+///     scene.bind();
+///     unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }
+///     opaque_mesh.draw();
+///
+///     oit.begin(Some(scene.get_depth_texture_id())); // Transparent meshes are still hidden behind opaque ones.
+///     transparent_shader.bind(); // Uses WeightedOit::GLSL and calls oit_write(color).
+///     glass_mesh.draw();
+///     smoke_mesh.draw();
+///     oit.end();
+///
+///     oit.composite(); // Blends transparency over the scene framebuffer.
+///     ...
+/// }
+/// ```
+pub struct WeightedOit {
+    framebuffer: GLuint,
+    accumulation: GLuint,
+    weight: GLuint,
+
+    shader: Shader,
+    mesh: Mesh,
+
+    width: u32,
+    height: u32,
+
+    previous_framebuffer: Cell<GLint>,
+    previous_blend: Cell<BlendState>,
+    previous_depth_mask: Cell<GLboolean>,
+    generation: u64,
+}
+
+impl WeightedOit {
+    /// GLSL helpers to paste into fragment shaders of transparent meshes (after ```#version```, GLSL 3.30+).
+    /// Call ```oit_write(color)``` instead of writing your own output, ```color``` is not premultiplied.
+    pub const GLSL: &'static str = "
+layout(location = 0) out vec4 f_Accumulation;
+layout(location = 1) out float f_Weight;
+
+void oit_write(vec4 color) {
+    float weight = clamp(pow(min(1.0, color.a * 10.0) + 0.01, 3.0) * 1e8 * pow(1.0 - gl_FragCoord.z * 0.9, 3.0), 0.01, 3000.0);
+    // RGB is summed, alpha multiplies revealage by (1 - alpha).
+    f_Accumulation = vec4(color.rgb * color.a * weight, color.a);
+    f_Weight = color.a * weight;
+}
+";
+
+    /// Creates ```width``` x ```height``` accumulation (RGBA16F) and weight (R16F) targets.
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut framebuffer = 0;
+        unsafe { gl::GenFramebuffers(1, &mut framebuffer); }
+
+        let mut oit = Self {
+            framebuffer,
+            accumulation: 0,
+            weight: 0,
+
+            shader: Shader::from_source(VERTEX_SOURCE, COMPOSITE_SOURCE),
+            mesh: Mesh::fullscreen_triangle(),
+
+            width: 0,
+            height: 0,
+
+            previous_framebuffer: Cell::new(0),
+            previous_blend: Cell::new(BlendState::default()),
+            previous_depth_mask: Cell::new(gl::TRUE),
+            generation: render::context_generation(),
+        };
+        oit.resize(width, height);
+        oit
+    }
+
+    /// Recreates targets with a new size. Does nothing if size hasn't changed.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if width == self.width && height == self.height {
+            return;
+        }
+
+        self.width = width;
+        self.height = height;
+
+        unsafe {
+            if self.accumulation != 0 && render::is_current_context(self.generation) {
+                gl::DeleteTextures(1, &self.accumulation);
+                gl::DeleteTextures(1, &self.weight);
+            }
+
+            self.accumulation = Self::create_target(width, height, gl::RGBA16F, gl::RGBA);
+            self.weight = Self::create_target(width, height, gl::R16F, gl::RED);
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, self.accumulation, 0);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT1, gl::TEXTURE_2D, self.weight, 0);
+            let draw_buffers: [GLenum; 2] = [gl::COLOR_ATTACHMENT0, gl::COLOR_ATTACHMENT1];
+            gl::DrawBuffers(2, draw_buffers.as_ptr());
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    /// Starts the transparent pass: binds OIT targets, clears them and sets up blending and depth writes.
+    /// The previous state is restored by [WeightedOit::end].
+    /// If ```depth_texture``` (depth-stencil texture of the same size, e.g. [crate::framebuffer::Framebuffer::get_depth_texture_id])
+    /// is given, it's used for depth test against opaque geometry (without writing to it).
+    pub fn begin(&self, depth_texture: Option<GLuint>) {
+        unsafe {
+            let mut previous_framebuffer: GLint = 0;
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous_framebuffer);
+            self.previous_framebuffer.set(previous_framebuffer);
+            self.previous_blend.set(BlendState::save());
+            let mut depth_mask: GLboolean = gl::TRUE;
+            gl::GetBooleanv(gl::DEPTH_WRITEMASK, &mut depth_mask);
+            self.previous_depth_mask.set(depth_mask);
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::TEXTURE_2D, depth_texture.unwrap_or(0), 0);
+            gl::Viewport(0, 0, self.width as GLsizei, self.height as GLsizei);
+
+            // Accumulated color starts at zero and revealage (alpha) at one.
+            let accumulation: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+            let weight: [f32; 4] = [0.0; 4];
+            gl::ClearBufferfv(gl::COLOR, 0, accumulation.as_ptr());
+            gl::ClearBufferfv(gl::COLOR, 1, weight.as_ptr());
+
+            gl::DepthMask(gl::FALSE);
+            gl::Enable(gl::BLEND);
+            gl::BlendFuncSeparate(gl::ONE, gl::ONE, gl::ZERO, gl::ONE_MINUS_SRC_ALPHA);
+        }
+    }
+    /// Ends the transparent pass: restores depth writes, blending and the framebuffer bound before [WeightedOit::begin].
+    pub fn end(&self) {
+        self.previous_blend.get().restore();
+        unsafe {
+            gl::DepthMask(self.previous_depth_mask.get());
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.previous_framebuffer.get() as GLuint);
+        }
+    }
+    /// Blends the transparent pass result over the currently bound framebuffer. Viewport and blending state aren't changed.
+    pub fn composite(&self) {
+        let blend = BlendState::save();
+        unsafe {
+            let depth_test: GLboolean = gl::IsEnabled(gl::DEPTH_TEST);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, self.accumulation);
+            gl::ActiveTexture(gl::TEXTURE1);
+            gl::BindTexture(gl::TEXTURE_2D, self.weight);
+
+            self.shader.bind();
+            self.shader.set_int("u_Accumulation", 0);
+            self.shader.set_int("u_Weight", 1);
+            self.mesh.draw();
+            Shader::unbind();
+
+            blend.restore();
+            if depth_test == gl::TRUE { gl::Enable(gl::DEPTH_TEST); }
+        }
+    }
+
+    unsafe fn create_target(width: u32, height: u32, internal_format: GLenum, format: GLenum) -> GLuint {
+        let mut texture = 0;
+        unsafe {
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                internal_format as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                0,
+                format,
+                gl::FLOAT,
+                std::ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        texture
+    }
+}
+impl Drop for WeightedOit {
+    /// You don't need to manually free OpenGL resources, it's done automatically.
+    fn drop(&mut self) {
+        if !render::is_current_context(self.generation) {
+            return;
+        }
+        unsafe {
+            gl::DeleteTextures(1, &self.accumulation);
+            gl::DeleteTextures(1, &self.weight);
+            gl::DeleteFramebuffers(1, &self.framebuffer);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use nalgebra::Vector3;
+
+/// A floating origin for planetary-scale worlds: positions are stored as ```f64``` world coordinates,
+/// but rendered as ```f32``` coordinates relative to an origin that follows the camera.
+/// Without it ```f32``` precision runs out a few kilometers away and everything starts to jitter.
+/// # Example
+/// ```rust
+/// use tinystorm::{origin::FloatingOrigin, nalgebra::Vector3};
+///
+/// let mut origin = FloatingOrigin::new(1024.0); // Rebase when camera is 1 km away from the origin.
+///
+/// while window.is_running() {
+///     window.poll_events();
+///     origin.update(&camera_world_position);
+///
+///     for planet in &planets {
+///         let position = origin.to_render(&planet.world_position); // Small f32 values near the camera.
+///         shader.set_mat4("u_Model", &Matrix4::new_translation(&position));
+///         planet.mesh.draw();
+///     }
+/// }
+/// ```
+pub struct FloatingOrigin {
+    origin: Vector3<f64>,
+    threshold: f64,
+}
+
+impl FloatingOrigin {
+    /// Creates an origin at world zero that is moved to the camera when it's farther than ```threshold``` world units.
+    pub fn new(threshold: f64) -> Self {
+        Self { origin: Vector3::zeros(), threshold }
+    }
+
+    /// Moves the origin to ```camera``` world position if it's too far. Call it once per frame.
+    /// Returns render-space offset everything was shifted by, if rebasing happened
+    /// (subtract it from render-space positions you've cached, e.g. particles).
+    pub fn update(&mut self, camera: &Vector3<f64>) -> Option<Vector3<f32>> {
+        let offset = camera - self.origin;
+        if offset.norm() <= self.threshold {
+            return None;
+        }
+
+        self.origin = *camera;
+        Some(offset.cast::<f32>())
+    }
+    /// Moves the origin to ```origin``` world position immediately. Useful for teleports.
+    pub fn set_origin(&mut self, origin: Vector3<f64>) {
+        self.origin = origin;
+    }
+
+    /// Converts world position into render position relative to the origin.
+    pub fn to_render(&self, world: &Vector3<f64>) -> Vector3<f32> {
+        (world - self.origin).cast::<f32>()
+    }
+    /// Converts render position relative to the origin back into world position.
+    pub fn to_world(&self, render: &Vector3<f32>) -> Vector3<f64> {
+        render.cast::<f64>() + self.origin
+    }
+
+    /// Gets current origin world position.
+    pub fn get_origin(&self) -> Vector3<f64> {
+        self.origin
+    }
+    /// Gets distance from the origin at which rebasing happens.
+    pub fn get_threshold(&self) -> f64 {
+        self.threshold
+    }
+}
@@ -0,0 +1,170 @@
+use gl::types::{GLboolean, GLfloat, GLint, GLuint};
+
+use crate::{framebuffer::Framebuffer, mesh::Mesh, shader::Shader};
+
+const VERTEX_SOURCE: &str = "#version 330 core
+layout(location = 0) in vec2 a_Position;
+
+out vec2 v_Uv;
+
+void main() {
+    v_Uv = a_Position * 0.5 + 0.5;
+    gl_Position = vec4(a_Position, 0.0, 1.0);
+}
+";
+const HEATMAP_SOURCE: &str = "#version 330 core
+out vec4 f_Color;
+
+uniform float u_Heat;
+
+void main() {
+    // Blue -> cyan -> green -> yellow -> red, white for everything above the last level.
+    vec3 color = u_Heat > 1.0 ? vec3(1.0) : clamp(vec3(
+        u_Heat * 4.0 - 2.0,
+        u_Heat < 0.5 ? u_Heat * 4.0 : 4.0 - u_Heat * 4.0,
+        2.0 - u_Heat * 4.0
+    ), 0.0, 1.0);
+    f_Color = vec4(color, 1.0);
+}
+";
+const BLIT_SOURCE: &str = "#version 330 core
+in vec2 v_Uv;
+out vec4 f_Color;
+
+uniform sampler2D u_Heatmap;
+
+void main() {
+    f_Color = texture(u_Heatmap, v_Uv);
+}
+";
+
+/// A debug view that shows how many times every pixel was drawn (overdraw) as a heatmap, to find fill-rate hotspots.
+/// Black - not drawn, blue - once, then cyan, green, yellow, red and white for [OverdrawView::LEVELS] and more times.
+///
+/// Fragments are counted in the stencil buffer of its own target, so your scene is drawn with its usual shaders.
+/// Every rasterized fragment is counted, even if it's rejected by depth test. Discarded fragments aren't counted.
+/// # Example
+/// ```rust
+/// use tinystorm::{glfw::Key, overdraw::OverdrawView};
+///
+/// let mut overdraw = OverdrawView::new(window.get_width(), window.get_height());
+/// while window.is_running() {
+///     window.poll_events();
+///     let debug = window.is_key_pressed(Key::F3);
+///
+///     if debug { overdraw.begin(); }
+///     draw_scene();
+///     draw_ui();
+///     if debug {
+///         overdraw.end();
+///         overdraw.draw(); // Replaces the picture with the heatmap.
+///     }
+///     window.swap_buffers();
+/// }
+/// ```
+pub struct OverdrawView {
+    framebuffer: Framebuffer,
+    heatmap_shader: Shader,
+    blit_shader: Shader,
+    mesh: Mesh,
+
+    previous_framebuffer: GLint,
+    previous_viewport: [GLint; 4],
+    previous_stencil_test: GLboolean,
+}
+
+impl OverdrawView {
+    /// Number of overdraw levels with their own color. Pixels drawn this many times or more are white.
+    pub const LEVELS: u32 = 8;
+
+    /// Creates a ```width``` x ```height``` counter target. Use your window size.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            framebuffer: Framebuffer::new(width, height),
+            heatmap_shader: Shader::from_source(VERTEX_SOURCE, HEATMAP_SOURCE),
+            blit_shader: Shader::from_source(VERTEX_SOURCE, BLIT_SOURCE),
+            mesh: Mesh::fullscreen_triangle(),
+
+            previous_framebuffer: 0,
+            previous_viewport: [0; 4],
+            previous_stencil_test: gl::FALSE,
+        }
+    }
+    /// Resizes the counter target. Call it when your window is resized.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.framebuffer.resize(width, height);
+    }
+
+    /// Starts counting: everything drawn until [OverdrawView::end] goes to the counter target instead of the bound framebuffer.
+    pub fn begin(&mut self) {
+        unsafe {
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut self.previous_framebuffer);
+            gl::GetIntegerv(gl::VIEWPORT, self.previous_viewport.as_mut_ptr());
+            self.previous_stencil_test = gl::IsEnabled(gl::STENCIL_TEST);
+
+            self.framebuffer.bind();
+            gl::ClearStencil(0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
+
+            gl::Enable(gl::STENCIL_TEST);
+            gl::StencilMask(0xFF);
+            gl::StencilFunc(gl::ALWAYS, 0, 0xFF);
+            gl::StencilOp(gl::INCR, gl::INCR, gl::INCR);
+        }
+    }
+    /// Stops counting and turns the counts into a heatmap. Previously bound framebuffer, viewport and stencil test are restored.
+    pub fn end(&mut self) {
+        unsafe {
+            let mut previous_program: GLint = 0;
+            let mut clear_color: [GLfloat; 4] = [0.0; 4];
+            gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut previous_program);
+            gl::GetFloatv(gl::COLOR_CLEAR_VALUE, clear_color.as_mut_ptr());
+            let depth_test: GLboolean = gl::IsEnabled(gl::DEPTH_TEST);
+            let blend: GLboolean = gl::IsEnabled(gl::BLEND);
+
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP);
+
+            // One fullscreen pass per level, stencil test picks pixels drawn exactly that many times (or more for the last one).
+            self.heatmap_shader.bind();
+            for level in 1..=Self::LEVELS {
+                gl::StencilFunc(if level == Self::LEVELS { gl::LEQUAL } else { gl::EQUAL }, level as GLint, 0xFF);
+                let heat = if level == Self::LEVELS { 2.0 } else { (level - 1) as f32 / (Self::LEVELS - 2) as f32 };
+                self.heatmap_shader.set_float("u_Heat", heat);
+                self.mesh.draw();
+            }
+
+            if self.previous_stencil_test != gl::TRUE { gl::Disable(gl::STENCIL_TEST); }
+            if depth_test == gl::TRUE { gl::Enable(gl::DEPTH_TEST); }
+            if blend == gl::TRUE { gl::Enable(gl::BLEND); }
+            gl::ClearColor(clear_color[0], clear_color[1], clear_color[2], clear_color[3]);
+            gl::UseProgram(previous_program as GLuint);
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.previous_framebuffer as GLuint);
+            gl::Viewport(self.previous_viewport[0], self.previous_viewport[1], self.previous_viewport[2], self.previous_viewport[3]);
+        }
+    }
+    /// Draws the heatmap over the whole currently bound framebuffer.
+    pub fn draw(&self) {
+        unsafe {
+            let depth_test: GLboolean = gl::IsEnabled(gl::DEPTH_TEST);
+            gl::Disable(gl::DEPTH_TEST);
+
+            self.framebuffer.bind_color_texture(0);
+            self.blit_shader.bind();
+            self.blit_shader.set_int("u_Heatmap", 0);
+            self.mesh.draw();
+            Shader::unbind();
+
+            if depth_test == gl::TRUE { gl::Enable(gl::DEPTH_TEST); }
+        }
+    }
+
+    /// Gets the heatmap framebuffer, e.g. to show it in a corner of the screen.
+    pub fn get_framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+}
@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use gl::types::{GLenum, GLint, GLuint, GLuint64};
+
+use crate::render;
+
+// ARB_pipeline_statistics_query (core in OpenGL 4.6), not included in gl bindings.
+const VERTEX_SHADER_INVOCATIONS: GLenum = 0x82F0;
+const TESS_EVALUATION_SHADER_INVOCATIONS: GLenum = 0x82F2;
+const FRAGMENT_SHADER_INVOCATIONS: GLenum = 0x82F4;
+
+const QUERY_TARGETS: [GLenum; 5] = [
+    gl::TIME_ELAPSED,
+    gl::PRIMITIVES_GENERATED,
+    VERTEX_SHADER_INVOCATIONS,
+    TESS_EVALUATION_SHADER_INVOCATIONS,
+    FRAGMENT_SHADER_INVOCATIONS,
+];
+
+/// GPU statistics of a single pass measured by [GpuProfiler].
+/// Pipeline statistics are ```None``` if the driver doesn't support OpenGL 4.6 or ```ARB_pipeline_statistics_query```.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct PassStatistics {
+    /// Time the GPU spent on the pass.
+    pub gpu_time: Duration,
+    /// Number of primitives (triangles, lines, points) generated by vertex processing.
+    pub primitives_generated: u64,
+    /// Number of vertex shader runs. Much bigger than vertex count means bad vertex cache usage or no indexing.
+    pub vertex_invocations: Option<u64>,
+    /// Number of tessellation evaluation shader runs. Catches tessellation factors gone wild.
+    pub tess_evaluation_invocations: Option<u64>,
+    /// Number of fragment shader runs. Much bigger than pixel count means overdraw.
+    pub fragment_invocations: Option<u64>,
+}
+
+struct ProfiledPass {
+    name: String,
+    // Two sets of queries, so results are read a frame later instead of waiting for the GPU.
+    queries: [[GLuint; QUERY_TARGETS.len()]; 2],
+    pending: [bool; 2],
+    statistics: PassStatistics,
+}
+
+/// Measures GPU time and pipeline statistics of named passes (e.g. "shadows", "scene", "ui").
+/// Results are 2 frames late, so measuring doesn't stall the CPU. Passes can't be nested.
+/// # Example
+/// ```rust
+/// use tinystorm::profiler::GpuProfiler;
+///
+/// let mut profiler = GpuProfiler::new();
+/// while window.is_running() {
+///     window.poll_events();
+///
+///     profiler.begin_pass("scene");
+///     terrain.draw();
+///     profiler.end_pass();
+///
+///     profiler.begin_pass("particles");
+///     particles.draw();
+///     profiler.end_pass();
+///
+///     profiler.end_frame();
+///     if let Some(stats) = profiler.get_statistics("particles") {
+///         println!("Overdraw: {:.1}x", stats.fragment_invocations.unwrap_or(0) as f32 / (window.get_width() * window.get_height()) as f32);
+///     }
+///     window.swap_buffers();
+/// }
+/// ```
+pub struct GpuProfiler {
+    passes: Vec<ProfiledPass>,
+    current: Option<usize>,
+    frame: usize,
+    pipeline_statistics: bool,
+    generation: u64,
+}
+
+impl GpuProfiler {
+    /// Creates a profiler with no passes. Passes are created when they're first used.
+    pub fn new() -> Self {
+        Self { passes: Vec::new(), current: None, frame: 0, pipeline_statistics: Self::supports_pipeline_statistics(), generation: render::context_generation() }
+    }
+
+    /// Starts measuring pass ```name```. Panics if another pass is still being measured.
+    pub fn begin_pass(&mut self, name: &str) {
+        if let Some(current) = self.current {
+            panic!("Failed to begin pass \"{}\". Pass \"{}\" hasn't ended yet, passes can't be nested.", name, self.passes[current].name);
+        }
+
+        let index = match self.passes.iter().position(|pass| pass.name == name) {
+            Some(index) => index,
+            None => {
+                let mut queries = [[0; QUERY_TARGETS.len()]; 2];
+                unsafe {
+                    for set in &mut queries {
+                        gl::GenQueries(set.len() as GLint, set.as_mut_ptr());
+                    }
+                }
+                self.passes.push(ProfiledPass { name: String::from(name), queries, pending: [false; 2], statistics: PassStatistics::default() });
+                self.passes.len() - 1
+            }
+        };
+
+        let slot = self.frame % 2;
+        let pipeline_statistics = self.pipeline_statistics;
+        let pass = &mut self.passes[index];
+        if pass.pending[slot] {
+            pass.statistics = Self::read_results(&pass.queries[slot], pipeline_statistics);
+        }
+
+        unsafe {
+            for (i, &target) in QUERY_TARGETS.iter().enumerate() {
+                if i < 2 || pipeline_statistics {
+                    gl::BeginQuery(target, pass.queries[slot][i]);
+                }
+            }
+        }
+        pass.pending[slot] = true;
+        self.current = Some(index);
+    }
+    /// Stops measuring the current pass.
+    pub fn end_pass(&mut self) {
+        if self.current.take().is_none() {
+            panic!("Failed to end pass. No pass was started with GpuProfiler::begin_pass.");
+        }
+
+        unsafe {
+            for (i, &target) in QUERY_TARGETS.iter().enumerate() {
+                if i < 2 || self.pipeline_statistics {
+                    gl::EndQuery(target);
+                }
+            }
+        }
+    }
+    /// Marks the end of a frame. Call it once per frame after all passes.
+    pub fn end_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    /// Gets the latest statistics of pass ```name```, or ```None``` if it was never measured.
+    pub fn get_statistics(&self, name: &str) -> Option<PassStatistics> {
+        self.passes.iter().find(|pass| pass.name == name).map(|pass| pass.statistics)
+    }
+    /// Gets the latest statistics of all passes in the order they were first used.
+    pub fn passes(&self) -> impl Iterator<Item = (&str, PassStatistics)> {
+        self.passes.iter().map(|pass| (pass.name.as_str(), pass.statistics))
+    }
+    /// Checks if vertex, tessellation and fragment invocation counts are available.
+    pub fn is_pipeline_statistics_supported(&self) -> bool {
+        self.pipeline_statistics
+    }
+
+    fn read_results(queries: &[GLuint; QUERY_TARGETS.len()], pipeline_statistics: bool) -> PassStatistics {
+        let mut results: [GLuint64; QUERY_TARGETS.len()] = [0; QUERY_TARGETS.len()];
+        unsafe {
+            for (i, result) in results.iter_mut().enumerate() {
+                if i < 2 || pipeline_statistics {
+                    gl::GetQueryObjectui64v(queries[i], gl::QUERY_RESULT, result);
+                }
+            }
+        }
+
+        let statistic = |value: GLuint64| pipeline_statistics.then_some(value);
+        PassStatistics {
+            gpu_time: Duration::from_nanos(results[0]),
+            primitives_generated: results[1],
+            vertex_invocations: statistic(results[2]),
+            tess_evaluation_invocations: statistic(results[3]),
+            fragment_invocations: statistic(results[4]),
+        }
+    }
+    fn supports_pipeline_statistics() -> bool {
+        unsafe {
+            let (mut major, mut minor): (GLint, GLint) = (0, 0);
+            gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+            gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+            if (major, minor) >= (4, 6) {
+                return true;
+            }
+        }
+        render::has_extension(b"GL_ARB_pipeline_statistics_query")
+    }
+}
+impl Default for GpuProfiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Drop for GpuProfiler {
+    /// You don't need to manually free OpenGL resources, it's done automatically.
+    fn drop(&mut self) {
+        if !render::is_current_context(self.generation) {
+            return;
+        }
+        unsafe {
+            for pass in &self.passes {
+                for set in &pass.queries {
+                    gl::DeleteQueries(set.len() as GLint, set.as_ptr());
+                }
+            }
+        }
+    }
+}
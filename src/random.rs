@@ -0,0 +1,160 @@
+use nalgebra::{Vector2, Vector3};
+
+/// A small seeded random number generator (PCG32). The same seed always gives the same numbers on every platform,
+/// so procedural generation, particles and tests can be reproduced. Not suitable for cryptography.
+/// # Example
+/// ```rust
+/// use tinystorm::random::Random;
+///
+/// let mut random = Random::new(1337);
+/// for _ in 0..500 {
+///     let position = Vector3::new(random.range_f32(-50.0, 50.0), 0.0, random.range_f32(-50.0, 50.0));
+///     // Mostly grass, sometimes bushes, rarely rocks.
+///     let kind = random.choose_weighted(&[10.0, 3.0, 0.5]).unwrap();
+///     props.push((kind, position, random.range_f32(0.0, std::f32::consts::TAU)));
+/// }
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Random {
+    state: u64,
+    increment: u64,
+}
+
+impl Random {
+    const MULTIPLIER: u64 = 6364136223846793005;
+
+    /// Creates a generator with ```seed```.
+    pub fn new(seed: u64) -> Self {
+        Self::with_stream(seed, 0)
+    }
+    /// Creates a generator seeded with current time, for when results don't have to be reproduced.
+    pub fn from_time() -> Self {
+        let time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+        Self::new(time.as_nanos() as u64)
+    }
+    /// Creates an independent generator seeded from this one, e.g. one per chunk or particle system,
+    /// so adding random calls in one system doesn't change results of another.
+    pub fn fork(&mut self) -> Self {
+        let (seed, stream) = (self.next_u64(), self.next_u64());
+        Self::with_stream(seed, stream)
+    }
+
+    /// Gets a random ```u32```.
+    pub fn next_u32(&mut self) -> u32 {
+        let state = self.state;
+        self.state = state.wrapping_mul(Self::MULTIPLIER).wrapping_add(self.increment);
+
+        let xorshifted = (((state >> 18) ^ state) >> 27) as u32;
+        xorshifted.rotate_right((state >> 59) as u32)
+    }
+    /// Gets a random ```u64```.
+    pub fn next_u64(&mut self) -> u64 {
+        ((self.next_u32() as u64) << 32) | self.next_u32() as u64
+    }
+    /// Gets a random number from 0.0 (inclusive) to 1.0 (exclusive).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+    /// Gets ```true``` or ```false``` with equal probability.
+    pub fn next_bool(&mut self) -> bool {
+        self.next_u32() & 1 == 1
+    }
+
+    /// Gets a random number from ```min``` (inclusive) to ```max``` (exclusive).
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+    /// Gets a random integer from ```min``` (inclusive) to ```max``` (exclusive). Returns ```min``` if the range is empty.
+    pub fn range_i32(&mut self, min: i32, max: i32) -> i32 {
+        if max <= min {
+            return min;
+        }
+        min.wrapping_add(self.below((max as i64 - min as i64) as u32) as i32)
+    }
+    /// Returns ```true``` with ```probability``` (0.0 - 1.0).
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    /// Picks a random item, or ```None``` if ```items``` are empty.
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        items.get(self.below(items.len() as u32) as usize)
+    }
+    /// Picks a random index with probability proportional to its weight, or ```None``` if all weights are zero (or there are none).
+    /// Negative weights are treated as zero.
+    pub fn choose_weighted(&mut self, weights: &[f32]) -> Option<usize> {
+        let total: f32 = weights.iter().map(|weight| weight.max(0.0)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut target = self.next_f32() * total;
+        for (index, weight) in weights.iter().enumerate() {
+            target -= weight.max(0.0);
+            if target < 0.0 {
+                return Some(index);
+            }
+        }
+        // Float rounding can leave a tiny remainder, it belongs to the last item with weight.
+        weights.iter().rposition(|&weight| weight > 0.0)
+    }
+    /// Shuffles ```items``` in place (Fisher-Yates).
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u32 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+
+    /// Gets a random point inside a circle with radius 1.0, evenly distributed.
+    pub fn in_unit_circle(&mut self) -> Vector2<f32> {
+        loop {
+            let point = Vector2::new(self.range_f32(-1.0, 1.0), self.range_f32(-1.0, 1.0));
+            if point.norm_squared() <= 1.0 {
+                return point;
+            }
+        }
+    }
+    /// Gets a random direction in 2D (a point on a circle with radius 1.0).
+    pub fn on_unit_circle(&mut self) -> Vector2<f32> {
+        let (sin, cos) = self.range_f32(0.0, std::f32::consts::TAU).sin_cos();
+        Vector2::new(cos, sin)
+    }
+    /// Gets a random point inside a sphere with radius 1.0, evenly distributed.
+    pub fn in_unit_sphere(&mut self) -> Vector3<f32> {
+        loop {
+            let point = Vector3::new(self.range_f32(-1.0, 1.0), self.range_f32(-1.0, 1.0), self.range_f32(-1.0, 1.0));
+            if point.norm_squared() <= 1.0 {
+                return point;
+            }
+        }
+    }
+    /// Gets a random direction in 3D (a point on a sphere with radius 1.0), evenly distributed.
+    pub fn on_unit_sphere(&mut self) -> Vector3<f32> {
+        let z = self.range_f32(-1.0, 1.0);
+        let (sin, cos) = self.range_f32(0.0, std::f32::consts::TAU).sin_cos();
+        let radius = (1.0 - z * z).max(0.0).sqrt();
+        Vector3::new(radius * cos, radius * sin, z)
+    }
+
+    fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut random = Self { state: 0, increment: (stream << 1) | 1 };
+        random.next_u32();
+        random.state = random.state.wrapping_add(seed);
+        random.next_u32();
+        random
+    }
+    // Unbiased random number from 0 to bound - 1 (Lemire's method).
+    fn below(&mut self, bound: u32) -> u32 {
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let product = self.next_u32() as u64 * bound as u64;
+            if (product as u32) >= threshold {
+                return (product >> 32) as u32;
+            }
+        }
+    }
+}
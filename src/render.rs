@@ -0,0 +1,350 @@
+//! Global OpenGL render state helpers, so you don't have to write ```unsafe``` blocks for common toggles.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use gl::types::{GLenum, GLint, GLuint};
+
+/// Enables/disables alpha-to-coverage: fragment alpha is turned into an MSAA coverage mask.
+/// Alpha-tested cutouts (foliage, fences, hair cards) get smooth edges without sorting or blending.
+///
+/// Only works when rendering into a multisampled target (e.g. window created with [crate::window::WindowBuilder::with_msaa]),
+/// otherwise it does nothing visible.
+/// # Example
+/// ```rust
+/// use tinystorm::render;
+///
+/// render::set_alpha_to_coverage(true);
+/// foliage_shader.bind(); // Write alpha as usual, no discard needed.
+/// foliage_mesh.draw();
+/// render::set_alpha_to_coverage(false);
+/// ```
+pub fn set_alpha_to_coverage(enabled: bool) {
+    unsafe {
+        if enabled {
+            gl::Enable(gl::SAMPLE_ALPHA_TO_COVERAGE);
+        } else {
+            gl::Disable(gl::SAMPLE_ALPHA_TO_COVERAGE);
+        }
+    }
+}
+/// Checks if alpha-to-coverage is enabled.
+pub fn is_alpha_to_coverage() -> bool {
+    unsafe { gl::IsEnabled(gl::SAMPLE_ALPHA_TO_COVERAGE) == gl::TRUE }
+}
+
+/// Enables per-sample shading: the fragment shader runs for at least ```min_fraction``` (0.0 - 1.0) of MSAA samples of every pixel
+/// instead of once per pixel, so alpha-tested and high-frequency shaders are antialiased too. ```None``` disables it.
+/// It's expensive: 1.0 with 4x MSAA shades up to 4 times more fragments.
+/// # Example
+/// ```rust
+/// use tinystorm::render;
+///
+/// render::set_sample_shading(Some(1.0));
+/// foliage_shader.bind(); // Uses discard for cutouts.
+/// foliage_mesh.draw();
+/// render::set_sample_shading(None);
+/// ```
+pub fn set_sample_shading(min_fraction: Option<f32>) {
+    unsafe {
+        match min_fraction {
+            Some(min_fraction) => {
+                gl::Enable(gl::SAMPLE_SHADING);
+                gl::MinSampleShading(min_fraction.clamp(0.0, 1.0));
+            }
+            None => gl::Disable(gl::SAMPLE_SHADING),
+        }
+    }
+}
+/// Gets minimum fraction of samples shaded per pixel, or ```None``` if per-sample shading is disabled. See [set_sample_shading].
+pub fn get_sample_shading() -> Option<f32> {
+    unsafe {
+        if gl::IsEnabled(gl::SAMPLE_SHADING) != gl::TRUE {
+            return None;
+        }
+        let mut min_fraction = 0.0;
+        gl::GetFloatv(gl::MIN_SAMPLE_SHADING_VALUE, &mut min_fraction);
+        Some(min_fraction)
+    }
+}
+/// Sets a bit mask of MSAA samples all following draws can write to (bit ```i``` is sample ```i```), ```None``` allows all of them.
+/// Useful for custom dithered transparency or rendering different things into different samples.
+pub fn set_sample_mask(mask: Option<u32>) {
+    unsafe {
+        match mask {
+            Some(mask) => {
+                gl::Enable(gl::SAMPLE_MASK);
+                gl::SampleMaski(0, mask);
+            }
+            None => gl::Disable(gl::SAMPLE_MASK),
+        }
+    }
+}
+/// Gets the sample mask, or ```None``` if it's disabled. See [set_sample_mask].
+pub fn get_sample_mask() -> Option<u32> {
+    unsafe {
+        if gl::IsEnabled(gl::SAMPLE_MASK) != gl::TRUE {
+            return None;
+        }
+        let mut mask = 0;
+        gl::GetIntegeri_v(gl::SAMPLE_MASK_VALUE, 0, &mut mask);
+        Some(mask as u32)
+    }
+}
+/// Sets fixed fraction (0.0 - 1.0) of MSAA samples covered by all following draws, ```invert``` picks the other samples.
+/// Two draws with the same ```coverage``` and different ```invert``` cover every sample exactly once, like a screen-door cross-fade.
+/// ```None``` disables it.
+pub fn set_sample_coverage(coverage: Option<(f32, bool)>) {
+    unsafe {
+        match coverage {
+            Some((value, invert)) => {
+                gl::Enable(gl::SAMPLE_COVERAGE);
+                gl::SampleCoverage(value.clamp(0.0, 1.0), if invert { gl::TRUE } else { gl::FALSE });
+            }
+            None => gl::Disable(gl::SAMPLE_COVERAGE),
+        }
+    }
+}
+
+/// How polygons are rasterized. See [set_polygon_mode].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PolygonMode {
+    /// Filled polygons (the default).
+    Fill,
+    /// Only polygon edges, useful to debug geometry.
+    Wireframe,
+    /// Only polygon vertices.
+    Points,
+}
+
+/// Sets how polygons are rasterized for all following draws. Lines and points aren't affected.
+/// # Example
+/// ```rust
+/// use tinystorm::render::{self, PolygonMode};
+///
+/// render::set_polygon_mode(PolygonMode::Wireframe);
+/// level.draw();
+/// render::set_polygon_mode(PolygonMode::Fill);
+/// ```
+pub fn set_polygon_mode(mode: PolygonMode) {
+    let mode = match mode {
+        PolygonMode::Fill => gl::FILL,
+        PolygonMode::Wireframe => gl::LINE,
+        PolygonMode::Points => gl::POINT,
+    };
+    unsafe {
+        gl::PolygonMode(gl::FRONT_AND_BACK, mode);
+    }
+}
+/// Gets current polygon mode.
+pub fn get_polygon_mode() -> PolygonMode {
+    // Some drivers still return front and back modes separately.
+    let mut modes = [0; 2];
+    unsafe {
+        gl::GetIntegerv(gl::POLYGON_MODE, modes.as_mut_ptr());
+    }
+    match modes[0] as GLenum {
+        gl::LINE => PolygonMode::Wireframe,
+        gl::POINT => PolygonMode::Points,
+        _ => PolygonMode::Fill,
+    }
+}
+/// Runs ```draw``` with polygon ```mode``` and restores the previous mode afterwards.
+/// # Example
+/// ```rust
+/// render::with_polygon_mode(PolygonMode::Wireframe, || {
+///     terrain.draw();
+///     rocks.draw();
+/// });
+/// ```
+pub fn with_polygon_mode<R>(mode: PolygonMode, draw: impl FnOnce() -> R) -> R {
+    let previous = get_polygon_mode();
+    set_polygon_mode(mode);
+    let result = draw();
+    set_polygon_mode(previous);
+    result
+}
+
+/// GLSL helpers for logarithmic depth, paste into your vertex shader right after ```#version```.
+/// Call ```log_depth_write()``` at the end of ```main()```, after ```gl_Position``` is written.
+///
+/// It's a fallback for large worlds when reverse-Z isn't supported (see [crate::window::Window::is_reverse_z]).
+/// Writing ```gl_FragDepth``` disables early depth test, so prefer reverse-Z when you can.
+/// # Example
+/// ```rust
+/// use tinystorm::render;
+///
+/// let shader = Shader::from_source(
+///     &format!("#version 330 core\n{}\n{}", render::LOG_DEPTH_VERTEX_GLSL, vertex_body),
+///     &format!("#version 330 core\n{}\n{}", render::LOG_DEPTH_FRAGMENT_GLSL, fragment_body),
+/// );
+///
+/// shader.bind();
+/// shader.set_float("u_LogDepthCoefficient", render::log_depth_coefficient(far));
+/// ```
+pub const LOG_DEPTH_VERTEX_GLSL: &str = "
+out float v_LogDepth;
+
+void log_depth_write() {
+    v_LogDepth = 1.0 + gl_Position.w;
+}
+";
+/// GLSL helpers for logarithmic depth, paste into your fragment shader right after ```#version```.
+/// Call ```log_depth_write()``` anywhere in ```main()```. See [LOG_DEPTH_VERTEX_GLSL].
+pub const LOG_DEPTH_FRAGMENT_GLSL: &str = "
+uniform float u_LogDepthCoefficient;
+in float v_LogDepth;
+
+void log_depth_write() {
+    gl_FragDepth = log2(v_LogDepth) * u_LogDepthCoefficient * 0.5;
+}
+";
+/// Gets ```u_LogDepthCoefficient``` uniform value for logarithmic depth with ```far``` plane distance.
+pub fn log_depth_coefficient(far: f32) -> f32 {
+    2.0 / (far + 1.0).log2()
+}
+
+// Bumped every time the window recreates a lost OpenGL context. GL wrappers remember it when created
+// and don't delete their names in a newer context, where the same names may belong to new objects.
+static CONTEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn context_generation() -> u64 {
+    CONTEXT_GENERATION.load(Ordering::Relaxed)
+}
+pub(crate) fn next_context_generation() {
+    CONTEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+// Checks if an object created at ```generation``` belongs to the current context, so its names can be deleted.
+pub(crate) fn is_current_context(generation: u64) -> bool {
+    generation == context_generation()
+}
+
+static DRAW_CALLS: AtomicU64 = AtomicU64::new(0);
+static TRIANGLES: AtomicU64 = AtomicU64::new(0);
+static TEXTURE_BINDS: AtomicU64 = AtomicU64::new(0);
+static SHADER_BINDS: AtomicU64 = AtomicU64::new(0);
+static BUFFER_UPLOADS: AtomicU64 = AtomicU64::new(0);
+static UPLOADED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Counts of rendering operations made through tinystorm since the last [reset_statistics].
+/// Raw ```gl``` calls made by your own code aren't counted.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct RenderStatistics {
+    /// Number of [crate::mesh::Mesh::draw] and [crate::mesh::IndexedMesh::draw] calls.
+    pub draw_calls: u64,
+    /// Number of triangles drawn (triangle lists, strips and fans, other modes are skipped).
+    pub triangles: u64,
+    /// Number of textures bound to slots (e.g. [crate::texture::Texture::bind]).
+    pub texture_binds: u64,
+    /// Number of [crate::shader::Shader::bind] calls.
+    pub shader_binds: u64,
+    /// Number of buffer uploads (mesh creation, [crate::upload::UploadScheduler] buffer parts).
+    pub buffer_uploads: u64,
+    /// Number of bytes uploaded to buffers.
+    pub uploaded_bytes: u64,
+}
+
+/// Gets rendering statistics counted since the last [reset_statistics].
+/// # Example
+/// ```rust
+/// use tinystorm::render;
+///
+/// while window.is_running() {
+///     window.poll_events();
+///     render::reset_statistics();
+///
+///     draw_scene();
+///
+///     let stats = render::get_statistics();
+///     debug_text.set(&format!("{} draws, {} triangles", stats.draw_calls, stats.triangles));
+///     assert!(stats.draw_calls < 500, "Draw call budget exceeded");
+///     window.swap_buffers();
+/// }
+/// ```
+pub fn get_statistics() -> RenderStatistics {
+    RenderStatistics {
+        draw_calls: DRAW_CALLS.load(Ordering::Relaxed),
+        triangles: TRIANGLES.load(Ordering::Relaxed),
+        texture_binds: TEXTURE_BINDS.load(Ordering::Relaxed),
+        shader_binds: SHADER_BINDS.load(Ordering::Relaxed),
+        buffer_uploads: BUFFER_UPLOADS.load(Ordering::Relaxed),
+        uploaded_bytes: UPLOADED_BYTES.load(Ordering::Relaxed),
+    }
+}
+/// Sets all rendering statistics to zero. Call it at the start of every frame to get per-frame numbers.
+pub fn reset_statistics() {
+    for counter in [&DRAW_CALLS, &TRIANGLES, &TEXTURE_BINDS, &SHADER_BINDS, &BUFFER_UPLOADS, &UPLOADED_BYTES] {
+        counter.store(0, Ordering::Relaxed);
+    }
+}
+
+// Checks if the current context exposes an extension, ```name``` is like b"GL_ARB_texture_storage".
+pub(crate) fn has_extension(name: &[u8]) -> bool {
+    unsafe {
+        let mut extensions: GLint = 0;
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut extensions);
+        (0..extensions.max(0) as GLuint).any(|i| {
+            let extension = gl::GetStringi(gl::EXTENSIONS, i);
+            !extension.is_null() && std::ffi::CStr::from_ptr(extension as *const std::ffi::c_char).to_bytes() == name
+        })
+    }
+}
+
+// Blending enable flag and functions, so passes that change blending can give the caller's state back.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct BlendState {
+    enabled: bool,
+    // Source RGB, destination RGB, source alpha, destination alpha.
+    functions: [GLint; 4],
+}
+impl BlendState {
+    pub(crate) fn save() -> Self {
+        let mut functions: [GLint; 4] = [0; 4];
+        unsafe {
+            for (function, name) in functions.iter_mut().zip([gl::BLEND_SRC_RGB, gl::BLEND_DST_RGB, gl::BLEND_SRC_ALPHA, gl::BLEND_DST_ALPHA]) {
+                gl::GetIntegerv(name, function);
+            }
+            Self { enabled: gl::IsEnabled(gl::BLEND) == gl::TRUE, functions }
+        }
+    }
+    pub(crate) fn restore(&self) {
+        let [src_rgb, dst_rgb, src_alpha, dst_alpha] = self.functions.map(|function| function as GLenum);
+        unsafe {
+            gl::BlendFuncSeparate(src_rgb, dst_rgb, src_alpha, dst_alpha);
+            if self.enabled { gl::Enable(gl::BLEND); } else { gl::Disable(gl::BLEND); }
+        }
+    }
+}
+
+pub(crate) fn count_draw(render_mode: GLenum, count: usize) {
+    let triangles = match render_mode {
+        gl::TRIANGLES => count / 3,
+        gl::TRIANGLE_STRIP | gl::TRIANGLE_FAN => count.saturating_sub(2),
+        _ => 0,
+    };
+    DRAW_CALLS.fetch_add(1, Ordering::Relaxed);
+    TRIANGLES.fetch_add(triangles as u64, Ordering::Relaxed);
+}
+pub(crate) fn count_texture_bind() {
+    TEXTURE_BINDS.fetch_add(1, Ordering::Relaxed);
+}
+pub(crate) fn count_shader_bind() {
+    SHADER_BINDS.fetch_add(1, Ordering::Relaxed);
+}
+pub(crate) fn count_buffer_upload(bytes: usize) {
+    BUFFER_UPLOADS.fetch_add(1, Ordering::Relaxed);
+    UPLOADED_BYTES.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn objects_of_an_old_context_are_not_current() {
+        let old = context_generation();
+        assert!(is_current_context(old));
+        next_context_generation();
+        assert!(!is_current_context(old));
+        assert!(is_current_context(context_generation()));
+    }
+}
@@ -1,12 +1,16 @@
 use std::ffi::CString;
+use std::path::PathBuf;
 use std::str;
 
-use gl::types::{GLchar, GLint, GLuint};
+use gl::types::{GLchar, GLenum, GLint, GLsizei, GLuint};
 use nalgebra::{Matrix2, Matrix2x3, Matrix2x4, Matrix3, Matrix3x2, Matrix3x4, Matrix4, Matrix4x2, Matrix4x3, Vector2, Vector3, Vector4};
 
+use crate::render;
+
 /// A simple OpenGL shader program ```program: GLuint``` wrapper.
 pub struct Shader {
     program: GLuint,
+    generation: u64,
 }
 
 impl Shader {
@@ -60,15 +64,31 @@ impl Shader {
             panic!("Failed to read fragment shader source at: {}. Error: {}", fragment_path, error);
         }
 
+        Self::link(&vertex_source.unwrap(), vertex_path, &fragment_source.unwrap(), fragment_path, false)
+    }
+    /// Compiles vertex and fragment shaders directly from ```vertex_source``` and ```fragment_source``` strings.
+    /// Useful for small built-in shaders you don't want to ship as separate files.
+    /// # Example
+    /// ```rust
+    /// let shader = Shader::from_source(
+    ///     "#version 330 core\nlayout(location = 0) in vec2 a_Position;\nvoid main() { gl_Position = vec4(a_Position, 0.0, 1.0); }",
+    ///     "#version 330 core\nout vec4 f_Color;\nvoid main() { f_Color = vec4(1.0); }",
+    /// );
+    /// ```
+    pub fn from_source(vertex_source: &str, fragment_source: &str) -> Self {
+        Self::link(vertex_source, "<source>", fragment_source, "<source>", false)
+    }
+
+    fn link(vertex_source: &str, vertex_path: &str, fragment_source: &str, fragment_path: &str, retrievable: bool) -> Self {
         unsafe {
             let vertex_shader = Self::load_shader(
-                vertex_source.unwrap().as_str(),
+                vertex_source,
                 vertex_path,
                 "vertex",
                 gl::VERTEX_SHADER
             );
             let fragment_shader = Self::load_shader(
-                fragment_source.unwrap().as_str(),
+                fragment_source,
                 fragment_path,
                 "fragment",
                 gl::FRAGMENT_SHADER
@@ -77,6 +97,9 @@ impl Shader {
             let program = gl::CreateProgram();
             gl::AttachShader(program, vertex_shader);
             gl::AttachShader(program, fragment_shader);
+            if retrievable {
+                gl::ProgramParameteri(program, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as GLint);
+            }
             gl::LinkProgram(program);
 
             let mut log_length: GLint = 0;
@@ -101,13 +124,76 @@ impl Shader {
             }
 
             Self::delete_shaders(vertex_shader, fragment_shader);
-            Self { program }
+            Self { program, generation: render::context_generation() }
+        }
+    }
+
+    /// Loads a compute shader from ```path```. Requires OpenGL 4.3 or ```ARB_compute_shader```.
+    pub fn new_compute(path: &str) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(source) => Self::link_compute(&source, path),
+            Err(error) => panic!("Failed to read compute shader source at: {}. Error: {}", path, error),
+        }
+    }
+    /// Compiles a compute shader directly from ```source``` string. Requires OpenGL 4.3 or ```ARB_compute_shader```.
+    /// # Example
+    /// ```rust
+    /// let shader = Shader::from_compute_source("#version 430 core
+    /// layout(local_size_x = 64) in;
+    /// layout(std430, binding = 0) buffer Data { float values[]; };
+    /// void main() { values[gl_GlobalInvocationID.x] *= 2.0; }
+    /// ");
+    /// shader.dispatch(count.div_ceil(64), 1, 1);
+    /// ```
+    pub fn from_compute_source(source: &str) -> Self {
+        Self::link_compute(source, "<source>")
+    }
+
+    fn link_compute(source: &str, path: &str) -> Self {
+        if !gl::DispatchCompute::is_loaded() {
+            panic!("Failed to create compute shader at: {}. Compute shaders require OpenGL 4.3 or ARB_compute_shader.", path);
+        }
+
+        unsafe {
+            let compute_shader = Self::load_shader(source, path, "compute", gl::COMPUTE_SHADER);
+
+            let program = gl::CreateProgram();
+            gl::AttachShader(program, compute_shader);
+            gl::LinkProgram(program);
+            gl::DeleteShader(compute_shader);
+
+            let mut log_length: GLint = 0;
+            gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut log_length);
+
+            let mut log: Vec<u8> = vec![0; log_length as usize];
+            gl::GetProgramInfoLog(program, log_length, std::ptr::null_mut(), log.as_mut_ptr() as *mut GLchar);
+
+            let log = std::str::from_utf8(&log).unwrap();
+
+            let mut success: GLint = 0;
+            gl::GetProgramiv(program, gl::LINK_STATUS, &mut success);
+
+            if success == gl::FALSE as GLint {
+                panic!("Failed to link program with shaders: Compute({}). Error: {}.", path, log);
+            }
+
+            Self { program, generation: render::context_generation() }
+        }
+    }
+
+    /// Binds the program and runs a compute shader with ```x``` * ```y``` * ```z``` work groups.
+    /// Don't forget ```gl::MemoryBarrier``` before using its results.
+    pub fn dispatch(&self, x: u32, y: u32, z: u32) {
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::DispatchCompute(x, y, z);
         }
     }
 
     /// Makes OpenGL use current shader program.
     pub fn bind(&self) {
         unsafe { gl::UseProgram(self.program); }
+        render::count_shader_bind();
     }
     /// Unbinds any shader programs from OpenGL's state.
     pub fn unbind() {
@@ -260,6 +346,179 @@ impl Shader {
 }
 impl Drop for Shader {
     fn drop(&mut self) {
+        if !render::is_current_context(self.generation) {
+            return;
+        }
         unsafe { gl::DeleteProgram(self.program); }
     }
-}
\ No newline at end of file
+}
+
+/// Caches linked shader programs on disk as driver binaries (```gl::GetProgramBinary```), so projects with lots of shader permutations start quickly.
+///
+/// Programs are keyed by a hash of preprocessed sources (with defines) and the driver (vendor, renderer and version),
+/// so changing a shader, its defines or updating drivers just compiles it again. Requires OpenGL 4.1 or ```ARB_get_program_binary```,
+/// without it (or if the driver has no binary formats) shaders are always compiled.
+/// # Example
+/// ```rust
+/// use tinystorm::shader::ShaderCache;
+///
+/// let mut cache = ShaderCache::new("cache/shaders");
+/// let lit = cache.load("assets/shaders/surface.vert", "assets/shaders/surface.frag", &[]);
+/// let lit_skinned = cache.load("assets/shaders/surface.vert", "assets/shaders/surface.frag", &[("SKINNED", "1"), ("MAX_BONES", "64")]);
+/// println!("Shader cache: {} hits, {} misses", cache.get_hits(), cache.get_misses());
+/// ```
+pub struct ShaderCache {
+    directory: PathBuf,
+    driver: String,
+    supported: bool,
+    hits: usize,
+    misses: usize,
+}
+
+impl ShaderCache {
+    /// Creates a cache storing binaries in ```directory```, which is created if it doesn't exist.
+    pub fn new(directory: &str) -> Self {
+        if let Err(error) = std::fs::create_dir_all(directory) {
+            panic!("Failed to create shader cache directory at: {}. Error: {}", directory, error);
+        }
+
+        let mut formats: GLint = 0;
+        let driver = unsafe {
+            if gl::GetProgramBinary::is_loaded() {
+                gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats);
+            }
+            [gl::VENDOR, gl::RENDERER, gl::VERSION].iter().map(|&name| {
+                let string = gl::GetString(name);
+                if string.is_null() { String::new() } else { std::ffi::CStr::from_ptr(string as *const std::ffi::c_char).to_string_lossy().into_owned() }
+            }).collect::<Vec<_>>().join("\n")
+        };
+
+        Self { directory: PathBuf::from(directory), driver, supported: formats > 0, hits: 0, misses: 0 }
+    }
+
+    /// Loads vertex and fragment shaders from ```vertex_path``` and ```fragment_path``` with ```defines``` (name, value) and links them,
+    /// or takes the program from the cache.
+    pub fn load(&mut self, vertex_path: &str, fragment_path: &str, defines: &[(&str, &str)]) -> Shader {
+        let read = |path: &str, typename: &str| match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => panic!("Failed to read {} shader source at: {}. Error: {}", typename, path, error),
+        };
+        let (vertex_source, fragment_source) = (read(vertex_path, "vertex"), read(fragment_path, "fragment"));
+        self.get_or_link(&vertex_source, vertex_path, &fragment_source, fragment_path, defines)
+    }
+    /// Same as [ShaderCache::load], but takes sources directly, like [Shader::from_source].
+    pub fn from_source(&mut self, vertex_source: &str, fragment_source: &str, defines: &[(&str, &str)]) -> Shader {
+        self.get_or_link(vertex_source, "<source>", fragment_source, "<source>", defines)
+    }
+    /// Inserts ```#define name value``` lines right after ```#version``` (or at the start, if there's none).
+    pub fn preprocess(source: &str, defines: &[(&str, &str)]) -> String {
+        let defines: String = defines.iter().map(|(name, value)| format!("#define {} {}\n", name, value)).collect();
+        let version_end = source.find("#version").map(|start| source[start..].find('\n').map_or(source.len(), |end| start + end + 1));
+
+        match version_end {
+            Some(end) if end == source.len() && !source.ends_with('\n') => format!("{}\n{}", source, defines),
+            Some(end) => format!("{}{}{}", &source[..end], defines, &source[end..]),
+            None => format!("{}{}", defines, source),
+        }
+    }
+
+    /// Removes all cached binaries.
+    pub fn clear(&self) {
+        if let Ok(entries) = std::fs::read_dir(&self.directory) {
+            for entry in entries.flatten() {
+                if entry.path().extension().is_some_and(|extension| extension == "bin") {
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+    }
+    /// Gets number of programs taken from the cache.
+    pub fn get_hits(&self) -> usize {
+        self.hits
+    }
+    /// Gets number of programs that were compiled, because they weren't cached (or cache was outdated).
+    pub fn get_misses(&self) -> usize {
+        self.misses
+    }
+    /// Checks if the driver supports program binaries. If it doesn't, every program is compiled.
+    pub fn is_supported(&self) -> bool {
+        self.supported
+    }
+
+    fn get_or_link(&mut self, vertex_source: &str, vertex_path: &str, fragment_source: &str, fragment_path: &str, defines: &[(&str, &str)]) -> Shader {
+        let vertex_source = Self::preprocess(vertex_source, defines);
+        let fragment_source = Self::preprocess(fragment_source, defines);
+        if !self.supported {
+            self.misses += 1;
+            return Shader::link(&vertex_source, vertex_path, &fragment_source, fragment_path, false);
+        }
+
+        let hash = Self::hash(&[vertex_source.as_bytes(), fragment_source.as_bytes(), self.driver.as_bytes()]);
+        let path = self.directory.join(format!("{:016x}.bin", hash));
+        if let Some(shader) = std::fs::read(&path).ok().and_then(|bytes| Self::load_binary(&bytes)) {
+            self.hits += 1;
+            return shader;
+        }
+
+        self.misses += 1;
+        let shader = Shader::link(&vertex_source, vertex_path, &fragment_source, fragment_path, true);
+        // Cache is only an optimization, so failing to write it isn't an error.
+        if let Some(bytes) = Self::save_binary(&shader) {
+            let _ = std::fs::write(&path, bytes);
+        }
+        shader
+    }
+    // File layout: binary format (u32, little endian), then the binary itself.
+    fn load_binary(bytes: &[u8]) -> Option<Shader> {
+        if bytes.len() <= 4 {
+            return None;
+        }
+
+        unsafe {
+            let format = GLenum::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            let program = gl::CreateProgram();
+            gl::ProgramBinary(program, format, bytes[4..].as_ptr() as *const std::ffi::c_void, (bytes.len() - 4) as GLsizei);
+
+            // Drivers reject binaries of other versions or GPUs, then it's just compiled again.
+            let mut success: GLint = 0;
+            gl::GetProgramiv(program, gl::LINK_STATUS, &mut success);
+            if success == gl::FALSE as GLint {
+                gl::DeleteProgram(program);
+                return None;
+            }
+            Some(Shader { program, generation: render::context_generation() })
+        }
+    }
+    fn save_binary(shader: &Shader) -> Option<Vec<u8>> {
+        unsafe {
+            let mut length: GLint = 0;
+            gl::GetProgramiv(shader.program, gl::PROGRAM_BINARY_LENGTH, &mut length);
+            if length <= 0 {
+                return None;
+            }
+
+            let mut bytes = vec![0_u8; 4 + length as usize];
+            let mut format: GLenum = 0;
+            let mut written: GLsizei = 0;
+            gl::GetProgramBinary(shader.program, length, &mut written, &mut format, bytes[4..].as_mut_ptr() as *mut std::ffi::c_void);
+            if written <= 0 {
+                return None;
+            }
+
+            bytes[..4].copy_from_slice(&format.to_le_bytes());
+            bytes.truncate(4 + written as usize);
+            Some(bytes)
+        }
+    }
+    // FNV-1a, stable between runs and Rust versions, unlike std's DefaultHasher.
+    fn hash(parts: &[&[u8]]) -> u64 {
+        let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+        for part in parts {
+            for &byte in part.iter().chain(&[0xFF]) {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+            }
+        }
+        hash
+    }
+}
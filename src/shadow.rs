@@ -0,0 +1,313 @@
+use std::collections::{BTreeSet, HashMap};
+
+use gl::types::{GLboolean, GLenum, GLint, GLsizei, GLuint};
+use nalgebra::Vector4;
+
+use crate::render;
+
+// Tiles can be at most this many times smaller than a layer on each side (2^4 = 16).
+const MAX_SUBDIVISIONS: u32 = 4;
+
+/// A square region of a [ShadowAtlas] layer to render one shadow map (or one cubemap face) into.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ShadowTile {
+    /// Texture array layer.
+    pub layer: u32,
+    /// Left edge in pixels.
+    pub x: u32,
+    /// Bottom edge in pixels.
+    pub y: u32,
+    /// Width and height in pixels.
+    pub size: u32,
+    level: u32,
+}
+
+impl ShadowTile {
+    /// Gets tile position (XY) and size (ZW) in 0.0 - 1.0 layer UVs. In the shader: ```uv = rect.xy + shadow_uv * rect.zw```.
+    pub fn get_uv_rect(&self, atlas_size: u32) -> Vector4<f32> {
+        let size = atlas_size as f32;
+        Vector4::new(self.x as f32 / size, self.y as f32 / size, self.size as f32 / size, self.size as f32 / size)
+    }
+}
+
+/// Tiles given to a light by [ShadowAtlas::request].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ShadowAllocation {
+    /// One tile for spot/directional lights, six (+X, -X, +Y, -Y, +Z, -Z) for point lights.
+    pub tiles: Vec<ShadowTile>,
+    /// ```true``` if tiles were just allocated and don't have a shadow map yet, render into them even if the light didn't move.
+    pub is_new: bool,
+}
+
+struct LightEntry {
+    tiles: Vec<ShadowTile>,
+    last_used: u64,
+}
+
+// Buddy allocator of square tiles: every layer is a quadtree, free blocks are kept per level and merged back when all 4 siblings are free.
+struct TileAllocator {
+    size: u32,
+    // Free (layer, x, y) blocks in block units of every level. Level 0 is a whole layer.
+    free: Vec<BTreeSet<(u32, u32, u32)>>,
+}
+
+impl TileAllocator {
+    fn new(size: u32, layers: u32) -> Self {
+        let mut free = vec![BTreeSet::new(); MAX_SUBDIVISIONS as usize + 1];
+        free[0] = (0..layers).map(|layer| (layer, 0, 0)).collect();
+        Self { size, free }
+    }
+
+    fn allocate(&mut self, level: u32) -> Option<ShadowTile> {
+        let (layer, x, y) = match self.free[level as usize].pop_first() {
+            Some(block) => block,
+            None if level > 0 => {
+                // Split a bigger block into 4, take one and keep the rest.
+                let parent = self.allocate(level - 1)?;
+                let (layer, x, y) = (parent.layer, parent.x / parent.size * 2, parent.y / parent.size * 2);
+                self.free[level as usize].extend([(layer, x + 1, y), (layer, x, y + 1), (layer, x + 1, y + 1)]);
+                (layer, x, y)
+            }
+            None => return None,
+        };
+
+        let size = self.size >> level;
+        Some(ShadowTile { layer, x: x * size, y: y * size, size, level })
+    }
+    fn free(&mut self, tile: &ShadowTile) {
+        let (mut level, mut x, mut y) = (tile.level, tile.x / tile.size, tile.y / tile.size);
+        loop {
+            let siblings = [(x & !1, y & !1), (x | 1, y & !1), (x & !1, y | 1), (x | 1, y | 1)];
+            let all_free = level > 0
+                && siblings.iter().all(|&(sx, sy)| (sx, sy) == (x, y) || self.free[level as usize].contains(&(tile.layer, sx, sy)));
+            if !all_free {
+                self.free[level as usize].insert((tile.layer, x, y));
+                return;
+            }
+
+            for (sx, sy) in siblings {
+                self.free[level as usize].remove(&(tile.layer, sx, sy));
+            }
+            (level, x, y) = (level - 1, x / 2, y / 2);
+        }
+    }
+}
+
+/// Packs shadow maps of many spot and point lights into one depth texture array, so dozens of lights can cast shadows
+/// without a texture per light. Every light gets square tiles sized by its requested resolution, lights that weren't
+/// requested for a while are evicted when space runs out (least recently used first).
+///
+/// The texture is ```DEPTH_COMPONENT32F``` with comparison enabled, sample it with ```sampler2DArrayShadow```
+/// at ```vec4(rect.xy + uv * rect.zw, layer, depth)```, see [ShadowTile::get_uv_rect].
+/// # Example
+/// ```rust
+/// use tinystorm::shadow::ShadowAtlas;
+///
+/// // 4 layers of 4096x4096.
+/// let mut atlas = ShadowAtlas::new(4096, 4);
+/// while window.is_running() {
+///     atlas.begin_frame();
+///     for light in &visible_lights {
+///         // Closer lights get sharper shadows. Point lights need 6 faces.
+///         let resolution = if light.distance < 20.0 { 1024 } else { 256 };
+///         let Some(allocation) = atlas.request(light.id, resolution, if light.is_point { 6 } else { 1 }) else { continue; };
+///
+///         if allocation.is_new || light.moved {
+///             for (face, tile) in allocation.tiles.iter().enumerate() {
+///                 atlas.begin_tile(tile);
+///                 depth_shader.bind();
+///                 depth_shader.set_mat4("u_ViewProjection", &light.view_projection(face));
+///                 draw_shadow_casters();
+///             }
+///             atlas.end();
+///         }
+///         light.tiles = allocation.tiles;
+///     }
+///
+///     atlas.bind(4);
+///     ...
+/// }
+/// ```
+pub struct ShadowAtlas {
+    texture: GLuint,
+    framebuffer: GLuint,
+    size: u32,
+    layers: u32,
+
+    allocator: TileAllocator,
+    lights: HashMap<u64, LightEntry>,
+    frame: u64,
+
+    // State saved by the first begin_tile and restored in end.
+    saved: Option<([GLint; 4], [GLint; 4], GLint, GLboolean)>,
+    generation: u64,
+}
+
+impl ShadowAtlas {
+    /// Creates an atlas of ```layers``` depth layers, each ```size``` x ```size``` pixels (rounded up to a power of two).
+    pub fn new(size: u32, layers: u32) -> Self {
+        let size = size.max(1 << MAX_SUBDIVISIONS).next_power_of_two();
+        let layers = layers.max(1);
+
+        let (mut texture, mut framebuffer) = (0, 0);
+        unsafe {
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, texture);
+            gl::TexStorage3D(gl::TEXTURE_2D_ARRAY, 1, gl::DEPTH_COMPONENT32F, size as GLsizei, size as GLsizei, layers as GLsizei);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL as GLint);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+
+            gl::GenFramebuffers(1, &mut framebuffer);
+        }
+
+        Self {
+            texture,
+            framebuffer,
+            size,
+            layers,
+            allocator: TileAllocator::new(size, layers),
+            lights: HashMap::new(),
+            frame: 0,
+            saved: None,
+            generation: render::context_generation(),
+        }
+    }
+
+    /// Starts a new frame. Lights requested in the current frame are never evicted. Call it once per frame before [ShadowAtlas::request].
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+    }
+    /// Gets tiles of ```light``` (any id unique per light) with ```resolution``` (rounded up to a power of two and clamped to the atlas)
+    /// and ```faces``` tiles (1 for spot lights, 6 for point lights). Keeps the previous tiles if resolution and faces haven't changed.
+    /// Evicts least recently used lights if there's no space, returns ```None``` if it's still not enough.
+    pub fn request(&mut self, light: u64, resolution: u32, faces: usize) -> Option<ShadowAllocation> {
+        let level = self.level_for(resolution);
+        if let Some(entry) = self.lights.get_mut(&light) {
+            if entry.tiles.len() == faces && entry.tiles.first().is_none_or(|tile| tile.level == level) {
+                entry.last_used = self.frame;
+                return Some(ShadowAllocation { tiles: entry.tiles.clone(), is_new: false });
+            }
+            self.release(light);
+        }
+
+        let mut tiles = Vec::with_capacity(faces);
+        while tiles.len() < faces {
+            if let Some(tile) = self.allocator.allocate(level) {
+                tiles.push(tile);
+                continue;
+            }
+
+            let oldest = self.lights.iter()
+                .filter(|(_, entry)| entry.last_used < self.frame)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(&light, _)| light);
+            match oldest {
+                Some(oldest) => self.release(oldest),
+                None => {
+                    for tile in &tiles {
+                        self.allocator.free(tile);
+                    }
+                    return None;
+                }
+            }
+        }
+
+        self.lights.insert(light, LightEntry { tiles: tiles.clone(), last_used: self.frame });
+        Some(ShadowAllocation { tiles, is_new: true })
+    }
+    /// Frees tiles of ```light```, e.g. when it's destroyed. Does nothing for unknown lights.
+    pub fn release(&mut self, light: u64) {
+        if let Some(entry) = self.lights.remove(&light) {
+            for tile in &entry.tiles {
+                self.allocator.free(tile);
+            }
+        }
+    }
+    /// Frees tiles of all lights.
+    pub fn clear(&mut self) {
+        self.lights.clear();
+        self.allocator = TileAllocator::new(self.size, self.layers);
+    }
+
+    /// Makes OpenGL render depth into ```tile``` and clears it. Previously bound framebuffer, viewport and scissor test
+    /// are saved on the first call and restored by [ShadowAtlas::end].
+    pub fn begin_tile(&mut self, tile: &ShadowTile) {
+        unsafe {
+            if self.saved.is_none() {
+                let (mut viewport, mut scissor, mut framebuffer) = ([0; 4], [0; 4], 0);
+                gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+                gl::GetIntegerv(gl::SCISSOR_BOX, scissor.as_mut_ptr());
+                gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut framebuffer);
+                self.saved = Some((viewport, scissor, framebuffer, gl::IsEnabled(gl::SCISSOR_TEST)));
+            }
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            gl::FramebufferTextureLayer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, self.texture, 0, tile.layer as GLint);
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+
+            let (x, y, size) = (tile.x as GLint, tile.y as GLint, tile.size as GLsizei);
+            gl::Viewport(x, y, size, size);
+            gl::Scissor(x, y, size, size);
+            gl::Enable(gl::SCISSOR_TEST);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+    }
+    /// Restores the state saved by [ShadowAtlas::begin_tile]. Does nothing if no tile was begun.
+    pub fn end(&mut self) {
+        let Some((viewport, scissor, framebuffer, scissor_test)) = self.saved.take() else { return; };
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer as GLuint);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+            gl::Scissor(scissor[0], scissor[1], scissor[2], scissor[3]);
+            if scissor_test != gl::TRUE { gl::Disable(gl::SCISSOR_TEST); }
+        }
+    }
+    /// Binds the depth texture array to certain slot.
+    pub fn bind(&self, slot: GLenum) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + slot);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.texture);
+        }
+        render::count_texture_bind();
+    }
+
+    /// Gets layer width and height in pixels.
+    pub fn get_size(&self) -> u32 {
+        self.size
+    }
+    /// Gets number of layers.
+    pub fn get_layers(&self) -> u32 {
+        self.layers
+    }
+    /// Gets number of lights with tiles.
+    pub fn get_light_count(&self) -> usize {
+        self.lights.len()
+    }
+    /// Gets OpenGL id of the depth texture array for more precise control.
+    pub fn get_texture_id(&self) -> GLuint {
+        self.texture
+    }
+
+    fn level_for(&self, resolution: u32) -> u32 {
+        let resolution = resolution.clamp(self.size >> MAX_SUBDIVISIONS, self.size).next_power_of_two();
+        self.size.trailing_zeros() - resolution.trailing_zeros()
+    }
+}
+impl Drop for ShadowAtlas {
+    /// You don't need to manually free OpenGL resources, it's done automatically.
+    fn drop(&mut self) {
+        if !render::is_current_context(self.generation) {
+            return;
+        }
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.framebuffer);
+            gl::DeleteTextures(1, &self.texture);
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+use nalgebra::Vector2;
+
+use crate::{mesh::Mesh, render, shader::Shader};
+
+const VERTEX_SOURCE: &str = "#version 330 core
+layout(location = 0) in vec2 a_Position;
+
+out vec2 v_Uv;
+
+void main() {
+    v_Uv = a_Position * 0.5 + 0.5;
+    gl_Position = vec4(a_Position, 0.0, 1.0);
+}
+";
+
+/// A double-buffered simulation running on the GPU: every step your update shader reads the previous state texture
+/// and writes the next one. Good for Game of Life, falling sand, fluids and reaction-diffusion effects.
+///
+/// State is stored in two RGBA32F textures with nearest filtering and repeat wrapping (the grid wraps around its edges).
+/// Update shader is a fragment shader (GLSL 3.30+) with these inputs:
+/// - ```in vec2 v_Uv``` - UV of the current cell.
+/// - ```uniform sampler2D u_State``` - previous state.
+/// - ```uniform vec2 u_TexelSize``` - size of one cell in UV units.
+/// - ```uniform int u_Step``` - index of the current step.
+/// # Example
+/// ```rust
+/// use tinystorm::sim::Simulation;
+///
+/// let mut life = Simulation::new(256, 256, "#version 330 core
+/// in vec2 v_Uv;
+/// out vec4 f_Color;
+/// uniform sampler2D u_State;
+/// uniform vec2 u_TexelSize;
+///
+/// void main() {
+///     int neighbors = 0;
+///     for (int y = -1; y <= 1; y++) for (int x = -1; x <= 1; x++) {
+///         if (x != 0 || y != 0) neighbors += int(texture(u_State, v_Uv + vec2(x, y) * u_TexelSize).r > 0.5);
+///     }
+///     bool alive = texture(u_State, v_Uv).r > 0.5;
+///     f_Color = vec4(float(neighbors == 3 || (alive && neighbors == 2)), 0.0, 0.0, 1.0);
+/// }
+/// ");
+/// life.seed_with(|x, y| [((x * 7 + y * 13) % 5 == 0) as u32 as f32, 0.0, 0.0, 1.0]);
+///
+/// while window.is_running() {
+///     window.poll_events();
+///     life.step();
+///
+///     life.bind_state(0); // Draw it with your own shader.
+///     fullscreen_triangle.draw();
+///     window.swap_buffers();
+/// }
+/// ```
+pub struct Simulation {
+    framebuffer: GLuint,
+    textures: [GLuint; 2],
+    read: usize,
+
+    shader: Shader,
+    mesh: Mesh,
+
+    width: u32,
+    height: u32,
+    steps: u64,
+    generation: u64,
+}
+
+impl Simulation {
+    /// Creates a ```width``` x ```height``` simulation filled with zeros, updated by ```update_source``` fragment shader.
+    pub fn new(width: u32, height: u32, update_source: &str) -> Self {
+        let (width, height) = (width.max(1), height.max(1));
+
+        let mut framebuffer = 0;
+        let mut textures = [0; 2];
+        unsafe {
+            gl::GenFramebuffers(1, &mut framebuffer);
+            gl::GenTextures(2, textures.as_mut_ptr());
+            for &texture in &textures {
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::RGBA32F as GLint,
+                    width as GLsizei,
+                    height as GLsizei,
+                    0,
+                    gl::RGBA,
+                    gl::FLOAT,
+                    std::ptr::null(),
+                );
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as GLint);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as GLint);
+            }
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        let mut simulation = Self {
+            framebuffer,
+            textures,
+            read: 0,
+
+            shader: Shader::from_source(VERTEX_SOURCE, update_source),
+            mesh: Mesh::fullscreen_triangle(),
+
+            width,
+            height,
+            steps: 0,
+            generation: render::context_generation(),
+        };
+        simulation.clear();
+        simulation
+    }
+    /// Sets state texture filtering (```gl::NEAREST``` by default). ```gl::LINEAR``` is useful for advection in fluids.
+    pub fn with_filter(self, filter: GLenum) -> Self {
+        self.set_texture_parameters(gl::TEXTURE_MIN_FILTER, filter);
+        self.set_texture_parameters(gl::TEXTURE_MAG_FILTER, filter);
+        self
+    }
+    /// Sets state texture wrapping (```gl::REPEAT``` by default). Use ```gl::CLAMP_TO_EDGE``` for a grid with walls.
+    pub fn with_wrap(self, wrap: GLenum) -> Self {
+        self.set_texture_parameters(gl::TEXTURE_WRAP_S, wrap);
+        self.set_texture_parameters(gl::TEXTURE_WRAP_T, wrap);
+        self
+    }
+
+    /// Replaces current state with raw ```data``` (4 floats per cell, row by row from the bottom).
+    pub fn seed(&mut self, data: &[f32]) {
+        let expected = (self.width * self.height * 4) as usize;
+        if data.len() != expected {
+            panic!("Failed to seed {}x{} simulation. Expected {} floats, but got {}.", self.width, self.height, expected, data.len());
+        }
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.textures[self.read]);
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                0,
+                0,
+                self.width as GLsizei,
+                self.height as GLsizei,
+                gl::RGBA,
+                gl::FLOAT,
+                data.as_ptr() as *const std::ffi::c_void,
+            );
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+    /// Replaces current state with values computed by ```f(x, y)``` for every cell.
+    pub fn seed_with(&mut self, f: impl Fn(u32, u32) -> [f32; 4]) {
+        let mut data = Vec::with_capacity((self.width * self.height * 4) as usize);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                data.extend_from_slice(&f(x, y));
+            }
+        }
+        self.seed(&data);
+    }
+    /// Fills both state textures with zeros and resets step counter.
+    pub fn clear(&mut self) {
+        self.steps = 0;
+        unsafe {
+            let mut previous_framebuffer: GLint = 0;
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous_framebuffer);
+
+            let zero: [f32; 4] = [0.0; 4];
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            for &texture in &self.textures {
+                gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, texture, 0);
+                gl::ClearBufferfv(gl::COLOR, 0, zero.as_ptr());
+            }
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous_framebuffer as GLuint);
+        }
+    }
+
+    /// Runs the update shader once. Set your own uniforms with [Simulation::get_shader] before it.
+    /// Bound framebuffer, viewport and shader are restored after that.
+    pub fn step(&mut self) {
+        self.step_n(1);
+    }
+    /// Runs the update shader ```count``` times, e.g. to run a simulation faster than the framerate.
+    pub fn step_n(&mut self, count: u32) {
+        unsafe {
+            let mut previous_framebuffer: GLint = 0;
+            let mut previous_viewport: [GLint; 4] = [0; 4];
+            let mut previous_program: GLint = 0;
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut previous_framebuffer);
+            gl::GetIntegerv(gl::VIEWPORT, previous_viewport.as_mut_ptr());
+            gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut previous_program);
+            let blend = gl::IsEnabled(gl::BLEND);
+            let depth_test = gl::IsEnabled(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+            gl::Disable(gl::DEPTH_TEST);
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            gl::Viewport(0, 0, self.width as GLsizei, self.height as GLsizei);
+            self.shader.bind();
+            self.shader.set_int("u_State", 0);
+            self.shader.set_vec2("u_TexelSize", &Vector2::new(1.0 / self.width as f32, 1.0 / self.height as f32));
+            gl::ActiveTexture(gl::TEXTURE0);
+
+            for _ in 0..count {
+                gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, self.textures[1 - self.read], 0);
+                gl::BindTexture(gl::TEXTURE_2D, self.textures[self.read]);
+                self.shader.set_int("u_Step", self.steps as i32);
+                self.mesh.draw();
+
+                self.read = 1 - self.read;
+                self.steps += 1;
+            }
+
+            if blend == gl::TRUE { gl::Enable(gl::BLEND); }
+            if depth_test == gl::TRUE { gl::Enable(gl::DEPTH_TEST); }
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous_framebuffer as GLuint);
+            gl::Viewport(previous_viewport[0], previous_viewport[1], previous_viewport[2], previous_viewport[3]);
+            gl::UseProgram(previous_program as GLuint);
+        }
+    }
+
+    /// Binds the current state texture to certain slot, just like [crate::texture::Texture::bind].
+    pub fn bind_state(&self, slot: GLenum) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + slot);
+            gl::BindTexture(gl::TEXTURE_2D, self.textures[self.read]);
+        }
+        render::count_texture_bind();
+    }
+    /// Reads the current state back to the CPU (4 floats per cell, row by row from the bottom). It's slow, use it for tools and debugging.
+    pub fn read_state(&self) -> Vec<f32> {
+        let mut data = vec![0.0_f32; (self.width * self.height * 4) as usize];
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.textures[self.read]);
+            gl::GetTexImage(gl::TEXTURE_2D, 0, gl::RGBA, gl::FLOAT, data.as_mut_ptr() as *mut std::ffi::c_void);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        data
+    }
+
+    /// Gets the update shader, so you can set your own uniforms (mouse position, brush size, etc.).
+    pub fn get_shader(&self) -> &Shader {
+        &self.shader
+    }
+    /// Gets OpenGL id of the current state texture for more precise control.
+    pub fn get_state_texture_id(&self) -> GLuint {
+        self.textures[self.read]
+    }
+    /// Gets number of steps made since the simulation was created.
+    pub fn get_steps(&self) -> u64 {
+        self.steps
+    }
+    /// Gets simulation width in cells.
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+    /// Gets simulation height in cells.
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    fn set_texture_parameters(&self, parameter: GLenum, value: GLenum) {
+        unsafe {
+            for &texture in &self.textures {
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                gl::TexParameteri(gl::TEXTURE_2D, parameter, value as GLint);
+            }
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+}
+impl Drop for Simulation {
+    /// You don't need to manually free OpenGL resources, it's done automatically.
+    fn drop(&mut self) {
+        if !render::is_current_context(self.generation) {
+            return;
+        }
+        unsafe {
+            gl::DeleteTextures(2, self.textures.as_ptr());
+            gl::DeleteFramebuffers(1, &self.framebuffer);
+        }
+    }
+}
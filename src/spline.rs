@@ -0,0 +1,208 @@
+use nalgebra::{Vector2, Vector3};
+
+use crate::mesh::{Attribute, IndexedMesh, Layout, Mesh, MeshData};
+
+/// Type of curve built through spline control points.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SplineKind {
+    /// Smooth curve passing through every control point.
+    CatmullRom,
+    /// Chain of cubic Bezier curves: ```start, control, control, end, control, control, end, ...```
+    Bezier,
+}
+
+/// A 3D spline with arc-length parameterization, so you can move along it with constant speed.
+/// Good for camera rails, patrol paths, roads, pipes and ribbons.
+/// # Example
+/// ```rust
+/// use tinystorm::{spline::Spline, nalgebra::{Vector2, Vector3}};
+///
+/// let path = Spline::catmull_rom(&[
+///     Vector3::new(0.0, 0.0, 0.0),
+///     Vector3::new(10.0, 0.0, -5.0),
+///     Vector3::new(20.0, 2.0, 0.0),
+/// ]);
+///
+/// // A flat 4 units wide road.
+/// let road = path.extrude(&[Vector2::new(-2.0, 0.0), Vector2::new(2.0, 0.0)], 64);
+///
+/// while window.is_running() {
+///     distance += speed * window.get_delta();
+///     car_position = path.evaluate_at_distance(distance % path.get_length());
+///     ...
+///     road.draw();
+///     path.debug_draw(64);
+/// }
+/// ```
+pub struct Spline {
+    points: Vec<Vector3<f32>>,
+    kind: SplineKind,
+
+    // Cumulative length at evenly spaced parameter samples.
+    lengths: Vec<f32>,
+}
+
+impl Spline {
+    const LENGTH_SAMPLES_PER_SEGMENT: usize = 32;
+
+    /// Creates a Catmull-Rom spline passing through all ```points```. There must be at least 2 points.
+    pub fn catmull_rom(points: &[Vector3<f32>]) -> Self {
+        if points.len() < 2 {
+            panic!("Failed to create Catmull-Rom spline. Got {} points, but at least 2 are required.", points.len());
+        }
+        Self::new(points, SplineKind::CatmullRom)
+    }
+    /// Creates a chain of cubic Bezier curves. Number of ```points``` must be ```3 * curves + 1```.
+    pub fn bezier(points: &[Vector3<f32>]) -> Self {
+        if points.len() < 4 || !(points.len() - 1).is_multiple_of(3) {
+            panic!("Failed to create Bezier spline. Got {} points, but it must be 3 * curves + 1.", points.len());
+        }
+        Self::new(points, SplineKind::Bezier)
+    }
+
+    fn new(points: &[Vector3<f32>], kind: SplineKind) -> Self {
+        let mut spline = Self { points: points.to_vec(), kind, lengths: Vec::new() };
+
+        let samples = spline.get_segment_count() * Self::LENGTH_SAMPLES_PER_SEGMENT;
+        let mut length = 0.0;
+        let mut previous = spline.evaluate(0.0);
+        spline.lengths.push(0.0);
+        for i in 1..=samples {
+            let current = spline.evaluate(i as f32 / samples as f32);
+            length += (current - previous).norm();
+            spline.lengths.push(length);
+            previous = current;
+        }
+
+        spline
+    }
+
+    /// Gets position on the spline at parameter ```t``` from 0.0 (start) to 1.0 (end).
+    /// Parameter isn't proportional to distance, use [Spline::evaluate_at_distance] for constant speed.
+    pub fn evaluate(&self, t: f32) -> Vector3<f32> {
+        let (segment, t) = self.locate(t);
+        let [p0, p1, p2, p3] = self.segment_points(segment);
+
+        match self.kind {
+            SplineKind::CatmullRom => 0.5 * (
+                2.0 * p1 + (p2 - p0) * t
+                + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t
+                + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t * t * t
+            ),
+            SplineKind::Bezier => {
+                let u = 1.0 - t;
+                p0 * (u * u * u) + p1 * (3.0 * u * u * t) + p2 * (3.0 * u * t * t) + p3 * (t * t * t)
+            }
+        }
+    }
+    /// Gets normalized direction of the spline at parameter ```t``` from 0.0 to 1.0.
+    pub fn tangent(&self, t: f32) -> Vector3<f32> {
+        let (segment, t) = self.locate(t);
+        let [p0, p1, p2, p3] = self.segment_points(segment);
+
+        let derivative = match self.kind {
+            SplineKind::CatmullRom => 0.5 * (
+                (p2 - p0)
+                + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * (2.0 * t)
+                + (3.0 * p1 - p0 - 3.0 * p2 + p3) * (3.0 * t * t)
+            ),
+            SplineKind::Bezier => {
+                let u = 1.0 - t;
+                (p1 - p0) * (3.0 * u * u) + (p2 - p1) * (6.0 * u * t) + (p3 - p2) * (3.0 * t * t)
+            }
+        };
+
+        derivative.try_normalize(f32::EPSILON).unwrap_or_else(|| (p3 - p0).try_normalize(f32::EPSILON).unwrap_or(Vector3::z()))
+    }
+
+    /// Gets position on the spline ```distance``` world units away from its start (clamped to spline length).
+    pub fn evaluate_at_distance(&self, distance: f32) -> Vector3<f32> {
+        self.evaluate(self.distance_to_t(distance))
+    }
+    /// Gets normalized direction of the spline ```distance``` world units away from its start.
+    pub fn tangent_at_distance(&self, distance: f32) -> Vector3<f32> {
+        self.tangent(self.distance_to_t(distance))
+    }
+    /// Converts ```distance``` from the spline start into parameter ```t``` from 0.0 to 1.0.
+    pub fn distance_to_t(&self, distance: f32) -> f32 {
+        let length = self.get_length();
+        if length <= 0.0 {
+            return 0.0;
+        }
+
+        let distance = distance.clamp(0.0, length);
+        let index = self.lengths.partition_point(|&value| value < distance).clamp(1, self.lengths.len() - 1);
+        let (start, end) = (self.lengths[index - 1], self.lengths[index]);
+        let fraction = if end > start { (distance - start) / (end - start) } else { 0.0 };
+
+        (index as f32 - 1.0 + fraction) / (self.lengths.len() - 1) as f32
+    }
+
+    /// Builds a mesh by sweeping 2D ```profile``` along the spline with ```samples``` evenly spaced steps.
+    /// See [MeshData::extrude] for profile and UV details.
+    pub fn extrude(&self, profile: &[Vector2<f32>], samples: usize) -> IndexedMesh {
+        let samples = samples.max(1);
+        let length = self.get_length();
+        let path: Vec<Vector3<f32>> = (0..=samples)
+            .map(|i| self.evaluate_at_distance(length * i as f32 / samples as f32))
+            .collect();
+
+        MeshData::extrude(profile, &path).build()
+    }
+    /// Draws the spline as a line strip with ```samples``` steps. Position is at ```layout(location = 0)``` as ```vec3```.
+    /// It creates a mesh every call, so use it only for debugging.
+    pub fn debug_draw(&self, samples: usize) {
+        let samples = samples.max(1);
+        let vertices: Vec<f32> = (0..=samples)
+            .flat_map(|i| {
+                let position = self.evaluate(i as f32 / samples as f32);
+                [position.x, position.y, position.z]
+            })
+            .collect();
+
+        Mesh::new::<f32>(&vertices, &Layout::default().next_attribute(Attribute::Vec3), gl::LINE_STRIP).draw();
+    }
+
+    /// Gets total spline length in world units (approximated).
+    pub fn get_length(&self) -> f32 {
+        *self.lengths.last().unwrap_or(&0.0)
+    }
+    /// Gets control points of the spline.
+    pub fn get_points(&self) -> &[Vector3<f32>] {
+        &self.points
+    }
+    /// Gets type of the spline.
+    pub fn get_kind(&self) -> SplineKind {
+        self.kind
+    }
+
+    fn get_segment_count(&self) -> usize {
+        match self.kind {
+            SplineKind::CatmullRom => self.points.len() - 1,
+            SplineKind::Bezier => (self.points.len() - 1) / 3,
+        }
+    }
+    fn locate(&self, t: f32) -> (usize, f32) {
+        let count = self.get_segment_count();
+        let scaled = t.clamp(0.0, 1.0) * count as f32;
+        let segment = (scaled.floor() as usize).min(count - 1);
+        (segment, scaled - segment as f32)
+    }
+    fn segment_points(&self, segment: usize) -> [Vector3<f32>; 4] {
+        match self.kind {
+            SplineKind::CatmullRom => {
+                let last = self.points.len() - 1;
+                [
+                    self.points[segment.saturating_sub(1)],
+                    self.points[segment],
+                    self.points[segment + 1],
+                    self.points[(segment + 2).min(last)],
+                ]
+            }
+            SplineKind::Bezier => {
+                let start = segment * 3;
+                [self.points[start], self.points[start + 1], self.points[start + 2], self.points[start + 3]]
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::window::Window;
+
+/// What the [StateStack] should do after [State::update] is called.
+pub enum StateAction {
+    /// Keep the current state running.
+    None,
+    /// Put a new state on top of the current one (e.g. open a pause menu).
+    Push(Box<dyn State>),
+    /// Remove the current state and return to the one below it.
+    Pop,
+    /// Swap the current state with a new one (e.g. main menu -> game).
+    Replace(Box<dyn State>),
+    /// Remove all states. [StateStack::run] stops after that.
+    Quit,
+}
+
+/// A single game state (menu, game, pause screen, etc.) managed by a [StateStack].
+/// Only [State::update] and [State::draw] are required, everything else has an empty default.
+pub trait State {
+    /// Called when the state is put on top of the stack.
+    fn on_enter(&mut self, _window: &mut Window) {}
+    /// Called when the state is removed from the stack.
+    fn on_exit(&mut self, _window: &mut Window) {}
+    /// Called when another state is pushed on top of this one.
+    fn on_pause(&mut self, _window: &mut Window) {}
+    /// Called when this state is on top of the stack again, after the one above was popped.
+    fn on_resume(&mut self, _window: &mut Window) {}
+
+    /// Updates the state. Called only for the top state, once per frame.
+    fn update(&mut self, delta: f32, window: &mut Window) -> StateAction;
+    /// Renders the state. Clearing the framebuffer is up to you.
+    fn draw(&mut self, window: &Window);
+
+    /// If true, the state below would be drawn first (e.g. game behind a pause menu).
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+/// A stack of [State]s that takes care of menu -> game -> pause flows.
+/// # Example
+/// ```rust
+/// use tinystorm::{state::{State, StateAction, StateStack}, window::{Window, WindowBuilder}, glfw::Key};
+///
+/// struct Menu;
+/// impl State for Menu {
+///     fn update(&mut self, _delta: f32, window: &mut Window) -> StateAction {
+///         if window.is_key_just_pressed(Key::Enter) {
+///             return StateAction::Replace(Box::new(Game));
+///         }
+///         StateAction::None
+///     }
+///     fn draw(&mut self, _window: &Window) {
+///         unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }
+///     }
+/// }
+///
+/// struct Game;
+/// impl State for Game { ... }
+///
+/// let window = WindowBuilder::default().build();
+/// StateStack::new(Box::new(Menu)).run(window);
+/// ```
+pub struct StateStack {
+    states: Vec<Box<dyn State>>,
+    pending_initial: bool,
+}
+
+impl StateStack {
+    /// Creates a stack with ```initial``` state on it.
+    /// [State::on_enter] of ```initial``` is called on the first [StateStack::update].
+    pub fn new(initial: Box<dyn State>) -> Self {
+        Self { states: vec![initial], pending_initial: true }
+    }
+
+    /// Puts ```state``` on top of the stack.
+    pub fn push(&mut self, mut state: Box<dyn State>, window: &mut Window) {
+        self.enter_initial(window);
+        if let Some(top) = self.states.last_mut() {
+            top.on_pause(window);
+        }
+
+        state.on_enter(window);
+        self.states.push(state);
+    }
+    /// Removes the top state from the stack and returns it.
+    pub fn pop(&mut self, window: &mut Window) -> Option<Box<dyn State>> {
+        self.enter_initial(window);
+        let mut state = self.states.pop()?;
+        state.on_exit(window);
+
+        if let Some(top) = self.states.last_mut() {
+            top.on_resume(window);
+        }
+        Some(state)
+    }
+    /// Replaces the top state with ```state``` and returns the old one.
+    pub fn replace(&mut self, mut state: Box<dyn State>, window: &mut Window) -> Option<Box<dyn State>> {
+        self.enter_initial(window);
+        let mut old = self.states.pop();
+        if let Some(old) = old.as_mut() {
+            old.on_exit(window);
+        }
+
+        state.on_enter(window);
+        self.states.push(state);
+        old
+    }
+    /// Removes all states from the stack.
+    pub fn clear(&mut self, window: &mut Window) {
+        self.enter_initial(window);
+        while let Some(mut state) = self.states.pop() {
+            state.on_exit(window);
+        }
+    }
+
+    /// Returns if there's no states left.
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+    /// Returns number of states on the stack.
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Updates the top state and applies the [StateAction] it returned.
+    pub fn update(&mut self, delta: f32, window: &mut Window) {
+        self.enter_initial(window);
+
+        let action = match self.states.last_mut() {
+            Some(top) => top.update(delta, window),
+            None => return,
+        };
+
+        match action {
+            StateAction::None => {}
+            StateAction::Push(state) => self.push(state, window),
+            StateAction::Pop => { self.pop(window); }
+            StateAction::Replace(state) => { self.replace(state, window); }
+            StateAction::Quit => self.clear(window),
+        }
+    }
+    /// Draws the top state, and states below it while they are overlays.
+    pub fn draw(&mut self, window: &Window) {
+        let mut first = self.states.len();
+        while first > 1 && self.states[first - 1].is_overlay() {
+            first -= 1;
+        }
+
+        let first = first.saturating_sub(1);
+        for state in &mut self.states[first..] {
+            state.draw(window);
+        }
+    }
+
+    /// Runs the whole main loop until window is closed or stack is empty.
+    /// It's just [Window::poll_events], [StateStack::update], [StateStack::draw] and [Window::swap_buffers] each frame.
+    pub fn run(mut self, mut window: Window) {
+        while window.is_running() && !self.is_empty() {
+            window.poll_events();
+            self.update(window.get_delta(), &mut window);
+            self.draw(&window);
+            window.swap_buffers();
+        }
+
+        self.clear(&mut window);
+    }
+
+    fn enter_initial(&mut self, window: &mut Window) {
+        if self.pending_initial {
+            self.pending_initial = false;
+            if let Some(initial) = self.states.last_mut() {
+                initial.on_enter(window);
+            }
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use std::{
+    fs::File,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+// First bytes of a gzip stream, so compressed and plain saves can be told apart on load.
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+/// Gets the directory for player settings of ```app_name``` and creates it if needed:
+/// ```%APPDATA%\app_name``` on Windows, ```~/Library/Application Support/app_name``` on macOS
+/// and ```$XDG_CONFIG_HOME/app_name``` (```~/.config/app_name```) elsewhere.
+/// Panics if the directory can't be found or created.
+/// # Example
+/// ```rust
+/// use tinystorm::{storage, config::WindowConfig};
+///
+/// let path = storage::config_dir("my_game").join("window.toml");
+/// let window = WindowBuilder::default().with_config(&WindowConfig::load(path.to_str().unwrap()).unwrap_or_default()).build();
+/// ```
+pub fn config_dir(app_name: &str) -> PathBuf {
+    let base = if cfg!(target_os = "windows") {
+        env_dir("APPDATA", None)
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME", Some("Library/Application Support"))
+    } else {
+        env_dir("XDG_CONFIG_HOME", None).or_else(|| env_dir("HOME", Some(".config")))
+    };
+    create_app_dir(base, app_name)
+}
+/// Gets the directory for save games of ```app_name``` and creates it if needed:
+/// ```%APPDATA%\app_name\saves``` on Windows, ```~/Library/Application Support/app_name/saves``` on macOS
+/// and ```$XDG_DATA_HOME/app_name``` (```~/.local/share/app_name```) elsewhere.
+/// Panics if the directory can't be found or created.
+pub fn save_dir(app_name: &str) -> PathBuf {
+    let base = if cfg!(target_os = "windows") {
+        env_dir("APPDATA", None)
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME", Some("Library/Application Support"))
+    } else {
+        env_dir("XDG_DATA_HOME", None).or_else(|| env_dir("HOME", Some(".local/share")))
+    };
+    let directory = create_app_dir(base, app_name);
+    if cfg!(any(target_os = "windows", target_os = "macos")) {
+        create_app_dir(Some(directory), "saves")
+    } else {
+        directory
+    }
+}
+
+fn env_dir(variable: &str, suffix: Option<&str>) -> Option<PathBuf> {
+    let value = std::env::var_os(variable).filter(|value| !value.is_empty())?;
+    let path = PathBuf::from(value);
+    Some(match suffix {
+        Some(suffix) => path.join(suffix),
+        None => path,
+    })
+}
+fn create_app_dir(base: Option<PathBuf>, name: &str) -> PathBuf {
+    let Some(base) = base else {
+        panic!("Failed to find a directory for: {}. Home directory isn't set.", name);
+    };
+    let path = base.join(name);
+    if let Err(error) = std::fs::create_dir_all(&path) {
+        panic!("Failed to create directory at: {}. Error: {}.", path.display(), error);
+    }
+    path
+}
+
+/// Writes ```bytes``` to a file at ```path``` so it's never left half-written: data goes to a temporary file next to it first,
+/// which then replaces the old file. A crash or power loss while saving keeps the previous save intact.
+/// Panics if writing failed.
+pub fn write_atomic(path: &Path, bytes: &[u8]) {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+
+    let result = File::create(&temporary)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&temporary, path));
+    if let Err(error) = result {
+        let _ = std::fs::remove_file(&temporary);
+        panic!("Failed to write file at: {}. Error: {}.", path.display(), error);
+    }
+}
+
+#[derive(Serialize)]
+struct SaveRef<'a, T> {
+    version: u32,
+    data: &'a T,
+}
+// Version is checked by SaveHeader, so only data is read here.
+#[derive(Deserialize)]
+struct Save<T> {
+    data: T,
+}
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+/// Saves ```value``` to a file at ```path``` as RON, tagged with ```version``` of your save format, see [load].
+/// The file is written with [write_atomic]. Panics if serializing or writing failed.
+/// # Example
+/// ```rust
+/// use tinystorm::storage;
+///
+/// const SAVE_VERSION: u32 = 2;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Progress {
+///     level: u32,
+///     coins: u32,
+/// }
+///
+/// let path = storage::save_dir("my_game").join("slot1.ron");
+/// storage::save(&path, SAVE_VERSION, &progress);
+/// let progress: Progress = match storage::load(&path, SAVE_VERSION) {
+///     Ok(progress) => progress,
+///     Err(LoadError::NotFound) => Progress::default(),
+///     Err(error) => { show_error_popup(&error.to_string()); Progress::default() }
+/// };
+/// ```
+pub fn save<T: Serialize>(path: &Path, version: u32, value: &T) {
+    write_atomic(path, serialize(path, version, value).as_bytes());
+}
+/// Same as [save], but the file is compressed with gzip. Worth it for big saves (worlds, replays), [load] reads both.
+#[cfg(feature = "compression")]
+pub fn save_compressed<T: Serialize>(path: &Path, version: u32, value: &T) {
+    let source = serialize(path, version, value);
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let bytes = encoder.write_all(source.as_bytes()).and_then(|_| encoder.finish());
+    match bytes {
+        Ok(bytes) => write_atomic(path, &bytes),
+        Err(error) => panic!("Failed to compress save for: {}. Error: {}.", path.display(), error),
+    }
+}
+fn serialize<T: Serialize>(path: &Path, version: u32, value: &T) -> String {
+    match ron::ser::to_string_pretty(&SaveRef { version, data: value }, ron::ser::PrettyConfig::default()) {
+        Ok(source) => source,
+        Err(error) => panic!("Failed to serialize save for: {}. Error: {}.", path.display(), error),
+    }
+}
+
+/// Reason a save couldn't be loaded by [load] or [load_with_migration].
+#[derive(Debug)]
+pub enum LoadError {
+    /// There's no save file, usually a new game should be started.
+    NotFound,
+    /// The file exists, but couldn't be read (permissions, disk errors).
+    Io(std::io::Error),
+    /// The file is broken or doesn't match the saved type.
+    Corrupt(String),
+    /// The file was saved with another version of the save format and wasn't migrated.
+    WrongVersion { found: u32, expected: u32 },
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "Save file doesn't exist"),
+            Self::Io(error) => write!(f, "Failed to read save file. Error: {}", error),
+            Self::Corrupt(error) => write!(f, "Save file is corrupt. Error: {}", error),
+            Self::WrongVersion { found, expected } => write!(f, "Save file version is {}, but {} is expected", found, expected),
+        }
+    }
+}
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Loads a value saved by [save] or [save_compressed] with the same ```version```.
+/// Returns [LoadError::WrongVersion] for other versions, use [load_with_migration] to upgrade old saves instead.
+pub fn load<T: DeserializeOwned>(path: &Path, version: u32) -> Result<T, LoadError> {
+    load_with_migration(path, version, |old| Err(LoadError::WrongVersion { found: old.get_version(), expected: version }))
+}
+/// Loads a value saved by [save] or [save_compressed]. If the file has another version than ```version```,
+/// ```migrate``` gets it to parse as the old type and convert. Returns errors like [load].
+/// # Example
+/// ```rust
+/// let progress = storage::load_with_migration(&path, 2, |old| match old.get_version() {
+///     // Version 1 had no coins.
+///     1 => old.parse::<ProgressV1>().map(|v1| Progress { level: v1.level, coins: 0 }),
+///     found => Err(LoadError::WrongVersion { found, expected: 2 }),
+/// });
+/// ```
+pub fn load_with_migration<T: DeserializeOwned>(
+    path: &Path,
+    version: u32,
+    migrate: impl FnOnce(OldSave) -> Result<T, LoadError>,
+) -> Result<T, LoadError> {
+    let bytes = std::fs::read(path).map_err(|error| match error.kind() {
+        ErrorKind::NotFound => LoadError::NotFound,
+        _ => LoadError::Io(error),
+    })?;
+    let source = decompress(bytes).map_err(LoadError::Corrupt)?;
+
+    let header = ron::from_str::<SaveHeader>(&source).map_err(|error| LoadError::Corrupt(error.to_string()))?;
+    let old = OldSave { version: header.version, source };
+    if header.version == version { old.parse() } else { migrate(old) }
+}
+
+fn decompress(bytes: Vec<u8>) -> Result<String, String> {
+    if !bytes.starts_with(&GZIP_MAGIC) {
+        return String::from_utf8(bytes).map_err(|error| error.to_string());
+    }
+
+    #[cfg(feature = "compression")]
+    {
+        let mut source = String::new();
+        std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(bytes.as_slice()), &mut source).map_err(|error| error.to_string())?;
+        Ok(source)
+    }
+    #[cfg(not(feature = "compression"))]
+    Err(String::from("The save is compressed, but the \"compression\" feature is disabled"))
+}
+
+/// A save file with a version other than the current one, passed to the ```migrate``` function of [load_with_migration].
+pub struct OldSave {
+    version: u32,
+    source: String,
+}
+
+impl OldSave {
+    /// Gets version the file was saved with.
+    pub fn get_version(&self) -> u32 {
+        self.version
+    }
+    /// Parses saved data as ```T```, usually the type the version was saved with.
+    /// Returns [LoadError::Corrupt] if it doesn't match.
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<T, LoadError> {
+        ron::from_str::<Save<T>>(&self.source).map(|save| save.data).map_err(|error| LoadError::Corrupt(error.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct ProgressV1 {
+        level: u32,
+    }
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Progress {
+        level: u32,
+        coins: u32,
+    }
+
+    // A fresh file path per test, tests run in parallel.
+    fn temp_path(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("tinystorm_storage_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(name);
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp_path("round_trip.ron");
+        let progress = Progress { level: 3, coins: 120 };
+        save(&path, 2, &progress);
+
+        assert_eq!(load::<Progress>(&path, 2).unwrap(), progress);
+        assert!(!path.with_extension("ron.tmp").exists());
+    }
+
+    #[test]
+    fn missing_file_is_not_found() {
+        let path = temp_path("missing.ron");
+        assert!(matches!(load::<Progress>(&path, 1), Err(LoadError::NotFound)));
+    }
+
+    #[test]
+    fn broken_file_is_corrupt() {
+        let path = temp_path("broken.ron");
+        write_atomic(&path, b"(version: 1, data: (level: ");
+        assert!(matches!(load::<Progress>(&path, 1), Err(LoadError::Corrupt(_))));
+
+        // Valid header, but the data doesn't match the type.
+        save(&path, 1, &ProgressV1 { level: 1 });
+        assert!(matches!(load::<Progress>(&path, 1), Err(LoadError::Corrupt(_))));
+    }
+
+    #[test]
+    fn other_version_is_wrong_version() {
+        let path = temp_path("wrong_version.ron");
+        save(&path, 1, &ProgressV1 { level: 4 });
+        assert!(matches!(load::<Progress>(&path, 2), Err(LoadError::WrongVersion { found: 1, expected: 2 })));
+    }
+
+    #[test]
+    fn migration_upgrades_old_saves() {
+        let path = temp_path("migration.ron");
+        save(&path, 1, &ProgressV1 { level: 4 });
+
+        let progress = load_with_migration(&path, 2, |old| match old.get_version() {
+            1 => old.parse::<ProgressV1>().map(|v1| Progress { level: v1.level, coins: 0 }),
+            found => Err(LoadError::WrongVersion { found, expected: 2 }),
+        });
+        assert_eq!(progress.unwrap(), Progress { level: 4, coins: 0 });
+
+        // Current version skips the migration.
+        save(&path, 2, &Progress { level: 5, coins: 7 });
+        let progress = load_with_migration::<Progress>(&path, 2, |_| panic!("Migration shouldn't run"));
+        assert_eq!(progress.unwrap(), Progress { level: 5, coins: 7 });
+    }
+
+    #[test]
+    fn plain_saves_are_not_treated_as_compressed() {
+        let path = temp_path("plain.ron");
+        save(&path, 1, &ProgressV1 { level: 2 });
+        assert!(!std::fs::read(&path).unwrap().starts_with(&GZIP_MAGIC));
+        assert_eq!(load::<ProgressV1>(&path, 1).unwrap(), ProgressV1 { level: 2 });
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compressed_saves_are_detected() {
+        let path = temp_path("compressed.ron.gz");
+        save_compressed(&path, 1, &Progress { level: 9, coins: 1 });
+        assert!(std::fs::read(&path).unwrap().starts_with(&GZIP_MAGIC));
+        assert_eq!(load::<Progress>(&path, 1).unwrap(), Progress { level: 9, coins: 1 });
+    }
+
+    #[cfg(not(feature = "compression"))]
+    #[test]
+    fn compressed_saves_need_the_feature() {
+        let path = temp_path("compressed.ron.gz");
+        write_atomic(&path, &[GZIP_MAGIC[0], GZIP_MAGIC[1], 8, 0]);
+        assert!(matches!(load::<Progress>(&path, 1), Err(LoadError::Corrupt(_))));
+    }
+}
@@ -0,0 +1,414 @@
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+use image::{GenericImageView, imageops::FilterType};
+
+use crate::{loader::WorkerPool, render};
+
+// Width, height and RGBA8 pixels of a single mip level.
+type Mip = (u32, u32, Vec<u8>);
+
+/// A handle of a texture loaded by [TextureStreamer].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct StreamedTexture(usize);
+
+struct StreamEntry {
+    path: String,
+    mips: Vec<Mip>,
+    texture: GLuint,
+    // Index of the finest mip level on the GPU (level 0 of the texture), mips.len() if nothing is uploaded yet.
+    resident_level: usize,
+    desired_level: usize,
+    requested_size: f32,
+    last_request: u64,
+    error: Option<String>,
+}
+
+impl StreamEntry {
+    fn min_level(&self, min_resident_size: u32) -> usize {
+        self.mips.iter().position(|(width, height, _)| *width.max(height) <= min_resident_size).unwrap_or(self.mips.len().saturating_sub(1))
+    }
+    fn chain_bytes(&self, level: usize) -> usize {
+        self.mips.iter().skip(level).map(|(width, height, _)| *width as usize * *height as usize * 4).sum()
+    }
+}
+
+/// Streams big textures under a GPU memory budget: a small mip is uploaded first, then resolution is upgraded
+/// one mip level at a time for textures that are requested bigger on screen, while textures nobody needs are downgraded
+/// to stay under the budget. Good for open-world-ish scenes on GPUs with little VRAM.
+///
+/// Images are decoded and their mip chains are generated by a pool of worker threads (one per CPU core by default),
+/// the whole chain is kept in RAM. Only resident levels have GPU storage: every upgrade or downgrade creates a texture
+/// of the new size, copies levels that are already there with ```glCopyImageSubData``` (OpenGL 4.3, re-uploaded from RAM on older versions)
+/// and deletes the old one, so downgrading really frees VRAM.
+/// # Example
+/// ```rust
+/// use tinystorm::streaming::TextureStreamer;
+///
+/// let mut streamer = TextureStreamer::new(512 * 1024 * 1024); // 512 MiB of textures at most.
+/// let rock = streamer.load("./assets/textures/rock_4k.png");
+///
+/// while window.is_running() {
+///     window.poll_events();
+///
+///     // Approximate size in pixels the texture covers on screen.
+///     let size = window.get_height() as f32 * rock_radius / (camera_position - rock_position).norm();
+///     streamer.request(rock, size);
+///     streamer.update();
+///
+///     streamer.bind(rock, 0);
+///     rock_mesh.draw();
+///     window.swap_buffers();
+/// }
+/// ```
+pub struct TextureStreamer {
+    entries: Vec<StreamEntry>,
+    pool: WorkerPool<Result<Vec<Mip>, String>>,
+
+    budget: usize,
+    used: usize,
+    upgrades_per_frame: usize,
+    min_resident_size: u32,
+    filter: GLenum,
+    wrap: GLenum,
+
+    frame: u64,
+    generation: u64,
+}
+
+impl TextureStreamer {
+    /// Creates a streamer that keeps uploaded textures under ```budget``` bytes (smallest resident mips don't count towards the limit).
+    pub fn new(budget: usize) -> Self {
+        Self {
+            entries: Vec::new(),
+            pool: WorkerPool::new(),
+
+            budget,
+            used: 0,
+            upgrades_per_frame: 2,
+            min_resident_size: 64,
+            filter: gl::LINEAR,
+            wrap: gl::REPEAT,
+
+            frame: 0,
+            generation: render::context_generation(),
+        }
+    }
+    /// Sets number of worker threads decoding images. One per CPU core by default.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.pool.set_threads(threads);
+        self
+    }
+    /// Sets how many textures can be upgraded by one mip level per frame. Uploads are slow, so keep it small. 2 by default.
+    pub fn with_upgrades_per_frame(mut self, upgrades: usize) -> Self {
+        self.upgrades_per_frame = upgrades;
+        self
+    }
+    /// Sets size in pixels of the biggest mip that always stays uploaded once the texture is loaded. 64 by default.
+    pub fn with_min_resident_size(mut self, size: u32) -> Self {
+        self.min_resident_size = size.max(1);
+        self
+    }
+    /// Sets ```filter``` and ```wrap``` of all streamed textures, just like [crate::texture::Texture::load_from_file].
+    pub fn with_sampling(mut self, filter: GLenum, wrap: GLenum) -> Self {
+        self.filter = filter;
+        self.wrap = wrap;
+        self
+    }
+
+    /// Starts loading an image at ```path``` on a worker thread. It's uploaded by [TextureStreamer::update] when ready.
+    pub fn load(&mut self, path: &str) -> StreamedTexture {
+        let index = self.entries.len();
+        self.entries.push(StreamEntry {
+            path: String::from(path),
+            mips: Vec::new(),
+            texture: 0,
+            resident_level: 0,
+            desired_level: 0,
+            requested_size: 0.0,
+            last_request: 0,
+            error: None,
+        });
+
+        let path = String::from(path);
+        self.pool.submit(index, move || Self::decode(&path));
+
+        StreamedTexture(index)
+    }
+    /// Tells that ```texture``` is drawn ```size``` pixels big (its bigger side) at this frame. Call it every frame the texture is visible.
+    /// Textures that weren't requested at the last frame are the first to be downgraded.
+    pub fn request(&mut self, texture: StreamedTexture, size: f32) {
+        let entry = &mut self.entries[texture.0];
+        if entry.last_request != self.frame {
+            entry.requested_size = 0.0;
+        }
+        entry.requested_size = entry.requested_size.max(size);
+        entry.last_request = self.frame;
+    }
+
+    /// Uploads loaded textures, upgrades requested ones and downgrades unused ones to stay under the budget. Call it once per frame.
+    pub fn update(&mut self) {
+        while let Some((index, result)) = self.pool.try_recv() {
+            let entry = &mut self.entries[index];
+            match result {
+                Ok(mips) => entry.mips = mips,
+                Err(error) => {
+                    entry.error = Some(format!("Failed to load texture at: {}. Error: {}.", entry.path, error));
+                    continue;
+                }
+            }
+            entry.resident_level = entry.mips.len();
+            let level = entry.min_level(self.min_resident_size);
+            self.set_resident_level(index, level);
+        }
+
+        for entry in &mut self.entries {
+            let min_level = entry.min_level(self.min_resident_size);
+            entry.desired_level = if entry.last_request == self.frame && entry.requested_size > 0.0 && !entry.mips.is_empty() {
+                let (width, height, _) = entry.mips[0];
+                ((width.max(height) as f32 / entry.requested_size).log2().floor().max(0.0) as usize).min(min_level)
+            } else {
+                min_level
+            };
+        }
+
+        // The biggest requested size first, it's the most visible.
+        let mut candidates: Vec<usize> = (0..self.entries.len())
+            .filter(|&index| !self.entries[index].mips.is_empty() && self.entries[index].resident_level > self.entries[index].desired_level)
+            .collect();
+        candidates.sort_by(|&a, &b| self.entries[b].requested_size.total_cmp(&self.entries[a].requested_size));
+
+        for index in candidates.into_iter().take(self.upgrades_per_frame) {
+            let entry = &self.entries[index];
+            let level = entry.resident_level - 1;
+            let cost = entry.chain_bytes(level) - entry.chain_bytes(entry.resident_level);
+            let requested_size = entry.requested_size;
+            if !self.make_room(cost, index, requested_size) {
+                break;
+            }
+            self.set_resident_level(index, level);
+        }
+        self.make_room(0, usize::MAX, f32::INFINITY);
+
+        self.frame += 1;
+    }
+
+    /// Binds ```texture``` to certain slot, just like [crate::texture::Texture::bind]. Binds nothing if it's not loaded yet or failed.
+    pub fn bind(&self, texture: StreamedTexture, slot: GLenum) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + slot);
+            gl::BindTexture(gl::TEXTURE_2D, self.entries[texture.0].texture);
+        }
+        render::count_texture_bind();
+    }
+    /// Checks if ```texture``` is loaded and uploaded at least at the smallest resolution.
+    pub fn is_resident(&self, texture: StreamedTexture) -> bool {
+        self.entries[texture.0].texture != 0
+    }
+    /// Checks if ```texture``` failed to load, see [TextureStreamer::get_error]. Failed textures stay unbound.
+    pub fn is_failed(&self, texture: StreamedTexture) -> bool {
+        self.entries[texture.0].error.is_some()
+    }
+    /// Gets the reason ```texture``` failed to load, ```None``` if it's loaded or still pending.
+    pub fn get_error(&self, texture: StreamedTexture) -> Option<&str> {
+        self.entries[texture.0].error.as_deref()
+    }
+    /// Gets current GPU resolution of ```texture```, or ```None``` if it's not loaded yet.
+    pub fn get_resident_size(&self, texture: StreamedTexture) -> Option<(u32, u32)> {
+        let entry = &self.entries[texture.0];
+        entry.mips.get(entry.resident_level).map(|(width, height, _)| (*width, *height))
+    }
+    /// Gets full resolution of ```texture```, or ```None``` if it's not loaded yet.
+    pub fn get_full_size(&self, texture: StreamedTexture) -> Option<(u32, u32)> {
+        self.entries[texture.0].mips.first().map(|(width, height, _)| (*width, *height))
+    }
+
+    /// Changes memory budget in bytes. Textures are downgraded at the next [TextureStreamer::update] if it's exceeded.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+    }
+    /// Gets memory budget in bytes.
+    pub fn get_budget(&self) -> usize {
+        self.budget
+    }
+    /// Gets GPU memory allocated for mip levels above the smallest resident ones, in bytes. This is what the budget limits.
+    pub fn get_used_memory(&self) -> usize {
+        self.used
+    }
+
+    // Downgrades textures until there's ```bytes``` free memory, never touching ```keep```. Returns false if it's impossible.
+    // Textures needed at this frame are downgraded only if they're requested smaller than ```size```, so two textures don't fight for memory.
+    fn make_room(&mut self, bytes: usize, keep: usize, size: f32) -> bool {
+        let frame = self.frame;
+        while self.used + bytes > self.budget {
+            let min_resident_size = self.min_resident_size;
+            let victim = (0..self.entries.len())
+                .filter(|&index| index != keep)
+                .filter(|&index| {
+                    let entry = &self.entries[index];
+                    let needed = entry.resident_level >= entry.desired_level && entry.last_request == frame;
+                    !entry.mips.is_empty() && entry.resident_level < entry.min_level(min_resident_size) && (!needed || entry.requested_size < size)
+                })
+                // Textures above their desired level first, then the least recently requested, then the smallest ones on screen.
+                .min_by(|&a, &b| {
+                    let (a, b) = (&self.entries[a], &self.entries[b]);
+                    (a.resident_level >= a.desired_level).cmp(&(b.resident_level >= b.desired_level))
+                        .then(a.last_request.cmp(&b.last_request))
+                        .then(a.requested_size.total_cmp(&b.requested_size))
+                });
+
+            let Some(victim) = victim else { return false; };
+            let level = self.entries[victim].resident_level + 1;
+            self.set_resident_level(victim, level);
+        }
+        true
+    }
+    fn set_resident_level(&mut self, index: usize, level: usize) {
+        let min_resident_size = self.min_resident_size;
+        let entry = &mut self.entries[index];
+        let min_level = entry.min_level(min_resident_size);
+        let budgeted = |entry: &StreamEntry, level: usize| entry.chain_bytes(level).saturating_sub(entry.chain_bytes(min_level));
+
+        if entry.resident_level < entry.mips.len() {
+            self.used -= budgeted(entry, entry.resident_level);
+        }
+        self.used += budgeted(entry, level);
+        let old_level = entry.resident_level;
+        let old_texture = entry.texture;
+        entry.resident_level = level;
+
+        unsafe {
+            Self::allocate(entry, self.filter, self.wrap);
+
+            // Levels that were resident are copied on GPU, new ones are uploaded from RAM.
+            let copy = old_texture != 0 && gl::CopyImageSubData::is_loaded();
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            for i in level..entry.mips.len() {
+                let (width, height, data) = &entry.mips[i];
+                if copy && i >= old_level {
+                    gl::CopyImageSubData(
+                        old_texture,
+                        gl::TEXTURE_2D,
+                        (i - old_level) as GLint,
+                        0,
+                        0,
+                        0,
+                        entry.texture,
+                        gl::TEXTURE_2D,
+                        (i - level) as GLint,
+                        0,
+                        0,
+                        0,
+                        *width as GLsizei,
+                        *height as GLsizei,
+                        1,
+                    );
+                } else {
+                    gl::TexSubImage2D(
+                        gl::TEXTURE_2D,
+                        (i - level) as GLint,
+                        0,
+                        0,
+                        *width as GLsizei,
+                        *height as GLsizei,
+                        gl::RGBA,
+                        gl::UNSIGNED_BYTE,
+                        data.as_ptr() as *const std::ffi::c_void,
+                    );
+                }
+            }
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+
+            if old_texture != 0 && render::is_current_context(self.generation) {
+                gl::DeleteTextures(1, &old_texture);
+            }
+        }
+    }
+    // Creates a texture with storage for mip levels from resident_level to the smallest one and leaves it bound.
+    unsafe fn allocate(entry: &mut StreamEntry, filter: GLenum, wrap: GLenum) {
+        let levels = &entry.mips[entry.resident_level..];
+        unsafe {
+            gl::GenTextures(1, &mut entry.texture);
+            gl::BindTexture(gl::TEXTURE_2D, entry.texture);
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, (filter + gl::NEAREST_MIPMAP_LINEAR - gl::NEAREST) as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, (levels.len() - 1) as GLint);
+
+            let (width, height, _) = levels[0];
+            if gl::TexStorage2D::is_loaded() {
+                gl::TexStorage2D(gl::TEXTURE_2D, levels.len() as GLsizei, gl::RGBA8, width as GLsizei, height as GLsizei);
+            } else {
+                // OpenGL 4.1 and older without ARB_texture_storage: allocate every level without data instead.
+                for (i, (width, height, _)) in levels.iter().enumerate() {
+                    gl::TexImage2D(
+                        gl::TEXTURE_2D,
+                        i as GLint,
+                        gl::RGBA8 as GLint,
+                        *width as GLsizei,
+                        *height as GLsizei,
+                        0,
+                        gl::RGBA,
+                        gl::UNSIGNED_BYTE,
+                        std::ptr::null(),
+                    );
+                }
+            }
+        }
+    }
+
+    fn decode(path: &str) -> Result<Vec<Mip>, String> {
+        let image = image::open(path).map_err(|error| error.to_string())?.flipv();
+        let (mut width, mut height) = image.dimensions();
+        let mut level = image.to_rgba8();
+
+        let mut mips = Vec::new();
+        loop {
+            let next = (width > 1 || height > 1).then(|| image::imageops::resize(&level, (width / 2).max(1), (height / 2).max(1), FilterType::Triangle));
+            mips.push((width, height, std::mem::take(&mut level).into_raw()));
+
+            let Some(next) = next else { break; };
+            (width, height) = next.dimensions();
+            level = next;
+        }
+        Ok(mips)
+    }
+}
+impl Drop for TextureStreamer {
+    /// You don't need to manually free OpenGL resources, it's done automatically.
+    fn drop(&mut self) {
+        if !render::is_current_context(self.generation) {
+            return;
+        }
+        unsafe {
+            for entry in &self.entries {
+                if entry.texture != 0 {
+                    gl::DeleteTextures(1, &entry.texture);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_file_fails_without_panicking() {
+        let mut streamer = TextureStreamer::new(1024).with_threads(1);
+        let texture = streamer.load("./this/texture/does/not/exist.png");
+
+        let start = std::time::Instant::now();
+        while !streamer.is_failed(texture) && start.elapsed() < std::time::Duration::from_secs(5) {
+            streamer.update();
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        assert!(streamer.is_failed(texture));
+        assert!(streamer.get_error(texture).unwrap().contains("does/not/exist.png"));
+        assert!(!streamer.is_resident(texture));
+        assert_eq!(streamer.get_used_memory(), 0);
+    }
+}
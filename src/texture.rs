@@ -1,9 +1,12 @@
 use gl::types::{GLenum, GLint, GLsizei, GLuint};
 use image::GenericImageView;
 
+use crate::render;
+
 /// A simple OpenGL texture ```id: GLuint``` wrapper.
 pub struct Texture {
     id: GLuint,
+    generation: u64,
 }
 
 impl Texture {
@@ -54,7 +57,76 @@ impl Texture {
             gl::BindTexture(gl::TEXTURE_2D, 0);
         }
 
-        Self { id }
+        Self { id, generation: render::context_generation() }
+    }
+
+    /// Creates a ```width``` x ```height``` floating-point RGB texture from raw ```data``` (3 floats per pixel, row by row).
+    /// It has no mipmaps and uses nearest filtering, so it's meant for storing data (like vertex positions) instead of images.
+    /// Read it with ```texelFetch``` in your shaders.
+    pub fn from_rgb32f(width: u32, height: u32, data: &[f32]) -> Self {
+        if data.len() != (width * height * 3) as usize {
+            panic!("Failed to create {}x{} RGB32F texture. Expected {} floats, but got {}.", width, height, width * height * 3, data.len());
+        }
+
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGB32F as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                0,
+                gl::RGB,
+                gl::FLOAT,
+                data.as_ptr() as *const std::ffi::c_void,
+            );
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        Self { id, generation: render::context_generation() }
+    }
+
+    /// Creates a ```width``` x ```height``` half-float RGB texture from raw ```data``` (3 floats per pixel, row by row, bottom row first)
+    /// with linear filtering, for images with values above 1.0 like lightmaps. See [crate::lightmap::LightmapBaker].
+    pub fn from_hdr(width: u32, height: u32, data: &[f32]) -> Self {
+        if data.len() != (width * height * 3) as usize {
+            panic!("Failed to create {}x{} HDR texture. Expected {} floats, but got {}.", width, height, width * height * 3, data.len());
+        }
+
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGB16F as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                0,
+                gl::RGB,
+                gl::FLOAT,
+                data.as_ptr() as *const std::ffi::c_void,
+            );
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        Self { id, generation: render::context_generation() }
     }
 
     /// Binds the texture to certain slot.
@@ -64,6 +136,7 @@ impl Texture {
             gl::ActiveTexture(gl::TEXTURE0 + slot);
             gl::BindTexture(gl::TEXTURE_2D, self.id);
         }
+        render::count_texture_bind();
     }
     /// Unbinds all texture from OpenGL's state.
     pub fn unbind() {
@@ -73,6 +146,9 @@ impl Texture {
 impl Drop for Texture {
     /// You don't need to manually unbind and delete textures, it's done automatically!
     fn drop(&mut self) {
+        if !render::is_current_context(self.generation) {
+            return;
+        }
         unsafe {
             gl::BindTexture(gl::TEXTURE_2D, 0);
             gl::DeleteTextures(1, &self.id);
@@ -0,0 +1,192 @@
+use gl::types::GLboolean;
+use nalgebra::Vector3;
+
+use crate::{curve::AnimationCurve, mesh::Mesh, render::BlendState, shader::Shader};
+
+const VERTEX_SOURCE: &str = "#version 330 core
+layout(location = 0) in vec2 a_Position;
+out vec2 v_Position;
+
+void main() {
+    v_Position = a_Position;
+    gl_Position = vec4(a_Position, 0.0, 1.0);
+}
+";
+const FADE_SOURCE: &str = "#version 330 core
+in vec2 v_Position;
+out vec4 f_Color;
+
+uniform float u_Progress;
+uniform vec3 u_Color;
+
+void main() {
+    f_Color = vec4(u_Color, u_Progress);
+}
+";
+const CIRCLE_WIPE_SOURCE: &str = "#version 330 core
+in vec2 v_Position;
+out vec4 f_Color;
+
+uniform float u_Progress;
+uniform float u_Aspect;
+uniform vec3 u_Color;
+
+void main() {
+    vec2 position = v_Position * vec2(u_Aspect, 1.0);
+    float radius = (1.0 - u_Progress) * length(vec2(u_Aspect, 1.0));
+    f_Color = vec4(u_Color, step(radius, length(position)));
+}
+";
+const DISSOLVE_SOURCE: &str = "#version 330 core
+in vec2 v_Position;
+out vec4 f_Color;
+
+uniform float u_Progress;
+uniform vec3 u_Color;
+
+void main() {
+    float noise = fract(sin(dot(floor(gl_FragCoord.xy / 4.0), vec2(12.9898, 78.233))) * 43758.5453);
+    f_Color = vec4(u_Color, step(noise, u_Progress * 1.001));
+}
+";
+
+/// Direction of the [Transition].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TransitionDirection {
+    /// Screen is getting covered. Use it before switching the scene.
+    Out,
+    /// Screen is getting uncovered. Use it right after switching the scene.
+    In,
+}
+
+/// A fullscreen overlay effect used for scene changes (fade to color, circle wipe, dissolve or your own shader).
+/// # Example
+/// ```rust
+/// use tinystorm::{transition::{Transition, TransitionDirection}, nalgebra::Vector3};
+///
+/// let mut transition = Transition::fade(Vector3::zeros(), 0.5);
+/// transition.start(TransitionDirection::Out);
+///
+/// while window.is_running() {
+///     window.poll_events();
+///     unsafe { gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT); }
+///
+///     transition.update(window.get_delta());
+///     if transition.is_finished() && transition.get_direction() == TransitionDirection::Out {
+///         // Change the scene here...
+///         transition.start(TransitionDirection::In);
+///     }
+///
+///     // Draw your scene here...
+///     transition.draw(window.get_aspect()); // Draw transition last, so it overlays everything.
+///     window.swap_buffers();
+/// }
+/// ```
+pub struct Transition {
+    shader: Shader,
+    mesh: Mesh,
+
+    color: Vector3<f32>,
+    duration: f32,
+    elapsed: f32,
+    direction: TransitionDirection,
+    curve: Option<AnimationCurve>,
+}
+
+impl Transition {
+    /// Fades the screen to ```color``` in ```duration``` seconds.
+    pub fn fade(color: Vector3<f32>, duration: f32) -> Self {
+        Self::custom(Shader::from_source(VERTEX_SOURCE, FADE_SOURCE), color, duration)
+    }
+    /// Covers the screen with ```color``` by shrinking a circle at the screen center in ```duration``` seconds.
+    pub fn circle_wipe(color: Vector3<f32>, duration: f32) -> Self {
+        Self::custom(Shader::from_source(VERTEX_SOURCE, CIRCLE_WIPE_SOURCE), color, duration)
+    }
+    /// Covers the screen with ```color``` pixel-block by pixel-block in random order in ```duration``` seconds.
+    pub fn dissolve(color: Vector3<f32>, duration: f32) -> Self {
+        Self::custom(Shader::from_source(VERTEX_SOURCE, DISSOLVE_SOURCE), color, duration)
+    }
+    /// Uses your own ```shader``` drawn over a fullscreen triangle.
+    /// # Uniforms
+    /// ```float u_Progress``` - 0.0 is fully uncovered screen, 1.0 is fully covered.
+    /// ```float u_Aspect``` - window aspect ratio passed to [Transition::draw].
+    /// ```vec3 u_Color``` - the ```color``` you passed here.
+    /// Vertex attribute 0 is ```vec2``` position in normalized device coordinates.
+    pub fn custom(shader: Shader, color: Vector3<f32>, duration: f32) -> Self {
+        Self {
+            shader,
+            mesh: Mesh::fullscreen_triangle(),
+
+            color,
+            duration,
+            elapsed: duration,
+            direction: TransitionDirection::In,
+            curve: None,
+        }
+    }
+    /// Sets easing ```curve```: it maps time (0.0 - 1.0 from the start) to coverage (0.0 - 1.0). Linear by default.
+    /// # Example
+    /// ```rust
+    /// let transition = Transition::circle_wipe(Vector3::zeros(), 0.8).with_curve(AnimationCurve::ease_in_out(0.0, 1.0));
+    /// ```
+    pub fn with_curve(mut self, curve: AnimationCurve) -> Self {
+        self.curve = Some(curve);
+        self
+    }
+
+    /// Starts the transition from the beginning in certain ```direction```.
+    pub fn start(&mut self, direction: TransitionDirection) {
+        self.direction = direction;
+        self.elapsed = 0.0;
+    }
+    /// Advances the transition by ```delta``` seconds. Call it once per frame.
+    pub fn update(&mut self, delta: f32) {
+        self.elapsed = (self.elapsed + delta).min(self.duration);
+    }
+
+    /// Returns if the transition has reached its end.
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+    /// Gets the direction the transition was started with.
+    pub fn get_direction(&self) -> TransitionDirection {
+        self.direction
+    }
+    /// Gets how much of the screen is covered. 0.0 is fully uncovered, 1.0 is fully covered.
+    pub fn get_progress(&self) -> f32 {
+        let time = if self.duration > 0.0 { self.elapsed / self.duration } else { 1.0 };
+        let time = self.curve.as_ref().map_or(time, |curve| curve.evaluate(time));
+        match self.direction {
+            TransitionDirection::Out => time,
+            TransitionDirection::In => 1.0 - time,
+        }
+    }
+
+    /// Draws the overlay over everything rendered before. Does nothing if screen is fully uncovered.
+    /// Blending is enabled and depth test is disabled while drawing, previous state (blend functions included) is restored after that.
+    pub fn draw(&self, aspect: f32) {
+        let progress = self.get_progress();
+        if progress <= 0.0 {
+            return;
+        }
+
+        let blend = BlendState::save();
+        unsafe {
+            let depth_test: GLboolean = gl::IsEnabled(gl::DEPTH_TEST);
+
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::Disable(gl::DEPTH_TEST);
+
+            self.shader.bind();
+            self.shader.set_float("u_Progress", progress);
+            self.shader.set_float("u_Aspect", aspect);
+            self.shader.set_vec3("u_Color", &self.color);
+            self.mesh.draw();
+            Shader::unbind();
+
+            blend.restore();
+            if depth_test == gl::TRUE { gl::Enable(gl::DEPTH_TEST); }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use gl::types::{GLenum, GLint, GLintptr, GLsizei, GLsizeiptr, GLuint};
+
+use crate::render;
+
+/// A handle of an upload queued in [UploadScheduler].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Upload(usize);
+
+enum Destination {
+    Buffer { buffer: GLuint, offset: usize },
+    Texture { texture: GLuint, level: GLint, x: GLint, y: GLint, width: GLsizei, height: GLsizei, format: GLenum, kind: GLenum },
+}
+
+struct PendingUpload {
+    id: usize,
+    priority: i32,
+    destination: Destination,
+    data: Vec<u8>,
+    // Bytes already uploaded, big uploads are split across frames.
+    uploaded: usize,
+}
+
+/// Queues buffer and texture uploads and limits how many bytes are uploaded per frame,
+/// so lots of assets finishing loading in the same frame don't cause a hitch.
+///
+/// Uploads with higher priority go first, equal priorities go in the order they were queued.
+/// Big uploads are split: buffers by bytes, textures by rows. At least one texture row is uploaded per frame, even if it's bigger than the budget.
+/// Destination buffers and textures must already have storage (```gl::BufferData```, ```gl::TexImage2D``` or ```gl::TexStorage2D```).
+/// # Example
+/// ```rust
+/// use tinystorm::upload::UploadScheduler;
+///
+/// // 4 MiB per frame.
+/// let mut uploads = UploadScheduler::new(4 * 1024 * 1024);
+/// let upload = uploads.queue_buffer(vertex_buffer, 0, vertices, 10);
+/// uploads.queue_texture(texture, 0, 0, 0, 2048, 2048, gl::RGBA, gl::UNSIGNED_BYTE, pixels, 0);
+///
+/// while window.is_running() {
+///     window.poll_events();
+///     uploads.update();
+///
+///     if uploads.is_done(upload) {
+///         mesh.draw();
+///     }
+///     window.swap_buffers();
+/// }
+/// ```
+pub struct UploadScheduler {
+    queue: Vec<PendingUpload>,
+    budget: usize,
+    next_id: usize,
+    uploaded_last_frame: usize,
+}
+
+impl UploadScheduler {
+    /// Creates a scheduler uploading at most ```budget``` bytes per frame.
+    pub fn new(budget: usize) -> Self {
+        Self { queue: Vec::new(), budget, next_id: 0, uploaded_last_frame: 0 }
+    }
+
+    /// Queues upload of ```data``` to ```buffer``` starting at ```offset``` bytes. Higher ```priority``` goes first.
+    pub fn queue_buffer(&mut self, buffer: GLuint, offset: usize, data: Vec<u8>, priority: i32) -> Upload {
+        self.push(Destination::Buffer { buffer, offset }, data, priority)
+    }
+    /// Queues upload of ```data``` to ```width``` x ```height``` region at (```x```, ```y```) of 2D ```texture``` mip ```level```.
+    /// ```format``` and ```kind``` are the same as in ```gl::TexSubImage2D``` (e.g. ```gl::RGBA``` and ```gl::UNSIGNED_BYTE```), rows are tightly packed.
+    /// Higher ```priority``` goes first.
+    #[allow(clippy::too_many_arguments)]
+    pub fn queue_texture(
+        &mut self,
+        texture: GLuint,
+        level: i32,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        format: GLenum,
+        kind: GLenum,
+        data: Vec<u8>,
+        priority: i32,
+    ) -> Upload {
+        if height == 0 || !data.len().is_multiple_of(height as usize) {
+            panic!("Failed to queue texture upload. Data size ({} bytes) isn't divisible into {} rows.", data.len(), height);
+        }
+        self.push(Destination::Texture { texture, level, x, y, width: width as GLsizei, height: height as GLsizei, format, kind }, data, priority)
+    }
+    /// Cancels an upload. Parts of it that were already uploaded stay on the GPU.
+    pub fn cancel(&mut self, upload: Upload) {
+        self.queue.retain(|pending| pending.id != upload.0);
+    }
+
+    /// Uploads queued data until the per-frame budget is spent. Call it once per frame.
+    /// Buffer bound to ```gl::COPY_WRITE_BUFFER``` and 2D texture bound to the active slot are changed.
+    pub fn update(&mut self) {
+        // Stable, so equal priorities keep their queue order.
+        self.queue.sort_by_key(|pending| std::cmp::Reverse(pending.priority));
+
+        let mut uploaded = 0;
+        unsafe {
+            let mut alignment: GLint = 0;
+            gl::GetIntegerv(gl::UNPACK_ALIGNMENT, &mut alignment);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+
+            for pending in &mut self.queue {
+                let remaining = pending.data.len() - pending.uploaded;
+                let size = match pending.destination {
+                    Destination::Buffer { buffer, offset } => {
+                        let size = remaining.min(self.budget.saturating_sub(uploaded));
+                        if size > 0 {
+                            gl::BindBuffer(gl::COPY_WRITE_BUFFER, buffer);
+                            gl::BufferSubData(
+                                gl::COPY_WRITE_BUFFER,
+                                (offset + pending.uploaded) as GLintptr,
+                                size as GLsizeiptr,
+                                pending.data[pending.uploaded..].as_ptr() as *const std::ffi::c_void,
+                            );
+                            render::count_buffer_upload(size);
+                        }
+                        size
+                    }
+                    Destination::Texture { texture, level, x, y, width, height, format, kind } => {
+                        let row_size = pending.data.len() / height as usize;
+                        let mut rows = (remaining.min(self.budget.saturating_sub(uploaded)) / row_size.max(1)) as GLsizei;
+                        if rows == 0 && remaining > 0 && uploaded == 0 {
+                            rows = 1;
+                        }
+
+                        if rows > 0 {
+                            let row = (pending.uploaded / row_size.max(1)) as GLsizei;
+                            gl::BindTexture(gl::TEXTURE_2D, texture);
+                            gl::TexSubImage2D(
+                                gl::TEXTURE_2D, level, x, y + row, width, rows, format, kind,
+                                pending.data[pending.uploaded..].as_ptr() as *const std::ffi::c_void,
+                            );
+                        }
+                        rows as usize * row_size
+                    }
+                };
+
+                pending.uploaded += size;
+                uploaded += size;
+                if uploaded >= self.budget {
+                    break;
+                }
+            }
+
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, alignment);
+        }
+
+        self.queue.retain(|pending| pending.uploaded < pending.data.len());
+        self.uploaded_last_frame = uploaded;
+    }
+
+    /// Checks if an upload is finished (or cancelled).
+    pub fn is_done(&self, upload: Upload) -> bool {
+        !self.queue.iter().any(|pending| pending.id == upload.0)
+    }
+    /// Gets number of unfinished uploads.
+    pub fn get_pending(&self) -> usize {
+        self.queue.len()
+    }
+    /// Gets number of bytes still waiting to be uploaded.
+    pub fn get_pending_bytes(&self) -> usize {
+        self.queue.iter().map(|pending| pending.data.len() - pending.uploaded).sum()
+    }
+    /// Gets number of bytes uploaded during the last [UploadScheduler::update].
+    pub fn get_uploaded_bytes(&self) -> usize {
+        self.uploaded_last_frame
+    }
+    /// Sets maximum number of bytes uploaded per frame.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+    }
+    /// Gets maximum number of bytes uploaded per frame.
+    pub fn get_budget(&self) -> usize {
+        self.budget
+    }
+
+    fn push(&mut self, destination: Destination, data: Vec<u8>, priority: i32) -> Upload {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.queue.push(PendingUpload { id, priority, destination, data, uploaded: 0 });
+        Upload(id)
+    }
+}
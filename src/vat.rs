@@ -0,0 +1,123 @@
+use gl::types::GLenum;
+
+use crate::{shader::Shader, texture::Texture};
+
+/// A vertex animation texture (aka. VAT): every animation frame of every vertex baked into float textures.
+/// Vertex shader samples them by ```gl_VertexID```, so you get cheap crowd/cloth animation without any skeletons.
+///
+/// Positions (and optionally normals) are stored as ```vertex_count``` x ```frame_count``` RGB32F textures,
+/// so vertex count is limited by ```GL_MAX_TEXTURE_SIZE``` of your GPU.
+/// # Example
+/// ```rust
+/// use tinystorm::vat::VertexAnimation;
+///
+/// // Each frame is a flat list of XYZ positions, one per vertex, in the same order as in your mesh.
+/// let animation = VertexAnimation::bake(&[&frame0, &frame1, &frame2], None, 24.0);
+///
+/// while window.is_running() {
+///     ...
+///     shader.bind();
+///     animation.bind(&shader, 0, 1, time); // Texture slots 0 and 1, time in seconds.
+///     mesh.draw();
+/// }
+/// ```
+/// In your vertex shader paste [VertexAnimation::GLSL] right after ```#version``` and use ```vat_position()``` / ```vat_normal()```.
+pub struct VertexAnimation {
+    positions: Texture,
+    normals: Option<Texture>,
+
+    vertex_count: usize,
+    frame_count: usize,
+    fps: f32,
+}
+
+impl VertexAnimation {
+    /// GLSL helpers to paste into your vertex shader (requires GLSL 1.30+ for ```texelFetch``` and ```gl_VertexID```).
+    /// ```vat_position()``` and ```vat_normal()``` return current vertex position/normal blended between two frames.
+    pub const GLSL: &'static str = "
+uniform sampler2D u_VatPositions;
+uniform sampler2D u_VatNormals;
+uniform int u_VatFrameA;
+uniform int u_VatFrameB;
+uniform float u_VatBlend;
+
+vec3 vat_position() {
+    vec3 a = texelFetch(u_VatPositions, ivec2(gl_VertexID, u_VatFrameA), 0).xyz;
+    vec3 b = texelFetch(u_VatPositions, ivec2(gl_VertexID, u_VatFrameB), 0).xyz;
+    return mix(a, b, u_VatBlend);
+}
+vec3 vat_normal() {
+    vec3 a = texelFetch(u_VatNormals, ivec2(gl_VertexID, u_VatFrameA), 0).xyz;
+    vec3 b = texelFetch(u_VatNormals, ivec2(gl_VertexID, u_VatFrameB), 0).xyz;
+    return normalize(mix(a, b, u_VatBlend));
+}
+";
+
+    /// Bakes animation ```frames``` (flat XYZ positions per frame) and optional ```normals``` (the same layout) into textures.
+    /// ```fps``` is the playback speed in frames per second.
+    /// All frames must have the same number of vertices.
+    pub fn bake(frames: &[&[f32]], normals: Option<&[&[f32]]>, fps: f32) -> Self {
+        if frames.is_empty() {
+            panic!("Failed to bake vertex animation. There must be at least one frame.");
+        }
+
+        let vertex_count = frames[0].len() / 3;
+        let positions = Texture::from_rgb32f(vertex_count as u32, frames.len() as u32, &Self::flatten(frames, vertex_count));
+        let normals = normals.map(|normals| {
+            if normals.len() != frames.len() {
+                panic!("Failed to bake vertex animation. Got {} position frames, but {} normal frames.", frames.len(), normals.len());
+            }
+            Texture::from_rgb32f(vertex_count as u32, normals.len() as u32, &Self::flatten(normals, vertex_count))
+        });
+
+        Self { positions, normals, vertex_count, frame_count: frames.len(), fps }
+    }
+
+    /// Binds position (and normal, if baked) textures to ```positions_slot``` and ```normals_slot```,
+    /// and sets uniforms from [VertexAnimation::GLSL] for the animation looped at ```time``` seconds.
+    /// ```shader``` must be bound before that.
+    pub fn bind(&self, shader: &Shader, positions_slot: GLenum, normals_slot: GLenum, time: f32) {
+        let (frame_a, frame_b, blend) = self.get_frames(time);
+
+        self.positions.bind(positions_slot);
+        shader.set_int("u_VatPositions", positions_slot as i32);
+        if let Some(normals) = &self.normals {
+            normals.bind(normals_slot);
+            shader.set_int("u_VatNormals", normals_slot as i32);
+        }
+
+        shader.set_int("u_VatFrameA", frame_a as i32);
+        shader.set_int("u_VatFrameB", frame_b as i32);
+        shader.set_float("u_VatBlend", blend);
+    }
+
+    /// Gets two frames to blend between and blend factor for the animation looped at ```time``` seconds.
+    pub fn get_frames(&self, time: f32) -> (usize, usize, f32) {
+        let frame = (time * self.fps).rem_euclid(self.frame_count as f32);
+        let frame_a = (frame.floor() as usize).min(self.frame_count - 1);
+        (frame_a, (frame_a + 1) % self.frame_count, frame.fract())
+    }
+    /// Gets animation duration in seconds.
+    pub fn get_duration(&self) -> f32 {
+        self.frame_count as f32 / self.fps
+    }
+    /// Gets number of vertices in each frame.
+    pub fn get_vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+    /// Gets number of baked frames.
+    pub fn get_frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    fn flatten(frames: &[&[f32]], vertex_count: usize) -> Vec<f32> {
+        let mut data = Vec::with_capacity(frames.len() * vertex_count * 3);
+        for (i, frame) in frames.iter().enumerate() {
+            if frame.len() != vertex_count * 3 {
+                panic!("Failed to bake vertex animation. Frame {} has {} floats, but {} were expected.", i, frame.len(), vertex_count * 3);
+            }
+            data.extend_from_slice(frame);
+        }
+        data
+    }
+}
@@ -0,0 +1,236 @@
+use crate::mesh::{Attribute, IndexedMesh, Layout};
+
+/// A side of a block, used to pick texture array layers per face.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VoxelFace {
+    /// +X
+    Right,
+    /// -X
+    Left,
+    /// +Y
+    Top,
+    /// -Y
+    Bottom,
+    /// +Z
+    Front,
+    /// -Z
+    Back,
+}
+
+/// A 3D array of blocks (Minecraft-like chunk) that can be turned into an optimized mesh with greedy meshing.
+/// Block id 0 is air, any other id is a solid block. Space outside the chunk is treated as air.
+///
+/// Adjacent faces with the same texture layer and ambient occlusion are merged into one quad,
+/// so a flat 16x16 floor is just 2 triangles instead of 512.
+/// # Example
+/// ```rust
+/// use tinystorm::voxel::{VoxelChunk, VoxelFace};
+///
+/// let mut chunk = VoxelChunk::new(16, 16, 16);
+/// for x in 0..16 { for z in 0..16 { chunk.set(x, 0, z, GRASS); } }
+///
+/// // Texture array layers: grass top, grass side and dirt.
+/// let mesh = chunk.build_mesh(|block, face| match (block, face) {
+///     (GRASS, VoxelFace::Top) => 0,
+///     (GRASS, VoxelFace::Bottom) => 2,
+///     (GRASS, _) => 1,
+///     _ => 2,
+/// });
+///
+/// while window.is_running() {
+///     ...
+///     mesh.draw(); // See VoxelChunk::layout for vertex attributes.
+/// }
+/// ```
+pub struct VoxelChunk {
+    width: usize,
+    height: usize,
+    depth: usize,
+    blocks: Vec<u16>,
+}
+
+impl VoxelChunk {
+    /// Block id of air (empty space).
+    pub const AIR: u16 = 0;
+
+    /// Creates a ```width``` x ```height``` x ```depth``` chunk filled with air.
+    pub fn new(width: usize, height: usize, depth: usize) -> Self {
+        Self { width, height, depth, blocks: vec![Self::AIR; width * height * depth] }
+    }
+
+    /// Vertex layout of meshes built by [VoxelChunk::build_mesh].
+    /// # Layout
+    /// position: [Attribute::Vec3]  
+    /// uv: [Attribute::Vec2] (in blocks, use ```gl::REPEAT``` wrapping)  
+    /// normal: [Attribute::Vec3]  
+    /// layer: [Attribute::Float] (texture array layer)  
+    /// ao: [Attribute::Float] (ambient occlusion, 0.0 - fully occluded, 1.0 - open)
+    pub fn layout() -> Layout {
+        Layout::default_3d()
+            .next_attribute(Attribute::Float)
+            .next_attribute(Attribute::Float)
+    }
+
+    /// Sets a block at ```x```, ```y```, ```z```. Panics if it's outside the chunk.
+    pub fn set(&mut self, x: usize, y: usize, z: usize, block: u16) {
+        let index = self.index(x, y, z);
+        self.blocks[index] = block;
+    }
+    /// Gets a block at ```x```, ```y```, ```z```. Returns air if it's outside the chunk.
+    pub fn get(&self, x: i64, y: i64, z: i64) -> u16 {
+        if x < 0 || y < 0 || z < 0 || x as usize >= self.width || y as usize >= self.height || z as usize >= self.depth {
+            return Self::AIR;
+        }
+        self.blocks[self.index(x as usize, y as usize, z as usize)]
+    }
+    /// Gets chunk size in blocks.
+    pub fn get_size(&self) -> (usize, usize, usize) {
+        (self.width, self.height, self.depth)
+    }
+
+    /// Builds an indexed mesh of all visible block faces in [VoxelChunk::layout] layout.
+    /// ```layer``` returns texture array layer for a block id and its face. Block at (0, 0, 0) starts at the origin.
+    pub fn build_mesh(&self, layer: impl Fn(u16, VoxelFace) -> u32) -> IndexedMesh {
+        let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+        self.build_mesh_into(&mut vertices, &mut indices, layer);
+        IndexedMesh::new::<f32>(&indices, &vertices, &Self::layout(), gl::TRIANGLES)
+    }
+    /// Same as [VoxelChunk::build_mesh], but writes vertices and indices into ```vertices``` and ```indices``` (cleared first) instead of uploading them.
+    /// Reuse the same vectors for all chunks, so remeshing a world doesn't allocate every time, or upload them yourself.
+    /// # Example
+    /// ```rust
+    /// let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+    /// for (chunk, mesh) in dirty_chunks {
+    ///     chunk.build_mesh_into(&mut vertices, &mut indices, layer);
+    ///     *mesh = IndexedMesh::new::<f32>(&indices, &vertices, &VoxelChunk::layout(), gl::TRIANGLES);
+    /// }
+    /// ```
+    pub fn build_mesh_into(&self, vertices: &mut Vec<f32>, indices: &mut Vec<u32>, layer: impl Fn(u16, VoxelFace) -> u32) {
+        let size = [self.width, self.height, self.depth];
+        vertices.clear();
+        indices.clear();
+
+        for d in 0..3 {
+            let (u, v) = ((d + 1) % 3, (d + 2) % 3);
+
+            for positive in [false, true] {
+                let face = Self::face(d, positive);
+                let mut mask: Vec<Option<(u32, [u8; 4])>> = vec![None; size[u] * size[v]];
+
+                for slice in 0..size[d] {
+                    for j in 0..size[v] {
+                        for i in 0..size[u] {
+                            let mut position = [0_i64; 3];
+                            position[d] = slice as i64;
+                            position[u] = i as i64;
+                            position[v] = j as i64;
+
+                            let block = self.get(position[0], position[1], position[2]);
+                            let mut outside = position;
+                            outside[d] += if positive { 1 } else { -1 };
+
+                            mask[j * size[u] + i] = if block != Self::AIR && !self.is_solid(outside) {
+                                Some((layer(block, face), self.ambient_occlusion(outside, u, v)))
+                            } else {
+                                None
+                            };
+                        }
+                    }
+
+                    for j in 0..size[v] {
+                        let mut i = 0;
+                        while i < size[u] {
+                            let Some(key) = mask[j * size[u] + i] else { i += 1; continue; };
+
+                            let mut width = 1;
+                            while i + width < size[u] && mask[j * size[u] + i + width] == Some(key) {
+                                width += 1;
+                            }
+                            let mut height = 1;
+                            while j + height < size[v] && (i..i + width).all(|k| mask[(j + height) * size[u] + k] == Some(key)) {
+                                height += 1;
+                            }
+                            for row in j..j + height {
+                                for column in i..i + width {
+                                    mask[row * size[u] + column] = None;
+                                }
+                            }
+
+                            let mut origin = [0.0_f32; 3];
+                            origin[d] = (slice + positive as usize) as f32;
+                            origin[u] = i as f32;
+                            origin[v] = j as f32;
+                            Self::push_quad(vertices, indices, origin, d, width as f32, height as f32, positive, key);
+
+                            i += width;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn push_quad(vertices: &mut Vec<f32>, indices: &mut Vec<u32>, origin: [f32; 3], d: usize, width: f32, height: f32, positive: bool, key: (u32, [u8; 4])) {
+        let (u, v) = ((d + 1) % 3, (d + 2) % 3);
+        let (layer, ao) = key;
+        let mut normal = [0.0_f32; 3];
+        normal[d] = if positive { 1.0 } else { -1.0 };
+
+        let start = (vertices.len() / 10) as u32;
+        for (corner, (du, dv)) in [(0.0, 0.0), (width, 0.0), (width, height), (0.0, height)].into_iter().enumerate() {
+            let mut position = origin;
+            position[u] += du;
+            position[v] += dv;
+
+            vertices.extend_from_slice(&position);
+            vertices.extend_from_slice(&[du, dv]);
+            vertices.extend_from_slice(&normal);
+            vertices.extend_from_slice(&[layer as f32, ao[corner] as f32 / 3.0]);
+        }
+
+        // Quad diagonal is flipped along darker corners, so occlusion is interpolated without seams.
+        let flip = (ao[0] + ao[2]) < (ao[1] + ao[3]);
+        let order: [u32; 6] = match (positive, flip) {
+            (true, false) => [0, 1, 2, 0, 2, 3],
+            (true, true) => [1, 2, 3, 1, 3, 0],
+            (false, false) => [0, 2, 1, 0, 3, 2],
+            (false, true) => [1, 3, 2, 1, 0, 3],
+        };
+        indices.extend(order.iter().map(|index| start + index));
+    }
+
+    // Occlusion level (0..3) of every face corner, in (-u, -v), (+u, -v), (+u, +v), (-u, +v) order.
+    fn ambient_occlusion(&self, outside: [i64; 3], u: usize, v: usize) -> [u8; 4] {
+        [(-1, -1), (1, -1), (1, 1), (-1, 1)].map(|(su, sv)| {
+            let mut side_u = outside;
+            side_u[u] += su;
+            let mut side_v = outside;
+            side_v[v] += sv;
+            let mut corner = side_u;
+            corner[v] += sv;
+
+            let (side_u, side_v, corner) = (self.is_solid(side_u), self.is_solid(side_v), self.is_solid(corner));
+            if side_u && side_v { 0 } else { 3 - side_u as u8 - side_v as u8 - corner as u8 }
+        })
+    }
+    fn is_solid(&self, position: [i64; 3]) -> bool {
+        self.get(position[0], position[1], position[2]) != Self::AIR
+    }
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        if x >= self.width || y >= self.height || z >= self.depth {
+            panic!("Block ({}, {}, {}) is outside of {}x{}x{} chunk.", x, y, z, self.width, self.height, self.depth);
+        }
+        (y * self.depth + z) * self.width + x
+    }
+    fn face(d: usize, positive: bool) -> VoxelFace {
+        match (d, positive) {
+            (0, true) => VoxelFace::Right,
+            (0, false) => VoxelFace::Left,
+            (1, true) => VoxelFace::Top,
+            (1, false) => VoxelFace::Bottom,
+            (2, true) => VoxelFace::Front,
+            _ => VoxelFace::Back,
+        }
+    }
+}
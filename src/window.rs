@@ -1,8 +1,51 @@
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
 use std::time::{Duration, Instant};
 
 use glfw::{self, Context};
+use nalgebra::{Vector2, Vector4};
 use spin_sleep::SpinSleeper;
 
+use crate::config::{WindowConfig, WindowGeometry};
+use crate::framebuffer::{Framebuffer, RenderTargets};
+use crate::input::{GamepadAxisConfig, InputState, VirtualCursor};
+use crate::render;
+
+/// Who caused the OpenGL context loss. See [Window::was_context_reset].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ContextResetStatus {
+    /// Reset was caused by this application (e.g. infinite loop in a shader).
+    Guilty,
+    /// Reset was caused by something else (e.g. another application or driver update).
+    Innocent,
+    /// Cause of the reset is unknown.
+    Unknown,
+}
+
+/// Gamepad hot-plug notification. See [Window::gamepad_events].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GamepadEvent {
+    /// A gamepad with a standard mapping was connected.
+    Connected(glfw::JoystickId),
+    /// A previously connected gamepad was disconnected.
+    Disconnected(glfw::JoystickId),
+}
+
+/// How frames are synchronized with the monitor. See [WindowBuilder::with_latency_mode].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LatencyMode {
+    /// Vsync and max FPS are used as they are set.
+    Default,
+    /// Vsync is disabled and frames are paced to the monitor refresh rate (or max FPS, if it's lower) with a precise CPU timer.
+    /// Input is read right before a frame is drawn instead of frames waiting in the driver's swap queue, so input lag is lower.
+    /// Some tearing is possible.
+    Low,
+    /// The same as [LatencyMode::Low], but [Window::swap_buffers] also waits for the GPU to finish the frame (```glFinish```),
+    /// so the CPU never runs ahead of the GPU. The lowest input lag, but CPU and GPU don't work in parallel anymore.
+    LowWithFinish,
+}
+
 /// It's just a simple GLFW window holder with custom basic input system.
 ///
 /// # Example
@@ -28,22 +71,39 @@ pub struct Window {
 
     /// GLFW window handle you can use for more precise control of your application.
     pub handle: glfw::PWindow,
-    /// Current frame events you can use like a custom event handler. Updated only after [Self::poll_events] is called.
+    /// Raw GLFW event receiver. It's drained by [Self::poll_events], so use [Self::frame_events] to see this frame's events.
     pub events: glfw::GlfwReceiver<(f64, glfw::WindowEvent)>,
+    frame_events: Vec<glfw::WindowEvent>,
+    gamepads: [bool; glfw::ffi::JOYSTICK_LAST as usize + 1],
+    gamepad_events: Vec<GamepadEvent>,
+    gamepad_axis_configs: [GamepadAxisConfig; glfw::ffi::GAMEPAD_AXIS_LAST as usize + 1],
+    virtual_cursor: Option<VirtualCursor>,
+    interactive_rects: Vec<Vector4<f32>>,
 
     title: String,
 
     keys: [u64; glfw::ffi::KEY_LAST as usize + 1],
+    key_press_times: [Option<Instant>; glfw::ffi::KEY_LAST as usize + 1],
+    pressed_keys: Vec<glfw::Key>,
     mouse_buttons: [u64; glfw::ffi::MOUSE_BUTTON_LAST as usize + 1],
+    mouse_click_times: [Option<Instant>; glfw::ffi::MOUSE_BUTTON_LAST as usize + 1],
+    mouse_double_clicks: [u64; glfw::ffi::MOUSE_BUTTON_LAST as usize + 1],
+    double_click_interval: Duration,
 
     current_frame: u64,
 
     frame_duration: Duration,
+    background_frame_duration: Duration,
     last_time: Instant,
     sleeper: spin_sleep::SpinSleeper,
 
     width: u32,
     height: u32,
+    position: (i32, i32),
+    size_change: Option<((u32, u32), (u32, u32))>,
+    position_change: Option<((i32, i32), (i32, i32))>,
+    // Position and size before the window was maximized, so saved geometry restores to them.
+    restored_geometry: ((i32, i32), (i32, i32)),
 
     aspect: f32,
 
@@ -56,8 +116,35 @@ pub struct Window {
     mouse_dx: f32,
     mouse_dy: f32,
 
+    scroll_dx: f32,
+    scroll_dy: f32,
+
     frame_time: Instant,
     delta_time: Duration,
+    time_scale: f32,
+    time_paused: bool,
+
+    fps: f32,
+    fps_frames: u32,
+    fps_time: Instant,
+    title_fps: bool,
+
+    focused: bool,
+    iconified: bool,
+
+    vsync: bool,
+    latency_mode: LatencyMode,
+    msaa: u32,
+    depth_test: bool,
+    reverse_z: bool,
+    max_fps: u32,
+    key_bindings: BTreeMap<String, glfw::Key>,
+
+    render_targets: RenderTargets,
+
+    robust: bool,
+    context_reset: Option<ContextResetStatus>,
+    context_reset_callback: Option<Box<dyn FnMut(ContextResetStatus)>>,
 }
 
 impl Window {
@@ -81,10 +168,12 @@ impl Window {
     pub fn poll_events(&mut self) {
         self.delta_time = self.frame_time.elapsed();
         self.frame_time = Instant::now();
+        self.update_fps();
 
+        let frame_duration = if self.focused { self.frame_duration } else { self.background_frame_duration };
         let elapsed = self.last_time.elapsed();
-        if elapsed < self.frame_duration {
-            self.sleeper.sleep(self.frame_duration - elapsed);
+        if elapsed < frame_duration {
+            self.sleeper.sleep(frame_duration - elapsed);
         }
 
         self.last_time = Instant::now();
@@ -92,6 +181,29 @@ impl Window {
         self.glfw.poll_events();
         self.current_frame += 1;
 
+        self.context_reset = None;
+        if self.robust {
+            let status = match unsafe { gl::GetGraphicsResetStatus() } {
+                gl::NO_ERROR => None,
+                gl::GUILTY_CONTEXT_RESET => Some(ContextResetStatus::Guilty),
+                gl::INNOCENT_CONTEXT_RESET => Some(ContextResetStatus::Innocent),
+                _ => Some(ContextResetStatus::Unknown),
+            };
+
+            if let Some(status) = status {
+                self.recreate_context();
+                self.context_reset = Some(status);
+                if let Some(callback) = self.context_reset_callback.as_mut() {
+                    callback(status);
+                }
+            }
+        }
+
+        let previous_size = (self.width, self.height);
+        let previous_position = self.position;
+        self.frame_events.clear();
+        self.scroll_dx = 0.0;
+        self.scroll_dy = 0.0;
         for (_, event) in glfw::flush_messages(&self.events) {
             match event {
                 glfw::WindowEvent::FramebufferSize(width, height) => {
@@ -100,14 +212,25 @@ impl Window {
                     self.aspect = width as f32 / height as f32;
 
                     unsafe { gl::Viewport(0, 0, width, height) }
+
+                    // Minimized window reports 0x0, there's no point in shrinking render targets for that.
+                    if width > 0 && height > 0 {
+                        self.render_targets.resize(width as u32, height as u32);
+                    }
                 }
                 glfw::WindowEvent::Key(key, _, action, _) => {
                     match action {
                         glfw::Action::Press => {
                             self.keys[key as usize] = self.current_frame;
+                            self.key_press_times[key as usize] = Some(Instant::now());
+                            if !self.pressed_keys.contains(&key) {
+                                self.pressed_keys.push(key);
+                            }
                         }
                         glfw::Action::Release => {
                             self.keys[key as usize] = 0;
+                            self.key_press_times[key as usize] = None;
+                            self.pressed_keys.retain(|&pressed| pressed != key);
                         }
                         _ => {}
                     }
@@ -116,6 +239,16 @@ impl Window {
                     match action {
                         glfw::Action::Press => {
                             self.mouse_buttons[button as usize] = self.current_frame;
+
+                            // Click time is reset after a double click, so a triple click isn't two double clicks.
+                            let now = Instant::now();
+                            let last_click = &mut self.mouse_click_times[button as usize];
+                            if last_click.is_some_and(|time| now - time <= self.double_click_interval) {
+                                self.mouse_double_clicks[button as usize] = self.current_frame;
+                                *last_click = None;
+                            } else {
+                                *last_click = Some(now);
+                            }
                         }
                         glfw::Action::Release => {
                             self.mouse_buttons[button as usize] = 0;
@@ -123,15 +256,40 @@ impl Window {
                         _ => {}
                     }
                 }
+                glfw::WindowEvent::Focus(focused) => {
+                    self.focused = focused;
+                }
+                glfw::WindowEvent::Iconify(iconified) => {
+                    self.iconified = iconified;
+                }
+                glfw::WindowEvent::Pos(x, y) => {
+                    self.position = (x, y);
+                }
+                glfw::WindowEvent::Scroll(x, y) => {
+                    self.scroll_dx += x as f32;
+                    self.scroll_dy += y as f32;
+                }
                 
                 _ => {}
             }
+
+            self.frame_events.push(event);
+        }
+
+        let size = (self.width, self.height);
+        self.size_change = (size != previous_size).then_some((previous_size, size));
+        self.position_change = (self.position != previous_position).then_some((previous_position, self.position));
+        if (self.size_change.is_some() || self.position_change.is_some()) && !self.iconified && !self.handle.is_maximized() {
+            self.restored_geometry = (self.handle.get_pos(), self.handle.get_size());
         }
 
+        self.update_gamepads();
+
         let cursor_pos = self.handle.get_cursor_pos();
 
         self.mouse_x = cursor_pos.0 as f32;
         self.mouse_y = cursor_pos.1 as f32;
+        self.update_virtual_cursor();
 
         self.mouse_dx = self.mouse_x - self.last_mouse_x;
         self.mouse_dy = self.mouse_y - self.last_mouse_y;
@@ -140,6 +298,136 @@ impl Window {
         self.last_mouse_y = self.mouse_y;
     }
 
+    /// Gets all events received in the current frame, after the window has handled them itself.
+    /// Built-in input keeps working, so you can react to anything it doesn't cover (text input, scroll, file drops, etc.).
+    /// Some events are sent only if you enable their polling on [Self::handle] (e.g. ```window.handle.set_char_polling(true)```).
+    /// # Example
+    /// ```rust
+    /// for event in window.frame_events() {
+    ///     if let glfw::WindowEvent::FileDrop(paths) = event {
+    ///         open_files(paths);
+    ///     }
+    /// }
+    /// ```
+    pub fn frame_events(&self) -> &[glfw::WindowEvent] {
+        &self.frame_events
+    }
+
+    /// Gets gamepads connected or disconnected since the previous frame.
+    /// Gamepads that were already connected when the window was created are reported as connected on the first frame.
+    /// # Example
+    /// ```rust
+    /// for event in window.gamepad_events() {
+    ///     if let GamepadEvent::Disconnected(_) = event {
+    ///         paused = true; // "Controller disconnected - press A to resume"
+    ///     }
+    /// }
+    /// ```
+    pub fn gamepad_events(&self) -> &[GamepadEvent] {
+        &self.gamepad_events
+    }
+    /// Checks if a gamepad with a standard mapping is connected at ```id```.
+    pub fn is_gamepad_connected(&self, id: glfw::JoystickId) -> bool {
+        self.gamepads[id as usize]
+    }
+
+    /// Checks if specific button of a gamepad at ```id``` is pressed. Returns false if there's no gamepad.
+    pub fn is_gamepad_button_pressed(&self, id: glfw::JoystickId, button: glfw::GamepadButton) -> bool {
+        self.glfw.get_joystick(id).get_gamepad_state()
+            .is_some_and(|state| state.get_button_state(button) == glfw::Action::Press)
+    }
+    /// Gets value of specific axis of a gamepad at ```id``` filtered by its [GamepadAxisConfig].
+    /// Sticks are in -1.0..1.0 range, triggers are in 0.0..1.0 range. Returns 0.0 if there's no gamepad.
+    /// # Example
+    /// ```rust
+    /// let movement = Vector2::new(
+    ///     window.get_gamepad_axis(JoystickId::Joystick1, GamepadAxis::AxisLeftX),
+    ///     window.get_gamepad_axis(JoystickId::Joystick1, GamepadAxis::AxisLeftY),
+    /// );
+    /// ```
+    pub fn get_gamepad_axis(&self, id: glfw::JoystickId, axis: glfw::GamepadAxis) -> f32 {
+        let Some(state) = self.glfw.get_joystick(id).get_gamepad_state() else { return 0.0; };
+
+        let value = match axis {
+            // GLFW reports released triggers as -1.0.
+            glfw::GamepadAxis::AxisLeftTrigger | glfw::GamepadAxis::AxisRightTrigger => (state.get_axis(axis) + 1.0) * 0.5,
+            _ => state.get_axis(axis),
+        };
+        self.gamepad_axis_configs[axis as usize].apply(value)
+    }
+    /// Sets deadzone, saturation and response curve of specific gamepad axis (for all gamepads).
+    pub fn set_gamepad_axis_config(&mut self, axis: glfw::GamepadAxis, config: GamepadAxisConfig) {
+        self.gamepad_axis_configs[axis as usize] = config;
+    }
+    /// Gets deadzone, saturation and response curve of specific gamepad axis.
+    pub fn get_gamepad_axis_config(&self, axis: glfw::GamepadAxis) -> GamepadAxisConfig {
+        self.gamepad_axis_configs[axis as usize]
+    }
+
+    /// Enables a gamepad-driven cursor that feeds the mouse API, or disables it with ```None```. See [VirtualCursor].
+    /// It does nothing while the mouse is grabbed.
+    pub fn set_virtual_cursor(&mut self, cursor: Option<VirtualCursor>) {
+        // Release the click if the gamepad is holding it, so it doesn't stay pressed forever.
+        if let Some(mut previous) = self.virtual_cursor.take()
+            && previous.update_click(false).is_some() {
+            self.mouse_buttons[glfw::MouseButton::Left as usize] = 0;
+        }
+        self.virtual_cursor = cursor;
+    }
+    /// Gets the gamepad-driven cursor with its current state, if enabled.
+    pub fn get_virtual_cursor(&self) -> Option<&VirtualCursor> {
+        self.virtual_cursor.as_ref()
+    }
+    /// Sets rects the [VirtualCursor] snaps to: XY is top-left corner, ZW is size, both in pixels like [Window::get_mouse_x].
+    /// They're kept until replaced, so set them when your UI layout changes.
+    pub fn set_interactive_rects(&mut self, rects: &[Vector4<f32>]) {
+        self.interactive_rects.clear();
+        self.interactive_rects.extend_from_slice(rects);
+    }
+
+    fn update_virtual_cursor(&mut self) {
+        let Some(mut cursor) = self.virtual_cursor else { return; };
+        if self.is_mouse_grabbed() {
+            return;
+        }
+
+        let id = cursor.get_gamepad();
+        if let Some(pressed) = cursor.update_click(self.is_gamepad_button_pressed(id, cursor.get_click_button())) {
+            self.mouse_buttons[glfw::MouseButton::Left as usize] = if pressed { self.current_frame } else { 0 };
+        }
+
+        let position = Vector2::new(self.mouse_x, self.mouse_y);
+        if position != Vector2::new(self.last_mouse_x, self.last_mouse_y) {
+            cursor.deactivate();
+        }
+
+        let (x_axis, y_axis) = cursor.get_axes();
+        let stick = Vector2::new(self.get_gamepad_axis(id, x_axis), self.get_gamepad_axis(id, y_axis));
+        let moved = cursor.step(position, stick, self.delta_time.as_secs_f32(), &self.interactive_rects);
+        let (width, height) = self.handle.get_size();
+        let moved = Vector2::new(moved.x.clamp(0.0, width as f32), moved.y.clamp(0.0, height as f32));
+        if moved != position {
+            // The real cursor follows, so hover effects of the OS and the next frame's position match.
+            self.handle.set_cursor_pos(moved.x as f64, moved.y as f64);
+            self.mouse_x = moved.x;
+            self.mouse_y = moved.y;
+        }
+        self.virtual_cursor = Some(cursor);
+    }
+
+    fn update_gamepads(&mut self) {
+        self.gamepad_events.clear();
+        for (index, connected) in self.gamepads.iter_mut().enumerate() {
+            let Some(id) = glfw::JoystickId::from_i32(index as i32) else { continue; };
+            let now_connected = self.glfw.get_joystick(id).is_gamepad();
+
+            if now_connected != *connected {
+                *connected = now_connected;
+                self.gamepad_events.push(if now_connected { GamepadEvent::Connected(id) } else { GamepadEvent::Disconnected(id) });
+            }
+        }
+    }
+
     /// Swaps front framebuffer with back that scene was rendered on. **Please call it at the frame end to avoid input lag.**
     /// # Example
     /// ```rust
@@ -150,6 +438,13 @@ impl Window {
     /// ```
     pub fn swap_buffers(&mut self) {
         self.handle.swap_buffers();
+        if self.latency_mode == LatencyMode::LowWithFinish {
+            unsafe { gl::Finish(); }
+        }
+    }
+    /// Gets latency mode the window was created with. See [WindowBuilder::with_latency_mode].
+    pub fn get_latency_mode(&self) -> LatencyMode {
+        self.latency_mode
     }
 
     /// Sets window X position in pixels from top-left corner
@@ -183,12 +478,40 @@ impl Window {
     /// Sets window title to a new one.
     pub fn set_title(&mut self, value: String) {
         self.title = value;
-        self.handle.set_title(&self.title);
+        self.refresh_title();
     }
     /// Gets current window title.
     pub fn get_title(&self) -> &str {
         &self.title
     }
+    /// Enables/disables FPS counter at the end of the window title (e.g. "My Game | FPS: 144").
+    /// It's averaged and updated once per second, so it's readable. [Window::get_title] still returns the title without it.
+    pub fn set_title_fps(&mut self, enabled: bool) {
+        self.title_fps = enabled;
+        self.refresh_title();
+    }
+
+    fn update_fps(&mut self) {
+        self.fps_frames += 1;
+
+        let elapsed = self.fps_time.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            self.fps = self.fps_frames as f32 / elapsed.as_secs_f32();
+            self.fps_frames = 0;
+            self.fps_time = Instant::now();
+
+            if self.title_fps {
+                self.refresh_title();
+            }
+        }
+    }
+    fn refresh_title(&mut self) {
+        if self.title_fps {
+            self.handle.set_title(&format!("{} | FPS: {:.0}", self.title, self.fps));
+        } else {
+            self.handle.set_title(&self.title);
+        }
+    }
 
     /// Hides mouse and keeps it at the window center.
     /// Used primarily for first-person games where you don't want to see the cursor.
@@ -263,6 +586,25 @@ impl Window {
     pub fn is_key_just_pressed(&self, key: glfw::Key) -> bool {
         self.keys[key as usize] == self.current_frame
     }
+    /// Iterates over all currently pressed keys, in the order they were pressed.
+    /// Useful for rebinding menus (the first just pressed key is the new binding).
+    pub fn pressed_keys(&self) -> impl Iterator<Item = glfw::Key> + '_ {
+        self.pressed_keys.iter().copied()
+    }
+    /// Checks if any key is just pressed at this frame. Useful for "Press any key" screens.
+    pub fn any_key_just_pressed(&self) -> bool {
+        self.pressed_keys.iter().any(|&key| self.is_key_just_pressed(key))
+    }
+    /// Gets how long specific key has been held down, or [None] if it's not pressed.
+    /// # Example
+    /// ```rust
+    /// if window.is_key_pressed(Key::Space) {
+    ///     charge = window.key_held_duration(Key::Space).unwrap().as_secs_f32().min(2.0);
+    /// }
+    /// ```
+    pub fn key_held_duration(&self, key: glfw::Key) -> Option<Duration> {
+        self.key_press_times[key as usize].map(|time| time.elapsed())
+    }
 
     /// Checks if specific mouse button is pressed.
     /// # Example
@@ -309,6 +651,31 @@ impl Window {
     pub fn is_mouse_button_just_pressed(&self, button: glfw::MouseButton) -> bool {
         self.mouse_buttons[button as usize] == self.current_frame
     }
+    /// Iterates over all currently pressed mouse buttons.
+    pub fn pressed_mouse_buttons(&self) -> impl Iterator<Item = glfw::MouseButton> + '_ {
+        self.mouse_buttons.iter().enumerate()
+            .filter(|(_, frame)| **frame > 0)
+            .filter_map(|(index, _)| glfw::MouseButton::from_i32(index as i32))
+    }
+    /// Checks if specific mouse button was double-clicked at this frame,
+    /// i.e. pressed twice within the double-click interval (500 ms by default).
+    /// # Example
+    /// ```rust
+    /// if window.is_mouse_button_double_clicked(MouseButton::Left) {
+    ///     open_file(hovered_file);
+    /// }
+    /// ```
+    pub fn is_mouse_button_double_clicked(&self, button: glfw::MouseButton) -> bool {
+        self.mouse_double_clicks[button as usize] == self.current_frame
+    }
+    /// Sets maximum time between two clicks for them to count as a double click.
+    pub fn set_double_click_interval(&mut self, interval: Duration) {
+        self.double_click_interval = interval;
+    }
+    /// Gets maximum time between two clicks for them to count as a double click.
+    pub fn get_double_click_interval(&self) -> Duration {
+        self.double_click_interval
+    }
 
     /// Gets mouse cursor X position in pixels from top-left corner relative to window.
     pub fn get_mouse_x(&self) -> f32 {
@@ -329,6 +696,84 @@ impl Window {
     pub fn get_mouse_dy(&self) -> f32 {
         self.mouse_dy
     }
+    /// Gets horizontal scroll offset in current frame (e.g. touchpad or tilting mouse wheel).
+    pub fn get_scroll_dx(&self) -> f32 {
+        self.scroll_dx
+    }
+    /// Gets vertical scroll offset in current frame. Positive is scrolling up (away from you).
+    pub fn get_scroll_dy(&self) -> f32 {
+        self.scroll_dy
+    }
+
+    /// Copies current key, mouse and scroll state into [InputState],
+    /// so game systems can take input by value instead of borrowing the whole window.
+    pub fn input_snapshot(&self) -> InputState {
+        InputState {
+            keys: self.keys,
+            mouse_buttons: self.mouse_buttons,
+            current_frame: self.current_frame,
+
+            mouse_x: self.mouse_x,
+            mouse_y: self.mouse_y,
+            mouse_dx: self.mouse_dx,
+            mouse_dy: self.mouse_dy,
+            scroll_dx: self.scroll_dx,
+            scroll_dy: self.scroll_dy,
+        }
+    }
+
+    /// Checks if the window (its framebuffer) was resized at this frame. Rebuild your size-dependent resources here.
+    pub fn was_resized_this_frame(&self) -> bool {
+        self.size_change.is_some()
+    }
+    /// Gets ```(old, new)``` framebuffer size in pixels if the window was resized at this frame.
+    pub fn get_size_change(&self) -> Option<((u32, u32), (u32, u32))> {
+        self.size_change
+    }
+    /// Checks if the window was moved at this frame.
+    pub fn was_moved_this_frame(&self) -> bool {
+        self.position_change.is_some()
+    }
+    /// Gets ```(old, new)``` window position in pixels if the window was moved at this frame.
+    pub fn get_position_change(&self) -> Option<((i32, i32), (i32, i32))> {
+        self.position_change
+    }
+
+    /// Gets current window position, size, maximized state and monitor, so it can be reopened the same way with [WindowBuilder::with_geometry].
+    /// If the window is maximized, position and size are the ones it would be restored to.
+    /// # Example
+    /// ```rust
+    /// use tinystorm::{config::WindowGeometry, window::WindowBuilder};
+    ///
+    /// let geometry: WindowGeometry = load_from_editor_settings().unwrap_or_default();
+    /// let mut window = WindowBuilder::default().with_geometry(&geometry).build();
+    /// while window.is_running() {
+    ///     ...
+    /// }
+    /// save_to_editor_settings(&window.geometry());
+    /// ```
+    pub fn geometry(&mut self) -> WindowGeometry {
+        let maximized = self.handle.is_maximized();
+        let ((x, y), (width, height)) = if maximized || self.iconified {
+            self.restored_geometry
+        } else {
+            (self.handle.get_pos(), self.handle.get_size())
+        };
+
+        // Monitor is the one containing the window center.
+        let (center_x, center_y) = (x + width / 2, y + height / 2);
+        let monitor = self.glfw.with_connected_monitors(|_, monitors| {
+            monitors.iter().find(|monitor| {
+                let (monitor_x, monitor_y) = monitor.get_pos();
+                monitor.get_video_mode().is_some_and(|mode| {
+                    center_x >= monitor_x && center_x < monitor_x + mode.width as i32 &&
+                    center_y >= monitor_y && center_y < monitor_y + mode.height as i32
+                })
+            }).and_then(|monitor| monitor.get_name())
+        });
+
+        WindowGeometry { x, y, width: width as u32, height: height as u32, maximized, monitor }
+    }
 
     /// Gets window X position in pixels from top-left corner.
     pub fn get_x(&self) -> i32 {
@@ -355,13 +800,51 @@ impl Window {
 
     /// Gets delta time between last and current frames as [Duration] so you can get it in any format you want.
     /// It's used primarily for physics calculation, player movement or animations that are time-related.
+    /// It's affected by [Window::set_time_scale] and [Window::pause_time].
     pub fn get_delta_raw(&self) -> Duration {
-        self.delta_time
+        if self.time_paused { Duration::ZERO } else { self.delta_time.mul_f32(self.time_scale) }
     }
     /// Gets delta time between last and current frames in seconds.
     /// It's used primarily for physics calculation, player movement or animations that are time-related.
+    /// It's affected by [Window::set_time_scale] and [Window::pause_time].
     pub fn get_delta(&self) -> f32 {
-        self.delta_time.as_secs_f32()
+        self.get_delta_raw().as_secs_f32()
+    }
+    /// Gets real delta time between last and current frames, ignoring time scale and pause.
+    /// Use it for things that must keep going while the game is paused, like UI animations.
+    pub fn get_delta_raw_unscaled(&self) -> Duration {
+        self.delta_time
+    }
+    /// Gets average frames per second over the last second.
+    pub fn get_fps(&self) -> f32 {
+        self.fps
+    }
+
+    /// Sets how fast game time goes. 1.0 is normal speed, 0.5 is slow motion, 2.0 is twice as fast.
+    /// # Example
+    /// ```rust
+    /// if window.is_key_just_pressed(Key::LeftShift) {
+    ///     window.set_time_scale(0.25); // Bullet time!
+    /// }
+    /// ```
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.time_scale = scale.max(0.0);
+    }
+    /// Gets current time scale. See [Window::set_time_scale].
+    pub fn get_time_scale(&self) -> f32 {
+        self.time_scale
+    }
+    /// Makes [Window::get_delta] and [Window::get_delta_raw] return zero, e.g. while pause menu is open.
+    pub fn pause_time(&mut self) {
+        self.time_paused = true;
+    }
+    /// Makes game time go again after [Window::pause_time].
+    pub fn resume_time(&mut self) {
+        self.time_paused = false;
+    }
+    /// Returns if game time is paused. See [Window::pause_time].
+    pub fn is_time_paused(&self) -> bool {
+        self.time_paused
     }
 
     /// Returns if mouse is grabbed (it means it's hidden and moved to window center, primarily used for first-person games) or released.
@@ -369,6 +852,216 @@ impl Window {
         self.handle.get_cursor_mode() == glfw::CursorMode::Disabled
     }
 
+    /// Returns if raw (unscaled and unaccelerated) mouse motion is supported on current system.
+    pub fn supports_raw_mouse_motion(&self) -> bool {
+        self.glfw.supports_raw_motion()
+    }
+    /// Enables/disables raw mouse motion. It's applied only while mouse is grabbed (see [Window::grab_mouse]).
+    /// Raw motion is the best for aiming, while OS-accelerated motion feels more natural for UI.
+    /// Does nothing if it's not supported (see [Window::supports_raw_mouse_motion]).
+    pub fn set_raw_mouse_motion(&mut self, enabled: bool) {
+        if self.supports_raw_mouse_motion() {
+            self.handle.set_raw_mouse_motion(enabled);
+        }
+    }
+    /// Returns if raw mouse motion is enabled.
+    pub fn is_raw_mouse_motion(&self) -> bool {
+        self.handle.uses_raw_mouse_motion()
+    }
+
+    /// Returns if the window currently has input focus.
+    /// Useful for pausing music or ignoring input while the game is in the background.
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+    /// Returns if the window is currently iconified (aka. minimized).
+    pub fn is_iconified(&self) -> bool {
+        self.iconified
+    }
+
+    /// Binds ```key``` to an ```action``` name, replacing the previous binding if there was one.
+    pub fn bind_key(&mut self, action: &str, key: glfw::Key) {
+        self.key_bindings.insert(String::from(action), key);
+    }
+    /// Gets key bound to an ```action```, if there's any.
+    pub fn get_key_binding(&self, action: &str) -> Option<glfw::Key> {
+        self.key_bindings.get(action).copied()
+    }
+    /// The same as [Window::is_key_pressed], but for a key bound to an ```action```. False if action isn't bound.
+    /// # Example
+    /// ```rust
+    /// window.bind_key("jump", Key::Space);
+    /// if window.is_action_pressed("jump") { ... }
+    /// ```
+    pub fn is_action_pressed(&self, action: &str) -> bool {
+        self.get_key_binding(action).is_some_and(|key| self.is_key_pressed(key))
+    }
+    /// The same as [Window::is_key_just_pressed], but for a key bound to an ```action```. False if action isn't bound.
+    pub fn is_action_just_pressed(&self, action: &str) -> bool {
+        self.get_key_binding(action).is_some_and(|key| self.is_key_just_pressed(key))
+    }
+
+    /// Gets current window settings, so you can save them back using [WindowConfig::save].
+    /// # Example
+    /// ```rust
+    /// window.bind_key("jump", Key::W);
+    /// if let Err(error) = window.get_config().save("./settings.toml") {
+    ///     show_error_popup(&error);
+    /// }
+    /// ```
+    pub fn get_config(&self) -> WindowConfig {
+        let (width, height) = self.handle.get_size();
+        WindowConfig {
+            width: width as u32,
+            height: height as u32,
+            vsync: self.vsync,
+            msaa: self.msaa,
+            fullscreen: self.handle.with_window_mode(|mode| matches!(mode, glfw::WindowMode::FullScreen(_))),
+            max_fps: self.max_fps,
+            key_bindings: self.key_bindings.clone(),
+        }
+    }
+
+    /// Creates a [Framebuffer] with window size multiplied by ```scale``` and stores it under ```name```.
+    /// It's resized automatically when the window is resized, so you never render into a stale-size target.
+    /// # Example
+    /// ```rust
+    /// window.register_render_target("half-res", 0.5);
+    /// 
+    /// while window.is_running() {
+    ///     window.poll_events();
+    /// 
+    ///     window.get_render_target("half-res").unwrap().bind();
+    ///     mesh.draw();
+    ///     Framebuffer::unbind(window.get_width(), window.get_height());
+    ///     ...
+    /// }
+    /// ```
+    pub fn register_render_target(&mut self, name: &str, scale: f32) {
+        self.render_targets.register(name, scale);
+    }
+    /// Removes a render target stored under ```name``` and returns it.
+    pub fn unregister_render_target(&mut self, name: &str) -> Option<Framebuffer> {
+        self.render_targets.unregister(name)
+    }
+    /// Gets a render target stored under ```name```. See [Window::register_render_target].
+    pub fn get_render_target(&self, name: &str) -> Option<&Framebuffer> {
+        self.render_targets.get(name)
+    }
+
+    /// Gets ```(major, minor)``` version of the OpenGL context the window got. See [WindowBuilder::with_gl_versions].
+    pub fn gl_version(&self) -> (u32, u32) {
+        let version = self.handle.get_context_version();
+        (version.major as u32, version.minor as u32)
+    }
+
+    /// Returns ```Some``` only at the frame when the OpenGL context was lost and recreated.
+    /// All GPU resources (meshes, shaders, textures, framebuffers, loaders, etc.) created before are invalid now, so you need to create them all again.
+    /// Old objects remember the context they were created in and don't delete anything when dropped, so they can't free objects of the new one.
+    /// Works only with [WindowBuilder::with_robustness] enabled and OpenGL 4.5+.
+    /// # Example
+    /// ```rust
+    /// while window.is_running() {
+    ///     window.poll_events();
+    ///     if window.was_context_reset().is_some() {
+    ///         mesh = Mesh::default_cube();
+    ///         shader = Shader::new("./assets/shaders/test.vert", "./assets/shaders/test.frag");
+    ///         texture = Texture::load_from_file("./assets/textures/test.png", gl::LINEAR, gl::REPEAT);
+    ///     }
+    ///     ...
+    /// }
+    /// ```
+    pub fn was_context_reset(&self) -> Option<ContextResetStatus> {
+        self.context_reset
+    }
+    /// Sets a hook that's called right after the OpenGL context was lost and recreated. See [Window::was_context_reset].
+    pub fn set_context_reset_callback(&mut self, callback: impl FnMut(ContextResetStatus) + 'static) {
+        self.context_reset_callback = Some(Box::new(callback));
+    }
+
+    fn recreate_context(&mut self) {
+        // Everything created from now on belongs to the new context.
+        render::next_context_generation();
+
+        let (x, y) = self.handle.get_pos();
+        let (width, height) = self.handle.get_size();
+        let grabbed = self.is_mouse_grabbed();
+        let raw_mouse_motion = self.is_raw_mouse_motion();
+        let visible = self.is_visible();
+        let maximized = self.handle.is_maximized();
+
+        // Fullscreen windows are recreated on the same monitor with the same video mode, so the game stays fullscreen.
+        let glfw = &mut self.glfw;
+        let (mut handle, events) = self.handle.with_window_mode(|mode| match mode {
+            glfw::WindowMode::FullScreen(monitor) => {
+                let video_mode = monitor.get_video_mode();
+                glfw.window_hint(glfw::WindowHint::RefreshRate(video_mode.map(|video_mode| video_mode.refresh_rate)));
+                let (width, height) = video_mode.map_or((width as u32, height as u32), |video_mode| (video_mode.width, video_mode.height));
+                glfw.create_window(width, height, &self.title, glfw::WindowMode::FullScreen(monitor))
+            }
+            glfw::WindowMode::Windowed => glfw.create_window(width as u32, height as u32, &self.title, glfw::WindowMode::Windowed),
+        }).expect("Failed to recreate a window after OpenGL context loss.");
+        if !handle.with_window_mode(|mode| matches!(mode, glfw::WindowMode::FullScreen(_))) {
+            handle.set_pos(x, y);
+            if maximized {
+                handle.maximize();
+            }
+        }
+        if visible {
+            handle.show();
+        }
+
+        let vsync = self.vsync && self.latency_mode == LatencyMode::Default;
+        let framebuffer_size = init_context(&mut self.glfw, &mut handle, vsync, self.msaa, self.depth_test, self.reverse_z);
+        if grabbed {
+            handle.set_cursor_mode(glfw::CursorMode::Disabled);
+        }
+        if raw_mouse_motion {
+            handle.set_raw_mouse_motion(true);
+        }
+
+        self.handle = handle;
+        self.events = events;
+
+        self.width = framebuffer_size.0 as u32;
+        self.height = framebuffer_size.1 as u32;
+        self.aspect = framebuffer_size.0 as f32 / framebuffer_size.1 as f32;
+
+        self.render_targets.recreate(self.width, self.height);
+    }
+
+    /// Checks if reverse-Z depth is enabled. It's false if [WindowBuilder::with_reverse_z] was used, but OpenGL 4.5 or ```ARB_clip_control``` isn't supported,
+    /// so check it after building the window to pick a matching projection.
+    pub fn is_reverse_z(&self) -> bool {
+        self.reverse_z
+    }
+
+    /// Shows the window if it was created hidden with [WindowBuilder::with_visible].
+    /// # Example
+    /// ```rust
+    /// use tinystorm::window::WindowBuilder;
+    ///
+    /// let mut window = WindowBuilder::default().with_visible(false).build();
+    /// let level = Level::load("./assets/levels/first.ron"); // Takes a while, the window is hidden meanwhile.
+    ///
+    /// level.draw();
+    /// window.swap_buffers();
+    /// window.show(); // Pops up with the first frame already drawn.
+    /// ```
+    pub fn show(&mut self) {
+        self.handle.show();
+    }
+    /// Checks if the window is visible (shown), even if it's minimized or covered by other windows.
+    pub fn is_visible(&self) -> bool {
+        self.handle.is_visible()
+    }
+
+    /// Asks the OS to draw player's attention to the window (taskbar flash on Windows, dock bounce on macOS).
+    /// Useful when a long loading is done or it's player's turn. Does nothing if the window is already focused.
+    pub fn request_attention(&mut self) {
+        self.handle.request_attention();
+    }
+
     /// Turn off the window prematurely. (It would just make [Window::is_running()] false)
     pub fn close(&mut self) {
         self.handle.set_should_close(true);
@@ -381,8 +1074,26 @@ pub struct WindowBuilder {
     height: u32,
     title: String,
     vsync: bool,
+    latency_mode: LatencyMode,
     max_fps: u32,
+    background_max_fps: u32,
     msaa: u32,
+    fullscreen: bool,
+    key_bindings: BTreeMap<String, glfw::Key>,
+    position: Option<(i32, i32)>,
+    centered: bool,
+    monitor: Option<usize>,
+    monitor_name: Option<String>,
+    robust: bool,
+    maximized: bool,
+    gl_versions: Vec<(u32, u32)>,
+    raw_mouse_motion: bool,
+    title_fps: bool,
+    depth_bits: u32,
+    stencil_bits: u32,
+    reverse_z: bool,
+    mouse_grabbed: bool,
+    visible: bool,
 }
 
 impl WindowBuilder {
@@ -404,7 +1115,35 @@ impl WindowBuilder {
     ///     .build();
     /// ```
     pub const NO_MSAA: u32 = 0;
+    /// Core profile OpenGL versions from the most wanted to the least, for [WindowBuilder::with_gl_versions].
+    /// Not used by default: without [WindowBuilder::with_gl_versions] the driver picks the context itself, like it always did.
+    pub const CORE_GL_VERSIONS: [(u32, u32); 3] = [(4, 6), (4, 3), (3, 3)];
     
+    /// Creates a builder with settings loaded from a RON or TOML file at ```path``` (see [WindowConfig]).
+    /// If file doesn't exist, can't be read or is broken - default settings are used. Use [WindowConfig::load] to handle errors yourself.
+    /// # Example
+    /// ```rust
+    /// use tinystorm::window::WindowBuilder;
+    /// 
+    /// let window = WindowBuilder::from_config("./settings.toml")
+    ///     .with_title(String::from("My Game")) // You can still override anything after loading.
+    ///     .build();
+    /// ```
+    pub fn from_config(path: &str) -> Self {
+        Self::default().with_config(&WindowConfig::load(path).unwrap_or_default())
+    }
+    /// Applies all settings from ```config```.
+    pub fn with_config(mut self, config: &WindowConfig) -> Self {
+        self.width = config.width;
+        self.height = config.height;
+        self.vsync = config.vsync;
+        self.msaa = config.msaa;
+        self.fullscreen = config.fullscreen;
+        self.max_fps = config.max_fps;
+        self.key_bindings = config.key_bindings.clone();
+        self
+    }
+
     /// Sets window default size in pixels.
     pub fn with_size(mut self, width: u32, height: u32) -> Self {
         self.width = width;
@@ -417,11 +1156,29 @@ impl WindowBuilder {
         self.title = title;
         self
     }
+    /// Shows FPS counter at the end of the window title. See [Window::set_title_fps].
+    pub fn with_title_fps(mut self, enabled: bool) -> Self {
+        self.title_fps = enabled;
+        self
+    }
     /// Enables/disables vsync for the window.
     pub fn with_vsync(mut self, vsync: bool) -> Self {
         self.vsync = vsync;
         self
     }
+    /// Sets how frames are synchronized with the monitor, to lower input lag in fast-paced games. Vsync setting is ignored in low latency modes.
+    /// # Example
+    /// ```rust
+    /// use tinystorm::window::{LatencyMode, WindowBuilder};
+    ///
+    /// let window = WindowBuilder::default()
+    ///     .with_latency_mode(LatencyMode::Low) // Paced to monitor refresh rate without vsync queue.
+    ///     .build();
+    /// ```
+    pub fn with_latency_mode(mut self, latency_mode: LatencyMode) -> Self {
+        self.latency_mode = latency_mode;
+        self
+    }
     /// Sets window FPS cap to max_fps.
     /// # No max FPS cap example
     /// ```rust
@@ -443,12 +1200,133 @@ impl WindowBuilder {
         self.max_fps = max_fps;
         self
     }
+    /// Sets window FPS cap used while the window isn't focused, so your game doesn't waste resources in the background.
+    /// [WindowBuilder::NO_MAX_FPS] means the same cap as [WindowBuilder::with_max_fps] is used.
+    /// # Example
+    /// ```rust
+    /// use tinystorm::window::WindowBuilder;
+    /// 
+    /// let window = WindowBuilder::default()
+    ///     .with_max_fps(144)
+    ///     .with_background_max_fps(15) // Would be clamped to 15 FPS while window is in the background.
+    ///     .build();
+    /// ```
+    pub fn with_background_max_fps(mut self, max_fps: u32) -> Self {
+        self.background_max_fps = max_fps;
+        self
+    }
     /// Enables multisampling for an OpenGL context. ([WindowBuilder::NO_MSAA] = no MSAA).
     pub fn with_msaa(mut self, msaa_quality: u32) -> Self {
         self.msaa = msaa_quality;
         self
     }
 
+    /// Sets window initial position in pixels from top-left corner of the desktop.
+    /// Window is moved before it's shown, so there's no visible jump. Overrides [WindowBuilder::with_centered].
+    pub fn with_position(mut self, x: i32, y: i32) -> Self {
+        self.position = Some((x, y));
+        self
+    }
+    /// Places the window at the center of the monitor's work area (primary one, unless [WindowBuilder::with_monitor] is used).
+    pub fn with_centered(mut self, centered: bool) -> Self {
+        self.centered = centered;
+        self
+    }
+    /// Selects a monitor by its index in the connected monitors list, used for centering and fullscreen.
+    /// If there's no monitor with such index - window is created as if no monitor was found.
+    pub fn with_monitor(mut self, index: usize) -> Self {
+        self.monitor = Some(index);
+        self.monitor_name = None;
+        self
+    }
+    /// Opens the window with position, size and maximized state saved by [Window::geometry].
+    /// If its monitor isn't connected anymore, the window is centered on the primary monitor instead of being lost off-screen.
+    pub fn with_geometry(mut self, geometry: &WindowGeometry) -> Self {
+        self.position = Some((geometry.x, geometry.y));
+        self.width = geometry.width;
+        self.height = geometry.height;
+        self.maximized = geometry.maximized;
+        self.monitor = None;
+        self.monitor_name = geometry.monitor.clone();
+        self
+    }
+    /// Sets number of bits in the depth buffer of the window. 0 means no depth buffer at all.
+    /// If it's greater than 0, depth test is enabled by default with ```gl::LEQUAL``` function,
+    /// so don't forget to clear ```gl::DEPTH_BUFFER_BIT``` each frame.
+    pub fn with_depth_bits(mut self, depth_bits: u32) -> Self {
+        self.depth_bits = depth_bits;
+        self
+    }
+    /// Sets number of bits in the stencil buffer of the window. 0 means no stencil buffer at all.
+    pub fn with_stencil_bits(mut self, stencil_bits: u32) -> Self {
+        self.stencil_bits = stencil_bits;
+        self
+    }
+    /// Enables reverse-Z depth: near plane is 1.0, far plane is 0.0, depth test uses ```gl::GEQUAL``` and depth is cleared to 0.0.
+    /// It removes z-fighting at large distances, but you must use reverse-Z projections (see [crate::camera::reverse_z_perspective]).
+    /// Requires OpenGL 4.5 or ```ARB_clip_control```, otherwise regular depth is used. Check it with [Window::is_reverse_z].
+    pub fn with_reverse_z(mut self, reverse_z: bool) -> Self {
+        self.reverse_z = reverse_z;
+        self
+    }
+    /// Opens the window maximized, so tools and editors fill the screen from the very first frame.
+    /// Window size is still used when user restores (un-maximizes) the window.
+    pub fn with_maximized(mut self, maximized: bool) -> Self {
+        self.maximized = maximized;
+        self
+    }
+    /// Makes window fullscreen on the primary monitor (or one chosen by [WindowBuilder::with_monitor]).
+    /// Window size is used as fullscreen resolution.
+    pub fn with_fullscreen(mut self, fullscreen: bool) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+    /// Sets OpenGL ```(major, minor)``` versions to try one by one until context is created, e.g. [WindowBuilder::CORE_GL_VERSIONS].
+    /// Versions 3.2 and higher are created with forward-compatible core profile, so legacy GLSL and fixed-function calls stop working.
+    /// If none of them is supported (or the list is empty, by default) - the driver picks the version itself.
+    /// Use [Window::gl_version] to see which one you got.
+    /// # Example
+    /// ```rust
+    /// use tinystorm::window::WindowBuilder;
+    /// 
+    /// let window = WindowBuilder::default()
+    ///     .with_gl_versions(&[(4, 5), (3, 3)]) // Try 4.5 first, then 3.3.
+    ///     .build();
+    /// println!("Got OpenGL {:?}", window.gl_version());
+    /// ```
+    pub fn with_gl_versions(mut self, versions: &[(u32, u32)]) -> Self {
+        self.gl_versions = versions.to_vec();
+        self
+    }
+    /// Requests a robust OpenGL context that reports GPU resets (driver crash, device removal, etc.) instead of rendering garbage.
+    /// When reset is detected, [Window::poll_events] recreates the window and its context automatically,
+    /// and [Window::was_context_reset] tells you it's time to recreate your meshes, shaders and textures.
+    pub fn with_robustness(mut self, robust: bool) -> Self {
+        self.robust = robust;
+        self
+    }
+    /// Enables/disables raw mouse motion from the start, if it's supported. See [Window::set_raw_mouse_motion].
+    pub fn with_raw_mouse_motion(mut self, enabled: bool) -> Self {
+        self.raw_mouse_motion = enabled;
+        self
+    }
+    /// Grabs the mouse from the very first frame, like [Window::grab_mouse]. Useful for first-person games.
+    pub fn with_mouse_grabbed(mut self, grabbed: bool) -> Self {
+        self.mouse_grabbed = grabbed;
+        self
+    }
+    /// Shows the window right after it's created. Disable it to load your assets first and call [Window::show] when the first frame is ready,
+    /// so the user doesn't see a blank window or garbage while loading.
+    pub fn with_visible(mut self, visible: bool) -> Self {
+        self.visible = visible;
+        self
+    }
+    /// Binds ```key``` to an ```action``` name. See [Window::is_action_pressed].
+    pub fn with_key_binding(mut self, action: &str, key: glfw::Key) -> Self {
+        self.key_bindings.insert(String::from(action), key);
+        self
+    }
+
     /// Builds the window itself from settings declared before.
     /// # Example
     /// ```rust
@@ -460,75 +1338,251 @@ impl WindowBuilder {
     ///     .build();
     /// ```
     pub fn build(&self) -> Window {
-        let mut glfw = match glfw::init(glfw::fail_on_errors) {
+        // Errors are ignored only while trying context versions from the fallback chain.
+        let suppress_errors = Rc::new(Cell::new(false));
+        let suppress = suppress_errors.clone();
+        let mut glfw = match glfw::init(move |error, description| {
+            if !suppress.get() { glfw::fail_on_errors(error, description); }
+        }) {
             Ok(value) => value,
             Err(error) => panic!("Error! You're trying to make multiple windows. Unfortunately, that's an unsupported feature. Result: {}", error),
         };
 
-        // glfw.window_hint(glfw::WindowHint::ContextVersion(4, 6));
-        // glfw.window_hint(glfw::WindowHint::OpenGlForwardCompat(true));
-        // glfw.window_hint(glfw::WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Compat));
-
         if self.msaa > 0 {
             glfw.window_hint(glfw::WindowHint::Samples(Some(self.msaa)));
         }
+        glfw.window_hint(glfw::WindowHint::DepthBits(Some(self.depth_bits)));
+        glfw.window_hint(glfw::WindowHint::StencilBits(Some(self.stencil_bits)));
+        if self.robust {
+            glfw.window_hint(glfw::WindowHint::ContextRobustness(glfw::ContextRobustnessHint::LoseContextOnReset));
+        }
+        // Positioned windows are maximized after moving, so they're maximized on the right monitor.
+        glfw.window_hint(glfw::WindowHint::Maximized(self.maximized && self.position.is_none()));
     
-        let (mut handle, events) = glfw.create_window(
-            self.width, self.height,
-            &self.title,
-            glfw::WindowMode::Windowed
-        ).expect("Failed to create a window.");
-
-        handle.make_current();
-        handle.set_raw_mouse_motion(true);
-        handle.set_key_polling(true);
-        handle.set_mouse_button_polling(true);
-        handle.set_framebuffer_size_polling(true);
-
-        glfw.set_swap_interval(if self.vsync { glfw::SwapInterval::Sync(1) } else { glfw::SwapInterval::None });
-
-        let framebuffer_size: (i32, i32) = handle.get_framebuffer_size();
-        gl::load_with(|procname| handle.get_proc_address(procname));
-        
-        unsafe { gl::Viewport(0, 0, framebuffer_size.0, framebuffer_size.1); }
-        if self.msaa > 0 {
-            unsafe { gl::Enable(gl::MULTISAMPLE); }
+        // Window is shown only after it's positioned, so it doesn't visibly jump on creation.
+        glfw.window_hint(glfw::WindowHint::Visible(false));
+
+        let (mut handle, events) = self.with_selected_monitor(&mut glfw, |glfw, monitor| {
+            let mode = match monitor {
+                Some(monitor) if self.fullscreen => glfw::WindowMode::FullScreen(monitor),
+                _ => glfw::WindowMode::Windowed,
+            };
+            let (mut handle, events) = self.create_window(glfw, mode, &suppress_errors)?;
+
+            // Saved position is useless if its monitor was disconnected.
+            let monitor_lost = self.monitor_name.is_some() && monitor.and_then(|monitor| monitor.get_name()) != self.monitor_name;
+            let position = self.position.filter(|_| !monitor_lost);
+
+            if !self.fullscreen && (!self.maximized || position.is_some()) {
+                if let Some((x, y)) = position {
+                    handle.set_pos(x, y);
+                    if self.maximized {
+                        handle.maximize();
+                    }
+                } else if let (true, Some(monitor)) = (self.centered || monitor_lost, monitor) {
+                    let (area_x, area_y, area_width, area_height) = monitor.get_workarea();
+                    let (width, height) = handle.get_size();
+                    handle.set_pos(area_x + (area_width - width) / 2, area_y + (area_height - height) / 2);
+                }
+            }
+
+            Some((handle, events))
+        }).expect("Failed to create a window.");
+        if self.visible {
+            handle.show();
+        }
+
+        let framebuffer_size = init_context(&mut glfw, &mut handle, self.vsync && self.latency_mode == LatencyMode::Default, self.msaa, self.depth_bits > 0, self.reverse_z);
+        if glfw.supports_raw_motion() {
+            handle.set_raw_mouse_motion(self.raw_mouse_motion);
+        }
+
+        if self.mouse_grabbed {
+            handle.set_cursor_mode(glfw::CursorMode::Disabled);
         }
 
+        // Low latency modes replace vsync with sleeping until the next monitor refresh.
+        let pacing = match self.latency_mode {
+            LatencyMode::Default => Duration::ZERO,
+            LatencyMode::Low | LatencyMode::LowWithFinish => {
+                let refresh_rate = self.with_selected_monitor(&mut glfw, |_, monitor| {
+                    monitor.and_then(|monitor| monitor.get_video_mode()).map(|mode| mode.refresh_rate)
+                });
+                Self::frame_duration(refresh_rate.filter(|&rate| rate > 0).unwrap_or(60))
+            }
+        };
+
+        // Last cursor position starts at the real one, so there's no mouse delta spike on the first frame.
+        let cursor_pos = handle.get_cursor_pos();
+        let focused = handle.is_focused();
+        let position = handle.get_pos();
+        let iconified = handle.is_iconified();
+        let restored_size = handle.get_size();
+
         Window {
             glfw,
             handle,
             events,
+            frame_events: Vec::new(),
+            gamepads: [false; glfw::ffi::JOYSTICK_LAST as usize + 1],
+            gamepad_events: Vec::new(),
+            gamepad_axis_configs: [GamepadAxisConfig::default(); glfw::ffi::GAMEPAD_AXIS_LAST as usize + 1],
+            virtual_cursor: None,
+            interactive_rects: Vec::new(),
 
             title: self.title.clone(),
 
             keys: [0; glfw::ffi::KEY_LAST as usize + 1],
+            key_press_times: [None; glfw::ffi::KEY_LAST as usize + 1],
+            pressed_keys: Vec::new(),
             mouse_buttons: [0; glfw::ffi::MOUSE_BUTTON_LAST as usize + 1],
+            mouse_click_times: [None; glfw::ffi::MOUSE_BUTTON_LAST as usize + 1],
+            mouse_double_clicks: [0; glfw::ffi::MOUSE_BUTTON_LAST as usize + 1],
+            double_click_interval: Duration::from_millis(500),
 
             current_frame: 0,
 
-            frame_duration: if self.max_fps == Self::NO_MAX_FPS { Duration::ZERO } else { Duration::from_secs_f32(1.0 / self.max_fps as f32) },
+            frame_duration: Self::frame_duration(self.max_fps).max(pacing),
+            background_frame_duration: Self::frame_duration(
+                if self.background_max_fps == Self::NO_MAX_FPS { self.max_fps } else { self.background_max_fps }
+            ).max(pacing),
             last_time: Instant::now(),
             sleeper: SpinSleeper::default(),
 
             width: framebuffer_size.0 as u32,
             height: framebuffer_size.1 as u32,
+            position,
+            size_change: None,
+            position_change: None,
+            restored_geometry: (position, restored_size),
 
             aspect: framebuffer_size.0 as f32 / framebuffer_size.1 as f32,
 
-            mouse_x: 0.0,
-            mouse_y: 0.0,
+            mouse_x: cursor_pos.0 as f32,
+            mouse_y: cursor_pos.1 as f32,
 
-            last_mouse_x: 0.0,
-            last_mouse_y: 0.0,
+            last_mouse_x: cursor_pos.0 as f32,
+            last_mouse_y: cursor_pos.1 as f32,
 
             mouse_dx: 0.0,
             mouse_dy: 0.0,
 
+            scroll_dx: 0.0,
+            scroll_dy: 0.0,
+
             frame_time: Instant::now(),
             delta_time: Duration::ZERO,
+            time_scale: 1.0,
+            time_paused: false,
+
+            fps: 0.0,
+            fps_frames: 0,
+            fps_time: Instant::now(),
+            title_fps: self.title_fps,
+
+            focused,
+            iconified,
+
+            vsync: self.vsync,
+            latency_mode: self.latency_mode,
+            msaa: self.msaa,
+            depth_test: self.depth_bits > 0,
+            reverse_z: self.reverse_z && gl::ClipControl::is_loaded(),
+            max_fps: self.max_fps,
+            key_bindings: self.key_bindings.clone(),
+
+            render_targets: RenderTargets::new(framebuffer_size.0 as u32, framebuffer_size.1 as u32),
+
+            robust: self.robust && gl::GetGraphicsResetStatus::is_loaded(),
+            context_reset: None,
+            context_reset_callback: None,
+        }
+    }
+
+    fn create_window(&self, glfw: &mut glfw::Glfw, mode: glfw::WindowMode, suppress_errors: &Cell<bool>) -> Option<(glfw::PWindow, glfw::GlfwReceiver<(f64, glfw::WindowEvent)>)> {
+        suppress_errors.set(true);
+        let mut result = None;
+        for &(major, minor) in &self.gl_versions {
+            let core = (major, minor) >= (3, 2);
+            glfw.window_hint(glfw::WindowHint::ContextVersion(major, minor));
+            glfw.window_hint(glfw::WindowHint::OpenGlForwardCompat(core));
+            glfw.window_hint(glfw::WindowHint::OpenGlProfile(
+                if core { glfw::OpenGlProfileHint::Core } else { glfw::OpenGlProfileHint::Any }
+            ));
+
+            result = glfw.create_window(self.width, self.height, &self.title, mode);
+            if result.is_some() {
+                break;
+            }
+        }
+        suppress_errors.set(false);
+
+        // If nothing from the chain worked (or there's no chain), let the driver pick the version itself.
+        result.or_else(|| {
+            glfw.window_hint(glfw::WindowHint::ContextVersion(1, 0));
+            glfw.window_hint(glfw::WindowHint::OpenGlForwardCompat(false));
+            glfw.window_hint(glfw::WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Any));
+            glfw.create_window(self.width, self.height, &self.title, mode)
+        })
+    }
+    fn with_selected_monitor<T>(&self, glfw: &mut glfw::Glfw, f: impl FnOnce(&mut glfw::Glfw, Option<&glfw::Monitor>) -> T) -> T {
+        if let Some(name) = &self.monitor_name {
+            // The first connected monitor is the primary one.
+            return glfw.with_connected_monitors(|glfw, monitors| {
+                let monitor = monitors.iter().find(|monitor| monitor.get_name().as_ref() == Some(name)).or(monitors.first());
+                f(glfw, monitor.map(|monitor| &**monitor))
+            });
+        }
+
+        match self.monitor {
+            Some(index) => glfw.with_connected_monitors(|glfw, monitors| f(glfw, monitors.get(index).map(|monitor| &**monitor))),
+            None => glfw.with_primary_monitor(|glfw, monitor| f(glfw, monitor.map(|monitor| &*monitor))),
+        }
+    }
+    fn frame_duration(max_fps: u32) -> Duration {
+        if max_fps == Self::NO_MAX_FPS { Duration::ZERO } else { Duration::from_secs_f32(1.0 / max_fps as f32) }
+    }
+}
+
+fn init_context(glfw: &mut glfw::Glfw, handle: &mut glfw::PWindow, vsync: bool, msaa: u32, depth_test: bool, reverse_z: bool) -> (i32, i32) {
+    handle.make_current();
+    handle.set_key_polling(true);
+    handle.set_mouse_button_polling(true);
+    handle.set_framebuffer_size_polling(true);
+    handle.set_focus_polling(true);
+    handle.set_scroll_polling(true);
+    handle.set_pos_polling(true);
+    handle.set_iconify_polling(true);
+
+    glfw.set_swap_interval(if vsync { glfw::SwapInterval::Sync(1) } else { glfw::SwapInterval::None });
+
+    let framebuffer_size: (i32, i32) = handle.get_framebuffer_size();
+    gl::load_with(|procname| handle.get_proc_address(procname));
+    
+    unsafe { gl::Viewport(0, 0, framebuffer_size.0, framebuffer_size.1); }
+    if msaa > 0 {
+        unsafe { gl::Enable(gl::MULTISAMPLE); }
+    }
+    if depth_test {
+        // LEQUAL instead of LESS, so 2D meshes drawn at the same depth don't reject each other.
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthFunc(gl::LEQUAL);
+            gl::ClearDepth(1.0);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
         }
     }
+    if reverse_z && gl::ClipControl::is_loaded() {
+        // Depth goes from 1.0 (near) to 0.0 (far) in [0; 1] range, where float precision is spread evenly.
+        unsafe {
+            gl::ClipControl(gl::LOWER_LEFT, gl::ZERO_TO_ONE);
+            gl::DepthFunc(gl::GEQUAL);
+            gl::ClearDepth(0.0);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+    }
+
+    framebuffer_size
 }
 
 /// # Default values
@@ -537,8 +1591,26 @@ impl WindowBuilder {
 /// height: 540  
 /// title: "Titled window, lol"  
 /// vsync: true  
+/// latency_mode: [LatencyMode::Default]  
 /// max_fps: [WindowBuilder::NO_MAX_FPS]  
-/// msaa: [WindowBuilder::NO_MSAA] 
+/// background_max_fps: [WindowBuilder::NO_MAX_FPS]  
+/// msaa: [WindowBuilder::NO_MSAA]  
+/// fullscreen: false  
+/// key_bindings: {}  
+/// position: None  
+/// centered: false  
+/// monitor: None (aka. primary monitor)  
+/// monitor_name: None  
+/// robust: false  
+/// maximized: false  
+/// gl_versions: [] (driver-default context)  
+/// raw_mouse_motion: true  
+/// title_fps: false  
+/// depth_bits: 24  
+/// stencil_bits: 8  
+/// reverse_z: false  
+/// mouse_grabbed: false  
+/// visible: true
 /// ```
 impl Default for WindowBuilder {
     fn default() -> Self {
@@ -547,8 +1619,26 @@ impl Default for WindowBuilder {
             height: 540,
             title: String::from("Titled window, lol"),
             vsync: true,
+            latency_mode: LatencyMode::Default,
             max_fps: Self::NO_MAX_FPS,
+            background_max_fps: Self::NO_MAX_FPS,
             msaa: Self::NO_MSAA,
+            fullscreen: false,
+            key_bindings: BTreeMap::new(),
+            position: None,
+            centered: false,
+            monitor: None,
+            monitor_name: None,
+            robust: false,
+            maximized: false,
+            gl_versions: Vec::new(),
+            raw_mouse_motion: true,
+            title_fps: false,
+            depth_bits: 24,
+            stencil_bits: 8,
+            reverse_z: false,
+            mouse_grabbed: false,
+            visible: true,
         }
     }
 }
\ No newline at end of file